@@ -3,11 +3,12 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result, anyhow, ensure};
 use clap::{Parser, ValueEnum};
 use deepseek_ocr_android::{
     AndroidImageInput, AndroidInferenceOptions, AndroidLogCallback, AndroidLogLevel,
-    AndroidModelKind, AndroidModelPaths, AndroidRunConfig, android_run_ocr,
+    AndroidModelKind, AndroidModelPaths, AndroidOcrTask, AndroidOrientationMode,
+    AndroidResolutionMode, AndroidRunConfig, android_run_ocr,
 };
 
 const DEFAULT_BASE_SIZE: u32 = 1024;
@@ -33,9 +34,11 @@ fn main() -> Result<()> {
             weights_path: path_to_string(&args.weights_path),
         },
         inference: AndroidInferenceOptions {
+            resolution: args.resolution.map(Into::into),
             base_size: args.base_size,
             image_size: args.image_size,
             crop_mode: args.crop_mode,
+            orientation: args.orientation.into(),
             max_new_tokens: args.max_new_tokens,
             use_cache: args.use_cache,
             do_sample: args.do_sample,
@@ -47,6 +50,7 @@ fn main() -> Result<()> {
             seed: args.seed,
             template: args.template,
             system_prompt: args.system_prompt,
+            task: args.task.map(Into::into),
         },
     };
 
@@ -99,6 +103,10 @@ struct CliArgs {
     #[arg(long = "image", value_name = "PATH", required = true)]
     images: Vec<PathBuf>,
 
+    /// Resolution preset; overrides the size and crop flags
+    #[arg(long, value_enum)]
+    resolution: Option<ResolutionArg>,
+
     #[arg(long, default_value_t = DEFAULT_BASE_SIZE)]
     base_size: u32,
 
@@ -108,6 +116,10 @@ struct CliArgs {
     #[arg(long, default_value_t = DEFAULT_CROP_MODE)]
     crop_mode: bool,
 
+    /// Image orientation handling: off, exif or auto (EXIF plus rotation detection)
+    #[arg(long, value_enum, default_value_t = OrientationArg::Exif)]
+    orientation: OrientationArg,
+
     #[arg(long, default_value_t = DEFAULT_MAX_NEW_TOKENS)]
     max_new_tokens: u32,
 
@@ -140,10 +152,21 @@ struct CliArgs {
 
     #[arg(long)]
     system_prompt: Option<String>,
+
+    /// Task preset; renders the model's own prompt and uses --prompt only as the text to
+    /// find for `locate`
+    #[arg(long, value_enum)]
+    task: Option<TaskArg>,
 }
 
 impl CliArgs {
     fn prompt_text(&self) -> Result<String> {
+        if let Some(task) = self.task {
+            ensure!(
+                task == TaskArg::Locate || (self.prompt.is_none() && self.prompt_file.is_none()),
+                "--prompt is only used as the text to find with --task locate"
+            );
+        }
         if let Some(text) = &self.prompt {
             return Ok(text.clone());
         }
@@ -152,6 +175,9 @@ impl CliArgs {
                 .with_context(|| format!("failed to load prompt file at {}", path.display()))?;
             return Ok(data);
         }
+        if self.task.is_some() {
+            return Ok(String::new());
+        }
         Err(anyhow!("either --prompt or --prompt-file must be provided"))
     }
 }
@@ -171,6 +197,71 @@ impl From<ModelKindArg> for AndroidModelKind {
     }
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+enum TaskArg {
+    Ocr,
+    Markdown,
+    Grounding,
+    Locate,
+    Table,
+    Formula,
+    Chart,
+    Figure,
+}
+
+impl From<TaskArg> for AndroidOcrTask {
+    fn from(value: TaskArg) -> Self {
+        match value {
+            TaskArg::Ocr => AndroidOcrTask::Ocr,
+            TaskArg::Markdown => AndroidOcrTask::Markdown,
+            TaskArg::Grounding => AndroidOcrTask::Grounding,
+            TaskArg::Locate => AndroidOcrTask::Locate,
+            TaskArg::Table => AndroidOcrTask::Table,
+            TaskArg::Formula => AndroidOcrTask::Formula,
+            TaskArg::Chart => AndroidOcrTask::Chart,
+            TaskArg::Figure => AndroidOcrTask::Figure,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+enum ResolutionArg {
+    Tiny,
+    Small,
+    Base,
+    Large,
+    Gundam,
+}
+
+impl From<ResolutionArg> for AndroidResolutionMode {
+    fn from(value: ResolutionArg) -> Self {
+        match value {
+            ResolutionArg::Tiny => AndroidResolutionMode::Tiny,
+            ResolutionArg::Small => AndroidResolutionMode::Small,
+            ResolutionArg::Base => AndroidResolutionMode::Base,
+            ResolutionArg::Large => AndroidResolutionMode::Large,
+            ResolutionArg::Gundam => AndroidResolutionMode::Gundam,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+enum OrientationArg {
+    Off,
+    Exif,
+    Auto,
+}
+
+impl From<OrientationArg> for AndroidOrientationMode {
+    fn from(value: OrientationArg) -> Self {
+        match value {
+            OrientationArg::Off => AndroidOrientationMode::Off,
+            OrientationArg::Exif => AndroidOrientationMode::Exif,
+            OrientationArg::Auto => AndroidOrientationMode::Auto,
+        }
+    }
+}
+
 fn load_images(paths: &[PathBuf]) -> Result<Vec<AndroidImageInput>> {
     paths
         .iter()
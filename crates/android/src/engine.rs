@@ -11,8 +11,12 @@ use anyhow::{Context, Result, anyhow, ensure};
 use candle_core::{DType, Device};
 use deepseek_ocr_core::{
     CancellationToken, ModelKind, ModelLoadArgs,
-    inference::{DecodeOutcome, DecodeParameters, OcrEngine, VisionSettings, render_prompt},
+    inference::{
+        DecodeOutcome, DecodeParameters, OcrEngine, TileEncodingConfig, VisionSettings,
+        render_prompt,
+    },
     streaming::DeltaTracker,
+    task::OcrTask,
 };
 use deepseek_ocr_infer_deepseek::load_model as load_deepseek_model;
 use deepseek_ocr_infer_paddleocr::load_model as load_paddle_model;
@@ -47,6 +51,7 @@ pub struct EngineModelConfig {
 pub struct EngineSettings {
     pub template: String,
     pub system_prompt: Option<String>,
+    pub task: Option<OcrTask>,
     pub vision: VisionSettings,
     pub decode: DecodeParameters,
 }
@@ -105,9 +110,16 @@ impl AndroidOcrEngine {
         progress: Option<Arc<dyn AndroidProgressCallback>>,
         cancel: Option<CancellationToken>,
     ) -> Result<DecodeOutcome> {
-        let system_prompt = self.settings.system_prompt.as_deref().unwrap_or("");
-        let prompt = render_prompt(&self.settings.template, system_prompt, raw_prompt)
-            .context("failed to render prompt")?;
+        let prompt = match self.settings.task {
+            Some(task) => task
+                .render_prompt(self.backend.kind(), images.len(), Some(raw_prompt))
+                .context("failed to render task prompt")?,
+            None => {
+                let system_prompt = self.settings.system_prompt.as_deref().unwrap_or("");
+                render_prompt(&self.settings.template, system_prompt, raw_prompt)
+                    .context("failed to render prompt")?
+            }
+        };
         let slots = prompt.matches("<image>").count();
         ensure!(
             slots == images.len(),
@@ -196,6 +208,8 @@ fn load_backend(config: &EngineModelConfig) -> Result<Box<dyn OcrEngine>> {
         weights_path: Some(config.weights_path.as_path()),
         device,
         dtype,
+        quantization: None,
+        tile_encoding: TileEncodingConfig::default(),
     };
     match config.kind {
         ModelKind::Deepseek => load_deepseek_model(load_args),
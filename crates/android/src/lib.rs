@@ -8,7 +8,9 @@ use std::{
 use anyhow::{Context, Result};
 use deepseek_ocr_core::{
     CancellationToken,
-    inference::{DecodeParameters, ModelKind, VisionSettings},
+    inference::{DecodeParameters, ModelKind, ResolutionMode, VisionSettings},
+    orientation::{OrientationMode, Rotation, decode_pages},
+    task::OcrTask,
 };
 use engine::{AndroidOcrEngine, EngineArgs, EngineModelConfig, EngineSettings};
 use image::DynamicImage;
@@ -29,6 +31,72 @@ impl From<AndroidModelKind> for ModelKind {
     }
 }
 
+#[derive(Clone, Copy, Debug, uniffi::Enum)]
+pub enum AndroidResolutionMode {
+    Tiny,
+    Small,
+    Base,
+    Large,
+    Gundam,
+}
+
+impl From<AndroidResolutionMode> for ResolutionMode {
+    fn from(value: AndroidResolutionMode) -> Self {
+        match value {
+            AndroidResolutionMode::Tiny => ResolutionMode::Tiny,
+            AndroidResolutionMode::Small => ResolutionMode::Small,
+            AndroidResolutionMode::Base => ResolutionMode::Base,
+            AndroidResolutionMode::Large => ResolutionMode::Large,
+            AndroidResolutionMode::Gundam => ResolutionMode::Gundam,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, uniffi::Enum)]
+pub enum AndroidOcrTask {
+    Ocr,
+    Markdown,
+    Grounding,
+    Locate,
+    Table,
+    Formula,
+    Chart,
+    Figure,
+}
+
+impl From<AndroidOcrTask> for OcrTask {
+    fn from(value: AndroidOcrTask) -> Self {
+        match value {
+            AndroidOcrTask::Ocr => OcrTask::Ocr,
+            AndroidOcrTask::Markdown => OcrTask::Markdown,
+            AndroidOcrTask::Grounding => OcrTask::Grounding,
+            AndroidOcrTask::Locate => OcrTask::Locate,
+            AndroidOcrTask::Table => OcrTask::Table,
+            AndroidOcrTask::Formula => OcrTask::Formula,
+            AndroidOcrTask::Chart => OcrTask::Chart,
+            AndroidOcrTask::Figure => OcrTask::Figure,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, uniffi::Enum)]
+pub enum AndroidOrientationMode {
+    Off,
+    #[default]
+    Exif,
+    Auto,
+}
+
+impl From<AndroidOrientationMode> for OrientationMode {
+    fn from(value: AndroidOrientationMode) -> Self {
+        match value {
+            AndroidOrientationMode::Off => OrientationMode::Off,
+            AndroidOrientationMode::Exif => OrientationMode::Exif,
+            AndroidOrientationMode::Auto => OrientationMode::Auto,
+        }
+    }
+}
+
 #[derive(Clone, Debug, uniffi::Record)]
 pub struct AndroidModelPaths {
     pub kind: AndroidModelKind,
@@ -39,9 +107,13 @@ pub struct AndroidModelPaths {
 
 #[derive(Clone, Debug, uniffi::Record)]
 pub struct AndroidInferenceOptions {
+    /// Resolution preset; when set, `base_size`, `image_size` and `crop_mode` are ignored.
+    pub resolution: Option<AndroidResolutionMode>,
     pub base_size: u32,
     pub image_size: u32,
     pub crop_mode: bool,
+    /// EXIF handling and rotation detection applied when images are decoded.
+    pub orientation: AndroidOrientationMode,
     pub max_new_tokens: u32,
     pub use_cache: bool,
     pub do_sample: bool,
@@ -53,14 +125,19 @@ pub struct AndroidInferenceOptions {
     pub seed: Option<u64>,
     pub template: String,
     pub system_prompt: Option<String>,
+    /// Task preset; when set, the prompt is rendered for the model and the prompt passed to
+    /// `android_run_ocr` is only used as the text to find for `Locate`.
+    pub task: Option<AndroidOcrTask>,
 }
 
 impl Default for AndroidInferenceOptions {
     fn default() -> Self {
         Self {
+            resolution: None,
             base_size: 1024,
             image_size: 640,
             crop_mode: true,
+            orientation: AndroidOrientationMode::default(),
             max_new_tokens: 512,
             use_cache: true,
             do_sample: false,
@@ -72,6 +149,7 @@ impl Default for AndroidInferenceOptions {
             seed: None,
             template: "plain".to_string(),
             system_prompt: None,
+            task: None,
         }
     }
 }
@@ -163,7 +241,8 @@ pub fn android_run_ocr(
     stop_handle: Option<Arc<AndroidStopHandle>>,
 ) -> Result<String, AndroidOcrError> {
     let _scoped_logger = ScopedLogCallback::install(log_callback);
-    let decoded_images = decode_images(images).map_err(AndroidOcrError::from)?;
+    let decoded_images = decode_images(images, config.inference.orientation.into())
+        .map_err(AndroidOcrError::from)?;
     let args = EngineArgs::try_from(config).map_err(AndroidOcrError::from)?;
     let engine = AndroidOcrEngine::new(args).map_err(AndroidOcrError::from)?;
     let progress_callback = progress_callback.map(|cb| Arc::from(cb));
@@ -179,15 +258,37 @@ pub fn android_run_ocr(
     Ok(outcome.text)
 }
 
-fn decode_images(inputs: Vec<AndroidImageInput>) -> Result<Vec<DynamicImage>> {
-    inputs
-        .into_iter()
-        .enumerate()
-        .map(|(idx, input)| {
-            image::load_from_memory(&input.data)
-                .with_context(|| format!("failed to decode image #{idx}"))
-        })
-        .collect()
+/// Decode every input, expanding multi-page TIFFs into one image (and prompt slot) per page.
+fn decode_images(
+    inputs: Vec<AndroidImageInput>,
+    orientation: OrientationMode,
+) -> Result<Vec<DynamicImage>> {
+    let mut images = Vec::with_capacity(inputs.len());
+    for (idx, input) in inputs.into_iter().enumerate() {
+        let pages = decode_pages(&input.data, orientation)
+            .with_context(|| format!("failed to decode image #{idx}"))?;
+        if pages.len() > 1 {
+            dispatch_log(
+                AndroidLogLevel::Info,
+                &format!("image #{idx} expanded into {} pages", pages.len()),
+            );
+        }
+        for oriented in pages {
+            let report = oriented.report;
+            if report.exif_orientation.is_some() || report.rotation != Rotation::None {
+                dispatch_log(
+                    AndroidLogLevel::Info,
+                    &format!(
+                        "image #{idx} reoriented (exif={:?}, rotated {}° clockwise)",
+                        report.exif_orientation,
+                        report.rotation.degrees()
+                    ),
+                );
+            }
+            images.push(oriented.image);
+        }
+    }
+    Ok(images)
 }
 
 impl TryFrom<AndroidRunConfig> for EngineArgs {
@@ -203,9 +304,11 @@ impl TryFrom<AndroidRunConfig> for EngineArgs {
         };
 
         let AndroidInferenceOptions {
+            resolution,
             base_size,
             image_size,
             crop_mode,
+            orientation: _,
             max_new_tokens,
             use_cache,
             do_sample,
@@ -217,6 +320,7 @@ impl TryFrom<AndroidRunConfig> for EngineArgs {
             seed,
             template,
             system_prompt,
+            task,
         } = inference;
 
         let template_value = if template.is_empty() {
@@ -229,10 +333,14 @@ impl TryFrom<AndroidRunConfig> for EngineArgs {
             other => Some(other.to_string()),
         });
 
-        let vision = VisionSettings {
-            base_size,
-            image_size,
-            crop_mode,
+        let vision = match resolution {
+            Some(mode) => ResolutionMode::from(mode).settings(),
+            None => VisionSettings {
+                base_size,
+                image_size,
+                crop_mode,
+                ..VisionSettings::default()
+            },
         };
         let decode = DecodeParameters {
             max_new_tokens: max_new_tokens as usize,
@@ -244,6 +352,7 @@ impl TryFrom<AndroidRunConfig> for EngineArgs {
             no_repeat_ngram_size: no_repeat_ngram_size.map(|value| value as usize),
             seed,
             use_cache,
+            constraint: None,
         };
 
         Ok(EngineArgs {
@@ -251,6 +360,7 @@ impl TryFrom<AndroidRunConfig> for EngineArgs {
             settings: EngineSettings {
                 template: template_value,
                 system_prompt: system_prompt_value,
+                task: task.map(Into::into),
                 vision,
                 decode,
             },
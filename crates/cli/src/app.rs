@@ -2,28 +2,43 @@ use std::{
     cell::{Cell, RefCell},
     convert::TryFrom,
     io::{self, Write},
+    path::{Path, PathBuf},
     rc::Rc,
     time::{Duration, Instant},
 };
 
 use anyhow::{Context, Result};
-use deepseek_ocr_config::{AppConfig, LocalFileSystem};
+use deepseek_ocr_config::{AppConfig, LocalFileSystem, ModelResources};
 use deepseek_ocr_core::{
-    ModelKind, ModelLoadArgs,
-    inference::{DecodeOutcome, DecodeParameters, VisionSettings, render_prompt},
-    runtime::{default_dtype_for_device, prepare_device_and_dtype},
+    ModelKind, ModelLoadArgs, OcrEngine,
+    cleanup::{CleanedImage, clean_document},
+    estimate::EstimateArgs,
+    export::{
+        OcrPage, OutputFormat, page_prompt,
+        pdf::{self, PageScan},
+    },
+    inference::{DecodeOutcome, DecodeParameters, ResolutionMode, VisionSettings},
+    layout::{LayoutConfig, LayoutMode},
+    orientation::{OrientedImage, Rotation, open_pages},
+    polish::{Polished, polish},
+    runtime::{default_dtype_for_device, planned_dtype, prepare_device_and_dtype},
+    segments::{decode_segmented, segment_image},
+    slicing::SliceConfig,
     streaming::DeltaTracker,
+    tables::{TableFormat, extract_tables},
 };
-use deepseek_ocr_infer_deepseek::load_model as load_deepseek_model;
-use deepseek_ocr_infer_paddleocr::load_model as load_paddle_model;
-use image::DynamicImage;
+use deepseek_ocr_infer_deepseek::{
+    estimate as estimate_deepseek, load_model as load_deepseek_model, vision::vision_token_count,
+};
+use deepseek_ocr_infer_paddleocr::{estimate as estimate_paddle, load_model as load_paddle_model};
+use image::{DynamicImage, GenericImageView};
 use tokenizers::Tokenizer;
 use tracing::info;
 
 use crate::{
-    args::Args,
-    bench,
-    prompt::load_prompt,
+    args::{Args, Command},
+    bench, commands,
+    prompt::{build_prompt, expand_image_slots},
     resources::{ensure_config_file, ensure_tokenizer_file, prepare_weights_path},
 };
 
@@ -33,106 +48,273 @@ struct StreamProgress {
     delta: DeltaTracker,
 }
 
-pub fn run(args: Args) -> Result<()> {
-    let quiet = args.quiet;
-    let bench_enabled = args.bench || args.bench_output.is_some();
-    let bench_session = bench::maybe_start(bench_enabled, args.bench_output.clone())?;
+/// Configuration with the command-line overrides applied, and the model config and tokenizer
+/// of the active model; the weights are loaded later, and only when needed.
+pub struct Setup {
+    pub fs: LocalFileSystem,
+    pub app_config: AppConfig,
+    pub resources: ModelResources,
+    pub config_path: PathBuf,
+    pub tokenizer: Tokenizer,
+}
 
-    let prompt_raw = load_prompt(&args)?;
+impl Setup {
+    pub fn load(args: &Args) -> Result<Self> {
+        let fs = LocalFileSystem::new("deepseek-ocr");
+        let (mut app_config, descriptor) = AppConfig::load_or_init(&fs, args.config.as_deref())?;
+        app_config += args;
+        app_config.normalise(&fs)?;
+        let resources = app_config.active_model_resources(&fs)?;
 
-    let fs = LocalFileSystem::new("deepseek-ocr");
-    let (mut app_config, descriptor) = AppConfig::load_or_init(&fs, args.config.as_deref())?;
-    app_config += &args;
-    app_config.normalise(&fs)?;
-    let resources = app_config.active_model_resources(&fs)?;
+        info!(
+            "Using configuration {} (active model `{}`)",
+            descriptor.location.display_with(&fs)?,
+            app_config.models.active
+        );
 
-    info!(
-        "Using configuration {} (active model `{}`)",
-        descriptor.location.display_with(&fs)?,
-        app_config.models.active
-    );
+        let config_path = ensure_config_file(&fs, &resources.config, resources.kind)?;
+        let tokenizer_path = ensure_tokenizer_file(&fs, &resources.tokenizer, resources.kind)?;
+        let tokenizer = Tokenizer::from_file(&tokenizer_path).map_err(|err| {
+            anyhow::anyhow!(
+                "failed to load tokenizer from {}: {err}",
+                tokenizer_path.display()
+            )
+        })?;
+        Ok(Self {
+            fs,
+            app_config,
+            resources,
+            config_path,
+            tokenizer,
+        })
+    }
 
-    let config_path = ensure_config_file(&fs, &resources.config, resources.kind)?;
-    let tokenizer_path = ensure_tokenizer_file(&fs, &resources.tokenizer, resources.kind)?;
-    let weights_path = prepare_weights_path(&fs, &resources.weights, resources.kind)?;
+    /// Load the weights of the active model onto the configured device.
+    pub fn load_engine(&self) -> Result<Box<dyn OcrEngine>> {
+        let inference = &self.app_config.inference;
+        let kind = self.resources.kind;
+        let weights_path = prepare_weights_path(&self.fs, &self.resources.weights, kind)?;
 
-    let (device, maybe_precision) =
-        prepare_device_and_dtype(app_config.inference.device, app_config.inference.precision)?;
-    let dtype = maybe_precision.unwrap_or_else(|| default_dtype_for_device(&device));
+        let (device, maybe_precision) =
+            prepare_device_and_dtype(inference.device, inference.precision)?;
+        let dtype = maybe_precision.unwrap_or_else(|| default_dtype_for_device(&device));
 
-    info!(
-        "Loading model `{}` (device={:?}, dtype={:?}) using config {}",
-        app_config.models.active,
-        device,
-        dtype,
-        config_path.display()
-    );
+        info!(
+            "Loading model `{}` (device={:?}, dtype={:?}) using config {}",
+            self.app_config.models.active,
+            device,
+            dtype,
+            self.config_path.display()
+        );
 
-    let load_start = Instant::now();
-    let load_args = ModelLoadArgs {
-        kind: resources.kind,
-        config_path: Some(&config_path),
-        weights_path: Some(&weights_path),
-        device: device.clone(),
-        dtype,
-    };
-    let model = match resources.kind {
-        ModelKind::Deepseek => load_deepseek_model(load_args)?,
-        ModelKind::PaddleOcrVl => load_paddle_model(load_args)?,
-    };
-    let load_elapsed = load_start.elapsed();
-    info!(
-        "Model ready in {:.2?} (kind={:?}, flash-attn: {}, weights={})",
-        load_elapsed,
-        model.kind(),
-        model.flash_attention_enabled(),
-        weights_path.display()
-    );
+        let load_start = Instant::now();
+        let load_args = ModelLoadArgs {
+            kind,
+            config_path: Some(&self.config_path),
+            weights_path: Some(&weights_path),
+            device,
+            dtype,
+            quantization: self.resources.quantization,
+            tile_encoding: inference.tile_encoding,
+        };
+        let model = match kind {
+            ModelKind::Deepseek => load_deepseek_model(load_args)?,
+            ModelKind::PaddleOcrVl => load_paddle_model(load_args)?,
+        };
+        info!(
+            "Model ready in {:.2?} (kind={:?}, flash-attn: {}, weights={})",
+            load_start.elapsed(),
+            model.kind(),
+            model.flash_attention_enabled(),
+            weights_path.display()
+        );
+        Ok(model)
+    }
 
-    let tokenizer = Tokenizer::from_file(&tokenizer_path).map_err(|err| {
-        anyhow::anyhow!(
-            "failed to load tokenizer from {}: {err}",
-            tokenizer_path.display()
-        )
-    })?;
+    /// Decoding options from the `[inference]` settings.
+    pub fn decode_parameters(&self) -> DecodeParameters {
+        let inference = &self.app_config.inference;
+        DecodeParameters {
+            max_new_tokens: inference.max_new_tokens,
+            do_sample: inference.do_sample,
+            temperature: inference.temperature,
+            top_p: if inference.top_p < 1.0 {
+                Some(inference.top_p)
+            } else {
+                None
+            },
+            top_k: inference.top_k,
+            repetition_penalty: inference.repetition_penalty,
+            no_repeat_ngram_size: inference.no_repeat_ngram_size,
+            seed: inference.seed,
+            use_cache: inference.use_cache,
+            constraint: None,
+        }
+    }
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let quiet = args.quiet;
+    let bench_enabled = args.bench || args.bench_output.is_some();
+    let bench_session = bench::maybe_start(bench_enabled, args.bench_output.clone())?;
+
+    let setup = Setup::load(&args)?;
+    if let Some(command) = &args.command {
+        return match command {
+            Command::Locate(locate) => commands::locate(&args, locate, &setup),
+            Command::Redact(redact) => commands::redact(&args, redact, &setup),
+            Command::Extract(extract) => commands::extract(&args, extract, &setup),
+        };
+    }
+    if let Some(path) = &args.tables {
+        TableFormat::from_path(path)?;
+    }
+    let decode_params = setup.decode_parameters();
+    let Setup {
+        app_config,
+        resources,
+        config_path,
+        tokenizer,
+        ..
+    } = &setup;
+
+    let document_output = args.output_format.is_document();
+    anyhow::ensure!(
+        !args.output_format.is_binary() || args.output.is_some(),
+        "--output-format {} requires --output",
+        args.output_format
+    );
 
-    let prompt_with_template = render_prompt(&app_config.inference.template, "", &prompt_raw)?;
+    let orientation = app_config.inference.orientation;
+    let mut pages = Vec::with_capacity(args.images.len());
+    let mut page_names = Vec::with_capacity(args.images.len());
+    let mut pages_per_file = Vec::with_capacity(args.images.len());
+    for path in &args.images {
+        let file_pages = open_pages(path, orientation)?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        page_names.extend(std::iter::repeat_n(name, file_pages.len()));
+        pages_per_file.push(file_pages.len());
+        if file_pages.len() > 1 {
+            info!(
+                "{} expanded into {} pages",
+                path.display(),
+                file_pages.len()
+            );
+        }
+        pages.extend(file_pages);
+    }
+    let prompt_with_template = expand_image_slots(
+        &build_prompt(
+            &args,
+            resources.kind,
+            &app_config.inference.template,
+            pages.len(),
+        )?,
+        &pages_per_file,
+    );
     let image_slots = prompt_with_template.matches("<image>").count();
     anyhow::ensure!(
-        image_slots == args.images.len(),
-        "prompt includes {image_slots} <image> tokens but {} image paths were provided",
+        image_slots == pages.len(),
+        "prompt includes {image_slots} <image> tokens but {} images were provided ({} paths; \
+         use one <image> per path or one per page of multi-page files)",
+        pages.len(),
         args.images.len()
     );
 
-    let images: Vec<DynamicImage> = args
-        .images
-        .iter()
-        .map(|path| {
-            image::open(path).with_context(|| format!("failed to open image at {}", path.display()))
-        })
-        .collect::<Result<Vec<_>>>()?;
+    let mut images: Vec<DynamicImage> = Vec::with_capacity(pages.len());
+    // PDF pages show the scans as read; every other output works on the cleaned images.
+    let mut scans = Vec::new();
+    for (idx, page) in pages.into_iter().enumerate() {
+        let input = (args.output_format == OutputFormat::Pdf).then(|| page.clone());
+        let OrientedImage { image, report, .. } = page;
+        if report.exif_orientation.is_some() || report.rotation != Rotation::None {
+            info!(
+                "Image #{idx} reoriented (exif={}, rotated {}° clockwise)",
+                report
+                    .exif_orientation
+                    .map(|tag| tag.to_string())
+                    .unwrap_or_else(|| "none".to_string()),
+                report.rotation.degrees()
+            );
+        }
+        let CleanedImage { image, report } = clean_document(image, &app_config.inference.cleanup);
+        if app_config.inference.cleanup.enabled() {
+            info!(
+                "Image #{idx} cleaned: skew={:.1}°, {}x{} -> {}x{}",
+                report.skew_degrees,
+                report.source_width,
+                report.source_height,
+                report.width,
+                report.height
+            );
+        }
+        if let Some(input) = input {
+            scans.push(PageScan::from_input(input, report));
+        }
+        images.push(image);
+    }
 
-    let vision_settings = VisionSettings {
-        base_size: app_config.inference.base_size,
-        image_size: app_config.inference.image_size,
-        crop_mode: app_config.inference.crop_mode,
-    };
-    let decode_params = DecodeParameters {
-        max_new_tokens: app_config.inference.max_new_tokens,
-        do_sample: app_config.inference.do_sample,
-        temperature: app_config.inference.temperature,
-        top_p: if app_config.inference.top_p < 1.0 {
-            Some(app_config.inference.top_p)
-        } else {
-            None
-        },
-        top_k: app_config.inference.top_k,
-        repetition_penalty: app_config.inference.repetition_penalty,
-        no_repeat_ngram_size: app_config.inference.no_repeat_ngram_size,
-        seed: app_config.inference.seed,
-        use_cache: app_config.inference.use_cache,
-    };
+    let vision_settings = app_config.inference.vision_settings();
+    if resources.kind == ModelKind::Deepseek {
+        let mode = ResolutionMode::from_settings(vision_settings)
+            .map(|mode| mode.to_string())
+            .unwrap_or_else(|| "custom".to_string());
+        for (idx, image) in images.iter().enumerate() {
+            let (width, height) = image.dimensions();
+            info!(
+                "Image #{idx} ({width}x{height}) -> {} vision tokens (resolution={mode})",
+                vision_token_count(width, height, vision_settings)
+            );
+        }
+    }
 
+    if args.dry_run {
+        let estimate_args = EstimateArgs {
+            config_path: Some(config_path),
+            tokenizer,
+            prompt: &prompt_with_template,
+            images: &images,
+            vision: vision_settings,
+            max_new_tokens: app_config.inference.max_new_tokens,
+            dtype: planned_dtype(app_config.inference.device, app_config.inference.precision),
+        };
+        let report = match resources.kind {
+            ModelKind::Deepseek => estimate_deepseek(estimate_args)?,
+            ModelKind::PaddleOcrVl => estimate_paddle(estimate_args)?,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let model = setup.load_engine()?;
+
+    let slicing = app_config.inference.slicing;
+    let layout = app_config.inference.layout;
+    let segmenting = slicing.enabled || layout.mode != LayoutMode::Off;
+    let segmented = match images.as_slice() {
+        _ if document_output => None,
+        [image] if segmenting => {
+            Some(segment_image(image.clone(), &layout, &slicing)).filter(|s| s.is_split())
+        }
+        _ => None,
+    };
+    if let Some(segmented) = &segmented {
+        info!(
+            "Image #0 ({}x{}) split into {} {}",
+            segmented.width,
+            segmented.height,
+            segmented.segments.len(),
+            if segmented.overlapping {
+                "overlapping slices"
+            } else {
+                "layout units"
+            }
+        );
+    } else if segmenting && images.len() > 1 && !document_output {
+        info!("Layout split and slicing skipped: they only apply to prompts with a single image");
+    }
     let tokenizer_for_stream = tokenizer.clone();
     let progress_state = Rc::new(RefCell::new(StreamProgress::default()));
     let stream_state = Rc::clone(&progress_state);
@@ -140,6 +322,10 @@ pub fn run(args: Args) -> Result<()> {
     let prefill_duration_cell = Rc::new(Cell::new(None::<Duration>));
     let start_time_for_cb = Rc::clone(&start_time_cell);
     let prefill_duration_for_cb = Rc::clone(&prefill_duration_cell);
+    let polish_config = app_config.inference.polish.for_task(args.task);
+    let polishing = polish_config.enabled() && !document_output;
+    // Polish rewrites the finished text, so it is printed whole once generation is done.
+    let streaming = args.output.is_none() && !document_output && !polishing;
     let stdout = Rc::new(RefCell::new(io::stdout()));
     let stdout_handle = Rc::clone(&stdout);
     let progress_callback = move |count: usize, ids: &[i64]| {
@@ -178,7 +364,7 @@ pub fn run(args: Args) -> Result<()> {
             state.last_count = count;
         }
 
-        if let Some(delta) = delta_to_emit {
+        if let Some(delta) = delta_to_emit.filter(|_| streaming) {
             let mut handle = stdout_handle.borrow_mut();
             let _ = write!(handle, "{}", delta);
             let _ = handle.flush();
@@ -197,17 +383,45 @@ pub fn run(args: Args) -> Result<()> {
     info!("--- Generation start ---");
     let gen_start = Instant::now();
     start_time_cell.set(Some(gen_start));
-    let outcome = model
-        .decode(
-            &tokenizer,
+    let mut page_texts = Vec::new();
+    let outcome = match &segmented {
+        // Pages of a document are decoded one by one so that every page gets its own boxes.
+        None if document_output => decode_pages(
+            model.as_ref(),
+            tokenizer,
+            &page_prompt(&prompt_with_template)?,
+            &images,
+            (&layout, &slicing),
+            vision_settings,
+            &decode_params,
+        )
+        .map(|(outcome, texts)| {
+            page_texts = texts;
+            outcome
+        }),
+        // Segments are decoded one after another without streaming; the merged text is printed
+        // once every segment is done.
+        Some(segmented) => decode_segmented(
+            model.as_ref(),
+            tokenizer,
+            &prompt_with_template,
+            segmented,
+            vision_settings,
+            &decode_params,
+            None,
+            None,
+        ),
+        None => model.decode(
+            tokenizer,
             &prompt_with_template,
             &images,
             vision_settings,
             &decode_params,
             callback_holder.as_deref(),
             None,
-        )
-        .context("generation failed")?;
+        ),
+    }
+    .context("generation failed")?;
     let elapsed = gen_start.elapsed();
     info!("--- Generation done in {:.2?} ---", elapsed);
 
@@ -217,6 +431,23 @@ pub fn run(args: Args) -> Result<()> {
         response_tokens,
         generated_tokens,
     } = outcome;
+    let text = if polishing {
+        let Polished {
+            text,
+            formula_issues,
+        } = polish(&normalized, &polish_config);
+        for issue in &formula_issues {
+            let state = if issue.repaired {
+                "repaired"
+            } else {
+                "left as is"
+            };
+            info!("Formula {}: {} ({state})", issue.formula + 1, issue.message);
+        }
+        text
+    } else {
+        normalized.clone()
+    };
 
     info!(
         "Prompt prepared: {} tokens ({} image slots)",
@@ -234,17 +465,54 @@ pub fn run(args: Args) -> Result<()> {
         )
         .unwrap_or_default();
 
-    let final_delta = {
+    let final_delta = if segmented.is_some() || polishing {
+        text.clone()
+    } else {
         let mut state = progress_state.borrow_mut();
         state.last_count = generated_tokens.len();
         state.delta.advance(&decoded, true)
     };
-    if !final_delta.is_empty() {
+    let document = if document_output {
+        let pages: Vec<OcrPage> = images
+            .iter()
+            .zip(&page_texts)
+            .zip(page_names)
+            .map(|((image, text), name)| {
+                let (width, height) = image.dimensions();
+                let page = OcrPage::from_grounded(text, width, height)
+                    .with_reading_order(app_config.inference.reading_order);
+                match name {
+                    Some(name) => page.with_image_name(name),
+                    None => page,
+                }
+            })
+            .collect();
+        Some(match args.output_format {
+            OutputFormat::Pdf => pdf::render(&pages, &scans)?,
+            format => format.render(&pages, &images)?,
+        })
+    } else {
+        None
+    };
+    if let Some(path) = &args.output {
+        let bytes = document.unwrap_or_else(|| text.clone().into_bytes());
+        std::fs::write(path, bytes)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        info!("Wrote {} output to {}", args.output_format, path.display());
+    } else if let Some(document) = document {
+        let mut handle = stdout.borrow_mut();
+        let _ = handle.write_all(&document);
+        let _ = handle.flush();
+    } else if !final_delta.is_empty() {
         let mut handle = stdout.borrow_mut();
         let _ = write!(handle, "{}", final_delta);
         let _ = handle.flush();
     }
-    info!("Final output:\n{normalized}");
+    info!("Final output:\n{text}");
+    // Tables come from the raw output, whose grounding boxes polish may strip.
+    if let Some(path) = &args.tables {
+        write_tables(path, &normalized)?;
+    }
 
     {
         let total_elapsed = elapsed;
@@ -280,3 +548,94 @@ pub fn run(args: Args) -> Result<()> {
 
     Ok(())
 }
+
+/// Decode each image on its own with a single-slot `prompt`, applying layout splitting and
+/// slicing per page. Returns the combined outcome and the text of every page.
+fn decode_pages(
+    model: &dyn OcrEngine,
+    tokenizer: &Tokenizer,
+    prompt: &str,
+    images: &[DynamicImage],
+    (layout, slicing): (&LayoutConfig, &SliceConfig),
+    vision: VisionSettings,
+    params: &DecodeParameters,
+) -> Result<(DecodeOutcome, Vec<String>)> {
+    let segmenting = slicing.enabled || layout.mode != LayoutMode::Off;
+    let mut combined = DecodeOutcome {
+        text: String::new(),
+        prompt_tokens: 0,
+        response_tokens: 0,
+        generated_tokens: Vec::new(),
+    };
+    let mut texts = Vec::with_capacity(images.len());
+    for (idx, image) in images.iter().enumerate() {
+        let segmented = Some(image)
+            .filter(|_| segmenting)
+            .map(|image| segment_image(image.clone(), layout, slicing))
+            .filter(|segmented| segmented.is_split());
+        let outcome = match &segmented {
+            Some(segmented) => decode_segmented(
+                model, tokenizer, prompt, segmented, vision, params, None, None,
+            )?,
+            None => model.decode(
+                tokenizer,
+                prompt,
+                std::slice::from_ref(image),
+                vision,
+                params,
+                None,
+                None,
+            )?,
+        };
+        info!("Page #{idx} decoded: {} tokens", outcome.response_tokens);
+        if !combined.text.is_empty() {
+            combined.text.push_str("\n\n");
+        }
+        combined.text.push_str(&outcome.text);
+        combined.prompt_tokens += outcome.prompt_tokens;
+        combined.response_tokens += outcome.response_tokens;
+        combined.generated_tokens.extend(outcome.generated_tokens);
+        texts.push(outcome.text);
+    }
+    Ok((combined, texts))
+}
+
+/// Write the tables found in `text` to `path`, in the format its extension names. CSV holds one
+/// table per file, so several tables go to `<stem>-1.csv`, `<stem>-2.csv`, ...
+fn write_tables(path: &Path, text: &str) -> Result<()> {
+    let format = TableFormat::from_path(path)?;
+    let tables = extract_tables(text);
+    if tables.is_empty() {
+        info!("No tables found; {} not written", path.display());
+        return Ok(());
+    }
+    let files = format.render(&tables)?;
+    let numbered = files.len() > 1;
+    for (idx, bytes) in files.into_iter().enumerate() {
+        let target = if numbered {
+            numbered_path(path, idx + 1)
+        } else {
+            path.to_path_buf()
+        };
+        std::fs::write(&target, bytes)
+            .with_context(|| format!("failed to write {}", target.display()))?;
+    }
+    info!(
+        "Wrote {} table(s) as {format} to {}",
+        tables.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn numbered_path(path: &Path, index: usize) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    path.with_file_name(format!("{stem}-{index}{extension}"))
+}
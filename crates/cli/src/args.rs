@@ -1,12 +1,26 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use deepseek_ocr_config::{AppConfig, ConfigOverride, ConfigOverrides};
-use deepseek_ocr_core::runtime::{DeviceKind, Precision};
+use deepseek_ocr_core::{
+    export::OutputFormat,
+    formula::{FormulaMode, MathDelimiters},
+    inference::ResolutionMode,
+    layout::LayoutMode,
+    orientation::OrientationMode,
+    pipeline::redact::RedactionArea,
+    quantization::{QuantizationKind, QuantizationTargets, VisionQuantization, VisionTower},
+    reading_order::ReadingOrder,
+    runtime::{DeviceKind, Precision},
+    task::OcrTask,
+};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "DeepSeek-OCR CLI", long_about = None)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Optional path to a configuration file (defaults to platform config dir).
     #[arg(long, value_name = "PATH", help_heading = "Application")]
     pub config: Option<PathBuf>,
@@ -19,7 +33,8 @@ pub struct Args {
     #[arg(long, value_name = "PATH", help_heading = "Application")]
     pub model_config: Option<PathBuf>,
 
-    /// Prompt text. Use `<image>` tokens to denote image slots.
+    /// Prompt text. Use `<image>` tokens to denote image slots. With `--task locate`, the text
+    /// to find instead.
     #[arg(long, conflicts_with = "prompt_file")]
     pub prompt: Option<String>,
 
@@ -27,6 +42,11 @@ pub struct Args {
     #[arg(long, value_name = "PATH", conflicts_with = "prompt")]
     pub prompt_file: Option<PathBuf>,
 
+    /// Render the model's own prompt for a task instead of passing `--prompt` through the
+    /// template, with one `<image>` slot per image.
+    #[arg(long, value_enum, value_name = "TASK", conflicts_with = "prompt_file")]
+    pub task: Option<OcrTask>,
+
     /// Conversation template name (plain/deepseek/deepseekv2/alignment).
     #[arg(long, help_heading = "Inference")]
     pub template: Option<String>,
@@ -51,6 +71,32 @@ pub struct Args {
     #[arg(long, help_heading = "Inference")]
     pub dtype: Option<Precision>,
 
+    /// Weight quantization for the selected model (none/q8_0/q4_k). CPU only.
+    #[arg(long, help_heading = "Inference")]
+    pub quant: Option<QuantizationKind>,
+
+    /// Which linear layers to quantize (text/text+projector).
+    #[arg(long, value_name = "TARGETS", help_heading = "Inference")]
+    pub quant_targets: Option<QuantizationTargets>,
+
+    /// Vision towers to quantize as well (comma separated: sam,clip,siglip).
+    #[arg(
+        long,
+        value_name = "TOWERS",
+        value_delimiter = ',',
+        help_heading = "Inference"
+    )]
+    pub quant_vision: Option<Vec<VisionTower>>,
+
+    /// Named resolution preset (tiny/small/base/large/gundam); replaces the raw size flags.
+    #[arg(
+        long,
+        value_name = "MODE",
+        conflicts_with_all = ["base_size", "image_size", "crop_mode"],
+        help_heading = "Inference"
+    )]
+    pub resolution: Option<ResolutionMode>,
+
     /// Global view resolution (defaults to 1024).
     #[arg(long, help_heading = "Inference")]
     pub base_size: Option<u32>,
@@ -63,6 +109,81 @@ pub struct Args {
     #[arg(long, help_heading = "Inference")]
     pub crop_mode: Option<bool>,
 
+    /// Input image orientation handling: off, exif (default) or auto (EXIF plus rotation detection).
+    #[arg(long, value_name = "MODE", help_heading = "Inference")]
+    pub orientation: Option<OrientationMode>,
+
+    /// Crop tiles encoded per SAM/CLIP forward pass (defaults to 1).
+    #[arg(long, value_name = "TILES", help_heading = "Inference")]
+    pub tile_chunk_size: Option<usize>,
+
+    /// Worker threads for crop-tile encoding (defaults to the global rayon pool).
+    #[arg(long, value_name = "THREADS", help_heading = "Inference")]
+    pub tile_threads: Option<usize>,
+
+    /// Memory budget for concurrently encoded tiles in MiB (defaults to 2048, 0 = unlimited).
+    #[arg(long, value_name = "MIB", help_heading = "Inference")]
+    pub tile_memory_mb: Option<u64>,
+
+    /// Straighten skewed scans before encoding (true/false).
+    #[arg(long, value_name = "BOOL", help_heading = "Document cleanup")]
+    pub deskew: Option<bool>,
+
+    /// Trim scanner borders and blank margins around the content (true/false).
+    #[arg(long, value_name = "BOOL", help_heading = "Document cleanup")]
+    pub crop_margins: Option<bool>,
+
+    /// Stretch contrast so faint scans reach full black and white (true/false).
+    #[arg(long, value_name = "BOOL", help_heading = "Document cleanup")]
+    pub normalize_contrast: Option<bool>,
+
+    /// Reduce pages to pure black and white (true/false).
+    #[arg(long, value_name = "BOOL", help_heading = "Document cleanup")]
+    pub binarize: Option<bool>,
+
+    /// OCR very tall images (receipts, long screenshots) as overlapping slices (true/false).
+    #[arg(long, value_name = "BOOL", help_heading = "Document cleanup")]
+    pub slice_tall: Option<bool>,
+
+    /// Fraction of each slice shared with the next one (defaults to 0.15).
+    #[arg(long, value_name = "FRACTION", help_heading = "Document cleanup")]
+    pub slice_overlap: Option<f32>,
+
+    /// Split two-page spreads or multi-column pages before OCR: off (default), spread or columns.
+    #[arg(long, value_name = "MODE", help_heading = "Document cleanup")]
+    pub split_layout: Option<LayoutMode>,
+
+    /// Remove `<|ref|>`/`<|det|>` grounding markup, keeping the text it labels. Kept for
+    /// `--task grounding` and `--task locate`.
+    #[arg(long, value_name = "BOOL", help_heading = "Markdown polish")]
+    pub strip_grounding: Option<bool>,
+
+    /// Replace HTML tables without spanning cells by GitHub pipe tables.
+    #[arg(long, value_name = "BOOL", help_heading = "Markdown polish")]
+    pub markdown_tables: Option<bool>,
+
+    /// Merge words hyphenated across a line break.
+    #[arg(long, value_name = "BOOL", help_heading = "Markdown polish")]
+    pub merge_hyphenation: Option<bool>,
+
+    /// Join paragraphs split mid-sentence where tiles or slices meet.
+    #[arg(long, value_name = "BOOL", help_heading = "Markdown polish")]
+    pub join_paragraphs: Option<bool>,
+
+    /// Start headings at `#` and close skipped heading levels.
+    #[arg(long, value_name = "BOOL", help_heading = "Markdown polish")]
+    pub fix_headings: Option<bool>,
+
+    /// Rewrite formulas in the output: off (default), latex to normalise delimiters and close
+    /// truncated formulas, or mathml to replace them with MathML (`mathml` feature). Disables
+    /// streaming.
+    #[arg(long, value_name = "MODE", help_heading = "Markdown polish")]
+    pub polish_formulas: Option<FormulaMode>,
+
+    /// Delimiters for normalised LaTeX: dollar (`$`/`$$`, default) or bracket (`\(`/`\[`).
+    #[arg(long, value_name = "STYLE", help_heading = "Markdown polish")]
+    pub math_delimiters: Option<MathDelimiters>,
+
     /// Maximum number of tokens to generate.
     #[arg(long, help_heading = "Inference")]
     pub max_new_tokens: Option<usize>,
@@ -110,6 +231,105 @@ pub struct Args {
     /// Quiet mode - output only the final result without logs or progress.
     #[arg(short, long, help_heading = "Application")]
     pub quiet: bool,
+
+    /// Output format: the model text as generated, or an hOCR/ALTO/PDF/markdown/HTML/DOCX
+    /// document built from the layout blocks once every page has been decoded. PDF and DOCX
+    /// require `--output`.
+    #[arg(
+        long,
+        visible_alias = "format",
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = OutputFormat::Text,
+        help_heading = "Application"
+    )]
+    pub output_format: OutputFormat,
+
+    /// Block order of page documents: layout (default) rebuilds it from the boxes, reading
+    /// columns in turn and keeping running headers and footers apart; native keeps the order
+    /// the model emitted.
+    #[arg(long, value_name = "ORDER", help_heading = "Application")]
+    pub reading_order: Option<ReadingOrder>,
+
+    /// Write the result to a file instead of streaming it to stdout.
+    #[arg(short, long, value_name = "PATH", help_heading = "Application")]
+    pub output: Option<PathBuf>,
+
+    /// Extract the tables of the output to a `.csv`, `.json` or `.xlsx` file; several tables
+    /// written as CSV go to numbered files next to PATH.
+    #[arg(long, value_name = "PATH", help_heading = "Application")]
+    pub tables: Option<PathBuf>,
+
+    /// Print per-image vision tokens, prompt size and a memory estimate as JSON, then exit
+    /// without loading weights.
+    #[arg(long, help_heading = "Application")]
+    pub dry_run: bool,
+}
+
+/// Tasks that print structured results instead of the model text. Model, device and
+/// inference flags go before the command name.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Find a text in an image and print the pixel boxes of its matches as JSON.
+    Locate(LocateArgs),
+    /// Black out personal data found by grounded OCR and print an audit of what was removed.
+    Redact(RedactArgs),
+    /// Read the fields of a form, described by a JSON schema, and print their typed values.
+    Extract(ExtractArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct LocateArgs {
+    /// Text to find.
+    pub query: String,
+
+    /// Image to search; boxes refer to it as turned upright by `--orientation`.
+    #[arg(long, value_name = "PATH")]
+    pub image: PathBuf,
+}
+
+/// Rules given here are added to the `[redaction]` section of the configuration.
+#[derive(clap::Args, Debug)]
+pub struct RedactArgs {
+    /// Image to redact.
+    #[arg(long, value_name = "PATH")]
+    pub image: PathBuf,
+
+    /// Where to write the redacted image; the format follows the extension.
+    #[arg(long, value_name = "PATH")]
+    pub redacted: PathBuf,
+
+    /// Redact matches of a regular expression, reported under NAME (repeatable).
+    #[arg(long = "pattern", value_name = "NAME=REGEX")]
+    pub patterns: Vec<String>,
+
+    /// Redact the terms of a word list, one per line, reported under the file stem
+    /// (repeatable). Blank lines and lines starting with `#` are skipped.
+    #[arg(long = "dictionary", value_name = "PATH")]
+    pub dictionaries: Vec<PathBuf>,
+
+    /// Redact numbers that pass the Luhn check as card numbers (true/false).
+    #[arg(long, value_name = "BOOL")]
+    pub card_numbers: Option<bool>,
+
+    /// Pixels added around every redacted box.
+    #[arg(long, value_name = "PX")]
+    pub padding: Option<u32>,
+
+    /// Black out every line a match touches, or only its interpolated word boxes.
+    #[arg(long, value_enum)]
+    pub area: Option<RedactionArea>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ExtractArgs {
+    /// Image of the form.
+    #[arg(long, value_name = "PATH")]
+    pub image: PathBuf,
+
+    /// JSON schema of the fields: `{"fields": [{"name", "type", "labels", "pattern"}]}`.
+    #[arg(long, value_name = "PATH")]
+    pub schema: PathBuf,
 }
 
 impl From<&Args> for ConfigOverrides {
@@ -120,12 +340,20 @@ impl From<&Args> for ConfigOverrides {
         overrides.model_config = args.model_config.clone();
         overrides.tokenizer = args.tokenizer.clone();
         overrides.weights = args.weights.clone();
+        overrides.quantization.kind = args.quant;
+        overrides.quantization.targets = args.quant_targets;
+        overrides.quantization.vision = args
+            .quant_vision
+            .as_deref()
+            .map(VisionQuantization::from_towers);
         overrides.inference.device = args.device;
         overrides.inference.precision = args.dtype;
         overrides.inference.template = args.template.clone();
+        overrides.inference.resolution = args.resolution;
         overrides.inference.base_size = args.base_size;
         overrides.inference.image_size = args.image_size;
         overrides.inference.crop_mode = args.crop_mode;
+        overrides.inference.orientation = args.orientation;
         overrides.inference.max_new_tokens = args.max_new_tokens;
         if args.no_cache {
             overrides.inference.use_cache = Some(false);
@@ -137,6 +365,24 @@ impl From<&Args> for ConfigOverrides {
         overrides.inference.repetition_penalty = args.repetition_penalty;
         overrides.inference.no_repeat_ngram_size = args.no_repeat_ngram_size;
         overrides.inference.seed = args.seed;
+        overrides.inference.tile_chunk_size = args.tile_chunk_size;
+        overrides.inference.tile_threads = args.tile_threads;
+        overrides.inference.tile_memory_mb = args.tile_memory_mb;
+        overrides.inference.deskew = args.deskew;
+        overrides.inference.crop_margins = args.crop_margins;
+        overrides.inference.normalize_contrast = args.normalize_contrast;
+        overrides.inference.binarize = args.binarize;
+        overrides.inference.slice_tall = args.slice_tall;
+        overrides.inference.slice_overlap = args.slice_overlap;
+        overrides.inference.split_layout = args.split_layout;
+        overrides.inference.strip_grounding = args.strip_grounding;
+        overrides.inference.markdown_tables = args.markdown_tables;
+        overrides.inference.merge_hyphenation = args.merge_hyphenation;
+        overrides.inference.join_paragraphs = args.join_paragraphs;
+        overrides.inference.fix_headings = args.fix_headings;
+        overrides.inference.polish_formulas = args.polish_formulas;
+        overrides.inference.math_delimiters = args.math_delimiters;
+        overrides.inference.reading_order = args.reading_order;
         overrides
     }
 }
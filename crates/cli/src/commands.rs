@@ -0,0 +1,176 @@
+//! Subcommands that run one typed pipeline task and print its result as JSON.
+
+use std::{fs, io::Write, path::Path};
+
+use anyhow::{Context, Result, anyhow, ensure};
+use deepseek_ocr_core::{
+    orientation::open_pages,
+    pipeline::{
+        extract::{ExtractionSchema, Extractor, extract as extract_fields},
+        locate::locate as locate_text,
+        redact::{DictionaryRule, PatternRule, RedactionRules, Redactor, redact as redact_image},
+    },
+};
+use image::{DynamicImage, GenericImageView};
+use serde::Serialize;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::{
+    app::Setup,
+    args::{Args, ExtractArgs, LocateArgs, RedactArgs},
+};
+
+/// Search `args.image` for `args.query` and print `{query, width, height, boxes}`.
+pub fn locate(global: &Args, args: &LocateArgs, setup: &Setup) -> Result<()> {
+    let image = open_single_page(&args.image, setup)?;
+    let (width, height) = image.dimensions();
+    let model = setup.load_engine()?;
+    let boxes = locate_text(
+        model.as_ref(),
+        &setup.tokenizer,
+        &image,
+        &args.query,
+        setup.app_config.inference.vision_settings(),
+        &setup.decode_parameters(),
+    )?;
+    info!("Found {} match(es) for {:?}", boxes.len(), args.query);
+    let result = json!({
+        "query": args.query,
+        "width": width,
+        "height": height,
+        "boxes": boxes,
+    });
+    write_json(global.output.as_deref(), &result)
+}
+
+/// Black out what the redaction rules match in `args.image`, save the result to
+/// `args.redacted` and print the audit `{width, height, complete, truncated, redactions}`.
+pub fn redact(global: &Args, args: &RedactArgs, setup: &Setup) -> Result<()> {
+    let rules = redaction_rules(args, setup.app_config.redaction.clone())?;
+    let redactor = Redactor::new(&rules)?;
+    let image = open_single_page(&args.image, setup)?;
+    let model = setup.load_engine()?;
+    let redacted = redact_image(
+        model.as_ref(),
+        &setup.tokenizer,
+        &image,
+        &redactor,
+        setup.app_config.inference.vision_settings(),
+        &setup.decode_parameters(),
+    )?;
+    redacted
+        .image
+        .save(&args.redacted)
+        .with_context(|| format!("failed to write {}", args.redacted.display()))?;
+    let report = &redacted.report;
+    info!(
+        "Redacted {} match(es) into {}",
+        report.redactions.len(),
+        args.redacted.display()
+    );
+    let unplaced = report.unplaced();
+    if unplaced > 0 {
+        warn!(
+            "{unplaced} match(es) had no grounding box; the band assumed for their text was \
+             blacked out but may have missed it"
+        );
+    }
+    if report.truncated {
+        warn!("Decoding hit --max-new-tokens; text past it was not checked");
+    }
+    write_json(global.output.as_deref(), report)
+}
+
+/// The configured rules with the ones given on the command line added.
+fn redaction_rules(args: &RedactArgs, mut rules: RedactionRules) -> Result<RedactionRules> {
+    for pattern in &args.patterns {
+        let (name, regex) = pattern
+            .split_once('=')
+            .ok_or_else(|| anyhow!("--pattern expects NAME=REGEX, got `{pattern}`"))?;
+        rules.patterns.push(PatternRule {
+            name: name.trim().to_owned(),
+            regex: regex.to_owned(),
+        });
+    }
+    for path in &args.dictionaries {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read dictionary {}", path.display()))?;
+        let terms = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_owned)
+            .collect();
+        let name = path.file_stem().map_or_else(
+            || path.display().to_string(),
+            |stem| stem.to_string_lossy().into_owned(),
+        );
+        rules.dictionaries.push(DictionaryRule { name, terms });
+    }
+    if let Some(card_numbers) = args.card_numbers {
+        rules.card_numbers = card_numbers;
+    }
+    if let Some(padding) = args.padding {
+        rules.padding = padding;
+    }
+    if let Some(area) = args.area {
+        rules.area = area;
+    }
+    Ok(rules)
+}
+
+/// Read the fields of `args.schema` from `args.image` and print `{width, height, fields}`.
+pub fn extract(global: &Args, args: &ExtractArgs, setup: &Setup) -> Result<()> {
+    let schema = fs::read_to_string(&args.schema)
+        .with_context(|| format!("failed to read schema {}", args.schema.display()))?;
+    let schema: ExtractionSchema = serde_json::from_str(&schema)
+        .with_context(|| format!("invalid schema {}", args.schema.display()))?;
+    let extractor = Extractor::new(&schema)?;
+    let image = open_single_page(&args.image, setup)?;
+    let model = setup.load_engine()?;
+    let extraction = extract_fields(
+        model.as_ref(),
+        &setup.tokenizer,
+        &image,
+        &extractor,
+        setup.app_config.inference.vision_settings(),
+        &setup.decode_parameters(),
+    )?;
+    let found = extraction
+        .fields
+        .iter()
+        .filter(|field| field.value.is_some())
+        .count();
+    info!("Extracted {found} of {} field(s)", extraction.fields.len());
+    write_json(global.output.as_deref(), &extraction)
+}
+
+/// The one page of `path`, turned upright as configured.
+fn open_single_page(path: &Path, setup: &Setup) -> Result<DynamicImage> {
+    let mut pages = open_pages(path, setup.app_config.inference.orientation)?;
+    ensure!(
+        pages.len() == 1,
+        "{} has {} pages; pass a single page",
+        path.display(),
+        pages.len()
+    );
+    Ok(pages.remove(0).image)
+}
+
+/// Pretty-print `value` to `output`, or to stdout when no file is given.
+fn write_json(output: Option<&Path>, value: &impl Serialize) -> Result<()> {
+    let mut text = serde_json::to_string_pretty(value)?;
+    text.push('\n');
+    match output {
+        Some(path) => {
+            fs::write(path, text).with_context(|| format!("failed to write {}", path.display()))?
+        }
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(text.as_bytes())?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
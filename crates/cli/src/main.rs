@@ -1,6 +1,7 @@
 mod app;
 mod args;
 mod bench;
+mod commands;
 mod logging;
 mod prompt;
 mod resources;
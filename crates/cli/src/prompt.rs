@@ -1,10 +1,11 @@
 use std::fs;
 
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result, anyhow, ensure};
+use deepseek_ocr_core::{ModelKind, render_prompt, task::OcrTask};
 
 use crate::args::Args;
 
-pub fn load_prompt(args: &Args) -> Result<String> {
+fn load_prompt(args: &Args) -> Result<String> {
     if let Some(path) = &args.prompt_file {
         return fs::read_to_string(path)
             .with_context(|| format!("failed to read prompt file {}", path.display()))
@@ -14,6 +15,35 @@ pub fn load_prompt(args: &Args) -> Result<String> {
         return Ok(prompt.clone());
     }
     Err(anyhow!(
-        "prompt is required (use --prompt or --prompt-file)"
+        "prompt is required (use --prompt, --prompt-file or --task)"
     ))
 }
+
+/// Final prompt for `images` images: the `--task` preset when one is selected, otherwise the
+/// user prompt rendered through `template`.
+pub fn build_prompt(args: &Args, kind: ModelKind, template: &str, images: usize) -> Result<String> {
+    let Some(task) = args.task else {
+        return render_prompt(template, "", &load_prompt(args)?);
+    };
+    ensure!(
+        task == OcrTask::Locate || args.prompt.is_none(),
+        "--prompt is only used as the text to find with --task locate"
+    );
+    task.render_prompt(kind, images, args.prompt.as_deref())
+}
+
+/// Repeat the `<image>` slot of every input file once per page it decoded into, as the server
+/// does for multi-page TIFFs. Prompts whose slot count does not match the files are returned
+/// unchanged, so prompts that already count pages keep working.
+pub fn expand_image_slots(prompt: &str, pages_per_file: &[usize]) -> String {
+    let parts: Vec<&str> = prompt.split("<image>").collect();
+    if parts.len() != pages_per_file.len() + 1 {
+        return prompt.to_owned();
+    }
+    let mut expanded = parts[0].to_owned();
+    for (part, pages) in parts[1..].iter().zip(pages_per_file) {
+        expanded.push_str(&"<image>".repeat(*pages));
+        expanded.push_str(part);
+    }
+    expanded
+}
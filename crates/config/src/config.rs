@@ -8,7 +8,17 @@ use std::{
 use anyhow::{Context, Result, anyhow};
 use deepseek_ocr_core::{
     ModelKind,
+    cleanup::CleanupConfig,
+    formula::{FormulaMode, MathDelimiters},
+    inference::{ResolutionMode, TileEncodingConfig, VisionSettings},
+    layout::{LayoutConfig, LayoutMode},
+    orientation::OrientationMode,
+    pipeline::redact::RedactionRules,
+    polish::PolishConfig,
+    quantization::{QuantizationConfig, QuantizationKind, QuantizationTargets, VisionQuantization},
+    reading_order::ReadingOrder,
     runtime::{DeviceKind, Precision},
+    slicing::SliceConfig,
 };
 use serde::{Deserialize, Serialize};
 
@@ -23,6 +33,7 @@ pub struct AppConfig {
     pub models: ModelRegistry,
     pub inference: InferenceSettings,
     pub server: ServerSettings,
+    pub redaction: RedactionRules,
 }
 
 impl Default for AppConfig {
@@ -31,6 +42,7 @@ impl Default for AppConfig {
             models: ModelRegistry::default(),
             inference: InferenceSettings::default(),
             server: ServerSettings::default(),
+            redaction: RedactionRules::default(),
         }
     }
 }
@@ -53,6 +65,7 @@ impl Default for ModelRegistry {
                 config: None,
                 tokenizer: None,
                 weights: None,
+                quantization: None,
             },
         );
         Self {
@@ -69,6 +82,8 @@ pub struct ModelEntry {
     pub config: Option<PathBuf>,
     pub tokenizer: Option<PathBuf>,
     pub weights: Option<PathBuf>,
+    /// Quantization for this model; when absent the `DEEPSEEK_OCR_QUANT*` variables apply.
+    pub quantization: Option<QuantizationConfig>,
 }
 
 impl Default for ModelEntry {
@@ -78,6 +93,7 @@ impl Default for ModelEntry {
             config: None,
             tokenizer: None,
             weights: None,
+            quantization: None,
         }
     }
 }
@@ -88,9 +104,13 @@ pub struct InferenceSettings {
     pub device: DeviceKind,
     pub precision: Option<Precision>,
     pub template: String,
+    /// Named resolution preset; when set it takes precedence over the raw size fields below.
+    pub resolution: Option<ResolutionMode>,
     pub base_size: u32,
     pub image_size: u32,
     pub crop_mode: bool,
+    /// EXIF handling and rotation detection applied when input images are decoded.
+    pub orientation: OrientationMode,
     pub max_new_tokens: usize,
     pub use_cache: bool,
     pub do_sample: bool,
@@ -100,6 +120,17 @@ pub struct InferenceSettings {
     pub repetition_penalty: f32,
     pub no_repeat_ngram_size: Option<usize>,
     pub seed: Option<u64>,
+    pub tile_encoding: TileEncodingConfig,
+    /// Optional document clean-up (deskew, margin crop, contrast, binarisation) for input pages.
+    pub cleanup: CleanupConfig,
+    /// Opt-in slicing of very tall images into overlapping segments.
+    pub slicing: SliceConfig,
+    /// Opt-in splitting of two-page spreads and multi-column pages.
+    pub layout: LayoutConfig,
+    /// Opt-in markdown post-processing of the generated text.
+    pub polish: PolishConfig,
+    /// Order of the blocks in page documents.
+    pub reading_order: ReadingOrder,
 }
 
 impl Default for InferenceSettings {
@@ -108,9 +139,11 @@ impl Default for InferenceSettings {
             device: DeviceKind::Cpu,
             precision: None,
             template: "plain".to_string(),
+            resolution: None,
             base_size: 1024,
             image_size: 640,
             crop_mode: true,
+            orientation: OrientationMode::default(),
             max_new_tokens: 512,
             use_cache: true,
             do_sample: false,
@@ -120,6 +153,27 @@ impl Default for InferenceSettings {
             repetition_penalty: 1.0,
             no_repeat_ngram_size: Some(20),
             seed: None,
+            tile_encoding: TileEncodingConfig::default(),
+            cleanup: CleanupConfig::default(),
+            slicing: SliceConfig::default(),
+            layout: LayoutConfig::default(),
+            polish: PolishConfig::default(),
+            reading_order: ReadingOrder::default(),
+        }
+    }
+}
+
+impl InferenceSettings {
+    /// Vision settings after resolving the optional preset.
+    pub fn vision_settings(&self) -> VisionSettings {
+        match self.resolution {
+            Some(mode) => mode.settings(),
+            None => VisionSettings {
+                base_size: self.base_size,
+                image_size: self.image_size,
+                crop_mode: self.crop_mode,
+                ..VisionSettings::default()
+            },
         }
     }
 }
@@ -163,6 +217,7 @@ pub struct ModelResources {
     pub tokenizer: ResourceLocation,
     pub weights: ResourceLocation,
     pub kind: ModelKind,
+    pub quantization: Option<QuantizationConfig>,
 }
 
 pub struct ConfigDescriptor {
@@ -255,6 +310,23 @@ impl AppConfig {
             if let Some(path) = overrides.weights.as_ref() {
                 entry.weights = Some(path.clone());
             }
+            let quant = &overrides.quantization;
+            if quant.kind.is_some() || quant.targets.is_some() || quant.vision.is_some() {
+                // Start from what would apply without the flags, so a partial override keeps the
+                // file or environment values for the fields it leaves alone.
+                let config = entry
+                    .quantization
+                    .get_or_insert_with(|| QuantizationConfig::resolve(None));
+                if let Some(kind) = quant.kind {
+                    config.kind = kind;
+                }
+                if let Some(targets) = quant.targets {
+                    config.targets = targets;
+                }
+                if let Some(vision) = quant.vision {
+                    config.vision = vision;
+                }
+            }
         }
 
         if let Some(device) = overrides.inference.device {
@@ -275,6 +347,19 @@ impl AppConfig {
         if let Some(crop_mode) = overrides.inference.crop_mode {
             self.inference.crop_mode = crop_mode;
         }
+        // Explicit sizes on the command line win over a preset stored in the config file.
+        if overrides.inference.base_size.is_some()
+            || overrides.inference.image_size.is_some()
+            || overrides.inference.crop_mode.is_some()
+        {
+            self.inference.resolution = None;
+        }
+        if overrides.inference.resolution.is_some() {
+            self.inference.resolution = overrides.inference.resolution;
+        }
+        if let Some(orientation) = overrides.inference.orientation {
+            self.inference.orientation = orientation;
+        }
         if let Some(max_new_tokens) = overrides.inference.max_new_tokens {
             self.inference.max_new_tokens = max_new_tokens;
         }
@@ -302,6 +387,60 @@ impl AppConfig {
         if overrides.inference.seed.is_some() {
             self.inference.seed = overrides.inference.seed;
         }
+        if let Some(chunk_size) = overrides.inference.tile_chunk_size {
+            self.inference.tile_encoding.chunk_size = chunk_size;
+        }
+        if overrides.inference.tile_threads.is_some() {
+            self.inference.tile_encoding.max_threads = overrides.inference.tile_threads;
+        }
+        if let Some(budget) = overrides.inference.tile_memory_mb {
+            self.inference.tile_encoding.memory_budget_mb = budget;
+        }
+        if let Some(deskew) = overrides.inference.deskew {
+            self.inference.cleanup.deskew = deskew;
+        }
+        if let Some(crop_margins) = overrides.inference.crop_margins {
+            self.inference.cleanup.crop_margins = crop_margins;
+        }
+        if let Some(normalize_contrast) = overrides.inference.normalize_contrast {
+            self.inference.cleanup.normalize_contrast = normalize_contrast;
+        }
+        if let Some(binarize) = overrides.inference.binarize {
+            self.inference.cleanup.binarize = binarize;
+        }
+        if let Some(slice_tall) = overrides.inference.slice_tall {
+            self.inference.slicing.enabled = slice_tall;
+        }
+        if let Some(overlap) = overrides.inference.slice_overlap {
+            self.inference.slicing.overlap = overlap;
+        }
+        if let Some(mode) = overrides.inference.split_layout {
+            self.inference.layout.mode = mode;
+        }
+        if let Some(strip) = overrides.inference.strip_grounding {
+            self.inference.polish.strip_grounding = strip;
+        }
+        if let Some(markdown_tables) = overrides.inference.markdown_tables {
+            self.inference.polish.markdown_tables = markdown_tables;
+        }
+        if let Some(merge) = overrides.inference.merge_hyphenation {
+            self.inference.polish.merge_hyphenation = merge;
+        }
+        if let Some(join) = overrides.inference.join_paragraphs {
+            self.inference.polish.join_paragraphs = join;
+        }
+        if let Some(fix) = overrides.inference.fix_headings {
+            self.inference.polish.fix_headings = fix;
+        }
+        if let Some(formulas) = overrides.inference.polish_formulas {
+            self.inference.polish.formulas = formulas;
+        }
+        if let Some(delimiters) = overrides.inference.math_delimiters {
+            self.inference.polish.math_delimiters = delimiters;
+        }
+        if let Some(order) = overrides.inference.reading_order {
+            self.inference.reading_order = order;
+        }
         if let Some(host) = overrides.server.host.as_ref() {
             self.server.host = host.clone();
         }
@@ -339,6 +478,7 @@ impl ModelEntry {
             tokenizer,
             weights,
             kind: self.kind,
+            quantization: self.quantization,
         }
     }
 }
@@ -414,18 +554,28 @@ pub struct ConfigOverrides {
     pub model_config: Option<PathBuf>,
     pub tokenizer: Option<PathBuf>,
     pub weights: Option<PathBuf>,
+    pub quantization: QuantizationOverride,
     pub inference: InferenceOverride,
     pub server: ServerOverride,
 }
 
+#[derive(Debug, Default, Clone)]
+pub struct QuantizationOverride {
+    pub kind: Option<QuantizationKind>,
+    pub targets: Option<QuantizationTargets>,
+    pub vision: Option<VisionQuantization>,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct InferenceOverride {
     pub device: Option<DeviceKind>,
     pub precision: Option<Precision>,
     pub template: Option<String>,
+    pub resolution: Option<ResolutionMode>,
     pub base_size: Option<u32>,
     pub image_size: Option<u32>,
     pub crop_mode: Option<bool>,
+    pub orientation: Option<OrientationMode>,
     pub max_new_tokens: Option<usize>,
     pub use_cache: Option<bool>,
     pub do_sample: Option<bool>,
@@ -435,6 +585,24 @@ pub struct InferenceOverride {
     pub repetition_penalty: Option<f32>,
     pub no_repeat_ngram_size: Option<usize>,
     pub seed: Option<u64>,
+    pub tile_chunk_size: Option<usize>,
+    pub tile_threads: Option<usize>,
+    pub tile_memory_mb: Option<u64>,
+    pub deskew: Option<bool>,
+    pub crop_margins: Option<bool>,
+    pub normalize_contrast: Option<bool>,
+    pub binarize: Option<bool>,
+    pub slice_tall: Option<bool>,
+    pub slice_overlap: Option<f32>,
+    pub split_layout: Option<LayoutMode>,
+    pub strip_grounding: Option<bool>,
+    pub markdown_tables: Option<bool>,
+    pub merge_hyphenation: Option<bool>,
+    pub join_paragraphs: Option<bool>,
+    pub fix_headings: Option<bool>,
+    pub polish_formulas: Option<FormulaMode>,
+    pub math_delimiters: Option<MathDelimiters>,
+    pub reading_order: Option<ReadingOrder>,
 }
 
 #[derive(Debug, Default, Clone)]
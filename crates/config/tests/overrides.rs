@@ -0,0 +1,59 @@
+use deepseek_ocr_config::{AppConfig, ConfigOverrides, config::QuantizationOverride};
+use deepseek_ocr_core::quantization::{
+    QuantizationConfig, QuantizationKind, QuantizationTargets, VisionQuantization, VisionTower,
+};
+
+fn active_quantization(config: &AppConfig) -> Option<QuantizationConfig> {
+    config.models.entries[&config.models.active].quantization
+}
+
+#[test]
+fn partial_quantization_override_keeps_file_values() {
+    let mut config = AppConfig::default();
+    let active = config.models.active.clone();
+    config.models.entries.get_mut(&active).unwrap().quantization = Some(QuantizationConfig {
+        kind: QuantizationKind::Q8_0,
+        targets: QuantizationTargets::TextAndProjector,
+        keep_full_precision_weights: true,
+        verbose_per_layer: true,
+        vision: VisionQuantization::default(),
+    });
+
+    config += ConfigOverrides {
+        quantization: QuantizationOverride {
+            vision: Some(VisionQuantization::from_towers(&[VisionTower::Sam])),
+            ..QuantizationOverride::default()
+        },
+        ..ConfigOverrides::default()
+    };
+
+    let quant = active_quantization(&config).unwrap();
+    assert_eq!(quant.kind, QuantizationKind::Q8_0);
+    assert_eq!(quant.targets, QuantizationTargets::TextAndProjector);
+    assert!(quant.keep_full_precision_weights && quant.verbose_per_layer);
+    assert!(quant.vision.enabled(VisionTower::Sam));
+}
+
+#[test]
+fn partial_quantization_override_keeps_environment_values() {
+    // The only test in this binary that touches the environment.
+    unsafe {
+        std::env::set_var("DEEPSEEK_OCR_QUANT", "Q8_0");
+        std::env::set_var("DEEPSEEK_OCR_QUANT_KEEP_FLOAT", "1");
+    }
+    let mut config = AppConfig::default();
+    assert_eq!(active_quantization(&config), None);
+
+    config += ConfigOverrides {
+        quantization: QuantizationOverride {
+            targets: Some(QuantizationTargets::TextAndProjector),
+            ..QuantizationOverride::default()
+        },
+        ..ConfigOverrides::default()
+    };
+
+    let quant = active_quantization(&config).unwrap();
+    assert_eq!(quant.kind, QuantizationKind::Q8_0);
+    assert_eq!(quant.targets, QuantizationTargets::TextAndProjector);
+    assert!(quant.keep_full_precision_weights);
+}
@@ -0,0 +1,40 @@
+//! Bicubic resampling at the sizes the vision preprocessors use.
+
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use deepseek_ocr_core::resample::resize_bicubic;
+use image::{Rgb, RgbImage};
+
+fn page(width: u32, height: u32) -> RgbImage {
+    RgbImage::from_fn(width, height, |x, y| {
+        let ink = if (x / 9 + y / 13) % 3 == 0 { 20 } else { 235 };
+        Rgb([
+            ink,
+            ink.wrapping_add((x % 7) as u8),
+            ink.wrapping_sub((y % 5) as u8),
+        ])
+    })
+}
+
+fn resize(c: &mut Criterion) {
+    let cases = [
+        // A phone photo shrunk to the DeepSeek global view.
+        ((3024, 4032), (1024, 1024)),
+        // A scan cut into DeepSeek tiles.
+        ((1240, 1754), (640, 640)),
+        // A small crop upscaled to a tile.
+        ((320, 200), (640, 640)),
+    ];
+    let mut group = c.benchmark_group("resize_bicubic");
+    for ((src_w, src_h), (dst_w, dst_h)) in cases {
+        let source = page(src_w, src_h);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{src_w}x{src_h}->{dst_w}x{dst_h}")),
+            &source,
+            |b, source| b.iter(|| resize_bicubic(black_box(source), dst_w, dst_h)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, resize);
+criterion_main!(benches);
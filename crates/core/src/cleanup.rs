@@ -0,0 +1,445 @@
+//! Optional clean-up of scanned document pages before vision preprocessing.
+//!
+//! Scans often carry dark scanner borders, wide blank margins and a few degrees of skew, all of
+//! which cost vision tokens without adding content. [`clean_document`] runs the enabled steps in
+//! a fixed order — deskew, margin crop, contrast normalisation, binarisation — and returns a
+//! [`CleanupReport`] recording the geometric changes so boxes predicted on the cleaned page can be
+//! mapped back onto the input image.
+
+use image::{DynamicImage, GrayImage, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+
+use crate::orientation::otsu_threshold;
+
+/// Longest side the skew search works on; larger pages are thumbnailed first.
+const SKEW_MAX_SIDE: u32 = 1024;
+const SKEW_COARSE_STEP: f32 = 0.5;
+const SKEW_FINE_STEP: f32 = 0.1;
+/// Skews below this (in degrees) are left alone rather than resampling the page.
+const MIN_SKEW: f32 = 0.1;
+/// Edge rows/columns darker than this fraction are treated as scanner border.
+const BORDER_DARK_FRACTION: f32 = 0.5;
+/// Rows/columns need at least this fraction of ink to count as content.
+const CONTENT_INK_FRACTION: f32 = 0.005;
+/// Padding kept around the detected content, relative to the shorter page side.
+const CROP_PADDING: f32 = 0.015;
+/// Luma percentiles mapped to black and white by contrast normalisation.
+const CONTRAST_LOW_PERCENTILE: f32 = 0.01;
+const CONTRAST_HIGH_PERCENTILE: f32 = 0.99;
+
+/// Which clean-up steps run on each input image. Everything is off by default.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CleanupConfig {
+    /// Rotate the page so text lines run horizontally.
+    pub deskew: bool,
+    /// Largest skew searched for, in degrees either way.
+    pub max_skew_degrees: f32,
+    /// Trim dark scanner borders and blank background around the content.
+    pub crop_margins: bool,
+    /// Stretch luma so the darkest and lightest 1% of pixels reach black and white.
+    pub normalize_contrast: bool,
+    /// Reduce the page to pure black and white with Otsu's threshold.
+    pub binarize: bool,
+}
+
+impl Default for CleanupConfig {
+    fn default() -> Self {
+        Self {
+            deskew: false,
+            max_skew_degrees: 10.0,
+            crop_margins: false,
+            normalize_contrast: false,
+            binarize: false,
+        }
+    }
+}
+
+impl CleanupConfig {
+    pub fn enabled(&self) -> bool {
+        self.deskew || self.crop_margins || self.normalize_contrast || self.binarize
+    }
+}
+
+/// Pixel rectangle kept by margin cropping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CropRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Geometric changes made by [`clean_document`], in the order they were applied.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CleanupReport {
+    /// Region left after removing the scanner border, in input pixel coordinates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border: Option<CropRect>,
+    /// Clockwise skew of the text lines that deskewing removed, in degrees.
+    pub skew_degrees: f32,
+    /// Region kept after trimming blank margins, in deskewed pixel coordinates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crop: Option<CropRect>,
+    pub source_width: u32,
+    pub source_height: u32,
+    /// Size of the cleaned image handed to the model.
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CleanupReport {
+    fn identity(width: u32, height: u32) -> Self {
+        Self {
+            border: None,
+            skew_degrees: 0.0,
+            crop: None,
+            source_width: width,
+            source_height: height,
+            width,
+            height,
+        }
+    }
+
+    /// Map a point in normalised cleaned-image coordinates (`0.0..=1.0`) back onto the input
+    /// image, also normalised.
+    pub fn to_source_point(&self, x: f32, y: f32) -> (f32, f32) {
+        let full = CropRect {
+            x: 0,
+            y: 0,
+            width: self.source_width,
+            height: self.source_height,
+        };
+        let border = self.border.unwrap_or(full);
+        let (width, height) = (border.width as f32, border.height as f32);
+        let (px, py) = match self.crop {
+            Some(crop) => (
+                crop.x as f32 + x * crop.width as f32,
+                crop.y as f32 + y * crop.height as f32,
+            ),
+            None => (x * width, y * height),
+        };
+        let (sx, sy) = rotate_about_centre(px, py, width, height, self.skew_degrees);
+        (
+            (border.x as f32 + sx) / self.source_width as f32,
+            (border.y as f32 + sy) / self.source_height as f32,
+        )
+    }
+
+    /// Map a normalised `[x0, y0, x1, y1]` box back onto the input image. Deskewed boxes come
+    /// back as the axis-aligned hull of their rotated corners.
+    pub fn to_source_box(&self, bbox: [f32; 4]) -> [f32; 4] {
+        let corners = [
+            self.to_source_point(bbox[0], bbox[1]),
+            self.to_source_point(bbox[2], bbox[1]),
+            self.to_source_point(bbox[0], bbox[3]),
+            self.to_source_point(bbox[2], bbox[3]),
+        ];
+        corners.iter().fold(
+            [f32::MAX, f32::MAX, f32::MIN, f32::MIN],
+            |[x0, y0, x1, y1], (x, y)| [x0.min(*x), y0.min(*y), x1.max(*x), y1.max(*y)],
+        )
+    }
+}
+
+/// A cleaned page plus the geometric changes made to it.
+#[derive(Debug, Clone)]
+pub struct CleanedImage {
+    pub image: DynamicImage,
+    pub report: CleanupReport,
+}
+
+/// Run the steps enabled in `config` on one page.
+pub fn clean_document(image: DynamicImage, config: &CleanupConfig) -> CleanedImage {
+    let mut report = CleanupReport::identity(image.width(), image.height());
+    if !config.enabled() {
+        return CleanedImage { image, report };
+    }
+    let mut rgb = image.to_rgb8();
+
+    // Scanner borders are aligned with the scan rather than the text, so they go before deskew.
+    if config.crop_margins
+        && let Some(border) = border_bounds(&image.to_luma8())
+    {
+        rgb = crop_page(&rgb, border);
+        report.border = Some(border);
+    }
+    if config.deskew {
+        let skew = estimate_skew(
+            &DynamicImage::ImageRgb8(rgb.clone()),
+            config.max_skew_degrees,
+        );
+        if skew.abs() >= MIN_SKEW {
+            rgb = rotate_page(&rgb, skew);
+            report.skew_degrees = skew;
+        }
+    }
+    if config.crop_margins
+        && let Some(crop) = content_bounds(&DynamicImage::ImageRgb8(rgb.clone()).to_luma8())
+    {
+        rgb = crop_page(&rgb, crop);
+        report.crop = Some(crop);
+    }
+    if config.normalize_contrast {
+        stretch_contrast(&mut rgb);
+    }
+    if config.binarize {
+        binarize(&mut rgb);
+    }
+
+    report.width = rgb.width();
+    report.height = rgb.height();
+    CleanedImage {
+        image: DynamicImage::ImageRgb8(rgb),
+        report,
+    }
+}
+
+/// Estimate the clockwise skew of the page's text lines, in degrees within `±max_degrees`.
+///
+/// Dark pixels inside the scanner border are projected onto the axis perpendicular to each
+/// candidate angle; the angle at which text lines collapse into the sharpest row histogram wins.
+/// A coarse sweep is refined around its best angle.
+pub fn estimate_skew(image: &DynamicImage, max_degrees: f32) -> f32 {
+    let gray = image.thumbnail(SKEW_MAX_SIDE, SKEW_MAX_SIDE).to_luma8();
+    let Some(mask) = DarkMask::from_gray(&gray) else {
+        return 0.0;
+    };
+    let (top, bottom, left, right) = mask.inside_border();
+    let inset = (mask.width.min(mask.height) as f32 * CROP_PADDING).round() as usize;
+    let points: Vec<(f32, f32)> = (top + inset..bottom.saturating_sub(inset))
+        .flat_map(|y| (left + inset..right.saturating_sub(inset)).map(move |x| (x, y)))
+        .filter(|(x, y)| mask.dark(*x, *y))
+        .map(|(x, y)| (x as f32, y as f32))
+        .collect();
+    if points.is_empty() || max_degrees <= 0.0 {
+        return 0.0;
+    }
+    let bins = mask.width + mask.height + 2;
+    let score = |degrees: f32| {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let mut histogram = vec![0u32; bins];
+        for (x, y) in &points {
+            let projected = y * cos - x * sin + mask.width as f32;
+            if let Some(bin) = histogram.get_mut(projected.max(0.0) as usize) {
+                *bin += 1;
+            }
+        }
+        histogram
+            .iter()
+            .map(|count| (*count as f64).powi(2))
+            .sum::<f64>()
+    };
+    let best_in = |from: f32, to: f32, step: f32| {
+        let steps = ((to - from) / step).round() as i32;
+        (0..=steps)
+            .map(|idx| from + idx as f32 * step)
+            .map(|angle| (angle, score(angle)))
+            .fold((0.0f32, f64::MIN), |best, candidate| {
+                if candidate.1 > best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            })
+            .0
+    };
+    let coarse = best_in(-max_degrees, max_degrees, SKEW_COARSE_STEP);
+    let fine = best_in(
+        (coarse - SKEW_COARSE_STEP).max(-max_degrees),
+        (coarse + SKEW_COARSE_STEP).min(max_degrees),
+        SKEW_FINE_STEP,
+    );
+    (fine / SKEW_FINE_STEP).round() * SKEW_FINE_STEP
+}
+
+/// Rotate `(x, y)` by `degrees` clockwise about the centre of a `width × height` page.
+fn rotate_about_centre(x: f32, y: f32, width: f32, height: f32, degrees: f32) -> (f32, f32) {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (cx, cy) = (width / 2.0, height / 2.0);
+    let (dx, dy) = (x - cx, y - cy);
+    (cx + dx * cos - dy * sin, cy + dx * sin + dy * cos)
+}
+
+/// Undo a clockwise skew of `degrees`, keeping the canvas size and filling uncovered corners
+/// with white.
+fn rotate_page(page: &RgbImage, degrees: f32) -> RgbImage {
+    let (width, height) = page.dimensions();
+    let (w, h) = (width as f32, height as f32);
+    RgbImage::from_fn(width, height, |x, y| {
+        let (sx, sy) = rotate_about_centre(x as f32 + 0.5, y as f32 + 0.5, w, h, degrees);
+        sample_bilinear(page, sx - 0.5, sy - 0.5)
+    })
+}
+
+fn sample_bilinear(page: &RgbImage, x: f32, y: f32) -> Rgb<u8> {
+    let (width, height) = page.dimensions();
+    if x < -0.5 || y < -0.5 || x > width as f32 - 0.5 || y > height as f32 - 0.5 {
+        return Rgb([255, 255, 255]);
+    }
+    let (x0, y0) = (x.floor().max(0.0), y.floor().max(0.0));
+    let (fx, fy) = ((x - x0).clamp(0.0, 1.0), (y - y0).clamp(0.0, 1.0));
+    let (x0, y0) = (x0 as u32, y0 as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (a, b) = (page.get_pixel(x0, y0).0, page.get_pixel(x1, y0).0);
+    let (c, d) = (page.get_pixel(x0, y1).0, page.get_pixel(x1, y1).0);
+    let mut out = [0u8; 3];
+    for channel in 0..3 {
+        let top = a[channel] as f32 * (1.0 - fx) + b[channel] as f32 * fx;
+        let bottom = c[channel] as f32 * (1.0 - fx) + d[channel] as f32 * fx;
+        out[channel] = (top * (1.0 - fy) + bottom * fy).round() as u8;
+    }
+    Rgb(out)
+}
+
+/// Pixels at or below the page's Otsu threshold: text and scanner borders alike.
+struct DarkMask {
+    width: usize,
+    height: usize,
+    data: Vec<bool>,
+}
+
+impl DarkMask {
+    fn from_gray(gray: &GrayImage) -> Option<Self> {
+        let threshold = otsu_threshold(gray)?;
+        Some(Self {
+            width: gray.width() as usize,
+            height: gray.height() as usize,
+            data: gray.pixels().map(|pixel| pixel.0[0] <= threshold).collect(),
+        })
+    }
+
+    fn dark(&self, x: usize, y: usize) -> bool {
+        self.data[y * self.width + x]
+    }
+
+    fn row_count(&self, y: usize, x0: usize, x1: usize) -> usize {
+        (x0..x1).filter(|x| self.dark(*x, y)).count()
+    }
+
+    fn column_count(&self, x: usize, y0: usize, y1: usize) -> usize {
+        (y0..y1).filter(|y| self.dark(x, *y)).count()
+    }
+
+    /// `(top, bottom, left, right)` after peeling mostly-dark edge lines (scanner lid, book
+    /// gutter), at most a quarter of the page per side.
+    fn inside_border(&self) -> (usize, usize, usize, usize) {
+        let (width, height) = (self.width, self.height);
+        let (mut top, mut bottom, mut left, mut right) = (0, height, 0, width);
+        let border = |count: usize, span: usize| count as f32 > span as f32 * BORDER_DARK_FRACTION;
+        while top < height / 4 && border(self.row_count(top, left, right), right - left) {
+            top += 1;
+        }
+        while bottom > height * 3 / 4
+            && border(self.row_count(bottom - 1, left, right), right - left)
+        {
+            bottom -= 1;
+        }
+        while left < width / 4 && border(self.column_count(left, top, bottom), bottom - top) {
+            left += 1;
+        }
+        while right > width * 3 / 4
+            && border(self.column_count(right - 1, top, bottom), bottom - top)
+        {
+            right -= 1;
+        }
+        (top, bottom, left, right)
+    }
+}
+
+fn crop_page(page: &RgbImage, rect: CropRect) -> RgbImage {
+    image::imageops::crop_imm(page, rect.x, rect.y, rect.width, rect.height).to_image()
+}
+
+/// Region inside the dark scanner border, or `None` when the page has no border.
+fn border_bounds(gray: &GrayImage) -> Option<CropRect> {
+    let mask = DarkMask::from_gray(gray)?;
+    let (top, bottom, left, right) = mask.inside_border();
+    if (top, bottom, left, right) == (0, mask.height, 0, mask.width) {
+        return None;
+    }
+    Some(CropRect {
+        x: left as u32,
+        y: top as u32,
+        width: (right - left) as u32,
+        height: (bottom - top) as u32,
+    })
+}
+
+/// Bounding box of the page content once dark scanner borders and blank margins are removed.
+/// Returns `None` when nothing would be trimmed or no content is found.
+fn content_bounds(gray: &GrayImage) -> Option<CropRect> {
+    let mask = DarkMask::from_gray(gray)?;
+    let (width, height) = (mask.width, mask.height);
+    let (top, bottom, left, right) = mask.inside_border();
+
+    let content = |count: usize, span: usize| count as f32 > span as f32 * CONTENT_INK_FRACTION;
+    let rows: Vec<usize> = (top..bottom)
+        .filter(|y| content(mask.row_count(*y, left, right), right - left))
+        .collect();
+    let columns: Vec<usize> = (left..right)
+        .filter(|x| content(mask.column_count(*x, top, bottom), bottom - top))
+        .collect();
+    let (first_row, last_row) = (*rows.first()?, *rows.last()?);
+    let (first_column, last_column) = (*columns.first()?, *columns.last()?);
+
+    let padding = (width.min(height) as f32 * CROP_PADDING).round() as usize;
+    let x0 = first_column.saturating_sub(padding).max(left);
+    let y0 = first_row.saturating_sub(padding).max(top);
+    let x1 = (last_column + 1 + padding).min(right);
+    let y1 = (last_row + 1 + padding).min(bottom);
+    if (x0, y0, x1, y1) == (0, 0, width, height) {
+        return None;
+    }
+    Some(CropRect {
+        x: x0 as u32,
+        y: y0 as u32,
+        width: (x1 - x0) as u32,
+        height: (y1 - y0) as u32,
+    })
+}
+
+/// Linearly stretch every channel so the luma percentiles map to black and white.
+fn stretch_contrast(page: &mut RgbImage) {
+    let gray = DynamicImage::ImageRgb8(page.clone()).to_luma8();
+    let mut histogram = [0u64; 256];
+    for pixel in gray.pixels() {
+        histogram[pixel.0[0] as usize] += 1;
+    }
+    let total = gray.len() as f32;
+    let percentile = |fraction: f32| {
+        let target = (total * fraction) as u64;
+        let mut seen = 0u64;
+        histogram
+            .iter()
+            .position(|count| {
+                seen += count;
+                seen > target
+            })
+            .unwrap_or(255) as f32
+    };
+    let (low, high) = (
+        percentile(CONTRAST_LOW_PERCENTILE),
+        percentile(CONTRAST_HIGH_PERCENTILE),
+    );
+    if high <= low {
+        return;
+    }
+    let scale = 255.0 / (high - low);
+    for pixel in page.pixels_mut() {
+        for channel in pixel.0.iter_mut() {
+            *channel = ((*channel as f32 - low) * scale).clamp(0.0, 255.0).round() as u8;
+        }
+    }
+}
+
+fn binarize(page: &mut RgbImage) {
+    let gray = DynamicImage::ImageRgb8(page.clone()).to_luma8();
+    let Some(threshold) = otsu_threshold(&gray) else {
+        return;
+    };
+    for (pixel, luma) in page.pixels_mut().zip(gray.pixels()) {
+        let value = if luma.0[0] <= threshold { 0 } else { 255 };
+        *pixel = Rgb([value; 3]);
+    }
+}
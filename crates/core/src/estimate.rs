@@ -0,0 +1,160 @@
+//! Dry-run budgeting: token counts, prefill cost and memory of a request, derived from the
+//! preprocessing path and the model config without loading any weights.
+//!
+//! Each backend runs its own image preprocessing maths (crop grids, `smart_resize`) and prompt
+//! tokenization, describes its model as a [`ModelFootprint`], and [`DryRunReport::new`] turns
+//! that into totals. The figures are estimates: weights are counted at the load dtype before any
+//! quantization, and allocator overhead and vision-encoder activations are left out.
+
+use std::path::Path;
+
+use candle_core::DType;
+use image::DynamicImage;
+use serde::Serialize;
+use tokenizers::Tokenizer;
+
+use crate::inference::{ModelKind, VisionSettings};
+
+/// Everything a backend needs to estimate one request.
+#[derive(Debug)]
+pub struct EstimateArgs<'a> {
+    pub config_path: Option<&'a Path>,
+    pub tokenizer: &'a Tokenizer,
+    /// Rendered prompt with one `<image>` slot per image.
+    pub prompt: &'a str,
+    pub images: &'a [DynamicImage],
+    pub vision: VisionSettings,
+    pub max_new_tokens: usize,
+    /// DType the weights would be loaded with.
+    pub dtype: DType,
+}
+
+/// How one image is encoded and how many prompt positions it takes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImageEstimate {
+    pub width: u32,
+    pub height: u32,
+    /// Crop tiles as `(columns, rows)`; `None` when the image is encoded as a single view.
+    pub tile_grid: Option<(u32, u32)>,
+    /// `(width, height)` of the resized view: the global view for DeepSeek-OCR, the
+    /// `smart_resize` output for PaddleOCR-VL.
+    pub resized: (u32, u32),
+    /// Image placeholder tokens the image occupies in the prompt.
+    pub vision_tokens: usize,
+}
+
+/// Model dimensions that drive memory use and prefill compute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelFootprint {
+    /// Every weight the backend loads: language model, vision encoder and projector.
+    pub total_parameters: u64,
+    /// Language-model weights one token is multiplied through; MoE layers count only the
+    /// experts a token is routed to, and the embedding lookup is left out.
+    pub active_parameters: u64,
+    pub layers: usize,
+    pub hidden_size: usize,
+    /// Widest MLP activation any decoder layer materialises.
+    pub intermediate_size: usize,
+    pub vocab_size: usize,
+    pub attention_heads: usize,
+    /// Query/key width of one attention head.
+    pub head_dim: usize,
+    /// Key plus value elements cached per token and layer, across all KV heads.
+    pub kv_width: usize,
+}
+
+/// Estimated memory of one request, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MemoryEstimate {
+    pub weights_bytes: u64,
+    /// KV cache once the prompt and the whole generation budget are cached.
+    pub kv_cache_bytes: u64,
+    /// Largest transient buffers of the prefill pass: hidden states, MLP activations, one
+    /// layer's attention scores and the logits.
+    pub activation_bytes: u64,
+    pub peak_bytes: u64,
+}
+
+/// Output of a dry run.
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunReport {
+    pub model: ModelKind,
+    pub dtype: &'static str,
+    pub images: Vec<ImageEstimate>,
+    pub text_tokens: usize,
+    pub vision_tokens: usize,
+    pub prompt_tokens: usize,
+    pub max_new_tokens: usize,
+    /// Language-model compute of the prefill pass, in GFLOPs.
+    pub prefill_gflops: f64,
+    pub memory: MemoryEstimate,
+}
+
+impl DryRunReport {
+    /// Combine per-image estimates and the prompt length with the model footprint.
+    pub fn new(
+        model: ModelKind,
+        dtype: DType,
+        images: Vec<ImageEstimate>,
+        prompt_tokens: usize,
+        max_new_tokens: usize,
+        footprint: &ModelFootprint,
+    ) -> Self {
+        let vision_tokens = images.iter().map(|image| image.vision_tokens).sum();
+        Self {
+            model,
+            dtype: dtype.as_str(),
+            images,
+            text_tokens: prompt_tokens.saturating_sub(vision_tokens),
+            vision_tokens,
+            prompt_tokens,
+            max_new_tokens,
+            prefill_gflops: footprint.prefill_flops(prompt_tokens) / 1e9,
+            memory: footprint.memory(prompt_tokens, max_new_tokens, dtype),
+        }
+    }
+}
+
+impl ModelFootprint {
+    /// Forward-pass FLOPs for `tokens` prompt positions: two per weight per token, plus the
+    /// `QK^T` and attention-times-`V` products, which are computed in full before masking.
+    pub fn prefill_flops(&self, tokens: usize) -> f64 {
+        let tokens = tokens as f64;
+        let weights = 2.0 * tokens * self.active_parameters as f64;
+        let attention =
+            4.0 * (self.layers * self.attention_heads * self.head_dim) as f64 * tokens * tokens;
+        weights + attention
+    }
+
+    pub fn memory(
+        &self,
+        prompt_tokens: usize,
+        max_new_tokens: usize,
+        dtype: DType,
+    ) -> MemoryEstimate {
+        let bytes = dtype.size_in_bytes() as u64;
+        let tokens = prompt_tokens as u64;
+        let weights_bytes = self.total_parameters * bytes;
+        let kv_cache_bytes =
+            (self.layers * self.kv_width) as u64 * (prompt_tokens + max_new_tokens) as u64 * bytes;
+        let per_token =
+            (4 * self.hidden_size + 2 * self.intermediate_size + self.vocab_size) as u64;
+        let scores = self.attention_heads as u64 * tokens * tokens;
+        let activation_bytes = (tokens * per_token + scores) * bytes;
+        MemoryEstimate {
+            weights_bytes,
+            kv_cache_bytes,
+            activation_bytes,
+            peak_bytes: weights_bytes + kv_cache_bytes + activation_bytes,
+        }
+    }
+}
+
+/// Parameters of a stack of pre-norm transformer encoder layers (attention, two-layer MLP,
+/// biases and norms), as used by the ViT-style vision towers.
+pub fn encoder_parameters(layers: usize, width: usize, intermediate: usize) -> u64 {
+    let attention = 4 * (width * width + width);
+    let mlp = 2 * width * intermediate + intermediate + width;
+    let norms = 4 * width;
+    (layers * (attention + mlp + norms)) as u64
+}
@@ -0,0 +1,171 @@
+//! ALTO v4 XML in pixel units: one `Page` per image holding a `PrintSpace` of `TextBlock`s
+//! (`TextLine`s of `String`/`SP`) and `Illustration`s, with running headers and footers in
+//! `TopMargin` and `BottomMargin`, and a `ReadingOrder` group listing the blocks in reading
+//! order. `WC` carries the block confidence when known.
+
+use std::fmt::Write;
+
+use super::{OCR_SYSTEM, OcrBlock, OcrPage, escape_xml, word_boxes};
+use crate::reading_order::PageRegion;
+
+pub const ALTO_NAMESPACE: &str = "http://www.loc.gov/standards/alto/ns-v4#";
+const ALTO_SCHEMA: &str = "http://www.loc.gov/alto/v4/alto-4-2.xsd";
+
+pub fn render(pages: &[OcrPage]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <alto xmlns=\"{ALTO_NAMESPACE}\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+         xsi:schemaLocation=\"{ALTO_NAMESPACE} {ALTO_SCHEMA}\">\n  \
+         <Description>\n    \
+         <MeasurementUnit>pixel</MeasurementUnit>"
+    );
+    if let [page] = pages
+        && let Some(name) = &page.image_name
+    {
+        let _ = writeln!(
+            out,
+            "    <sourceImageInformation>\n      <fileName>{}</fileName>\n    </sourceImageInformation>",
+            escape_xml(name)
+        );
+    }
+    let _ = writeln!(
+        out,
+        "    <OCRProcessing ID=\"OCR_0\">\n      <ocrProcessingStep>\n        \
+         <processingSoftware>\n          <softwareName>{OCR_SYSTEM}</softwareName>\n        \
+         </processingSoftware>\n      </ocrProcessingStep>\n    </OCRProcessing>\n  \
+         </Description>"
+    );
+
+    if pages.iter().any(|page| !page.blocks.is_empty()) {
+        out.push_str("  <ReadingOrder>\n    <OrderedGroup ID=\"RO_0\">\n");
+        let mut order = 0;
+        for (page_idx, page) in pages.iter().enumerate() {
+            for block_idx in 0..page.blocks.len() {
+                order += 1;
+                let _ = writeln!(
+                    out,
+                    "      <ElementRef ID=\"RO_0_{order}\" REF=\"{}\"/>",
+                    block_id(page_idx, block_idx)
+                );
+            }
+        }
+        out.push_str("    </OrderedGroup>\n  </ReadingOrder>\n");
+    }
+
+    out.push_str("  <Layout>\n");
+    for (page_idx, page) in pages.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "    <Page ID=\"P{}\" PHYSICAL_IMG_NR=\"{}\" WIDTH=\"{}\" HEIGHT=\"{}\">",
+            page_idx + 1,
+            page_idx + 1,
+            page.width,
+            page.height,
+        );
+        let blocks: Vec<(String, &OcrBlock)> = page
+            .ordered_blocks()
+            .into_iter()
+            .enumerate()
+            .map(|(block_idx, block)| (block_id(page_idx, block_idx), block))
+            .collect();
+        let in_region = |region: PageRegion| {
+            blocks
+                .iter()
+                .filter(move |(_, block)| block.region == region)
+        };
+        let top = in_region(PageRegion::Header)
+            .map(|(_, block)| block.bbox[3])
+            .max()
+            .unwrap_or(0);
+        let bottom = in_region(PageRegion::Footer)
+            .map(|(_, block)| block.bbox[1])
+            .min()
+            .unwrap_or(page.height);
+        // ALTO orders the margins before the print space.
+        let margins = [
+            ("TopMargin", PageRegion::Header, [0, 0, page.width, top]),
+            (
+                "BottomMargin",
+                PageRegion::Footer,
+                [0, bottom, page.width, page.height],
+            ),
+        ];
+        for (element, region, bbox) in margins {
+            if in_region(region).next().is_none() {
+                continue;
+            }
+            let _ = writeln!(out, "      <{element} {}>", position(bbox));
+            for (id, block) in in_region(region) {
+                write_block(&mut out, id, block);
+            }
+            let _ = writeln!(out, "      </{element}>");
+        }
+        let _ = writeln!(
+            out,
+            "      <PrintSpace {}>",
+            position([0, top, page.width, bottom])
+        );
+        for (id, block) in in_region(PageRegion::Body) {
+            write_block(&mut out, id, block);
+        }
+        out.push_str("      </PrintSpace>\n    </Page>\n");
+    }
+    out.push_str("  </Layout>\n</alto>\n");
+    out
+}
+
+fn write_block(out: &mut String, id: &str, block: &OcrBlock) {
+    if block.is_illustration() {
+        let _ = writeln!(
+            out,
+            "        <Illustration ID=\"{id}\" {}/>",
+            position(block.bbox)
+        );
+        return;
+    }
+    let _ = writeln!(
+        out,
+        "        <TextBlock ID=\"{id}\" {}>",
+        position(block.bbox)
+    );
+    let confidence = block
+        .confidence
+        .map(|value| format!(" WC=\"{:.2}\"", value.clamp(0.0, 1.0)))
+        .unwrap_or_default();
+    for (line_idx, (line, line_box)) in block.line_boxes().enumerate() {
+        let line_id = format!("{id}_L{}", line_idx + 1);
+        let _ = writeln!(
+            out,
+            "          <TextLine ID=\"{line_id}\" {}>",
+            position(line_box)
+        );
+        for (word_idx, (word, word_box)) in word_boxes(line, line_box).into_iter().enumerate() {
+            if word_idx > 0 {
+                out.push_str("            <SP/>\n");
+            }
+            let _ = writeln!(
+                out,
+                "            <String ID=\"{line_id}_W{}\" CONTENT=\"{}\" {}{confidence}/>",
+                word_idx + 1,
+                escape_xml(word),
+                position(word_box)
+            );
+        }
+        out.push_str("          </TextLine>\n");
+    }
+    out.push_str("        </TextBlock>\n");
+}
+
+fn block_id(page_idx: usize, block_idx: usize) -> String {
+    format!("P{}_B{}", page_idx + 1, block_idx + 1)
+}
+
+fn position([x0, y0, x1, y1]: [u32; 4]) -> String {
+    format!(
+        "HPOS=\"{x0}\" VPOS=\"{y0}\" WIDTH=\"{}\" HEIGHT=\"{}\"",
+        x1.saturating_sub(x0),
+        y1.saturating_sub(y0)
+    )
+}
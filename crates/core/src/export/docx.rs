@@ -0,0 +1,350 @@
+//! Minimal WordprocessingML document: headings, paragraphs and lists with the built-in Word
+//! styles, tables with merged cells, formulas as centred Office Math in a `Formula` style
+//! (LaTeX text without the `mathml` feature) and figures as PNG crops drawn inline. Blocks
+//! follow the reading order; running headers and footers keep their place at the top and
+//! bottom of each input page in the `Header` and `Footer` styles. Pages of the input are separated by page breaks; the package is written
+//! with [`ZipWriter`].
+
+use std::fmt::Write as _;
+
+use anyhow::Result;
+use image::DynamicImage;
+
+use super::{BlockKind, OcrBlock, OcrPage, crop_png, escape_xml, list_item};
+#[cfg(feature = "mathml")]
+use crate::formula::omml::to_omml;
+use crate::{reading_order::PageRegion, tables::Table, zip::ZipWriter};
+
+const MAIN_NS: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+const REL_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const PACKAGE_REL_NS: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+const DRAWING_NS: &str = "http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing";
+const GRAPHIC_NS: &str = "http://schemas.openxmlformats.org/drawingml/2006/main";
+const PICTURE_NS: &str = "http://schemas.openxmlformats.org/drawingml/2006/picture";
+const MATH_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/math";
+const XML_DECL: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+
+/// A4 with one-inch margins, in twentieths of a point.
+const PAGE_WIDTH: u32 = 11906;
+const PAGE_HEIGHT: u32 = 16838;
+const MARGIN: u32 = 1440;
+const TEXT_WIDTH: u32 = PAGE_WIDTH - 2 * MARGIN;
+/// EMUs per twip and per pixel at 96 DPI.
+const EMU_PER_TWIP: u64 = 635;
+const EMU_PER_PIXEL: u64 = 9525;
+
+/// Relationship ids of the fixed parts; images follow.
+const STYLES_REL: usize = 1;
+const NUMBERING_REL: usize = 2;
+
+const HEADING_SIZES: [u32; 6] = [32, 28, 26, 24, 22, 22];
+
+const STYLES_BODY: &str = concat!(
+    "<w:docDefaults><w:rPrDefault><w:rPr><w:rFonts w:ascii=\"Calibri\" w:hAnsi=\"Calibri\" ",
+    "w:eastAsia=\"Calibri\" w:cs=\"Calibri\"/><w:sz w:val=\"22\"/></w:rPr></w:rPrDefault>",
+    "<w:pPrDefault><w:pPr><w:spacing w:after=\"160\" w:line=\"259\" w:lineRule=\"auto\"/>",
+    "</w:pPr></w:pPrDefault></w:docDefaults>",
+    "<w:style w:type=\"paragraph\" w:default=\"1\" w:styleId=\"Normal\"><w:name w:val=\"Normal\"/>",
+    "<w:qFormat/></w:style>",
+    "<w:style w:type=\"paragraph\" w:styleId=\"Caption\"><w:name w:val=\"caption\"/>",
+    "<w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/>",
+    "<w:rPr><w:i/><w:sz w:val=\"18\"/></w:rPr></w:style>",
+    "<w:style w:type=\"paragraph\" w:styleId=\"Header\"><w:name w:val=\"header\"/>",
+    "<w:basedOn w:val=\"Normal\"/><w:rPr><w:color w:val=\"595959\"/><w:sz w:val=\"18\"/></w:rPr>",
+    "</w:style>",
+    "<w:style w:type=\"paragraph\" w:styleId=\"Footer\"><w:name w:val=\"footer\"/>",
+    "<w:basedOn w:val=\"Normal\"/><w:rPr><w:color w:val=\"595959\"/><w:sz w:val=\"18\"/></w:rPr>",
+    "</w:style>",
+    "<w:style w:type=\"paragraph\" w:styleId=\"ListParagraph\"><w:name w:val=\"List Paragraph\"/>",
+    "<w:basedOn w:val=\"Normal\"/><w:qFormat/><w:pPr><w:ind w:left=\"720\"/><w:contextualSpacing/>",
+    "</w:pPr></w:style>",
+    "<w:style w:type=\"paragraph\" w:customStyle=\"1\" w:styleId=\"Formula\"><w:name w:val=\"Formula\"/>",
+    "<w:basedOn w:val=\"Normal\"/><w:qFormat/><w:pPr><w:jc w:val=\"center\"/></w:pPr>",
+    "<w:rPr><w:rFonts w:ascii=\"Cambria Math\" w:hAnsi=\"Cambria Math\"/></w:rPr></w:style>",
+    "<w:style w:type=\"table\" w:styleId=\"TableGrid\"><w:name w:val=\"Table Grid\"/><w:tblPr>",
+    "<w:tblBorders><w:top w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>",
+    "<w:left w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>",
+    "<w:bottom w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>",
+    "<w:right w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>",
+    "<w:insideH w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>",
+    "<w:insideV w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/></w:tblBorders>",
+    "<w:tblCellMar><w:left w:w=\"108\" w:type=\"dxa\"/><w:right w:w=\"108\" w:type=\"dxa\"/>",
+    "</w:tblCellMar></w:tblPr></w:style>",
+);
+
+/// Render `pages` as a `.docx` package, cropping figures from `images`, one per page. Figures
+/// of pages without an image are left out.
+pub fn render(pages: &[OcrPage], images: &[DynamicImage]) -> Result<Vec<u8>> {
+    let mut body = String::new();
+    let mut media: Vec<Vec<u8>> = Vec::new();
+    let mut lists: Vec<bool> = Vec::new();
+    for (idx, page) in pages.iter().enumerate() {
+        if idx > 0 {
+            body.push_str("<w:p><w:r><w:br w:type=\"page\"/></w:r></w:p>");
+        }
+        for block in page.ordered_blocks() {
+            let running = match block.region {
+                PageRegion::Header => Some("Header"),
+                PageRegion::Body => None,
+                PageRegion::Footer => Some("Footer"),
+            };
+            if let Some(style) = running.filter(|_| !block.is_illustration()) {
+                paragraph(&mut body, style, block);
+                continue;
+            }
+            match &block.kind {
+                BlockKind::Heading(level) => {
+                    paragraph(&mut body, &format!("Heading{level}"), block);
+                }
+                BlockKind::Paragraph => paragraph(&mut body, "Normal", block),
+                BlockKind::Caption => paragraph(&mut body, "Caption", block),
+                BlockKind::List { ordered } => {
+                    lists.push(*ordered);
+                    for line in &block.lines {
+                        let item = list_item(line).map_or(line.as_str(), |(_, item)| item);
+                        let _ = write!(
+                            body,
+                            "<w:p><w:pPr><w:pStyle w:val=\"ListParagraph\"/><w:numPr>\
+                             <w:ilvl w:val=\"0\"/><w:numId w:val=\"{}\"/></w:numPr></w:pPr>{}</w:p>",
+                            lists.len(),
+                            run(item, false)
+                        );
+                    }
+                }
+                BlockKind::Table(table) => table_xml(&mut body, table),
+                BlockKind::Formula(latex) => {
+                    #[cfg(feature = "mathml")]
+                    let formula = to_omml(latex, true);
+                    #[cfg(not(feature = "mathml"))]
+                    let formula = run(latex, false);
+                    let _ = write!(
+                        body,
+                        "<w:p><w:pPr><w:pStyle w:val=\"Formula\"/></w:pPr>{formula}</w:p>"
+                    );
+                }
+                BlockKind::Figure => {
+                    let Some(image) = images.get(idx) else {
+                        continue;
+                    };
+                    let Some((png, width, height)) =
+                        crop_png(image, block.bbox, page.width, page.height)?
+                    else {
+                        continue;
+                    };
+                    media.push(png);
+                    drawing(&mut body, media.len(), width, height);
+                }
+            }
+        }
+    }
+
+    let mut zip = ZipWriter::default();
+    zip.add(
+        "[Content_Types].xml",
+        format!(
+            "{XML_DECL}<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+             <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+             <Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+             <Default Extension=\"png\" ContentType=\"image/png\"/>\
+             <Override PartName=\"/word/document.xml\" \
+             ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml\"/>\
+             <Override PartName=\"/word/styles.xml\" \
+             ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml\"/>\
+             <Override PartName=\"/word/numbering.xml\" \
+             ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.numbering+xml\"/>\
+             </Types>"
+        )
+        .as_bytes(),
+    )?;
+    zip.add(
+        "_rels/.rels",
+        format!(
+            "{XML_DECL}<Relationships xmlns=\"{PACKAGE_REL_NS}\">\
+             <Relationship Id=\"rId1\" Type=\"{REL_NS}/officeDocument\" Target=\"word/document.xml\"/>\
+             </Relationships>"
+        )
+        .as_bytes(),
+    )?;
+
+    let mut rels = format!(
+        "{XML_DECL}<Relationships xmlns=\"{PACKAGE_REL_NS}\">\
+         <Relationship Id=\"rId{STYLES_REL}\" Type=\"{REL_NS}/styles\" Target=\"styles.xml\"/>\
+         <Relationship Id=\"rId{NUMBERING_REL}\" Type=\"{REL_NS}/numbering\" Target=\"numbering.xml\"/>"
+    );
+    for figure in 1..=media.len() {
+        let _ = write!(
+            rels,
+            "<Relationship Id=\"rId{}\" Type=\"{REL_NS}/image\" Target=\"media/figure{figure}.png\"/>",
+            NUMBERING_REL + figure
+        );
+    }
+    rels.push_str("</Relationships>");
+
+    if body.is_empty() {
+        body.push_str("<w:p/>");
+    }
+    let document = format!(
+        "{XML_DECL}<w:document xmlns:w=\"{MAIN_NS}\" xmlns:r=\"{REL_NS}\" xmlns:wp=\"{DRAWING_NS}\" \
+         xmlns:a=\"{GRAPHIC_NS}\" xmlns:pic=\"{PICTURE_NS}\" xmlns:m=\"{MATH_NS}\"><w:body>{body}\
+         <w:sectPr><w:pgSz w:w=\"{PAGE_WIDTH}\" w:h=\"{PAGE_HEIGHT}\"/>\
+         <w:pgMar w:top=\"{MARGIN}\" w:right=\"{MARGIN}\" w:bottom=\"{MARGIN}\" w:left=\"{MARGIN}\" \
+         w:header=\"708\" w:footer=\"708\" w:gutter=\"0\"/></w:sectPr></w:body></w:document>"
+    );
+    zip.add("word/document.xml", document.as_bytes())?;
+    zip.add("word/_rels/document.xml.rels", rels.as_bytes())?;
+    zip.add("word/styles.xml", styles().as_bytes())?;
+    zip.add("word/numbering.xml", numbering(&lists).as_bytes())?;
+    for (idx, png) in media.iter().enumerate() {
+        zip.add(&format!("word/media/figure{}.png", idx + 1), png)?;
+    }
+    Ok(zip.finish())
+}
+
+/// A text run; `xml:space` keeps leading and trailing spaces.
+fn run(text: &str, bold: bool) -> String {
+    format!(
+        "<w:r>{}<w:t xml:space=\"preserve\">{}</w:t></w:r>",
+        if bold { "<w:rPr><w:b/></w:rPr>" } else { "" },
+        escape_xml(text)
+    )
+}
+
+/// One paragraph of `style` holding the lines of `block`, joined with spaces so the text
+/// reflows.
+fn paragraph(body: &mut String, style: &str, block: &OcrBlock) {
+    let _ = write!(
+        body,
+        "<w:p><w:pPr><w:pStyle w:val=\"{style}\"/></w:pPr>{}</w:p>",
+        run(&block.lines.join(" "), false)
+    );
+}
+
+/// `table` on a grid of equal columns. Spanning cells become `gridSpan` and `vMerge` cells;
+/// rows of header cells repeat on each page.
+fn table_xml(body: &mut String, table: &Table) {
+    let columns = table.columns.max(1);
+    let column_width = TEXT_WIDTH / columns as u32;
+    // Index of the cell covering each grid position.
+    let mut owner = vec![vec![None; columns]; table.rows];
+    for (idx, cell) in table.cells.iter().enumerate() {
+        for row in owner.iter_mut().skip(cell.row).take(cell.row_span) {
+            for slot in row.iter_mut().skip(cell.column).take(cell.column_span) {
+                *slot = Some(idx);
+            }
+        }
+    }
+    let _ = write!(
+        body,
+        "<w:tbl><w:tblPr><w:tblStyle w:val=\"TableGrid\"/><w:tblW w:w=\"0\" w:type=\"auto\"/>\
+         </w:tblPr><w:tblGrid>{}</w:tblGrid>",
+        format!("<w:gridCol w:w=\"{column_width}\"/>").repeat(columns)
+    );
+    for (row, slots) in owner.iter().enumerate() {
+        body.push_str("<w:tr>");
+        let header = table
+            .cells
+            .iter()
+            .filter(|cell| cell.row == row)
+            .all(|cell| cell.header);
+        if header && table.cells.iter().any(|cell| cell.row == row) {
+            body.push_str("<w:trPr><w:tblHeader/></w:trPr>");
+        }
+        let mut column = 0;
+        while column < columns {
+            let cell = slots[column].map(|idx| &table.cells[idx]);
+            let span = cell.map_or(1, |cell| cell.column_span);
+            let _ = write!(
+                body,
+                "<w:tc><w:tcPr><w:tcW w:w=\"{}\" w:type=\"dxa\"/>",
+                column_width * span as u32
+            );
+            if span > 1 {
+                let _ = write!(body, "<w:gridSpan w:val=\"{span}\"/>");
+            }
+            match cell {
+                Some(cell) if cell.row < row => body.push_str("<w:vMerge/></w:tcPr><w:p/>"),
+                Some(cell) => {
+                    if cell.row_span > 1 {
+                        body.push_str("<w:vMerge w:val=\"restart\"/>");
+                    }
+                    let _ = write!(body, "</w:tcPr><w:p>{}</w:p>", run(&cell.text, cell.header));
+                }
+                None => body.push_str("</w:tcPr><w:p/>"),
+            }
+            body.push_str("</w:tc>");
+            column += span;
+        }
+        body.push_str("</w:tr>");
+    }
+    // Word needs a paragraph between adjacent tables to keep them apart.
+    body.push_str("</w:tbl><w:p/>");
+}
+
+/// An inline picture of relationship `figure`, `width`×`height` pixels at 96 DPI, shrunk to
+/// the text width when wider.
+fn drawing(body: &mut String, figure: usize, width: u32, height: u32) {
+    let max_width = u64::from(TEXT_WIDTH) * EMU_PER_TWIP;
+    let mut cx = u64::from(width) * EMU_PER_PIXEL;
+    let mut cy = u64::from(height) * EMU_PER_PIXEL;
+    if cx > max_width {
+        cy = cy * max_width / cx;
+        cx = max_width;
+    }
+    let _ = write!(
+        body,
+        "<w:p><w:pPr><w:jc w:val=\"center\"/></w:pPr><w:r><w:drawing>\
+         <wp:inline distT=\"0\" distB=\"0\" distL=\"0\" distR=\"0\">\
+         <wp:extent cx=\"{cx}\" cy=\"{cy}\"/><wp:docPr id=\"{figure}\" name=\"Figure {figure}\"/>\
+         <wp:cNvGraphicFramePr><a:graphicFrameLocks noChangeAspect=\"1\"/></wp:cNvGraphicFramePr>\
+         <a:graphic><a:graphicData uri=\"{PICTURE_NS}\"><pic:pic><pic:nvPicPr>\
+         <pic:cNvPr id=\"{figure}\" name=\"figure{figure}.png\"/><pic:cNvPicPr/></pic:nvPicPr>\
+         <pic:blipFill><a:blip r:embed=\"rId{}\"/><a:stretch><a:fillRect/></a:stretch></pic:blipFill>\
+         <pic:spPr><a:xfrm><a:off x=\"0\" y=\"0\"/><a:ext cx=\"{cx}\" cy=\"{cy}\"/></a:xfrm>\
+         <a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom></pic:spPr></pic:pic></a:graphicData>\
+         </a:graphic></wp:inline></w:drawing></w:r></w:p>",
+        NUMBERING_REL + figure
+    );
+}
+
+fn styles() -> String {
+    let mut xml = format!("{XML_DECL}<w:styles xmlns:w=\"{MAIN_NS}\">{STYLES_BODY}");
+    for (idx, size) in HEADING_SIZES.iter().enumerate() {
+        let level = idx + 1;
+        let _ = write!(
+            xml,
+            "<w:style w:type=\"paragraph\" w:styleId=\"Heading{level}\">\
+             <w:name w:val=\"heading {level}\"/><w:basedOn w:val=\"Normal\"/>\
+             <w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/>\
+             <w:spacing w:before=\"240\" w:after=\"80\"/><w:outlineLvl w:val=\"{idx}\"/></w:pPr>\
+             <w:rPr><w:b/><w:sz w:val=\"{size}\"/></w:rPr></w:style>"
+        );
+    }
+    xml.push_str("</w:styles>");
+    xml
+}
+
+/// Bullet and decimal list definitions, and one numbering instance per list so each numbered
+/// list starts again at 1.
+fn numbering(lists: &[bool]) -> String {
+    let mut xml = format!("{XML_DECL}<w:numbering xmlns:w=\"{MAIN_NS}\">");
+    for (id, (format, text)) in [("bullet", "•"), ("decimal", "%1.")].iter().enumerate() {
+        let _ = write!(
+            xml,
+            "<w:abstractNum w:abstractNumId=\"{id}\"><w:multiLevelType w:val=\"singleLevel\"/>\
+             <w:lvl w:ilvl=\"0\"><w:start w:val=\"1\"/><w:numFmt w:val=\"{format}\"/>\
+             <w:lvlText w:val=\"{text}\"/><w:lvlJc w:val=\"left\"/>\
+             <w:pPr><w:ind w:left=\"720\" w:hanging=\"360\"/></w:pPr></w:lvl></w:abstractNum>"
+        );
+    }
+    for (idx, ordered) in lists.iter().enumerate() {
+        let _ = write!(
+            xml,
+            "<w:num w:numId=\"{}\"><w:abstractNumId w:val=\"{}\"/><w:lvlOverride w:ilvl=\"0\">\
+             <w:startOverride w:val=\"1\"/></w:lvlOverride></w:num>",
+            idx + 1,
+            u8::from(*ordered)
+        );
+    }
+    xml.push_str("</w:numbering>");
+    xml
+}
@@ -0,0 +1,122 @@
+//! hOCR 1.2: XHTML whose `ocr_page`, `ocr_carea`, `ocr_par`, `ocr_line` and `ocrx_word`
+//! elements carry `bbox` (and `x_wconf` when a confidence is known) in their `title`.
+//! Illustrations become empty `ocr_photo` elements, and running headers and footers use
+//! `ocr_header` and `ocr_footer` in place of `ocr_carea`. Blocks are written in reading order;
+//! element ids follow Tesseract's `block_<page>_<n>` scheme.
+
+use std::fmt::Write;
+
+use super::{OCR_SYSTEM, OcrPage, escape_xml, word_boxes};
+use crate::reading_order::PageRegion;
+
+pub fn render(pages: &[OcrPage]) -> String {
+    let has_confidence = pages
+        .iter()
+        .flat_map(|page| &page.blocks)
+        .any(|block| block.confidence.is_some());
+    let mut capabilities = "ocr_page ocr_carea ocr_par ocr_line ocrx_word ocr_photo".to_owned();
+    for (region, class) in [
+        (PageRegion::Header, " ocr_header"),
+        (PageRegion::Footer, " ocr_footer"),
+    ] {
+        if pages
+            .iter()
+            .flat_map(|page| &page.blocks)
+            .any(|block| block.region == region)
+        {
+            capabilities.push_str(class);
+        }
+    }
+    if has_confidence {
+        capabilities.push_str(" ocrp_wconf");
+    }
+
+    let mut out = String::new();
+    out.push_str(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Transitional//EN\"\n",
+        "    \"http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd\">\n",
+        "<html xmlns=\"http://www.w3.org/1999/xhtml\" xml:lang=\"en\" lang=\"en\">\n",
+        " <head>\n",
+        "  <title></title>\n",
+        "  <meta http-equiv=\"Content-Type\" content=\"text/html;charset=utf-8\"/>\n",
+    ));
+    let _ = writeln!(
+        out,
+        "  <meta name=\"ocr-system\" content=\"{OCR_SYSTEM}\"/>\n  \
+         <meta name=\"ocr-capabilities\" content=\"{capabilities}\"/>\n \
+         </head>\n <body>"
+    );
+
+    for (index, page) in pages.iter().enumerate() {
+        let page_no = index + 1;
+        let image = page
+            .image_name
+            .as_deref()
+            .map(|name| format!("image &quot;{}&quot;; ", escape_xml(name)))
+            .unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "  <div class=\"ocr_page\" id=\"page_{page_no}\" title=\"{image}bbox 0 0 {} {}; ppageno {index}\">",
+            page.width, page.height
+        );
+        let (mut line_no, mut word_no) = (0, 0);
+        for (block_idx, block) in page.ordered_blocks().into_iter().enumerate() {
+            let id = format!("{page_no}_{}", block_idx + 1);
+            if block.is_illustration() {
+                let _ = writeln!(
+                    out,
+                    "   <div class=\"ocr_photo\" id=\"block_{id}\" title=\"{}\"></div>",
+                    bbox(block.bbox)
+                );
+                continue;
+            }
+            let title = bbox(block.bbox);
+            let class = match block.region {
+                PageRegion::Header => "ocr_header",
+                PageRegion::Body => "ocr_carea",
+                PageRegion::Footer => "ocr_footer",
+            };
+            let _ = writeln!(
+                out,
+                "   <div class=\"{class}\" id=\"block_{id}\" title=\"{title}\">\n    \
+                 <p class=\"ocr_par\" id=\"par_{id}\" title=\"{title}\">"
+            );
+            let wconf = block
+                .confidence
+                .map(|value| format!("; x_wconf {}", (value.clamp(0.0, 1.0) * 100.0).round()))
+                .unwrap_or_default();
+            for (line, line_box) in block.line_boxes() {
+                line_no += 1;
+                let _ = write!(
+                    out,
+                    "     <span class=\"ocr_line\" id=\"line_{page_no}_{line_no}\" title=\"{}\">",
+                    bbox(line_box)
+                );
+                for (word_idx, (word, word_box)) in
+                    word_boxes(line, line_box).into_iter().enumerate()
+                {
+                    word_no += 1;
+                    if word_idx > 0 {
+                        out.push(' ');
+                    }
+                    let _ = write!(
+                        out,
+                        "<span class=\"ocrx_word\" id=\"word_{page_no}_{word_no}\" title=\"{}{wconf}\">{}</span>",
+                        bbox(word_box),
+                        escape_xml(word)
+                    );
+                }
+                out.push_str("</span>\n");
+            }
+            out.push_str("    </p>\n   </div>\n");
+        }
+        out.push_str("  </div>\n");
+    }
+    out.push_str(" </body>\n</html>\n");
+    out
+}
+
+fn bbox([x0, y0, x1, y1]: [u32; 4]) -> String {
+    format!("bbox {x0} {y0} {x1} {y1}")
+}
@@ -0,0 +1,166 @@
+//! Standalone HTML5 document: one `<section>` per page with headings, paragraphs, lists,
+//! tables with their spanning cells, formulas as MathML (`\[...\]` LaTeX without the `mathml`
+//! feature) and figures as PNG crops embedded in `data:` URIs, so the file opens and edits
+//! without the page images next to it. Blocks follow the reading order, with running headers
+//! and footers in `<header>` and `<footer>`.
+
+use std::fmt::Write as _;
+
+use anyhow::Result;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use image::DynamicImage;
+
+use super::{BlockKind, OCR_SYSTEM, OcrBlock, OcrPage, crop_png, escape_xml, list_item};
+#[cfg(feature = "mathml")]
+use crate::formula::mathml::to_mathml;
+use crate::{reading_order::PageRegion, tables::Table};
+
+const STYLE: &str = "body{font-family:sans-serif;line-height:1.5;max-width:50em;margin:2em auto;padding:0 1em}\
+section.page+section.page{border-top:1px solid #ccc;margin-top:2em;padding-top:1em}\
+table{border-collapse:collapse;margin:1em 0}th,td{border:1px solid #999;padding:.25em .5em}\
+figure{margin:1em 0}figure img{max-width:100%;height:auto}\
+.caption,figcaption{font-style:italic}.formula{margin:1em 0;text-align:center}";
+
+/// Render `pages`, cropping figures from `images`, one per page. Figures of pages without an
+/// image are left out.
+pub fn render(pages: &[OcrPage], images: &[DynamicImage]) -> Result<String> {
+    let title = pages
+        .iter()
+        .flat_map(|page| &page.blocks)
+        .find(|block| matches!(block.kind, BlockKind::Heading(_)))
+        .map(|block| block.lines.join(" "))
+        .or_else(|| pages.iter().find_map(|page| page.image_name.clone()))
+        .unwrap_or_else(|| "OCR output".to_owned());
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"generator\" content=\"{OCR_SYSTEM}\">\n<title>{}</title>\n\
+         <style>{STYLE}</style>\n</head>\n<body>\n",
+        escape_xml(&title)
+    );
+    for (idx, page) in pages.iter().enumerate() {
+        let _ = write!(html, "<section class=\"page\" id=\"page-{}\"", idx + 1);
+        if let Some(name) = &page.image_name {
+            let _ = write!(html, " data-image=\"{}\"", escape_xml(name));
+        }
+        html.push_str(">\n");
+        let ordered = page.ordered_blocks();
+        for (region, wrapper) in [
+            (PageRegion::Header, Some("header")),
+            (PageRegion::Body, None),
+            (PageRegion::Footer, Some("footer")),
+        ] {
+            let mut blocks = ordered
+                .iter()
+                .copied()
+                .filter(|block| block.region == region)
+                .peekable();
+            if blocks.peek().is_none() {
+                continue;
+            }
+            if let Some(tag) = wrapper {
+                let _ = writeln!(html, "<{tag}>");
+            }
+            while let Some(block) = blocks.next() {
+                if block.kind == BlockKind::Figure {
+                    let caption = blocks.next_if(|next| next.kind == BlockKind::Caption);
+                    if let Some(image) = images.get(idx) {
+                        figure(&mut html, page, block, caption, image)?;
+                    } else if let Some(caption) = caption {
+                        text_block(&mut html, caption);
+                    }
+                    continue;
+                }
+                text_block(&mut html, block);
+            }
+            if let Some(tag) = wrapper {
+                let _ = writeln!(html, "</{tag}>");
+            }
+        }
+        html.push_str("</section>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+fn figure(
+    html: &mut String,
+    page: &OcrPage,
+    block: &OcrBlock,
+    caption: Option<&OcrBlock>,
+    image: &DynamicImage,
+) -> Result<()> {
+    let Some((png, width, height)) = crop_png(image, block.bbox, page.width, page.height)? else {
+        return Ok(());
+    };
+    let alt = caption.map(|caption| caption.lines.join(" "));
+    let _ = write!(
+        html,
+        "<figure><img src=\"data:image/png;base64,{}\" width=\"{width}\" height=\"{height}\" alt=\"{}\">",
+        STANDARD.encode(png),
+        escape_xml(alt.as_deref().unwrap_or("Figure"))
+    );
+    if let Some(alt) = alt {
+        let _ = write!(html, "<figcaption>{}</figcaption>", escape_xml(&alt));
+    }
+    html.push_str("</figure>\n");
+    Ok(())
+}
+
+fn text_block(html: &mut String, block: &OcrBlock) {
+    let text = || escape_xml(&block.lines.join(" "));
+    match &block.kind {
+        BlockKind::Heading(level) => {
+            let _ = writeln!(html, "<h{level}>{}</h{level}>", text());
+        }
+        BlockKind::Paragraph => {
+            let _ = writeln!(html, "<p>{}</p>", text());
+        }
+        BlockKind::List { ordered } => {
+            let tag = if *ordered { "ol" } else { "ul" };
+            let _ = write!(html, "<{tag}>");
+            for line in &block.lines {
+                let item = list_item(line).map_or(line.as_str(), |(_, item)| item);
+                let _ = write!(html, "<li>{}</li>", escape_xml(item));
+            }
+            let _ = writeln!(html, "</{tag}>");
+        }
+        BlockKind::Table(table) => {
+            html.push_str(&table_html(table));
+            html.push('\n');
+        }
+        BlockKind::Formula(latex) => {
+            #[cfg(feature = "mathml")]
+            let formula = to_mathml(latex, true);
+            #[cfg(not(feature = "mathml"))]
+            let formula = format!("\\[{}\\]", escape_xml(latex));
+            let _ = writeln!(html, "<div class=\"formula\">{formula}</div>");
+        }
+        BlockKind::Figure => {}
+        BlockKind::Caption => {
+            let _ = writeln!(html, "<p class=\"caption\">{}</p>", text());
+        }
+    }
+}
+
+/// `table` as an HTML table, header cells as `<th>` and spans as `rowspan`/`colspan`.
+pub(crate) fn table_html(table: &Table) -> String {
+    let mut html = String::from("<table>");
+    let mut cells = table.cells.iter().peekable();
+    for row in 0..table.rows {
+        html.push_str("<tr>");
+        while let Some(cell) = cells.next_if(|cell| cell.row == row) {
+            let tag = if cell.header { "th" } else { "td" };
+            let _ = write!(html, "<{tag}");
+            if cell.row_span > 1 {
+                let _ = write!(html, " rowspan=\"{}\"", cell.row_span);
+            }
+            if cell.column_span > 1 {
+                let _ = write!(html, " colspan=\"{}\"", cell.column_span);
+            }
+            let _ = write!(html, ">{}</{tag}>", escape_xml(&cell.text));
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</table>");
+    html
+}
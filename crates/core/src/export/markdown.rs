@@ -0,0 +1,38 @@
+//! Markdown rebuilt from the layout blocks in reading order: `#` headings, pipe tables (HTML
+//! for tables with spanning cells), `$$` display formulas and captions in italics. Figures have
+//! no text and are left out.
+
+use super::{BlockKind, OcrPage, html::table_html};
+
+/// Render `pages` as one markdown document, blocks separated by blank lines.
+pub fn render(pages: &[OcrPage]) -> String {
+    let mut blocks = Vec::new();
+    for block in pages.iter().flat_map(OcrPage::ordered_blocks) {
+        let text = match &block.kind {
+            BlockKind::Heading(level) => format!(
+                "{} {}",
+                "#".repeat(usize::from(*level)),
+                block.lines.join(" ")
+            ),
+            BlockKind::Paragraph | BlockKind::List { .. } => block.lines.join("\n"),
+            BlockKind::Table(table) => table.to_markdown().unwrap_or_else(|| table_html(table)),
+            BlockKind::Formula(latex) => format!("$$\n{latex}\n$$"),
+            BlockKind::Figure => continue,
+            BlockKind::Caption => format!("*{}*", escape_emphasis(&block.lines.join(" "))),
+        };
+        blocks.push(text);
+    }
+    blocks.join("\n\n")
+}
+
+/// `text` with the characters that would end or nest the caption's `*` emphasis escaped.
+fn escape_emphasis(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(ch, '\\' | '*' | '_') {
+            out.push('\\');
+        }
+        out.push(ch);
+    }
+    out
+}
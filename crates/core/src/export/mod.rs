@@ -0,0 +1,553 @@
+//! Structured page exports of OCR output: hOCR, ALTO XML, searchable PDF, and markdown, HTML
+//! and DOCX documents.
+//!
+//! [`OcrPage::from_grounded`] turns grounded output into blocks with pixel boxes, kept in the
+//! order the model emitted them, and numbers them in the reading order rebuilt by
+//! [`crate::reading_order`], which every export follows unless the native order is asked for
+//! with [`OcrPage::with_reading_order`]. Running headers and footers are kept apart from the
+//! body. Each block also gets a [`BlockKind`] recovered from its label and markup — heading,
+//! paragraph, list, table, formula, figure or caption — which the markdown, HTML and DOCX
+//! documents are built from; figures are cropped from the page images. The model only boxes
+//! whole blocks; line boxes split a block's height evenly between its lines and word boxes
+//! split a line's width by character count, which is enough for search highlighting but not
+//! for glyph-level alignment. Output without grounding markup is split into blocks at blank
+//! lines, each given a share of the page height by its line count; those made-up boxes take no
+//! part in the layout order, and each such block stays after the grounded block the model
+//! emitted before it. Each page is decoded on its own, with the prompt from [`page_prompt`].
+
+pub mod alto;
+pub mod docx;
+pub mod hocr;
+pub mod html;
+pub mod markdown;
+pub mod pdf;
+
+use std::{collections::HashMap, fmt, io::Cursor};
+
+use anyhow::{Context, Result, ensure};
+use clap::ValueEnum;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    formula::find_formulas,
+    grounding::{parse_grounded, to_pixels},
+    reading_order::{PageRegion, ReadingOrder, page_region, reading_order},
+    tables::{Table, extract_tables},
+};
+
+/// Software name recorded in the exported documents.
+pub(crate) const OCR_SYSTEM: &str = "deepseek-ocr.rs";
+
+/// How decoded text is returned.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[value(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// The model output as generated.
+    #[default]
+    Text,
+    /// hOCR 1.2 XHTML.
+    Hocr,
+    /// ALTO v4 XML.
+    Alto,
+    /// PDF with the page images under an invisible text layer.
+    Pdf,
+    /// Markdown rebuilt from the layout blocks.
+    Markdown,
+    /// Standalone HTML with embedded figure crops.
+    Html,
+    /// Word document with embedded figure crops.
+    Docx,
+}
+
+impl OutputFormat {
+    /// Whether the output is a page document, rendered once every page has been decoded.
+    pub fn is_document(self) -> bool {
+        self != Self::Text
+    }
+
+    /// Whether the rendered output is binary rather than UTF-8 text.
+    pub fn is_binary(self) -> bool {
+        matches!(self, Self::Pdf | Self::Docx)
+    }
+
+    /// Whether rendering uses the page images.
+    pub fn embeds_images(self) -> bool {
+        matches!(self, Self::Pdf | Self::Html | Self::Docx)
+    }
+
+    /// Render `pages`. PDF embeds `images`, one per page, and HTML and DOCX crop their figures
+    /// from them; the other formats ignore them. [`OutputFormat::Text`] joins the block texts
+    /// in reading order, without grounding markup. To show the original scans rather than the
+    /// images the boxes refer to, call [`pdf::render`] with [`pdf::PageScan`]s instead.
+    pub fn render(self, pages: &[OcrPage], images: &[DynamicImage]) -> Result<Vec<u8>> {
+        Ok(match self {
+            Self::Text => pages
+                .iter()
+                .flat_map(OcrPage::ordered_blocks)
+                .map(|block| block.lines.join("\n"))
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n\n")
+                .into_bytes(),
+            Self::Hocr => hocr::render(pages).into_bytes(),
+            Self::Alto => alto::render(pages).into_bytes(),
+            Self::Pdf => {
+                let scans: Vec<_> = images.iter().cloned().map(pdf::PageScan::new).collect();
+                pdf::render(pages, &scans)?
+            }
+            Self::Markdown => markdown::render(pages).into_bytes(),
+            Self::Html => html::render(pages, images)?.into_bytes(),
+            Self::Docx => docx::render(pages, images)?,
+        })
+    }
+}
+
+/// Prompt for decoding one page of a document: the `<image>` slots of `prompt` must form a
+/// single run, which collapses to one slot, so a prompt written for every page applies to each.
+pub fn page_prompt(prompt: &str) -> Result<String> {
+    const SLOT: &str = "<image>";
+    let Some(start) = prompt.find(SLOT) else {
+        return Ok(prompt.to_owned());
+    };
+    let mut rest = &prompt[start..];
+    while let Some(next) = rest.strip_prefix(SLOT) {
+        rest = next;
+    }
+    ensure!(
+        !rest.contains(SLOT),
+        "document output decodes pages one at a time, so the <image> slots must be adjacent"
+    );
+    Ok(format!("{}{SLOT}{rest}", &prompt[..start]))
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Text => "text",
+            Self::Hocr => "hocr",
+            Self::Alto => "alto",
+            Self::Pdf => "pdf",
+            Self::Markdown => "markdown",
+            Self::Html => "html",
+            Self::Docx => "docx",
+        };
+        f.write_str(name)
+    }
+}
+
+/// One recognised image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrPage {
+    pub width: u32,
+    pub height: u32,
+    /// Source file name, recorded in the document when known.
+    pub image_name: Option<String>,
+    /// Blocks in the order the model emitted them; [`OcrPage::ordered_blocks`] follows
+    /// [`OcrBlock::order`].
+    pub blocks: Vec<OcrBlock>,
+}
+
+/// The structure of a block, from its grounding label or, for unlabelled text, its markup.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockKind {
+    /// A heading of level 1 to 6; titles are level 1.
+    Heading(u8),
+    Paragraph,
+    /// List items, one per line, markers included.
+    List {
+        ordered: bool,
+    },
+    Table(Table),
+    /// A display formula, as LaTeX without delimiters.
+    Formula(String),
+    /// A figure, chart or photo, cropped from the page image by its box.
+    Figure,
+    /// The caption of a figure or table.
+    Caption,
+}
+
+/// A text block or, when it has no lines, an illustration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrBlock {
+    /// Grounding label (`title`, `text`, `table`, `image`, ...); empty for ungrounded text.
+    pub label: String,
+    /// What the block holds.
+    pub kind: BlockKind,
+    /// Position in reading order on the page, from 0.
+    pub order: usize,
+    /// Header, body or footer.
+    pub region: PageRegion,
+    /// `[x0, y0, x1, y1]` in pixels of the page.
+    pub bbox: [u32; 4],
+    /// Whether `bbox` came from the model rather than from stacking ungrounded text down the
+    /// page.
+    pub grounded: bool,
+    /// Text lines with markdown headings and HTML table markup removed.
+    pub lines: Vec<String>,
+    /// Recognition confidence in `0.0..=1.0`, applied to every word of the block.
+    pub confidence: Option<f32>,
+}
+
+impl OcrPage {
+    /// Build a page from model output for an image of `width`×`height` pixels, numbered in layout
+    /// reading order. Entries without text are dropped unless labelled as an image or figure;
+    /// entries without boxes are split at blank lines into blocks stacked down the page.
+    pub fn from_grounded(text: &str, width: u32, height: u32) -> Self {
+        let mut blocks = Vec::new();
+        for block in parse_grounded(text) {
+            if block.boxes.is_empty() {
+                let chunks = chunks(&block.text);
+                let total = chunks
+                    .iter()
+                    .map(|chunk| chunk.lines().count())
+                    .sum::<usize>();
+                let mut offset = 0;
+                for chunk in chunks {
+                    let count = chunk.lines().count();
+                    let bbox = [
+                        0,
+                        lerp(0, height, offset as u64, total as u64),
+                        width,
+                        lerp(0, height, (offset + count) as u64, total as u64),
+                    ];
+                    offset += count;
+                    blocks.extend(OcrBlock::new(&block.label, chunk, bbox, None));
+                }
+                continue;
+            }
+            let bbox = block
+                .boxes
+                .iter()
+                .map(|bbox| to_pixels(*bbox, width, height))
+                .reduce(|a, b| {
+                    [
+                        a[0].min(b[0]),
+                        a[1].min(b[1]),
+                        a[2].max(b[2]),
+                        a[3].max(b[3]),
+                    ]
+                })
+                .unwrap_or([0, 0, width, height]);
+            blocks.extend(OcrBlock::new(&block.label, &block.text, bbox, Some(height)));
+        }
+        Self {
+            width,
+            height,
+            image_name: None,
+            blocks,
+        }
+        .with_reading_order(ReadingOrder::Layout)
+    }
+
+    /// Number the blocks in `order`: rebuilt from their boxes, or as the model emitted them.
+    pub fn with_reading_order(mut self, order: ReadingOrder) -> Self {
+        let sequence = match order {
+            ReadingOrder::Layout => self.layout_sequence(),
+            ReadingOrder::Native => (0..self.blocks.len()).collect(),
+        };
+        for (position, idx) in sequence.into_iter().enumerate() {
+            self.blocks[idx].order = position;
+        }
+        self
+    }
+
+    /// Block indices in layout order: grounded blocks by XY-cut of their boxes, each followed by
+    /// the ungrounded blocks the model emitted after it, whose stacked boxes say nothing about
+    /// where they sit.
+    fn layout_sequence(&self) -> Vec<usize> {
+        let grounded: Vec<usize> = (0..self.blocks.len())
+            .filter(|&idx| self.blocks[idx].grounded)
+            .collect();
+        let boxes: Vec<_> = grounded.iter().map(|&idx| self.blocks[idx].bbox).collect();
+        let regions: Vec<_> = grounded
+            .iter()
+            .map(|&idx| self.blocks[idx].region)
+            .collect();
+        let laid_out = reading_order(&boxes, &regions, self.width, self.height);
+
+        let mut anchor = None;
+        let mut following: HashMap<Option<usize>, Vec<usize>> = HashMap::new();
+        for (idx, block) in self.blocks.iter().enumerate() {
+            if block.grounded {
+                anchor = Some(idx);
+            } else {
+                following.entry(anchor).or_default().push(idx);
+            }
+        }
+        let mut sequence = following.remove(&None).unwrap_or_default();
+        for idx in laid_out.into_iter().map(|position| grounded[position]) {
+            sequence.push(idx);
+            sequence.extend(following.remove(&Some(idx)).unwrap_or_default());
+        }
+        sequence
+    }
+
+    /// Blocks in reading order.
+    pub fn ordered_blocks(&self) -> Vec<&OcrBlock> {
+        let mut blocks: Vec<&OcrBlock> = self.blocks.iter().collect();
+        blocks.sort_by_key(|block| block.order);
+        blocks
+    }
+
+    pub fn with_image_name(mut self, name: impl Into<String>) -> Self {
+        self.image_name = Some(name.into());
+        self
+    }
+}
+
+impl OcrBlock {
+    /// A block for `text`, or `None` when it has no text and is not an image or figure. Only
+    /// blocks boxed on a page `page_height` pixels tall can be headers or footers.
+    fn new(label: &str, text: &str, bbox: [u32; 4], page_height: Option<u32>) -> Option<Self> {
+        let lines = block_lines(text);
+        let kind = block_kind(label, text);
+        if lines.is_empty() && kind != BlockKind::Figure {
+            return None;
+        }
+        let region = page_height.map_or(PageRegion::Body, |height| {
+            page_region(label, bbox, lines.len(), height)
+        });
+        Some(Self {
+            label: label.to_owned(),
+            kind,
+            order: 0,
+            region,
+            bbox,
+            grounded: page_height.is_some(),
+            lines,
+            confidence: None,
+        })
+    }
+
+    pub fn is_illustration(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Each line with its interpolated box.
+    pub fn line_boxes(&self) -> impl Iterator<Item = (&str, [u32; 4])> {
+        let [x0, y0, x1, y1] = self.bbox;
+        let count = self.lines.len() as u64;
+        self.lines.iter().enumerate().map(move |(idx, line)| {
+            let top = lerp(y0, y1, idx as u64, count);
+            let bottom = lerp(y0, y1, idx as u64 + 1, count);
+            (line.as_str(), [x0, top, x1, bottom])
+        })
+    }
+}
+
+/// Classify a block by its grounding label, falling back on its markup: `#` headings, lists,
+/// a table, or a text that is a single display formula.
+fn block_kind(label: &str, text: &str) -> BlockKind {
+    let text = text.trim();
+    let hashes = text.bytes().take_while(|&b| b == b'#').count();
+    let heading = (1..=6).contains(&hashes) && text[hashes..].starts_with([' ', '\t']);
+    match label {
+        "image" | "figure" => return BlockKind::Figure,
+        "title" => return BlockKind::Heading(if heading { hashes as u8 } else { 1 }),
+        "sub_title" | "subtitle" => {
+            return BlockKind::Heading(if heading { hashes as u8 } else { 2 });
+        }
+        label if label.ends_with("caption") => return BlockKind::Caption,
+        _ => {}
+    }
+    if (label == "table" || text.starts_with(['<', '|']))
+        && let [table] = extract_tables(text).as_slice()
+    {
+        return BlockKind::Table(table.clone());
+    }
+    let formulas = find_formulas(text);
+    if let [formula] = formulas.as_slice()
+        && formula.display
+        && formula.start == 0
+        && formula.end == text.len()
+    {
+        return BlockKind::Formula(formula.latex.clone());
+    }
+    if matches!(label, "equation" | "formula") {
+        return BlockKind::Formula(text.to_owned());
+    }
+    if heading && !text.contains('\n') {
+        return BlockKind::Heading(hashes as u8);
+    }
+    let mut markers = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(list_item);
+    match markers.next().flatten() {
+        Some((ordered, _)) if markers.all(|item| item.is_some()) => BlockKind::List { ordered },
+        _ => BlockKind::Paragraph,
+    }
+}
+
+/// Whether `line` is a list item, whether it is numbered, and the text after its marker.
+pub(crate) fn list_item(line: &str) -> Option<(bool, &str)> {
+    let (marker, rest) = line.trim_start().split_once(' ')?;
+    if matches!(marker, "-" | "*" | "+" | "•") {
+        return Some((false, rest.trim_start()));
+    }
+    let number = marker.strip_suffix(['.', ')'])?;
+    (!number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+        .then_some((true, rest.trim_start()))
+}
+
+/// Blank-line separated chunks of `text`; blank lines inside code fences and display math do
+/// not split.
+fn chunks(text: &str) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut end = 0;
+    let mut closer: Option<&str> = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let line_start = offset;
+        offset += line.len();
+        if trimmed.is_empty() && closer.is_none() {
+            if let Some(first) = start.take() {
+                chunks.push(&text[first..end]);
+            }
+            continue;
+        }
+        start.get_or_insert(line_start);
+        end = line_start + line.trim_end().len();
+        closer = match closer {
+            Some(close) if trimmed.ends_with(close) => None,
+            Some(close) => Some(close),
+            None if trimmed.starts_with("```") => Some("```"),
+            None if trimmed.starts_with("$$") && !trimmed[2..].contains("$$") => Some("$$"),
+            None if trimmed.starts_with("\\[") && !trimmed.ends_with("\\]") => Some("\\]"),
+            None => None,
+        };
+    }
+    if let Some(first) = start {
+        chunks.push(&text[first..end]);
+    }
+    chunks
+}
+
+/// The part of `image` under `bbox`, a box on a `width`×`height` page, as PNG. `None` when the
+/// box is empty.
+pub(crate) fn crop_png(
+    image: &DynamicImage,
+    bbox: [u32; 4],
+    width: u32,
+    height: u32,
+) -> Result<Option<(Vec<u8>, u32, u32)>> {
+    let (image_width, image_height) = image.dimensions();
+    let scale = |value: u32, page: u32, size: u32| {
+        (u64::from(value) * u64::from(size) / u64::from(page.max(1))).min(u64::from(size)) as u32
+    };
+    let x0 = scale(bbox[0], width, image_width);
+    let y0 = scale(bbox[1], height, image_height);
+    let x1 = scale(bbox[2], width, image_width);
+    let y1 = scale(bbox[3], height, image_height);
+    if x1 <= x0 || y1 <= y0 {
+        return Ok(None);
+    }
+    let crop = image.crop_imm(x0, y0, x1 - x0, y1 - y0);
+    let mut png = Vec::new();
+    crop.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .context("failed to encode figure crop")?;
+    Ok(Some((png, x1 - x0, y1 - y0)))
+}
+
+/// Whitespace-separated words of `line` with boxes proportional to their character offsets
+/// within `bbox`.
+pub(crate) fn word_boxes(line: &str, bbox: [u32; 4]) -> Vec<(&str, [u32; 4])> {
+    let [x0, y0, x1, y1] = bbox;
+    let total = line.chars().count() as u64;
+    let mut words = Vec::new();
+    let mut start = None;
+    let chars = line.char_indices().chain([(line.len(), ' ')]);
+    for (offset, (byte, ch)) in (0u64..).zip(chars) {
+        match (ch.is_whitespace(), start) {
+            (false, None) => start = Some((byte, offset)),
+            (true, Some((begin, first))) => {
+                words.push((
+                    &line[begin..byte],
+                    [
+                        lerp(x0, x1, first, total),
+                        y0,
+                        lerp(x0, x1, offset, total),
+                        y1,
+                    ],
+                ));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+/// Escape text for XML content and double-quoted attributes, dropping control characters
+/// XML 1.0 does not allow.
+pub(crate) fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            '\t' | '\n' | '\r' => out.push(ch),
+            ch if ch.is_control() => {}
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+fn lerp(start: u32, end: u32, step: u64, steps: u64) -> u32 {
+    if steps == 0 {
+        return start;
+    }
+    let span = u64::from(end.saturating_sub(start));
+    start + (span * step / steps) as u32
+}
+
+/// Non-empty lines of a block's text, without markdown heading markers and with HTML tags
+/// removed; table rows and `<br>` become line breaks, cells are separated by spaces.
+fn block_lines(text: &str) -> Vec<String> {
+    let mut plain = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        let after = &rest[start + 1..];
+        let is_tag = after.starts_with(|ch: char| ch.is_ascii_alphabetic() || ch == '/');
+        let Some(len) = after.find('>').filter(|_| is_tag) else {
+            plain.push_str(&rest[..=start]);
+            rest = after;
+            continue;
+        };
+        plain.push_str(&rest[..start]);
+        let tag = after[..len].trim_start_matches('/').to_ascii_lowercase();
+        let name = tag
+            .split(|ch: char| ch.is_whitespace() || ch == '/')
+            .next()
+            .unwrap_or_default();
+        plain.push(if matches!(name, "tr" | "br" | "p" | "div") {
+            '\n'
+        } else {
+            ' '
+        });
+        rest = &after[len + 1..];
+    }
+    plain.push_str(rest);
+    plain
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            let heading = line.trim_start_matches('#');
+            let line = if heading.len() < line.len() && heading.starts_with(' ') {
+                heading
+            } else {
+                line
+            };
+            line.split_whitespace().collect::<Vec<_>>().join(" ")
+        })
+        .filter(|line| !line.is_empty())
+        .collect()
+}
@@ -0,0 +1,582 @@
+//! Searchable PDF: every page shows its image at full pixel resolution under an invisible text
+//! layer (render mode 3) placed on the interpolated line boxes, so viewers can select and search
+//! the recognised text.
+//!
+//! Text is set in a Type0 font whose codes are Unicode code points and whose embedded TrueType
+//! program has no visible glyphs, the approach Tesseract uses; a `ToUnicode` map makes copy and
+//! search work for any script in the Basic Multilingual Plane.
+//!
+//! Each page shows the input as decoded, before cleanup: OCR boxes, which refer to the cleaned
+//! image, are mapped back through the [`CleanupReport`]. JPEG inputs are embedded unchanged with
+//! `DCTDecode` and drawn through a matrix that applies their orientation fix; other images are
+//! Flate-compressed losslessly, grayscale pages as `DeviceGray`.
+
+use std::{io::Write, sync::Arc};
+
+use anyhow::{Context, Result, ensure};
+use flate2::{Compression, write::ZlibEncoder};
+use image::DynamicImage;
+
+use super::{OCR_SYSTEM, OcrPage};
+use crate::{
+    cleanup::CleanupReport,
+    orientation::{OrientationReport, OrientedImage, Rotation},
+};
+
+/// Resolution used to turn pixels into page points.
+pub const PDF_DPI: f32 = 300.0;
+
+const FONT: &str = "GlyphLessFont";
+/// Advance of every glyph, in 1/1000 em.
+const GLYPH_ADVANCE: f32 = 500.0;
+const ASCENT: f32 = 800.0;
+const DESCENT: f32 = -200.0;
+
+// Fixed objects; page objects follow from `FIRST_PAGE_OBJECT`, three per page.
+const CATALOG: usize = 1;
+const PAGES: usize = 2;
+const TYPE0_FONT: usize = 3;
+const CID_FONT: usize = 4;
+const FONT_DESCRIPTOR: usize = 5;
+const FONT_FILE: usize = 6;
+const CID_TO_GID: usize = 7;
+const TO_UNICODE: usize = 8;
+const INFO: usize = 9;
+const FIRST_PAGE_OBJECT: usize = 10;
+
+/// One page image for [`render`]: the scan as the reader should see it.
+#[derive(Debug, Clone)]
+pub struct PageScan {
+    /// The upright page before cleanup; the PDF page is sized to it.
+    pub image: DynamicImage,
+    /// The JPEG stream `image` was decoded from, embedded as is when set.
+    pub source_jpeg: Option<Arc<[u8]>>,
+    /// Orientation fixes that turned the stored JPEG into `image`.
+    pub orientation: Option<OrientationReport>,
+    /// Cleanup that produced the image the OCR boxes refer to; boxes are mapped back through it.
+    pub cleanup: Option<CleanupReport>,
+}
+
+impl PageScan {
+    /// Page showing `image`, which the OCR boxes refer to directly.
+    pub fn new(image: DynamicImage) -> Self {
+        Self {
+            image,
+            source_jpeg: None,
+            orientation: None,
+            cleanup: None,
+        }
+    }
+
+    /// Page showing an input image as decoded, before `cleanup` prepared it for the model.
+    pub fn from_input(input: OrientedImage, cleanup: CleanupReport) -> Self {
+        Self {
+            image: input.image,
+            source_jpeg: input.source_jpeg,
+            orientation: Some(input.report),
+            cleanup: Some(cleanup),
+        }
+    }
+
+    /// Map a pixel box on the recognised `page` onto this page, in points from the top left.
+    fn page_box(
+        &self,
+        page: &OcrPage,
+        [x0, y0, x1, y1]: [u32; 4],
+        (width, height): (f32, f32),
+    ) -> [f32; 4] {
+        let (page_width, page_height) = (page.width.max(1) as f32, page.height.max(1) as f32);
+        let mut bbox = [
+            x0 as f32 / page_width,
+            y0 as f32 / page_height,
+            x1 as f32 / page_width,
+            y1 as f32 / page_height,
+        ];
+        if let Some(cleanup) = &self.cleanup {
+            bbox = cleanup.to_source_box(bbox);
+        }
+        [
+            bbox[0] * width,
+            bbox[1] * height,
+            bbox[2] * width,
+            bbox[3] * height,
+        ]
+    }
+
+    /// The source JPEG with its colour space and the transform from its stored orientation to
+    /// the upright page, when it can be embedded unchanged.
+    fn passthrough_jpeg(&self) -> Option<(&[u8], &'static str, Affine)> {
+        let jpeg = self.source_jpeg.as_deref()?;
+        let (width, height, components) = jpeg_frame(jpeg)?;
+        let color_space = match components {
+            1 => "DeviceGray",
+            3 => "DeviceRGB",
+            // CMYK JPEGs are often stored inverted; re-encode them from the decoded pixels.
+            _ => return None,
+        };
+        let transform = self
+            .orientation
+            .as_ref()
+            .map_or(IDENTITY, upright_transform);
+        let swapped = transform[0][0] == 0.0;
+        let upright = if swapped {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        (upright == (self.image.width(), self.image.height())).then_some((
+            jpeg,
+            color_space,
+            transform,
+        ))
+    }
+}
+
+impl From<DynamicImage> for PageScan {
+    fn from(image: DynamicImage) -> Self {
+        Self::new(image)
+    }
+}
+
+/// Write `pages` over their `scans`, one PDF page per scan.
+pub fn render(pages: &[OcrPage], scans: &[PageScan]) -> Result<Vec<u8>> {
+    ensure!(
+        pages.len() == scans.len(),
+        "PDF output needs one image per page ({} pages, {} images)",
+        pages.len(),
+        scans.len()
+    );
+    ensure!(!pages.is_empty(), "PDF output needs at least one page");
+
+    let mut pdf = PdfWriter::new(FIRST_PAGE_OBJECT + 3 * pages.len() - 1);
+    pdf.object(CATALOG, &format!("<< /Type /Catalog /Pages {PAGES} 0 R >>"));
+    let kids = (0..pages.len())
+        .map(|idx| format!("{} 0 R", FIRST_PAGE_OBJECT + 3 * idx))
+        .collect::<Vec<_>>()
+        .join(" ");
+    pdf.object(
+        PAGES,
+        &format!("<< /Type /Pages /Kids [{kids}] /Count {} >>", pages.len()),
+    );
+    pdf.object(INFO, &format!("<< /Producer ({OCR_SYSTEM}) >>"));
+    write_font(&mut pdf)?;
+
+    for (idx, (page, scan)) in pages.iter().zip(scans).enumerate() {
+        let page_id = FIRST_PAGE_OBJECT + 3 * idx;
+        let (content_id, image_id) = (page_id + 1, page_id + 2);
+        let scale = 72.0 / PDF_DPI;
+        let (page_width, page_height) = (
+            scan.image.width() as f32 * scale,
+            scan.image.height() as f32 * scale,
+        );
+        pdf.object(
+            page_id,
+            &format!(
+                "<< /Type /Page /Parent {PAGES} 0 R /MediaBox [0 0 {page_width:.2} {page_height:.2}] \
+                 /Resources << /XObject << /Im0 {image_id} 0 R >> /Font << /F1 {TYPE0_FONT} 0 R >> \
+                 /ProcSet [/PDF /Text /ImageB /ImageC] >> /Contents {content_id} 0 R >>"
+            ),
+        );
+
+        let placement = match scan.passthrough_jpeg() {
+            Some((jpeg, color_space, transform)) => {
+                let (width, height) = jpeg_frame(jpeg).map_or((0, 0), |(w, h, _)| (w, h));
+                pdf.stream_with_filter(
+                    image_id,
+                    &format!(
+                        "/Type /XObject /Subtype /Image /Width {width} /Height {height} \
+                         /ColorSpace /{color_space} /BitsPerComponent 8"
+                    ),
+                    "DCTDecode",
+                    jpeg,
+                );
+                transform
+            }
+            None => {
+                let (width, height) = (scan.image.width(), scan.image.height());
+                let (color_space, pixels) = match &scan.image {
+                    DynamicImage::ImageLuma8(gray) => ("DeviceGray", gray.as_raw().clone()),
+                    other => ("DeviceRGB", other.to_rgb8().into_raw()),
+                };
+                pdf.stream(
+                    image_id,
+                    &format!(
+                        "/Type /XObject /Subtype /Image /Width {width} /Height {height} \
+                         /ColorSpace /{color_space} /BitsPerComponent 8"
+                    ),
+                    &deflate(&pixels)?,
+                );
+                IDENTITY
+            }
+        };
+        let content = page_content(page, scan, (page_width, page_height), placement);
+        pdf.stream(content_id, "", &deflate(content.as_bytes())?);
+    }
+    Ok(pdf.finish())
+}
+
+/// Affine map `[[a1, b1, c1], [a2, b2, c2]]` of normalised coordinates, y pointing down.
+type Affine = [[f32; 3]; 2];
+
+const IDENTITY: Affine = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+
+/// Map from the stored image to the upright one: the EXIF orientation, then the rotation.
+fn upright_transform(report: &OrientationReport) -> Affine {
+    let rotation = match report.rotation {
+        Rotation::None => 1,
+        Rotation::Cw90 => 6,
+        Rotation::Cw180 => 3,
+        Rotation::Cw270 => 8,
+    };
+    compose(
+        exif_transform(rotation),
+        exif_transform(report.exif_orientation.unwrap_or(1)),
+    )
+}
+
+/// Map from stored to displayed coordinates for an EXIF orientation tag.
+fn exif_transform(tag: u8) -> Affine {
+    match tag {
+        2 => [[-1.0, 0.0, 1.0], [0.0, 1.0, 0.0]],
+        3 => [[-1.0, 0.0, 1.0], [0.0, -1.0, 1.0]],
+        4 => [[1.0, 0.0, 0.0], [0.0, -1.0, 1.0]],
+        5 => [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0]],
+        6 => [[0.0, -1.0, 1.0], [1.0, 0.0, 0.0]],
+        7 => [[0.0, -1.0, 1.0], [-1.0, 0.0, 1.0]],
+        8 => [[0.0, 1.0, 0.0], [-1.0, 0.0, 1.0]],
+        _ => IDENTITY,
+    }
+}
+
+/// `outer` after `inner`.
+fn compose(outer: Affine, inner: Affine) -> Affine {
+    let row = |[a, b, c]: [f32; 3]| {
+        [
+            a * inner[0][0] + b * inner[1][0],
+            a * inner[0][1] + b * inner[1][1],
+            a * inner[0][2] + b * inner[1][2] + c,
+        ]
+    };
+    [row(outer[0]), row(outer[1])]
+}
+
+/// Image matrix drawing an image whose normalised coordinates map onto the page by `transform`.
+/// PDF images fill the unit square with their first row at the top, and page space points up.
+fn image_matrix([[a1, b1, c1], [a2, b2, c2]]: Affine, width: f32, height: f32) -> [f32; 6] {
+    [
+        width * a1,
+        -height * a2,
+        -width * b1,
+        height * b2,
+        width * (b1 + c1),
+        height * (1.0 - b2 - c2),
+    ]
+    // Adding zero turns `-0.0` into `0.0`, which keeps the content stream tidy.
+    .map(|value| value + 0.0)
+}
+
+/// Width, height and component count from the frame header of an 8-bit JPEG.
+fn jpeg_frame(data: &[u8]) -> Option<(u32, u32, u8)> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut pos = 2;
+    while let Some(&[0xFF, marker, high, low]) = data.get(pos..pos + 4) {
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        // Start-of-frame markers, without DHT (C4), JPG (C8) and DAC (CC).
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let frame = data.get(pos + 4..pos + 10)?;
+            let height = u16::from_be_bytes([frame[1], frame[2]]);
+            let width = u16::from_be_bytes([frame[3], frame[4]]);
+            return (frame[0] == 8).then_some((width.into(), height.into(), frame[5]));
+        }
+        pos += 2 + u16::from_be_bytes([high, low]) as usize;
+    }
+    None
+}
+
+/// Draw the image over the whole page, then each text line, stretched to its box.
+fn page_content(
+    page: &OcrPage,
+    scan: &PageScan,
+    (width, height): (f32, f32),
+    placement: Affine,
+) -> String {
+    let [a, b, c, d, e, f] = image_matrix(placement, width, height);
+    let mut content = format!("q\n{a:.2} {b:.2} {c:.2} {d:.2} {e:.2} {f:.2} cm\n/Im0 Do\nQ\n");
+    let lines: Vec<_> = page
+        .ordered_blocks()
+        .into_iter()
+        .flat_map(|block| block.line_boxes())
+        .collect();
+    if lines.is_empty() {
+        return content;
+    }
+    content.push_str("BT\n3 Tr\n");
+    for (line, bbox) in lines {
+        let [x0, y0, x1, y1] = scan.page_box(page, bbox, (width, height));
+        let (box_width, box_height) = (x1 - x0, y1 - y0);
+        let chars = line.chars().count();
+        if chars == 0 || box_width <= 0.0 || box_height <= 0.0 {
+            continue;
+        }
+        // Size the font to the line height and scale it horizontally to the line width; the
+        // baseline sits above the bottom edge by the descent so selections cover the box.
+        let size = box_height * 1000.0 / (ASCENT - DESCENT);
+        let natural_width = chars as f32 * GLYPH_ADVANCE / 1000.0 * size;
+        let stretch = 100.0 * box_width / natural_width;
+        let baseline = height - y1 - DESCENT / 1000.0 * size;
+        let codes: String = line
+            .chars()
+            .map(|ch| format!("{:04X}", bmp_code(ch)))
+            .collect();
+        content.push_str(&format!(
+            "/F1 {size:.2} Tf\n{stretch:.2} Tz\n1 0 0 1 {x0:.2} {baseline:.2} Tm\n<{codes}> Tj\n"
+        ));
+    }
+    content.push_str("ET\n");
+    content
+}
+
+/// Two-byte code of `ch`; characters outside the BMP become U+FFFD.
+fn bmp_code(ch: char) -> u16 {
+    u16::try_from(u32::from(ch)).unwrap_or(0xFFFD)
+}
+
+fn write_font(pdf: &mut PdfWriter) -> Result<()> {
+    pdf.object(
+        TYPE0_FONT,
+        &format!(
+            "<< /Type /Font /Subtype /Type0 /BaseFont /{FONT} /Encoding /Identity-H \
+             /DescendantFonts [{CID_FONT} 0 R] /ToUnicode {TO_UNICODE} 0 R >>"
+        ),
+    );
+    pdf.object(
+        CID_FONT,
+        &format!(
+            "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /{FONT} \
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> \
+             /FontDescriptor {FONT_DESCRIPTOR} 0 R /DW {GLYPH_ADVANCE} /CIDToGIDMap {CID_TO_GID} 0 R >>"
+        ),
+    );
+    pdf.object(
+        FONT_DESCRIPTOR,
+        &format!(
+            "<< /Type /FontDescriptor /FontName /{FONT} /Flags 5 \
+             /FontBBox [0 {DESCENT} {GLYPH_ADVANCE} {ASCENT}] /ItalicAngle 0 /Ascent {ASCENT} \
+             /Descent {DESCENT} /CapHeight {ASCENT} /StemV 80 /FontFile2 {FONT_FILE} 0 R >>"
+        ),
+    );
+    let program = glyphless_truetype();
+    pdf.stream(
+        FONT_FILE,
+        &format!("/Length1 {}", program.len()),
+        &deflate(&program)?,
+    );
+    // Every code point maps to glyph 1, the empty one.
+    let gids: Vec<u8> = (0..=u16::MAX).flat_map(|_| 1u16.to_be_bytes()).collect();
+    pdf.stream(CID_TO_GID, "", &deflate(&gids)?);
+    pdf.stream(TO_UNICODE, "", &deflate(to_unicode_cmap().as_bytes())?);
+    Ok(())
+}
+
+/// Identity `ToUnicode` map over the BMP, as one `bfrange` per high byte.
+fn to_unicode_cmap() -> String {
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    for chunk in (0u16..256).collect::<Vec<_>>().chunks(100) {
+        cmap.push_str(&format!("{} beginbfrange\n", chunk.len()));
+        for high in chunk {
+            cmap.push_str(&format!("<{high:02X}00> <{high:02X}FF> <{high:02X}00>\n"));
+        }
+        cmap.push_str("endbfrange\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    cmap
+}
+
+/// Minimal TrueType program with two empty glyphs (`.notdef` and the blank glyph every code
+/// maps to), each 500 units wide on a 1000-unit em.
+fn glyphless_truetype() -> Vec<u8> {
+    fn be(values: &[i64], widths: &[usize]) -> Vec<u8> {
+        values
+            .iter()
+            .zip(widths)
+            .flat_map(|(value, width)| value.to_be_bytes()[8 - width..].to_vec())
+            .collect()
+    }
+    let advance = GLYPH_ADVANCE as i64;
+    let (ascent, descent) = (ASCENT as i64, DESCENT as i64);
+    let head = be(
+        &[
+            0x0001_0000,
+            0x0001_0000,
+            0,
+            0x5F0F_3CF5,
+            0x000B,
+            1000,
+            0,
+            0,
+            0,
+            descent,
+            advance,
+            ascent,
+            0,
+            3,
+            2,
+            0,
+            0,
+        ],
+        &[4, 4, 4, 4, 2, 2, 8, 8, 2, 2, 2, 2, 2, 2, 2, 2, 2],
+    );
+    let hhea = be(
+        &[
+            0x0001_0000,
+            ascent,
+            descent,
+            0,
+            advance,
+            0,
+            0,
+            0,
+            1,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            2,
+        ],
+        &[4, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2],
+    );
+    let maxp = be(
+        &[0x0001_0000, 2, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0],
+        &[4, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2],
+    );
+    let hmtx = be(&[advance, 0, advance, 0], &[2, 2, 2, 2]);
+    let loca = vec![0u8; 6];
+    let glyf = Vec::new();
+    let tables = [
+        (*b"glyf", glyf),
+        (*b"head", head),
+        (*b"hhea", hhea),
+        (*b"hmtx", hmtx),
+        (*b"loca", loca),
+        (*b"maxp", maxp),
+    ];
+
+    let count = tables.len();
+    let mut font = be(&[0x0001_0000, count as i64, 64, 2, 32], &[4, 2, 2, 2, 2]);
+    let mut data = Vec::new();
+    let mut head_offset = 0;
+    for (tag, table) in &tables {
+        let offset = 12 + 16 * count + data.len();
+        if tag == b"head" {
+            head_offset = offset;
+        }
+        font.extend_from_slice(tag);
+        font.extend(be(
+            &[
+                i64::from(checksum(table)),
+                offset as i64,
+                table.len() as i64,
+            ],
+            &[4, 4, 4],
+        ));
+        data.extend_from_slice(table);
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+    font.extend(data);
+    // `checkSumAdjustment` in `head` makes the whole file sum to the magic number.
+    let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&font));
+    font[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+    font
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(data)
+        .context("failed to compress PDF stream")?;
+    encoder.finish().context("failed to compress PDF stream")
+}
+
+/// Objects are written as they come and indexed by number for the cross-reference table.
+struct PdfWriter {
+    buf: Vec<u8>,
+    offsets: Vec<Option<usize>>,
+}
+
+impl PdfWriter {
+    /// Writer for objects `1..=last_id`.
+    fn new(last_id: usize) -> Self {
+        Self {
+            buf: b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec(),
+            offsets: vec![None; last_id + 1],
+        }
+    }
+
+    fn object(&mut self, id: usize, body: &str) {
+        self.begin(id);
+        self.buf.extend_from_slice(body.as_bytes());
+        self.buf.extend_from_slice(b"\nendobj\n");
+    }
+
+    /// Write a Flate-compressed stream; `dict` holds the entries besides the filter and length.
+    fn stream(&mut self, id: usize, dict: &str, data: &[u8]) {
+        self.stream_with_filter(id, dict, "FlateDecode", data);
+    }
+
+    /// Write a stream whose `data` is already encoded with `filter`.
+    fn stream_with_filter(&mut self, id: usize, dict: &str, filter: &str, data: &[u8]) {
+        self.begin(id);
+        let separator = if dict.is_empty() { "" } else { " " };
+        self.buf.extend_from_slice(
+            format!(
+                "<< {dict}{separator}/Filter /{filter} /Length {} >>\nstream\n",
+                data.len()
+            )
+            .as_bytes(),
+        );
+        self.buf.extend_from_slice(data);
+        self.buf.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    fn begin(&mut self, id: usize) {
+        self.offsets[id] = Some(self.buf.len());
+        self.buf
+            .extend_from_slice(format!("{id} 0 obj\n").as_bytes());
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let xref = self.buf.len();
+        let size = self.offsets.len();
+        let mut table = format!("xref\n0 {size}\n0000000000 65535 f \n");
+        for offset in &self.offsets[1..] {
+            let offset = offset.expect("every reserved PDF object is written");
+            table.push_str(&format!("{offset:010} 00000 n \n"));
+        }
+        table.push_str(&format!(
+            "trailer\n<< /Size {size} /Root {CATALOG} 0 R /Info {INFO} 0 R >>\nstartxref\n{xref}\n%%EOF\n"
+        ));
+        self.buf.extend_from_slice(table.as_bytes());
+        self.buf
+    }
+}
@@ -0,0 +1,549 @@
+//! Image formats compiled into this build, multi-page TIFF decoding and scanned PDFs.
+//!
+//! The workspace `image` dependency only enables PNG and JPEG. WebP, TIFF, BMP, GIF and PDF sit
+//! behind the cargo features of the same names (or `extra-formats` for all of them) so slim
+//! builds such as the Android library can leave them out. Decode errors for formats that are not
+//! compiled in name the ones that are.
+//!
+//! PDFs are read as scans, not rendered: each page yields the largest image it draws, at the
+//! image's own resolution. That covers the output of scanners and of the `pdf` export, but text
+//! and vector graphics around the image are lost and pages without an image are rejected.
+
+use image::ImageError;
+
+/// Formats the frontends can decode in this build.
+pub const COMPILED_FORMATS: &[&str] = &[
+    "png",
+    "jpeg",
+    #[cfg(feature = "webp")]
+    "webp",
+    #[cfg(feature = "tiff")]
+    "tiff",
+    #[cfg(feature = "bmp")]
+    "bmp",
+    #[cfg(feature = "gif")]
+    "gif",
+    #[cfg(feature = "pdf")]
+    "pdf",
+];
+
+/// Attach the list of compiled-in formats to unsupported-format errors.
+pub fn describe_decode_error(err: ImageError) -> anyhow::Error {
+    match err {
+        ImageError::Unsupported(_) => anyhow::anyhow!(
+            "{err} (formats compiled in: {}; enable the `webp`, `tiff`, `bmp`, `gif` or `pdf` \
+             cargo features for more)",
+            COMPILED_FORMATS.join(", ")
+        ),
+        other => other.into(),
+    }
+}
+
+#[cfg(feature = "tiff")]
+pub(crate) use multipage::tiff_pages;
+#[cfg(feature = "pdf")]
+pub(crate) use scanned_pdf::pdf_pages;
+
+/// Convert 8-bit CMYK samples to RGB without a colour profile.
+#[cfg(any(feature = "tiff", feature = "pdf"))]
+fn cmyk_to_rgb(width: u32, height: u32, data: &[u8]) -> image::RgbImage {
+    let mut rgb = image::RgbImage::new(width, height);
+    for (pixel, cmyk) in rgb.pixels_mut().zip(data.chunks_exact(4)) {
+        let black = 255 - cmyk[3] as u16;
+        let channel = |value: u8| ((255 - value as u16) * black / 255) as u8;
+        *pixel = image::Rgb([channel(cmyk[0]), channel(cmyk[1]), channel(cmyk[2])]);
+    }
+    rgb
+}
+
+/// Unpack 1-bit rows, each padded to a whole byte, into 8-bit grey. `white_is_one` tells which
+/// bit value is white.
+#[cfg(any(feature = "tiff", feature = "pdf"))]
+fn unpack_bilevel(
+    width: u32,
+    height: u32,
+    data: &[u8],
+    white_is_one: bool,
+) -> anyhow::Result<image::GrayImage> {
+    let stride = width.div_ceil(8) as usize;
+    anyhow::ensure!(
+        data.len() >= stride * height as usize,
+        "bilevel data shorter than {width}x{height}"
+    );
+    let mut gray = image::GrayImage::new(width, height);
+    for (row, pixels) in data.chunks_exact(stride).zip(gray.rows_mut()) {
+        for (x, pixel) in pixels.enumerate() {
+            let bit = row[x / 8] >> (7 - x % 8) & 1;
+            pixel.0[0] = if (bit == 1) == white_is_one { 255 } else { 0 };
+        }
+    }
+    Ok(gray)
+}
+
+/// Decode CCITT fax data: Group 4 when `k` is negative, one-dimensional Group 3 when it is zero.
+/// Black runs decode to sample 0, or to 255 with `black_is_1` as in the PDF filter parameter of
+/// that name. Rows missing at the end of the data are left as background.
+#[cfg(any(feature = "tiff", feature = "pdf"))]
+fn decode_fax(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    k: i64,
+    black_is_1: bool,
+) -> anyhow::Result<image::GrayImage> {
+    use fax::{Color, decoder};
+
+    let columns =
+        u16::try_from(width).map_err(|_| anyhow::anyhow!("fax image wider than 65535"))?;
+    let (ink, paper) = if black_is_1 { (255, 0) } else { (0, 255) };
+    let mut gray = image::GrayImage::from_pixel(width, height, image::Luma([paper]));
+    let mut lines = gray.rows_mut();
+    let mut decoded = 0u32;
+    let mut line = |transitions: &[u16]| {
+        if let Some(row) = lines.next() {
+            for (pixel, color) in row.zip(decoder::pels(transitions, columns)) {
+                if color == Color::Black {
+                    pixel.0[0] = ink;
+                }
+            }
+            decoded += 1;
+        }
+    };
+    let complete = match k {
+        k if k < 0 => decoder::decode_g4(data.iter().copied(), columns, None, &mut line),
+        0 => decoder::decode_g3(data.iter().copied(), &mut line),
+        _ => anyhow::bail!("two-dimensional Group 3 fax images are not supported"),
+    };
+    // Encoders may stop without an end-of-block marker once every row is written.
+    anyhow::ensure!(
+        decoded > 0 && (complete.is_some() || decoded >= height),
+        "invalid CCITT fax data"
+    );
+    Ok(gray)
+}
+
+#[cfg(feature = "tiff")]
+mod multipage {
+    use std::{borrow::Cow, io::Cursor};
+
+    use anyhow::{Context, Result, bail};
+    use image::{
+        DynamicImage, GenericImage, GrayAlphaImage, GrayImage, ImageBuffer, RgbImage, RgbaImage,
+        metadata::Orientation,
+    };
+    use tiff::{
+        ColorType,
+        decoder::{Decoder, DecodingResult},
+        tags::{CompressionMethod, Tag},
+    };
+
+    /// T4Options, which the tiff crate has no name for.
+    const T4_OPTIONS: u16 = 292;
+
+    /// Decode every page of a TIFF file, with the orientation tag of each page.
+    pub(crate) fn tiff_pages(bytes: &[u8]) -> Result<Vec<(DynamicImage, Option<Orientation>)>> {
+        let mut decoder = Decoder::new(Cursor::new(bytes)).context("failed to read TIFF header")?;
+        let mut pages = Vec::new();
+        loop {
+            let page = read_page(bytes, &mut decoder)
+                .with_context(|| format!("failed to decode TIFF page {}", pages.len() + 1))?;
+            pages.push(page);
+            if !decoder.more_images() {
+                break;
+            }
+            decoder.next_image()?;
+        }
+        Ok(pages)
+    }
+
+    fn read_page(
+        bytes: &[u8],
+        decoder: &mut Decoder<Cursor<&[u8]>>,
+    ) -> Result<(DynamicImage, Option<Orientation>)> {
+        let (width, height) = decoder.dimensions()?;
+        let color = decoder.colortype()?;
+        let orientation = decoder
+            .find_tag_unsigned::<u8>(Tag::Orientation)?
+            .and_then(Orientation::from_exif);
+        let compression = decoder.find_tag_unsigned::<u16>(Tag::Compression)?;
+        if compression == Some(CompressionMethod::Fax3.to_u16()) {
+            // The tiff crate only decodes Group 4 itself.
+            let gray = read_group3(bytes, decoder, width, height)?;
+            return Ok((DynamicImage::ImageLuma8(gray), orientation));
+        }
+        let too_short = || anyhow::anyhow!("TIFF page data shorter than {width}x{height}");
+        let image = match (color, decoder.read_image()?) {
+            // Bilevel (fax) pages pack eight pixels per byte; the decoder has already flipped
+            // WhiteIsZero pages so that one is white.
+            (ColorType::Gray(1), DecodingResult::U8(data)) => DynamicImage::ImageLuma8(
+                super::unpack_bilevel(width, height, &data, true)
+                    .context("TIFF page data too short")?,
+            ),
+            (ColorType::Gray(8), DecodingResult::U8(data)) => DynamicImage::ImageLuma8(
+                GrayImage::from_raw(width, height, data).ok_or_else(too_short)?,
+            ),
+            (ColorType::Gray(16), DecodingResult::U16(data)) => DynamicImage::ImageLuma16(
+                ImageBuffer::from_raw(width, height, data).ok_or_else(too_short)?,
+            ),
+            (ColorType::GrayA(8), DecodingResult::U8(data)) => DynamicImage::ImageLumaA8(
+                GrayAlphaImage::from_raw(width, height, data).ok_or_else(too_short)?,
+            ),
+            (ColorType::RGB(8), DecodingResult::U8(data)) => DynamicImage::ImageRgb8(
+                RgbImage::from_raw(width, height, data).ok_or_else(too_short)?,
+            ),
+            (ColorType::RGBA(8), DecodingResult::U8(data)) => DynamicImage::ImageRgba8(
+                RgbaImage::from_raw(width, height, data).ok_or_else(too_short)?,
+            ),
+            (ColorType::RGB(16), DecodingResult::U16(data)) => DynamicImage::ImageRgb16(
+                ImageBuffer::from_raw(width, height, data).ok_or_else(too_short)?,
+            ),
+            (ColorType::RGBA(16), DecodingResult::U16(data)) => DynamicImage::ImageRgba16(
+                ImageBuffer::from_raw(width, height, data).ok_or_else(too_short)?,
+            ),
+            (ColorType::CMYK(8), DecodingResult::U8(data)) => {
+                DynamicImage::ImageRgb8(super::cmyk_to_rgb(width, height, &data))
+            }
+            (other, _) => bail!("unsupported TIFF colour type {other:?}"),
+        };
+        Ok((image, orientation))
+    }
+
+    /// Decode a one-dimensional Group 3 (T.4) page strip by strip; each strip starts with its
+    /// own end-of-line code.
+    fn read_group3(
+        bytes: &[u8],
+        decoder: &mut Decoder<Cursor<&[u8]>>,
+        width: u32,
+        height: u32,
+    ) -> Result<GrayImage> {
+        // 0 is WhiteIsZero, where the black runs of the code are one bits.
+        let white_is_one = decoder
+            .find_tag_unsigned::<u16>(Tag::PhotometricInterpretation)?
+            .is_some_and(|photometric| photometric != 0);
+        let options = decoder
+            .find_tag_unsigned::<u32>(Tag::Unknown(T4_OPTIONS))?
+            .unwrap_or(0);
+        if options & 1 != 0 {
+            bail!("two-dimensional Group 3 fax pages are not supported");
+        }
+        // FillOrder 2 stores the first pixel in the least significant bit.
+        let reversed = decoder.find_tag_unsigned::<u16>(Tag::FillOrder)? == Some(2);
+        let rows_per_strip = decoder
+            .find_tag_unsigned::<u32>(Tag::RowsPerStrip)?
+            .unwrap_or(height)
+            .clamp(1, height.max(1));
+        let offsets = decoder.get_tag_u64_vec(Tag::StripOffsets)?;
+        let counts = decoder.get_tag_u64_vec(Tag::StripByteCounts)?;
+        let mut page = GrayImage::new(width, height);
+        for (index, (offset, count)) in offsets.into_iter().zip(counts).enumerate() {
+            let top = index as u32 * rows_per_strip;
+            if top >= height {
+                break;
+            }
+            let rows = rows_per_strip.min(height - top);
+            let strip = usize::try_from(offset)
+                .ok()
+                .zip(usize::try_from(count).ok())
+                .and_then(|(start, len)| bytes.get(start..start.checked_add(len)?))
+                .with_context(|| format!("fax strip {index} lies outside the file"))?;
+            let strip: Cow<'_, [u8]> = if reversed {
+                strip.iter().map(|byte| byte.reverse_bits()).collect()
+            } else {
+                Cow::Borrowed(strip)
+            };
+            let decoded = super::decode_fax(&strip, width, rows, 0, white_is_one)
+                .with_context(|| format!("failed to decode fax strip {index}"))?;
+            page.copy_from(&decoded, 0, top)?;
+        }
+        Ok(page)
+    }
+}
+
+#[cfg(feature = "pdf")]
+mod scanned_pdf {
+    use std::{io::Read, sync::Arc};
+
+    use anyhow::{Context, Result, bail, ensure};
+    use flate2::read::ZlibDecoder;
+    use image::{DynamicImage, GrayImage, ImageFormat, RgbImage, metadata::Orientation};
+    use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+
+    /// Page nesting deeper than this is treated as a reference cycle.
+    const MAX_PAGE_DEPTH: usize = 32;
+
+    /// The scan of one PDF page.
+    pub(crate) struct ScannedPage {
+        pub image: DynamicImage,
+        /// The page's `/Rotate`, as the orientation that shows it upright.
+        pub orientation: Option<Orientation>,
+        /// The JPEG stream the image was decoded from, for `DCTDecode` images.
+        pub jpeg: Option<Arc<[u8]>>,
+    }
+
+    /// Decode the scan of every page of a PDF.
+    pub(crate) fn pdf_pages(bytes: &[u8]) -> Result<Vec<ScannedPage>> {
+        let doc = Document::load_mem(bytes).context("failed to parse PDF")?;
+        ensure!(!doc.is_encrypted(), "encrypted PDFs are not supported");
+        let pages = doc.get_pages();
+        ensure!(!pages.is_empty(), "the PDF has no pages");
+        pages
+            .values()
+            .enumerate()
+            .map(|(idx, &page_id)| {
+                read_page(&doc, page_id)
+                    .with_context(|| format!("failed to decode PDF page {}", idx + 1))
+            })
+            .collect()
+    }
+
+    fn read_page(doc: &Document, page_id: ObjectId) -> Result<ScannedPage> {
+        let Some(stream) = page_images(doc, page_id)?.into_iter().max_by_key(|stream| {
+            let int = |key: &[u8]| stream.dict.get(key).and_then(Object::as_i64).unwrap_or(0);
+            int(b"Width").saturating_mul(int(b"Height"))
+        }) else {
+            bail!("the page draws no image; only scanned PDFs can be read");
+        };
+        let rotate = inherited(doc, page_id, b"Rotate")
+            .and_then(|rotate| rotate.as_i64().ok())
+            .unwrap_or(0);
+        let orientation = match rotate.rem_euclid(360) {
+            90 => Some(Orientation::Rotate90),
+            180 => Some(Orientation::Rotate180),
+            270 => Some(Orientation::Rotate270),
+            _ => None,
+        };
+        let (image, jpeg) = decode_image(doc, stream)?;
+        Ok(ScannedPage {
+            image,
+            orientation,
+            jpeg,
+        })
+    }
+
+    /// Image XObjects in the page's own and inherited resources.
+    fn page_images(doc: &Document, page_id: ObjectId) -> Result<Vec<&Stream>> {
+        let (own, inherited) = doc.get_page_resources(page_id)?;
+        let resources = own.into_iter().chain(
+            inherited
+                .into_iter()
+                .filter_map(|id| doc.get_dictionary(id).ok()),
+        );
+        let mut images = Vec::new();
+        for resources in resources {
+            let Ok(xobjects) = resources
+                .get_deref(b"XObject", doc)
+                .and_then(Object::as_dict)
+            else {
+                continue;
+            };
+            for (_, xobject) in xobjects.iter() {
+                let Ok((_, Object::Stream(stream))) = doc.dereference(xobject) else {
+                    continue;
+                };
+                if stream.dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Image") {
+                    images.push(stream);
+                }
+            }
+        }
+        Ok(images)
+    }
+
+    /// Look `key` up on the page, then on its ancestors in the page tree.
+    fn inherited<'a>(doc: &'a Document, page_id: ObjectId, key: &[u8]) -> Option<&'a Object> {
+        let mut node = doc.get_dictionary(page_id).ok()?;
+        for _ in 0..MAX_PAGE_DEPTH {
+            if let Ok(value) = node.get_deref(key, doc) {
+                return Some(value);
+            }
+            node = node
+                .get_deref(b"Parent", doc)
+                .and_then(Object::as_dict)
+                .ok()?;
+        }
+        None
+    }
+
+    fn decode_image(doc: &Document, stream: &Stream) -> Result<(DynamicImage, Option<Arc<[u8]>>)> {
+        let dict = &stream.dict;
+        let int =
+            |dict: &Dictionary, key: &[u8]| dict.get_deref(key, doc).and_then(Object::as_i64).ok();
+        let width = u32::try_from(int(dict, b"Width").context("image without /Width")?)?;
+        let height = u32::try_from(int(dict, b"Height").context("image without /Height")?)?;
+        let filters = stream.filters().unwrap_or_default();
+        let params = match dict.get_deref(b"DecodeParms", doc) {
+            Ok(Object::Dictionary(params)) => Some(params),
+            Ok(Object::Array(params)) => params
+                .last()
+                .and_then(|params| doc.dereference(params).ok())
+                .and_then(|(_, params)| params.as_dict().ok()),
+            _ => None,
+        };
+        let data = stream.content.as_slice();
+        // `/Decode [1 0]` swaps black and white; image masks paint their zero samples black.
+        let inverted = dict
+            .get_deref(b"Decode", doc)
+            .and_then(Object::as_array)
+            .ok()
+            .and_then(|decode| decode.first())
+            .and_then(|first| first.as_float().ok())
+            .is_some_and(|first| first >= 1.0);
+        let pixels = match filters.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            ["DCTDecode"] => {
+                let image = image::load_from_memory_with_format(data, ImageFormat::Jpeg)
+                    .context("invalid JPEG image")?;
+                return Ok((image, Some(Arc::from(data))));
+            }
+            ["CCITTFaxDecode"] => {
+                let k = params.and_then(|params| int(params, b"K")).unwrap_or(0);
+                let black_is_1 = params
+                    .and_then(|params| params.get(b"BlackIs1").and_then(Object::as_bool).ok())
+                    .unwrap_or(false);
+                let mut image = super::decode_fax(data, width, height, k, black_is_1)?;
+                if inverted {
+                    image::imageops::invert(&mut image);
+                }
+                return Ok((DynamicImage::ImageLuma8(image), None));
+            }
+            ["FlateDecode"] => {
+                let mut inflated = Vec::new();
+                ZlibDecoder::new(data)
+                    .read_to_end(&mut inflated)
+                    .context("invalid Flate image data")?;
+                match params
+                    .and_then(|params| int(params, b"Predictor"))
+                    .unwrap_or(1)
+                {
+                    1 => inflated,
+                    predictor if predictor >= 10 => {
+                        let param = |key: &[u8], default| {
+                            params
+                                .and_then(|params| int(params, key))
+                                .unwrap_or(default)
+                        };
+                        let row = (param(b"Columns", 1)
+                            * param(b"Colors", 1)
+                            * param(b"BitsPerComponent", 8))
+                            as usize;
+                        let bpp = (param(b"Colors", 1) * param(b"BitsPerComponent", 8)) as usize;
+                        unpredict_png(&inflated, row.div_ceil(8), bpp.div_ceil(8).max(1))?
+                    }
+                    predictor => bail!("unsupported Flate predictor {predictor}"),
+                }
+            }
+            [] => data.to_vec(),
+            _ => bail!("unsupported image filter {}", filters.join(" ")),
+        };
+
+        let mask = dict
+            .get(b"ImageMask")
+            .and_then(Object::as_bool)
+            .unwrap_or(false);
+        let bits = if mask {
+            1
+        } else {
+            int(dict, b"BitsPerComponent").unwrap_or(8)
+        };
+        let components = if mask { 1 } else { components(doc, dict)? };
+        let too_short = || anyhow::anyhow!("image data shorter than {width}x{height}");
+        let samples = width as usize * height as usize * components as usize;
+        let image = match (bits, components) {
+            (1, 1) => {
+                DynamicImage::ImageLuma8(super::unpack_bilevel(width, height, &pixels, !inverted)?)
+            }
+            (8, 1) => {
+                let mut gray = GrayImage::from_raw(
+                    width,
+                    height,
+                    pixels.get(..samples).ok_or_else(too_short)?.to_vec(),
+                )
+                .ok_or_else(too_short)?;
+                if inverted {
+                    image::imageops::invert(&mut gray);
+                }
+                DynamicImage::ImageLuma8(gray)
+            }
+            (8, 3) => DynamicImage::ImageRgb8(
+                RgbImage::from_raw(
+                    width,
+                    height,
+                    pixels.get(..samples).ok_or_else(too_short)?.to_vec(),
+                )
+                .ok_or_else(too_short)?,
+            ),
+            (8, 4) => DynamicImage::ImageRgb8(super::cmyk_to_rgb(
+                width,
+                height,
+                pixels.get(..samples).ok_or_else(too_short)?,
+            )),
+            _ => bail!("unsupported image layout: {components} components of {bits} bits"),
+        };
+        Ok((image, None))
+    }
+
+    /// Number of colour components of an image's colour space.
+    fn components(doc: &Document, dict: &Dictionary) -> Result<i64> {
+        let space = dict
+            .get_deref(b"ColorSpace", doc)
+            .context("image without /ColorSpace")?;
+        let (name, arguments) = match space {
+            Object::Name(name) => (name.as_slice(), &[][..]),
+            Object::Array(array) => match array.split_first() {
+                Some((name, arguments)) => (name.as_name()?, arguments),
+                None => bail!("empty colour space"),
+            },
+            _ => bail!("invalid colour space"),
+        };
+        Ok(match name {
+            b"DeviceGray" | b"CalGray" => 1,
+            b"DeviceRGB" | b"CalRGB" => 3,
+            b"DeviceCMYK" => 4,
+            b"ICCBased" => {
+                let profile = arguments.first().context("ICCBased without a profile")?;
+                let (_, profile) = doc.dereference(profile)?;
+                profile.as_stream()?.dict.get(b"N")?.as_i64()?
+            }
+            other => bail!(
+                "unsupported colour space {}",
+                String::from_utf8_lossy(other)
+            ),
+        })
+    }
+
+    /// Undo the PNG row filters of a Flate stream with a predictor of 10 or more.
+    fn unpredict_png(data: &[u8], stride: usize, bpp: usize) -> Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(data.len());
+        let mut previous = vec![0u8; stride];
+        for row in data.chunks_exact(stride + 1) {
+            let (filter, mut current) = (row[0], row[1..].to_vec());
+            for idx in 0..stride {
+                let left = if idx >= bpp { current[idx - bpp] } else { 0 };
+                let up = previous[idx];
+                let up_left = if idx >= bpp { previous[idx - bpp] } else { 0 };
+                let prediction = match filter {
+                    0 => 0,
+                    1 => left,
+                    2 => up,
+                    3 => ((left as u16 + up as u16) / 2) as u8,
+                    4 => paeth(left, up, up_left),
+                    other => bail!("invalid PNG row filter {other}"),
+                };
+                current[idx] = current[idx].wrapping_add(prediction);
+            }
+            out.extend_from_slice(&current);
+            previous = current;
+        }
+        Ok(out)
+    }
+
+    fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+        let estimate = left as i16 + up as i16 - up_left as i16;
+        let distance = |value: u8| (estimate - value as i16).abs();
+        if distance(left) <= distance(up) && distance(left) <= distance(up_left) {
+            left
+        } else if distance(up) <= distance(up_left) {
+            up
+        } else {
+            up_left
+        }
+    }
+}
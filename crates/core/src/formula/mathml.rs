@@ -0,0 +1,863 @@
+//! LaTeX to Presentation MathML for the subset OCR models emit: scripts, fractions, roots,
+//! Greek letters and common symbols, fonts (`\mathbb`, `\mathbf`, ...), accents, `\left`/`\right`
+//! fences, `\text` and matrix-like environments (`matrix`, `pmatrix`, `cases`, `aligned`,
+//! `array`, ...). Styled letters use the Unicode mathematical alphanumeric symbols, so they
+//! render without `mathvariant` support. Unknown commands become `<merror>` so nothing is lost
+//! silently.
+
+use std::fmt::Write;
+
+use crate::export::escape_xml;
+
+const MATHML_NAMESPACE: &str = "http://www.w3.org/1998/Math/MathML";
+
+/// `<math>` element for `latex`, in block layout when `display` is set.
+pub fn to_mathml(latex: &str, display: bool) -> String {
+    let mut parser = Parser::new(latex);
+    let mut items = Vec::new();
+    loop {
+        items.extend(parser.row());
+        if parser.at_end() {
+            break;
+        }
+        // Stray `}`, `&`, `\\`, `\right` or `\end` at the top level.
+        parser.skip_terminator();
+    }
+    let mode = if display { "block" } else { "inline" };
+    format!(
+        "<math xmlns=\"{MATHML_NAMESPACE}\" display=\"{mode}\">{}</math>",
+        mrow(items)
+    )
+}
+
+/// A parsed element and whether scripts attach as limits (above/below) rather than at the side.
+struct Atom {
+    xml: String,
+    limits: bool,
+}
+
+impl Atom {
+    fn new(xml: String) -> Self {
+        Self { xml, limits: false }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Variant {
+    Normal,
+    Bold,
+    Italic,
+    DoubleStruck,
+    Script,
+    Fraktur,
+    SansSerif,
+    Monospace,
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    variant: Option<Variant>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            pos: 0,
+            variant: None,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.src.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    /// Name of the command at the cursor without consuming it.
+    fn peek_command(&self) -> Option<&'a str> {
+        let rest = self.rest().strip_prefix('\\')?;
+        let letters = rest
+            .find(|ch: char| !ch.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        Some(match letters {
+            0 => &rest[..rest.chars().next()?.len_utf8()],
+            len => &rest[..len],
+        })
+    }
+
+    fn command(&mut self) -> Option<&'a str> {
+        let name = self.peek_command()?;
+        self.pos += 1 + name.len();
+        Some(name)
+    }
+
+    fn at_terminator(&self) -> bool {
+        matches!(self.peek(), Some('}' | '&'))
+            || matches!(self.peek_command(), Some("\\" | "right" | "end" | "cr"))
+    }
+
+    fn skip_terminator(&mut self) {
+        match self.peek_command() {
+            Some("end") => {
+                self.command();
+                self.raw_group();
+            }
+            Some(_) => {
+                self.command();
+            }
+            None => {
+                self.bump();
+            }
+        }
+    }
+
+    /// Elements up to the next terminator, with scripts attached.
+    fn row(&mut self) -> Vec<String> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_ws();
+            if self.at_end() || self.at_terminator() {
+                return items;
+            }
+            if let Some(atom) = self.atom(false) {
+                let atom = self.scripts(atom);
+                items.push(atom);
+            }
+        }
+    }
+
+    /// `{...}` as one element, or `None` without a group at the cursor.
+    fn group(&mut self) -> Option<String> {
+        self.skip_ws();
+        if self.peek() != Some('{') {
+            return None;
+        }
+        self.bump();
+        let items = self.row();
+        if self.peek() == Some('}') {
+            self.bump();
+        }
+        Some(mrow(items))
+    }
+
+    /// Raw text of a `{...}` group, with nested braces kept.
+    fn raw_group(&mut self) -> Option<&'a str> {
+        self.skip_ws();
+        let rest = self.rest().strip_prefix('{')?;
+        let mut depth = 0usize;
+        let mut escaped = false;
+        for (idx, ch) in rest.char_indices() {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '{' => depth += 1,
+                '}' if depth == 0 => {
+                    self.pos += 1 + idx + 1;
+                    return Some(&rest[..idx]);
+                }
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        self.pos = self.src.len();
+        Some(rest)
+    }
+
+    /// Optional `[...]` argument.
+    fn optional(&mut self) -> Option<&'a str> {
+        self.skip_ws();
+        let rest = self.rest().strip_prefix('[')?;
+        let len = rest.find(']')?;
+        self.pos += len + 2;
+        Some(&rest[..len])
+    }
+
+    /// A command or group argument: a group, or a single token (`\frac12`).
+    fn argument(&mut self) -> String {
+        self.skip_ws();
+        if let Some(group) = self.group() {
+            return group;
+        }
+        if self.at_end() || self.at_terminator() {
+            return "<mrow></mrow>".to_owned();
+        }
+        self.atom(true)
+            .map_or_else(|| "<mrow></mrow>".to_owned(), |atom| atom.xml)
+    }
+
+    /// Argument parsed with letters and digits in `variant`.
+    fn styled_argument(&mut self, variant: Variant) -> String {
+        let outer = self.variant.replace(variant);
+        let xml = self.argument();
+        self.variant = outer;
+        xml
+    }
+
+    fn scripts(&mut self, base: Atom) -> String {
+        let (mut sub, mut sup) = (None, None);
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                Some('_') if sub.is_none() => {
+                    self.bump();
+                    sub = Some(self.argument());
+                }
+                Some('^') if sup.is_none() => {
+                    self.bump();
+                    sup = Some(self.argument());
+                }
+                Some('\'') => {
+                    // Primes join the superscript.
+                    self.bump();
+                    let prime = "<mo>\u{2032}</mo>".to_owned();
+                    sup = Some(match sup {
+                        Some(sup) => mrow(vec![sup, prime]),
+                        None => prime,
+                    });
+                }
+                _ if matches!(self.peek_command(), Some("limits" | "nolimits")) => {
+                    self.command();
+                }
+                _ => break,
+            }
+        }
+        let (under, over) = if base.limits {
+            ("munder", "mover")
+        } else {
+            ("msub", "msup")
+        };
+        let both = if base.limits { "munderover" } else { "msubsup" };
+        match (sub, sup) {
+            (None, None) => base.xml,
+            (Some(sub), None) => format!("<{under}>{}{sub}</{under}>", base.xml),
+            (None, Some(sup)) => format!("<{over}>{}{sup}</{over}>", base.xml),
+            (Some(sub), Some(sup)) => format!("<{both}>{}{sub}{sup}</{both}>", base.xml),
+        }
+    }
+
+    /// One element at the cursor. `single` limits numbers to one digit, as in `x^23`.
+    fn atom(&mut self, single: bool) -> Option<Atom> {
+        let ch = self.peek()?;
+        match ch {
+            '{' => self.group().map(Atom::new),
+            '\\' => self.command_atom(),
+            '_' | '^' => Some(Atom::new("<mrow></mrow>".to_owned())),
+            '\'' => {
+                self.bump();
+                Some(Atom::new("<mo>\u{2032}</mo>".to_owned()))
+            }
+            '~' => {
+                self.bump();
+                Some(Atom::new("<mspace width=\"0.333em\"></mspace>".to_owned()))
+            }
+            ch if ch.is_ascii_digit() => {
+                let len = if single {
+                    1
+                } else {
+                    let rest = self.rest();
+                    let mut len = rest
+                        .find(|c: char| !c.is_ascii_digit() && c != '.')
+                        .unwrap_or(rest.len());
+                    while rest[..len].ends_with('.') {
+                        len -= 1;
+                    }
+                    len
+                };
+                let number = &self.rest()[..len];
+                self.pos += len;
+                Some(Atom::new(format!("<mn>{}</mn>", self.styled(number))))
+            }
+            ch if ch.is_alphabetic() => {
+                self.bump();
+                Some(Atom::new(self.identifier(&ch.to_string())))
+            }
+            _ => {
+                self.bump();
+                let text = match ch {
+                    '-' => "\u{2212}".to_owned(),
+                    '*' => "\u{2217}".to_owned(),
+                    _ => ch.to_string(),
+                };
+                Some(Atom::new(format!("<mo>{}</mo>", escape_xml(&text))))
+            }
+        }
+    }
+
+    /// `<mi>` for letters in the current font.
+    fn identifier(&self, name: &str) -> String {
+        match self.variant {
+            Some(Variant::Normal) => {
+                format!("<mi mathvariant=\"normal\">{}</mi>", escape_xml(name))
+            }
+            _ => format!("<mi>{}</mi>", self.styled(name)),
+        }
+    }
+
+    fn styled(&self, text: &str) -> String {
+        match self.variant {
+            Some(variant) => text.chars().map(|ch| styled_char(ch, variant)).collect(),
+            None => escape_xml(text),
+        }
+    }
+
+    fn command_atom(&mut self) -> Option<Atom> {
+        let name = self.command()?;
+        if let Some(symbol) = greek(name) {
+            let xml = match self.variant {
+                Some(Variant::Normal) | None if name.starts_with(char::is_uppercase) => {
+                    format!("<mi mathvariant=\"normal\">{symbol}</mi>")
+                }
+                _ => format!("<mi>{symbol}</mi>"),
+            };
+            return Some(Atom::new(xml));
+        }
+        if let Some(symbol) = operator(name) {
+            return Some(Atom::new(format!("<mo>{}</mo>", escape_xml(symbol))));
+        }
+        if let Some(symbol) = large_operator(name) {
+            // Integrals keep their limits at the side.
+            let limits = !name.contains("int");
+            return Some(Atom {
+                xml: format!("<mo>{symbol}</mo>"),
+                limits,
+            });
+        }
+        if FUNCTIONS.contains(&name) {
+            let limits = LIMIT_FUNCTIONS.contains(&name);
+            return Some(Atom {
+                xml: format!("<mi>{name}</mi>"),
+                limits,
+            });
+        }
+        if let Some(width) = space(name) {
+            return Some(Atom::new(format!("<mspace width=\"{width}\"></mspace>")));
+        }
+        let xml = match name {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                let (num, den) = (self.argument(), self.argument());
+                format!("<mfrac>{num}{den}</mfrac>")
+            }
+            "binom" | "dbinom" | "tbinom" => {
+                let (top, bottom) = (self.argument(), self.argument());
+                format!(
+                    "<mrow><mo>(</mo><mfrac linethickness=\"0\">{top}{bottom}</mfrac><mo>)</mo></mrow>"
+                )
+            }
+            "sqrt" => match self.optional() {
+                Some(index) => {
+                    let index = Parser::new(index).row();
+                    let radicand = self.argument();
+                    format!("<mroot>{radicand}{}</mroot>", mrow(index))
+                }
+                None => format!("<msqrt>{}</msqrt>", self.argument()),
+            },
+            "text" | "textrm" | "textnormal" | "mbox" | "textit" | "textbf" | "textsf"
+            | "texttt" => {
+                let text = self.raw_group().map_or_else(
+                    || self.bump().map(String::from).unwrap_or_default(),
+                    str::to_owned,
+                );
+                format!("<mtext>{}</mtext>", escape_xml(&text))
+            }
+            "operatorname" => {
+                let limits = self.peek() == Some('*');
+                if limits {
+                    self.bump();
+                }
+                let name = self.raw_group().unwrap_or_default();
+                return Some(Atom {
+                    xml: format!("<mi>{}</mi>", escape_xml(name.trim())),
+                    limits,
+                });
+            }
+            "mathrm" | "rm" | "mathup" => self.styled_argument(Variant::Normal),
+            "mathbf" | "bf" | "boldsymbol" | "bm" => self.styled_argument(Variant::Bold),
+            "mathit" | "it" => self.styled_argument(Variant::Italic),
+            "mathbb" => self.styled_argument(Variant::DoubleStruck),
+            "mathcal" | "mathscr" => self.styled_argument(Variant::Script),
+            "mathfrak" => self.styled_argument(Variant::Fraktur),
+            "mathsf" => self.styled_argument(Variant::SansSerif),
+            "mathtt" => self.styled_argument(Variant::Monospace),
+            "hat" | "widehat" | "bar" | "overline" | "tilde" | "widetilde" | "vec" | "dot"
+            | "ddot" | "check" | "breve" | "acute" | "grave" | "overrightarrow" => {
+                let mark = accent(name);
+                format!(
+                    "<mover accent=\"true\">{}<mo stretchy=\"{}\">{mark}</mo></mover>",
+                    self.argument(),
+                    name.starts_with("wide") || name.starts_with("over")
+                )
+            }
+            "underline" => format!(
+                "<munder accentunder=\"true\">{}<mo stretchy=\"true\">_</mo></munder>",
+                self.argument()
+            ),
+            "overbrace" => format!(
+                "<mover>{}<mo stretchy=\"true\">\u{23DE}</mo></mover>",
+                self.argument()
+            ),
+            "underbrace" => format!(
+                "<munder>{}<mo stretchy=\"true\">\u{23DF}</mo></munder>",
+                self.argument()
+            ),
+            "overset" | "stackrel" => {
+                let (over, base) = (self.argument(), self.argument());
+                format!("<mover>{base}{over}</mover>")
+            }
+            "underset" => {
+                let (under, base) = (self.argument(), self.argument());
+                format!("<munder>{base}{under}</munder>")
+            }
+            "boxed" => format!("<menclose notation=\"box\">{}</menclose>", self.argument()),
+            "left" => self.fenced(),
+            "big" | "Big" | "bigg" | "Bigg" | "bigl" | "bigr" | "Bigl" | "Bigr" | "biggl"
+            | "biggr" | "Biggl" | "Biggr" | "middle" => {
+                let delimiter = self.delimiter();
+                format!("<mo stretchy=\"true\">{}</mo>", escape_xml(&delimiter))
+            }
+            "begin" => self.environment(),
+            "not" => {
+                let negated = self.argument();
+                format!("<menclose notation=\"updiagonalstrike\">{negated}</menclose>")
+            }
+            "color" | "textcolor" => {
+                self.raw_group();
+                if name == "textcolor" {
+                    self.argument()
+                } else {
+                    String::new()
+                }
+            }
+            "label" | "tag" | "ref" | "eqref" => {
+                self.raw_group();
+                String::new()
+            }
+            "displaystyle" | "textstyle" | "scriptstyle" | "scriptscriptstyle" | "nonumber"
+            | "notag" | "limits" | "nolimits" | "mathstrut" | "strut" | "hline" => String::new(),
+            _ => format!("<merror><mtext>\\{}</mtext></merror>", escape_xml(name)),
+        };
+        Some(Atom::new(xml))
+    }
+
+    /// Delimiter after `\left`, `\right` or `\big`; `.` is empty.
+    fn delimiter(&mut self) -> String {
+        self.skip_ws();
+        if self.peek() == Some('\\') {
+            let name = self.command().unwrap_or_default();
+            return fence(name).unwrap_or(name).to_owned();
+        }
+        match self.bump() {
+            Some('.') | None => String::new(),
+            Some(ch) => ch.to_string(),
+        }
+    }
+
+    /// `\left( ... \right)` as a fenced row.
+    fn fenced(&mut self) -> String {
+        let open = self.delimiter();
+        let mut items = Vec::new();
+        let close = loop {
+            items.extend(self.row());
+            match self.peek_command() {
+                Some("right") => {
+                    self.command();
+                    break self.delimiter();
+                }
+                _ if self.at_end() || self.peek() == Some('}') => break String::new(),
+                _ => self.skip_terminator(),
+            }
+        };
+        format!(
+            "<mrow><mo fence=\"true\" form=\"prefix\">{}</mo>{}<mo fence=\"true\" form=\"postfix\">{}</mo></mrow>",
+            escape_xml(&open),
+            items.concat(),
+            escape_xml(&close)
+        )
+    }
+
+    /// `\begin{name} ... \end{name}` as a table, with fences for matrix variants.
+    fn environment(&mut self) -> String {
+        let name = self.raw_group().unwrap_or_default().trim();
+        if matches!(name, "array" | "alignedat" | "alignat" | "alignat*") {
+            self.raw_group();
+        }
+        let mut rows = Vec::new();
+        let mut cells = Vec::new();
+        loop {
+            cells.push(mrow(self.row()));
+            if self.at_end() {
+                rows.push(std::mem::take(&mut cells));
+                break;
+            }
+            match (self.peek(), self.peek_command()) {
+                (Some('&'), _) => {
+                    self.bump();
+                }
+                (_, Some("\\" | "cr")) => {
+                    self.command();
+                    self.optional();
+                    rows.push(std::mem::take(&mut cells));
+                }
+                (_, Some("end")) => {
+                    self.command();
+                    self.raw_group();
+                    rows.push(std::mem::take(&mut cells));
+                    break;
+                }
+                (_, Some("right")) | (Some('}'), _) => {
+                    rows.push(std::mem::take(&mut cells));
+                    break;
+                }
+                _ => {
+                    self.skip_terminator();
+                }
+            }
+        }
+        // A trailing `\\` leaves an empty last row.
+        if rows
+            .last()
+            .is_some_and(|row| row.len() == 1 && row[0] == "<mrow></mrow>")
+            && rows.len() > 1
+        {
+            rows.pop();
+        }
+
+        let base = name.trim_end_matches('*');
+        if matches!(base, "equation" | "displaymath" | "math") {
+            return rows.into_iter().flatten().collect();
+        }
+        let align = match base {
+            "cases" | "dcases" => " columnalign=\"left left\"",
+            "aligned" | "align" | "alignat" | "alignedat" | "split" | "eqnarray" => {
+                " columnalign=\"right left right left right left\" columnspacing=\"0em 2em\""
+            }
+            _ => "",
+        };
+        let mut table = format!("<mtable{align}>");
+        for row in rows {
+            table.push_str("<mtr>");
+            for cell in row {
+                let _ = write!(table, "<mtd>{cell}</mtd>");
+            }
+            table.push_str("</mtr>");
+        }
+        table.push_str("</mtable>");
+        let fences = match base {
+            "pmatrix" => Some(("(", ")")),
+            "bmatrix" => Some(("[", "]")),
+            "Bmatrix" => Some(("{", "}")),
+            "cases" | "dcases" => Some(("{", "")),
+            "vmatrix" => Some(("|", "|")),
+            "Vmatrix" => Some(("\u{2016}", "\u{2016}")),
+            _ => None,
+        };
+        match fences {
+            Some((open, close)) => format!(
+                "<mrow><mo fence=\"true\">{open}</mo>{table}<mo fence=\"true\">{close}</mo></mrow>"
+            ),
+            None => table,
+        }
+    }
+}
+
+fn mrow(mut items: Vec<String>) -> String {
+    if items.len() == 1 {
+        return items.pop().unwrap_or_default();
+    }
+    format!("<mrow>{}</mrow>", items.concat())
+}
+
+fn greek(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "alpha" => "α",
+        "beta" => "β",
+        "gamma" => "γ",
+        "delta" => "δ",
+        "epsilon" => "ϵ",
+        "varepsilon" => "ε",
+        "zeta" => "ζ",
+        "eta" => "η",
+        "theta" => "θ",
+        "vartheta" => "ϑ",
+        "iota" => "ι",
+        "kappa" => "κ",
+        "lambda" => "λ",
+        "mu" => "μ",
+        "nu" => "ν",
+        "xi" => "ξ",
+        "pi" => "π",
+        "varpi" => "ϖ",
+        "rho" => "ρ",
+        "varrho" => "ϱ",
+        "sigma" => "σ",
+        "varsigma" => "ς",
+        "tau" => "τ",
+        "upsilon" => "υ",
+        "phi" => "ϕ",
+        "varphi" => "φ",
+        "chi" => "χ",
+        "psi" => "ψ",
+        "omega" => "ω",
+        "Gamma" => "Γ",
+        "Delta" => "Δ",
+        "Theta" => "Θ",
+        "Lambda" => "Λ",
+        "Xi" => "Ξ",
+        "Pi" => "Π",
+        "Sigma" => "Σ",
+        "Upsilon" => "Υ",
+        "Phi" => "Φ",
+        "Psi" => "Ψ",
+        "Omega" => "Ω",
+        "infty" => "∞",
+        "ell" => "ℓ",
+        "hbar" => "ℏ",
+        "aleph" => "ℵ",
+        "emptyset" | "varnothing" => "∅",
+        "Re" => "ℜ",
+        "Im" => "ℑ",
+        "wp" => "℘",
+        _ => return None,
+    })
+}
+
+fn operator(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "pm" => "±",
+        "mp" => "∓",
+        "times" => "×",
+        "div" => "÷",
+        "cdot" => "⋅",
+        "ast" => "∗",
+        "star" => "⋆",
+        "circ" => "∘",
+        "bullet" => "∙",
+        "oplus" => "⊕",
+        "ominus" => "⊖",
+        "otimes" => "⊗",
+        "odot" => "⊙",
+        "leq" | "le" => "≤",
+        "geq" | "ge" => "≥",
+        "leqslant" => "⩽",
+        "geqslant" => "⩾",
+        "neq" | "ne" => "≠",
+        "ll" => "≪",
+        "gg" => "≫",
+        "approx" => "≈",
+        "equiv" => "≡",
+        "sim" => "∼",
+        "simeq" => "≃",
+        "cong" => "≅",
+        "propto" => "∝",
+        "in" => "∈",
+        "notin" => "∉",
+        "ni" => "∋",
+        "subset" => "⊂",
+        "supset" => "⊃",
+        "subseteq" => "⊆",
+        "supseteq" => "⊇",
+        "cup" => "∪",
+        "cap" => "∩",
+        "setminus" => "∖",
+        "wedge" | "land" => "∧",
+        "vee" | "lor" => "∨",
+        "neg" | "lnot" => "¬",
+        "forall" => "∀",
+        "exists" => "∃",
+        "partial" => "∂",
+        "nabla" => "∇",
+        "to" | "rightarrow" => "→",
+        "leftarrow" | "gets" => "←",
+        "Rightarrow" => "⇒",
+        "Leftarrow" => "⇐",
+        "leftrightarrow" => "↔",
+        "Leftrightarrow" => "⇔",
+        "longrightarrow" => "⟶",
+        "Longrightarrow" | "implies" => "⟹",
+        "Longleftrightarrow" | "iff" => "⟺",
+        "mapsto" => "↦",
+        "uparrow" => "↑",
+        "downarrow" => "↓",
+        "ldots" | "dots" | "dotsc" | "dotsb" => "…",
+        "cdots" => "⋯",
+        "vdots" => "⋮",
+        "ddots" => "⋱",
+        "prime" => "′",
+        "angle" => "∠",
+        "perp" => "⊥",
+        "parallel" => "∥",
+        "mid" => "∣",
+        "triangle" => "△",
+        "degree" => "°",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "{" | "lbrace" => "{",
+        "}" | "rbrace" => "}",
+        "|" | "Vert" => "‖",
+        "vert" => "|",
+        "%" => "%",
+        "$" => "$",
+        "&" => "&",
+        "#" => "#",
+        "_" => "_",
+        "colon" => ":",
+        _ => return None,
+    })
+}
+
+fn large_operator(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "sum" => "∑",
+        "prod" => "∏",
+        "coprod" => "∐",
+        "int" => "∫",
+        "iint" => "∬",
+        "iiint" => "∭",
+        "oint" => "∮",
+        "bigcup" => "⋃",
+        "bigcap" => "⋂",
+        "bigoplus" => "⨁",
+        "bigotimes" => "⨂",
+        "bigvee" => "⋁",
+        "bigwedge" => "⋀",
+        _ => return None,
+    })
+}
+
+const FUNCTIONS: [&str; 31] = [
+    "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "coth", "log", "ln", "lg", "exp", "lim", "liminf", "limsup", "max", "min", "sup", "inf", "det",
+    "gcd", "deg", "dim", "ker", "arg", "Pr",
+];
+/// Functions whose scripts sit below them, like `\lim_{x \to 0}`.
+const LIMIT_FUNCTIONS: [&str; 9] = [
+    "lim", "liminf", "limsup", "max", "min", "sup", "inf", "det", "Pr",
+];
+
+fn space(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "," | "thinspace" => "0.1667em",
+        ":" | ">" | "medspace" => "0.2222em",
+        ";" | "thickspace" => "0.2778em",
+        " " => "0.333em",
+        "quad" => "1em",
+        "qquad" => "2em",
+        "!" | "negthinspace" => "-0.1667em",
+        _ => return None,
+    })
+}
+
+fn accent(name: &str) -> &'static str {
+    match name {
+        "hat" | "widehat" => "^",
+        "bar" | "overline" => "\u{AF}",
+        "tilde" | "widetilde" => "~",
+        "vec" | "overrightarrow" => "\u{2192}",
+        "dot" => "\u{2D9}",
+        "ddot" => "\u{A8}",
+        "check" => "\u{2C7}",
+        "breve" => "\u{2D8}",
+        "acute" => "\u{B4}",
+        _ => "`",
+    }
+}
+
+fn fence(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "{" | "lbrace" => "{",
+        "}" | "rbrace" => "}",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "|" | "Vert" | "lVert" | "rVert" => "‖",
+        "vert" | "lvert" | "rvert" => "|",
+        "lbrack" => "[",
+        "rbrack" => "]",
+        _ => return None,
+    })
+}
+
+/// `ch` from the Unicode mathematical alphanumeric block for `variant`; characters the block
+/// does not cover are returned unchanged, XML-escaped.
+fn styled_char(ch: char, variant: Variant) -> String {
+    let letterlike = match (variant, ch) {
+        (Variant::Italic, 'h') => Some('ℎ'),
+        (Variant::DoubleStruck, 'C') => Some('ℂ'),
+        (Variant::DoubleStruck, 'H') => Some('ℍ'),
+        (Variant::DoubleStruck, 'N') => Some('ℕ'),
+        (Variant::DoubleStruck, 'P') => Some('ℙ'),
+        (Variant::DoubleStruck, 'Q') => Some('ℚ'),
+        (Variant::DoubleStruck, 'R') => Some('ℝ'),
+        (Variant::DoubleStruck, 'Z') => Some('ℤ'),
+        (Variant::Script, 'B') => Some('ℬ'),
+        (Variant::Script, 'E') => Some('ℰ'),
+        (Variant::Script, 'F') => Some('ℱ'),
+        (Variant::Script, 'H') => Some('ℋ'),
+        (Variant::Script, 'I') => Some('ℐ'),
+        (Variant::Script, 'L') => Some('ℒ'),
+        (Variant::Script, 'M') => Some('ℳ'),
+        (Variant::Script, 'R') => Some('ℛ'),
+        (Variant::Script, 'e') => Some('ℯ'),
+        (Variant::Script, 'g') => Some('ℊ'),
+        (Variant::Script, 'o') => Some('ℴ'),
+        (Variant::Fraktur, 'C') => Some('ℭ'),
+        (Variant::Fraktur, 'H') => Some('ℌ'),
+        (Variant::Fraktur, 'I') => Some('ℑ'),
+        (Variant::Fraktur, 'R') => Some('ℜ'),
+        (Variant::Fraktur, 'Z') => Some('ℨ'),
+        _ => None,
+    };
+    if let Some(ch) = letterlike {
+        return ch.to_string();
+    }
+    // (capital A, small a, digit 0) for each alphabet.
+    let bases: Option<(u32, u32, Option<u32>)> = match variant {
+        Variant::Normal => None,
+        Variant::Bold => Some((0x1D400, 0x1D41A, Some(0x1D7CE))),
+        Variant::Italic => Some((0x1D434, 0x1D44E, None)),
+        Variant::DoubleStruck => Some((0x1D538, 0x1D552, Some(0x1D7D8))),
+        Variant::Script => Some((0x1D49C, 0x1D4B6, None)),
+        Variant::Fraktur => Some((0x1D504, 0x1D51E, None)),
+        Variant::SansSerif => Some((0x1D5A0, 0x1D5BA, Some(0x1D7E2))),
+        Variant::Monospace => Some((0x1D670, 0x1D68A, Some(0x1D7F6))),
+    };
+    let mapped = bases.and_then(|(upper, lower, digit)| {
+        let code = match ch {
+            'A'..='Z' => upper + (ch as u32 - 'A' as u32),
+            'a'..='z' => lower + (ch as u32 - 'a' as u32),
+            '0'..='9' => digit? + (ch as u32 - '0' as u32),
+            _ => return None,
+        };
+        char::from_u32(code)
+    });
+    match mapped {
+        Some(ch) => ch.to_string(),
+        None => escape_xml(&ch.to_string()),
+    }
+}
@@ -0,0 +1,415 @@
+//! Formulas in model output.
+//!
+//! The models mix `$...$`, `\(...\)`, `$$...$$`, `\[...\]` and bare `\begin{equation}`-style
+//! environments, sometimes cut off mid-formula. [`find_formulas`] locates those spans outside
+//! code, [`check_balance`] reports braces, `\left`/`\right` pairs and environments that do not
+//! close, and [`normalize_formulas`] rewrites every span with one delimiter style (or as MathML,
+//! with the `mathml` cargo feature), appending the closers a truncated formula is missing.
+//!
+//! A single `$` only opens inline math when followed by a non-space character and closes on a
+//! `$` preceded by one and not followed by a digit, as in Pandoc, so prices such as `$5 and $10`
+//! stay text. Unlike Pandoc, the closing `$` must be on the same line.
+
+#[cfg(feature = "mathml")]
+pub mod mathml;
+#[cfg(feature = "mathml")]
+pub mod omml;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Display environments recognised without surrounding delimiters.
+const DISPLAY_ENVIRONMENTS: [&str; 12] = [
+    "equation",
+    "equation*",
+    "align",
+    "align*",
+    "gather",
+    "gather*",
+    "multline",
+    "multline*",
+    "eqnarray",
+    "eqnarray*",
+    "displaymath",
+    "math",
+];
+/// Environments whose wrapper only switches to display mode and is dropped on normalisation.
+const WRAPPER_ENVIRONMENTS: [&str; 4] = ["equation", "equation*", "displaymath", "math"];
+
+/// A formula found in text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormulaSpan {
+    /// Byte range of the formula including its delimiters.
+    pub start: usize,
+    pub end: usize,
+    /// LaTeX between the delimiters, trimmed.
+    pub latex: String,
+    /// Display (block) rather than inline math.
+    pub display: bool,
+    /// The closing delimiter was missing and the span runs to the end of its paragraph or of
+    /// the text.
+    pub unclosed: bool,
+}
+
+/// Delimiters used for normalised LaTeX.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[value(rename_all = "lowercase")]
+pub enum MathDelimiters {
+    /// `$...$` inline and `$$...$$` display, as most markdown renderers expect.
+    #[default]
+    Dollar,
+    /// `\(...\)` inline and `\[...\]` display.
+    Bracket,
+}
+
+/// How [`normalize_formulas`] writes each formula.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[value(rename_all = "lowercase")]
+pub enum FormulaMode {
+    /// Leave formulas as generated.
+    #[default]
+    Off,
+    /// Rewrite delimiters and close truncated formulas.
+    Latex,
+    /// Replace formulas with MathML `<math>` elements.
+    #[cfg(feature = "mathml")]
+    Mathml,
+}
+
+/// A formula whose markup does not balance.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FormulaIssue {
+    /// Index of the formula in the text, counting from 0.
+    pub formula: usize,
+    pub message: String,
+    /// Whether normalisation appended the missing closers.
+    pub repaired: bool,
+}
+
+/// Every formula in `text`, in order. Fenced and inline code is skipped, as are escaped
+/// dollars (`\$`).
+pub fn find_formulas(text: &str) -> Vec<FormulaSpan> {
+    let bytes = text.as_bytes();
+    let mut spans = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let rest = &text[pos..];
+        let line_start = pos == 0 || bytes[pos - 1] == b'\n';
+        if line_start && rest.starts_with("```") {
+            pos = rest[3..]
+                .find("\n```")
+                .map_or(text.len(), |end| pos + 3 + end + 4);
+            continue;
+        }
+        if bytes[pos] == b'`' {
+            let ticks = rest.bytes().take_while(|&b| b == b'`').count();
+            let fence = &rest[..ticks];
+            pos = rest[ticks..]
+                .find(fence)
+                .map_or(pos + ticks, |end| pos + ticks + end + ticks);
+            continue;
+        }
+        let span = if rest.starts_with("$$") {
+            delimited(text, pos, "$$", "$$", true)
+        } else if rest.starts_with("\\[") {
+            delimited(text, pos, "\\[", "\\]", true)
+        } else if rest.starts_with("\\(") {
+            delimited(text, pos, "\\(", "\\)", false)
+        } else if rest.starts_with("\\begin{") {
+            environment(text, pos)
+        } else if rest.starts_with('$') {
+            inline_dollar(text, pos)
+        } else {
+            None
+        };
+        match span {
+            Some(span) => {
+                pos = span.end;
+                spans.push(span);
+            }
+            // Skip escaped characters such as `\$` whole.
+            None if bytes[pos] == b'\\' => pos += 1 + next_char_len(text, pos + 1),
+            None => pos += next_char_len(text, pos),
+        }
+    }
+    spans
+}
+
+fn next_char_len(text: &str, pos: usize) -> usize {
+    text[pos..].chars().next().map_or(1, char::len_utf8)
+}
+
+/// Span opened by `open` at `start`. Inline math stops at a blank line when unclosed;
+/// display math runs to the end of the text.
+fn delimited(
+    text: &str,
+    start: usize,
+    open: &str,
+    close: &str,
+    display: bool,
+) -> Option<FormulaSpan> {
+    let body_start = start + open.len();
+    let body = &text[body_start..];
+    let limit = if display {
+        body.len()
+    } else {
+        body.find("\n\n").unwrap_or(body.len())
+    };
+    let (body_end, end, unclosed) = match body[..limit].find(close) {
+        Some(len) => (body_start + len, body_start + len + close.len(), false),
+        None => (body_start + limit, body_start + limit, true),
+    };
+    let latex = text[body_start..body_end].trim();
+    (!latex.is_empty()).then(|| FormulaSpan {
+        start,
+        end,
+        latex: latex.to_owned(),
+        display,
+        unclosed,
+    })
+}
+
+fn environment(text: &str, start: usize) -> Option<FormulaSpan> {
+    let name_start = start + "\\begin{".len();
+    let name_len = text[name_start..].find('}')?;
+    let name = &text[name_start..name_start + name_len];
+    if !DISPLAY_ENVIRONMENTS.contains(&name) {
+        return None;
+    }
+    let close = format!("\\end{{{name}}}");
+    let body_start = name_start + name_len + 1;
+    let (end, unclosed) = match text[body_start..].find(&close) {
+        Some(len) => (body_start + len + close.len(), false),
+        None => (text.len(), true),
+    };
+    // Wrappers only mark display math and are dropped; other environments stay in the
+    // formula, where `check_balance` reports a missing `\end`.
+    let wrapper = WRAPPER_ENVIRONMENTS.contains(&name);
+    let latex = if wrapper {
+        let body_end = if unclosed { end } else { end - close.len() };
+        &text[body_start..body_end]
+    } else {
+        &text[start..end]
+    }
+    .trim();
+    (!latex.is_empty()).then(|| FormulaSpan {
+        start,
+        end,
+        latex: latex.to_owned(),
+        display: true,
+        unclosed: unclosed && wrapper,
+    })
+}
+
+fn inline_dollar(text: &str, start: usize) -> Option<FormulaSpan> {
+    let body_start = start + 1;
+    let body = &text[body_start..];
+    if body.starts_with(char::is_whitespace) || body.is_empty() {
+        return None;
+    }
+    let limit = body.find('\n').unwrap_or(body.len());
+    let bytes = body.as_bytes();
+    let mut idx = 0;
+    while idx < limit {
+        match bytes[idx] {
+            b'\\' => idx += 2,
+            b'$' => {
+                let closes = idx > 0
+                    && !bytes[idx - 1].is_ascii_whitespace()
+                    && !bytes.get(idx + 1).is_some_and(u8::is_ascii_digit);
+                if closes {
+                    return Some(FormulaSpan {
+                        start,
+                        end: body_start + idx + 1,
+                        latex: body[..idx].trim().to_owned(),
+                        display: false,
+                        unclosed: false,
+                    });
+                }
+                // A `$` that cannot close ends the candidate: it starts the next one.
+                return None;
+            }
+            _ => idx += 1,
+        }
+    }
+    None
+}
+
+/// Constructs left open at the end of a formula, innermost last.
+#[derive(Debug, Clone, PartialEq)]
+enum Open {
+    Brace,
+    Left,
+    Environment(String),
+}
+
+/// Result of [`check_balance`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Balance {
+    /// One message per brace, `\left` or environment that does not balance.
+    pub problems: Vec<String>,
+    /// Closers that complete a formula that was only cut short; `None` when something closes
+    /// that never opened.
+    pub closers: Option<String>,
+}
+
+/// Check that braces, `\left`/`\right` pairs and environments in `latex` balance.
+pub fn check_balance(latex: &str) -> Balance {
+    let mut stack = Vec::new();
+    let mut problems = Vec::new();
+    let mut repairable = true;
+    let mut rest = latex;
+    while let Some(ch) = rest.chars().next() {
+        if ch == '\\' {
+            let name = command_name(&rest[1..]);
+            let after = &rest[1 + name.len()..];
+            match name {
+                "left" => stack.push(Open::Left),
+                "right" => {
+                    if stack.last() == Some(&Open::Left) {
+                        stack.pop();
+                    } else {
+                        problems.push("\\right without a matching \\left".to_owned());
+                        repairable = false;
+                    }
+                }
+                "begin" | "end" => {
+                    if let Some(env) = braced(after) {
+                        if name == "begin" {
+                            stack.push(Open::Environment(env.to_owned()));
+                        } else if stack.last() == Some(&Open::Environment(env.to_owned())) {
+                            stack.pop();
+                        } else {
+                            problems
+                                .push(format!("\\end{{{env}}} does not close an open environment"));
+                            repairable = false;
+                        }
+                        rest = &after[env.len() + 2..];
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+            rest = after;
+            continue;
+        }
+        match ch {
+            '{' => stack.push(Open::Brace),
+            '}' => {
+                if stack.last() == Some(&Open::Brace) {
+                    stack.pop();
+                } else {
+                    problems.push("unexpected }".to_owned());
+                    repairable = false;
+                }
+            }
+            _ => {}
+        }
+        rest = &rest[ch.len_utf8()..];
+    }
+
+    let mut closers = String::new();
+    for open in stack.iter().rev() {
+        match open {
+            Open::Brace => {
+                problems.push("unclosed {".to_owned());
+                closers.push('}');
+            }
+            Open::Left => {
+                problems.push("\\left without a matching \\right".to_owned());
+                closers.push_str("\\right.");
+            }
+            Open::Environment(env) => {
+                problems.push(format!("unclosed \\begin{{{env}}}"));
+                closers.push_str(&format!("\\end{{{env}}}"));
+            }
+        }
+    }
+    Balance {
+        problems,
+        closers: repairable.then_some(closers),
+    }
+}
+
+/// Name of the command after a backslash: a run of letters, or a single other character.
+fn command_name(rest: &str) -> &str {
+    let letters = rest
+        .find(|ch: char| !ch.is_ascii_alphabetic())
+        .unwrap_or(rest.len());
+    match letters {
+        0 => rest.chars().next().map_or("", |ch| &rest[..ch.len_utf8()]),
+        len => &rest[..len],
+    }
+}
+
+/// Content of a `{...}` argument at the start of `rest` without nested braces.
+fn braced(rest: &str) -> Option<&str> {
+    let inner = rest.strip_prefix('{')?;
+    let len = inner.find('}')?;
+    Some(&inner[..len]).filter(|name| !name.contains('{'))
+}
+
+/// Text with every formula rewritten by `mode`, and the formulas that did not balance.
+/// Missing closers of formulas that were only cut short are appended.
+pub fn normalize_formulas(
+    text: &str,
+    mode: FormulaMode,
+    delimiters: MathDelimiters,
+) -> (String, Vec<FormulaIssue>) {
+    if mode == FormulaMode::Off {
+        return (text.to_owned(), Vec::new());
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut issues = Vec::new();
+    let mut last = 0;
+    for (index, span) in find_formulas(text).into_iter().enumerate() {
+        out.push_str(&text[last..span.start]);
+        last = span.end;
+
+        let Balance {
+            mut problems,
+            closers,
+        } = check_balance(&span.latex);
+        if span.unclosed {
+            problems.insert(0, "missing closing delimiter".to_owned());
+        }
+        let mut latex = span.latex;
+        if let Some(closers) = &closers {
+            latex.push_str(closers);
+        }
+        issues.extend(problems.into_iter().map(|message| FormulaIssue {
+            formula: index,
+            message,
+            repaired: closers.is_some(),
+        }));
+
+        let rendered = match (mode, delimiters, span.display) {
+            #[cfg(feature = "mathml")]
+            (FormulaMode::Mathml, _, display) => mathml::to_mathml(&latex, display),
+            (_, MathDelimiters::Dollar, false) => format!("${latex}$"),
+            (_, MathDelimiters::Dollar, true) => format!("$$\n{latex}\n$$"),
+            (_, MathDelimiters::Bracket, false) => format!("\\({latex}\\)"),
+            (_, MathDelimiters::Bracket, true) => format!("\\[\n{latex}\n\\]"),
+        };
+        // Display math goes on lines of its own, without the spaces that separated it from the
+        // surrounding text.
+        if span.display {
+            out.truncate(out.trim_end_matches([' ', '\t']).len());
+            if !(out.is_empty() || out.ends_with('\n')) {
+                out.push('\n');
+            }
+        }
+        out.push_str(&rendered);
+        if span.display {
+            let after = text[last..].trim_start_matches([' ', '\t']);
+            last = text.len() - after.len();
+            if !(after.is_empty() || after.starts_with('\n')) {
+                out.push('\n');
+            }
+        }
+    }
+    out.push_str(&text[last..]);
+    (out, issues)
+}
@@ -0,0 +1,232 @@
+//! Office Math Markup (OMML) for Word, converted from the Presentation MathML of
+//! [`to_mathml`]: fractions, scripts, radicals, accents, fences, matrices and n-ary operators
+//! map onto their `m:` counterparts so formulas stay editable in the equation editor. Elements
+//! without a counterpart keep their text.
+
+use std::fmt::Write;
+
+use roxmltree::{Document, Node};
+
+use super::mathml::to_mathml;
+use crate::export::escape_xml;
+
+/// Operators written as `m:nary`, with their scripts as limits.
+const NARY_OPERATORS: [&str; 13] = [
+    "∑", "∏", "∐", "∫", "∬", "∭", "∮", "⋃", "⋂", "⨁", "⨂", "⋁", "⋀",
+];
+
+/// `<m:oMath>` for `latex`, wrapped in `<m:oMathPara>` when `display` is set. The `m` prefix is
+/// left for the enclosing document to bind to the Office Math namespace.
+pub fn to_omml(latex: &str, display: bool) -> String {
+    let mathml = to_mathml(latex, display);
+    let math = match Document::parse(&mathml) {
+        Ok(doc) => format!("<m:oMath>{}</m:oMath>", children(doc.root_element())),
+        // `to_mathml` always writes well-formed markup; keep the source rather than lose it.
+        Err(_) => format!("<m:oMath>{}</m:oMath>", run(latex, Style::Text)),
+    };
+    if display {
+        format!("<m:oMathPara>{math}</m:oMathPara>")
+    } else {
+        math
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Style {
+    /// Word's default math italic.
+    Math,
+    /// Upright, as for numbers, operators and multi-letter identifiers.
+    Plain,
+    /// Ordinary text.
+    Text,
+}
+
+fn run(text: &str, style: Style) -> String {
+    let properties = match style {
+        Style::Math => "",
+        Style::Plain => "<m:rPr><m:sty m:val=\"p\"/></m:rPr>",
+        Style::Text => "<m:rPr><m:nor/></m:rPr>",
+    };
+    format!(
+        "<m:r>{properties}<m:t xml:space=\"preserve\">{}</m:t></m:r>",
+        escape_xml(text)
+    )
+}
+
+fn text(node: Node) -> String {
+    node.descendants()
+        .filter(Node::is_text)
+        .filter_map(|node| node.text())
+        .collect()
+}
+
+fn elements<'a, 'input>(node: Node<'a, 'input>) -> Vec<Node<'a, 'input>> {
+    node.children().filter(Node::is_element).collect()
+}
+
+fn children(node: Node) -> String {
+    elements(node).into_iter().map(element).collect()
+}
+
+/// `name` holding the OMML of `node`, empty when the MathML argument is missing.
+fn argument(name: &str, node: Option<Node>) -> String {
+    format!(
+        "<m:{name}>{}</m:{name}>",
+        node.map(element).unwrap_or_default()
+    )
+}
+
+fn element(node: Node) -> String {
+    let args = elements(node);
+    let arg = |index: usize| args.get(index).copied();
+    match node.tag_name().name() {
+        "mi" => {
+            let name = text(node);
+            let upright =
+                node.attribute("mathvariant") == Some("normal") || name.chars().count() > 1;
+            run(&name, if upright { Style::Plain } else { Style::Math })
+        }
+        "mn" | "mo" => run(&text(node), Style::Plain),
+        "mtext" | "merror" => run(&text(node), Style::Text),
+        "mspace" => run(" ", Style::Plain),
+        "mrow" => fenced(&args).unwrap_or_else(|| children(node)),
+        "mfrac" => format!(
+            "<m:f>{}{}</m:f>",
+            argument("num", arg(0)),
+            argument("den", arg(1))
+        ),
+        "msqrt" => format!(
+            "<m:rad><m:radPr><m:degHide m:val=\"1\"/></m:radPr><m:deg/><m:e>{}</m:e></m:rad>",
+            children(node)
+        ),
+        "mroot" => format!(
+            "<m:rad>{}{}</m:rad>",
+            argument("deg", arg(1)),
+            argument("e", arg(0))
+        ),
+        "msub" => scripts(arg(0), arg(1), None, false),
+        "msup" => scripts(arg(0), None, arg(1), false),
+        "msubsup" => scripts(arg(0), arg(1), arg(2), false),
+        "munder" => scripts(arg(0), arg(1), None, true),
+        "mover" if node.attribute("accent") == Some("true") => format!(
+            "<m:acc><m:accPr><m:chr m:val=\"{}\"/></m:accPr>{}</m:acc>",
+            escape_xml(&arg(1).map(text).unwrap_or_default()),
+            argument("e", arg(0))
+        ),
+        "mover" => scripts(arg(0), None, arg(1), true),
+        "munderover" => scripts(arg(0), arg(1), arg(2), true),
+        "menclose" => {
+            let strike = node.attribute("notation") == Some("updiagonalstrike");
+            let properties = if strike {
+                "<m:borderBoxPr><m:hideTop m:val=\"1\"/><m:hideBot m:val=\"1\"/>\
+                 <m:hideLeft m:val=\"1\"/><m:hideRight m:val=\"1\"/>\
+                 <m:strikeBLTR m:val=\"1\"/></m:borderBoxPr>"
+            } else {
+                ""
+            };
+            format!(
+                "<m:borderBox>{properties}<m:e>{}</m:e></m:borderBox>",
+                children(node)
+            )
+        }
+        "mtable" => matrix(&args),
+        _ => children(node),
+    }
+}
+
+/// An `mrow` opened and closed by fence operators as `m:d`.
+fn fenced(args: &[Node]) -> Option<String> {
+    let is_fence = |node: &Node| node.has_tag_name("mo") && node.attribute("fence") == Some("true");
+    let [open, inner @ .., close] = args else {
+        return None;
+    };
+    if !(is_fence(open) && is_fence(close)) {
+        return None;
+    }
+    let content: String = inner.iter().copied().map(element).collect();
+    Some(format!(
+        "<m:d><m:dPr><m:begChr m:val=\"{}\"/><m:endChr m:val=\"{}\"/></m:dPr><m:e>{content}</m:e></m:d>",
+        escape_xml(&text(*open)),
+        escape_xml(&text(*close))
+    ))
+}
+
+/// Scripts on `base`: an `m:nary` for large operators, limits above and below when `limits`
+/// is set, `m:sSub`/`m:sSup`/`m:sSubSup` otherwise.
+fn scripts(base: Option<Node>, sub: Option<Node>, sup: Option<Node>, limits: bool) -> String {
+    let operator = base
+        .filter(|base| base.has_tag_name("mo"))
+        .map(text)
+        .filter(|symbol| NARY_OPERATORS.contains(&symbol.as_str()));
+    if let Some(symbol) = operator {
+        let mut properties = format!("<m:chr m:val=\"{symbol}\"/>");
+        let location = if limits { "undOvr" } else { "subSup" };
+        let _ = write!(properties, "<m:limLoc m:val=\"{location}\"/>");
+        if sub.is_none() {
+            properties.push_str("<m:subHide m:val=\"1\"/>");
+        }
+        if sup.is_none() {
+            properties.push_str("<m:supHide m:val=\"1\"/>");
+        }
+        // The operand follows the operator in MathML, so the body stays empty.
+        return format!(
+            "<m:nary><m:naryPr>{properties}</m:naryPr>{}{}<m:e/></m:nary>",
+            argument("sub", sub),
+            argument("sup", sup)
+        );
+    }
+    let body = base.map(element).unwrap_or_default();
+    let base = format!("<m:e>{body}</m:e>");
+    match (sub, sup, limits) {
+        (Some(sub), None, false) => {
+            format!("<m:sSub>{base}{}</m:sSub>", argument("sub", Some(sub)))
+        }
+        (None, Some(sup), false) => {
+            format!("<m:sSup>{base}{}</m:sSup>", argument("sup", Some(sup)))
+        }
+        (Some(sub), Some(sup), false) => format!(
+            "<m:sSubSup>{base}{}{}</m:sSubSup>",
+            argument("sub", Some(sub)),
+            argument("sup", Some(sup))
+        ),
+        (sub, sup, _) => {
+            let mut out = body;
+            if let Some(sub) = sub {
+                out = format!(
+                    "<m:limLow><m:e>{out}</m:e>{}</m:limLow>",
+                    argument("lim", Some(sub))
+                );
+            }
+            if let Some(sup) = sup {
+                out = format!(
+                    "<m:limUpp><m:e>{out}</m:e>{}</m:limUpp>",
+                    argument("lim", Some(sup))
+                );
+            }
+            out
+        }
+    }
+}
+
+/// `mtable` rows as an `m:m` matrix, short rows padded so every row has the same columns.
+fn matrix(rows: &[Node]) -> String {
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| elements(*row).into_iter().map(children).collect())
+        .collect();
+    let columns = cells.iter().map(Vec::len).max().unwrap_or(0).max(1);
+    let mut out = String::from("<m:m>");
+    for row in cells {
+        out.push_str("<m:mr>");
+        for index in 0..columns {
+            let _ = write!(
+                out,
+                "<m:e>{}</m:e>",
+                row.get(index).map_or("", String::as_str)
+            );
+        }
+        out.push_str("</m:mr>");
+    }
+    out.push_str("</m:m>");
+    out
+}
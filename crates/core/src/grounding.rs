@@ -0,0 +1,184 @@
+//! DeepSeek-OCR grounding markup.
+//!
+//! Grounded prompts make the model emit `<|ref|>label<|/ref|><|det|>[[x0, y0, x1, y1], ...]<|/det|>`
+//! with coordinates normalised to `0..=999` over the image it was shown. Helpers here rewrite or
+//! strip those boxes when outputs from several views of one image are combined, and split an
+//! output into its labelled blocks for structured exports.
+
+/// Upper bound of the normalised grounding coordinate range.
+pub const GROUNDING_SCALE: f32 = 999.0;
+
+const REF_OPEN: &str = "<|ref|>";
+const REF_CLOSE: &str = "<|/ref|>";
+const DET_OPEN: &str = "<|det|>";
+const DET_CLOSE: &str = "<|/det|>";
+const GROUNDING_TAG: &str = "<|grounding|>";
+
+/// One `<|ref|>label<|/ref|><|det|>boxes<|/det|>` entry and the text up to the next entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroundedBlock {
+    pub label: String,
+    /// Boxes in grounding units; empty for text outside any entry or an entry without a
+    /// well-formed `<|det|>` span.
+    pub boxes: Vec<[f32; 4]>,
+    pub text: String,
+}
+
+/// Rewrite every grounding box in `text`. `map` receives and returns boxes normalised to
+/// `0.0..=1.0`; results are clamped back into the `0..=999` integer grid. Malformed `<|det|>`
+/// spans are left untouched.
+pub fn map_boxes(text: &str, map: impl Fn([f32; 4]) -> [f32; 4]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(DET_OPEN) {
+        let body_start = start + DET_OPEN.len();
+        let Some(len) = rest[body_start..].find(DET_CLOSE) else {
+            break;
+        };
+        let body = &rest[body_start..body_start + len];
+        out.push_str(&rest[..body_start]);
+        match parse_boxes(body) {
+            Some(boxes) => out.push_str(&format_boxes(boxes.into_iter().map(|bbox| {
+                let normalised = bbox.map(|value| value / GROUNDING_SCALE);
+                map(normalised).map(|value| value * GROUNDING_SCALE)
+            }))),
+            None => out.push_str(body),
+        }
+        out.push_str(DET_CLOSE);
+        rest = &rest[body_start + len + DET_CLOSE.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Remove `<|det|>…<|/det|>` spans so outputs can be compared on their text alone.
+pub fn strip_boxes(text: &str) -> String {
+    strip_span(text, DET_OPEN, DET_CLOSE)
+}
+
+/// Remove `<|ref|>…<|/ref|>` labels, `<|det|>…<|/det|>` boxes and `<|grounding|>` tags, keeping
+/// the text they annotate. Lines left holding only markup are dropped along with the blank line
+/// they would leave behind.
+pub fn strip_grounding(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut dropped = false;
+    for line in text.lines() {
+        let stripped = strip_span(&strip_span(line, REF_OPEN, REF_CLOSE), DET_OPEN, DET_CLOSE)
+            .replace(GROUNDING_TAG, "");
+        if stripped.trim().is_empty() {
+            if !line.trim().is_empty() {
+                dropped = true;
+                continue;
+            }
+            if dropped && lines.last().is_none_or(|last| last.is_empty()) {
+                continue;
+            }
+        } else {
+            dropped = false;
+        }
+        if stripped.len() == line.len() {
+            lines.push(stripped);
+        } else {
+            lines.push(stripped.trim_end().to_owned());
+        }
+    }
+    lines.join("\n")
+}
+
+fn strip_span(text: &str, open: &str, close: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(open) {
+        let Some(len) = rest[start..].find(close) else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        rest = &rest[start + len + close.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Split grounded output into its entries, in the order the model emitted them. Text before the
+/// first entry becomes an unlabelled block without boxes, so plain outputs yield one block.
+pub fn parse_grounded(text: &str) -> Vec<GroundedBlock> {
+    let mut blocks = Vec::new();
+    let mut push = |label: &str, boxes: Vec<[f32; 4]>, text: &str| {
+        let text = text.trim();
+        if !text.is_empty() || !boxes.is_empty() {
+            blocks.push(GroundedBlock {
+                label: label.trim().to_owned(),
+                boxes,
+                text: text.to_owned(),
+            });
+        }
+    };
+    let lead = text.find(REF_OPEN).unwrap_or(text.len());
+    push("", Vec::new(), &text[..lead]);
+    let mut rest = &text[lead..];
+    while let Some(entry) = rest.strip_prefix(REF_OPEN) {
+        let Some(label_len) = entry.find(REF_CLOSE) else {
+            push("", Vec::new(), entry);
+            break;
+        };
+        let label = &entry[..label_len];
+        let mut tail = &entry[label_len + REF_CLOSE.len()..];
+        let mut boxes = Vec::new();
+        if let Some(det) = tail.strip_prefix(DET_OPEN)
+            && let Some(len) = det.find(DET_CLOSE)
+        {
+            boxes = parse_boxes(&det[..len]).unwrap_or_default();
+            tail = &det[len + DET_CLOSE.len()..];
+        }
+        let end = tail.find(REF_OPEN).unwrap_or(tail.len());
+        push(label, boxes, &tail[..end]);
+        rest = &tail[end..];
+    }
+    blocks
+}
+
+/// Map a box in grounding units onto an image of `width`×`height` pixels, as
+/// `[x0, y0, x1, y1]` with the corners ordered and clamped to the image.
+pub fn to_pixels(bbox: [f32; 4], width: u32, height: u32) -> [u32; 4] {
+    let scale = |value: f32, size: u32| {
+        (value / GROUNDING_SCALE * size as f32)
+            .round()
+            .clamp(0.0, size as f32) as u32
+    };
+    let [x0, y0, x1, y1] = [
+        scale(bbox[0], width),
+        scale(bbox[1], height),
+        scale(bbox[2], width),
+        scale(bbox[3], height),
+    ];
+    [x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)]
+}
+
+/// Parse `[[x0, y0, x1, y1], ...]` into boxes in grounding units.
+pub fn parse_boxes(body: &str) -> Option<Vec<[f32; 4]>> {
+    let values = body
+        .split(|ch: char| matches!(ch, '[' | ']' | ',') || ch.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.parse::<f32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if values.is_empty() || values.len() % 4 != 0 {
+        return None;
+    }
+    Some(
+        values
+            .chunks_exact(4)
+            .map(|chunk| [chunk[0], chunk[1], chunk[2], chunk[3]])
+            .collect(),
+    )
+}
+
+fn format_boxes(boxes: impl Iterator<Item = [f32; 4]>) -> String {
+    let boxes = boxes
+        .map(|bbox| {
+            let [x0, y0, x1, y1] =
+                bbox.map(|value| value.round().clamp(0.0, GROUNDING_SCALE) as u32);
+            format!("[{x0}, {y0}, {x1}, {y1}]")
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]", boxes.join(", "))
+}
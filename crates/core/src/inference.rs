@@ -1,20 +1,216 @@
+use std::{fmt, sync::Arc};
+
 use anyhow::{Context, Result};
 use candle_core::Device;
+use clap::ValueEnum;
 use image::DynamicImage;
 use serde::{Deserialize, Serialize};
 use tokenizers::Tokenizer;
 
 use crate::{
-    benchmark::Timer, cancellation::CancellationToken, conversation::get_conv_template,
-    sampling::TokenSelectionParams,
+    benchmark::Timer,
+    cancellation::CancellationToken,
+    conversation::get_conv_template,
+    quantization::QuantizationConfig,
+    sampling::{TokenConstraint, TokenSelectionParams},
 };
 
 /// Vision pre-processing knobs shared across OCR backends.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct VisionSettings {
     pub base_size: u32,
     pub image_size: u32,
     pub crop_mode: bool,
+    /// Smallest resized image area PaddleOCR-VL may produce; `None` keeps the processor default.
+    pub min_pixels: Option<u32>,
+    /// Largest resized image area PaddleOCR-VL may produce; `None` keeps the processor default.
+    pub max_pixels: Option<u32>,
+}
+
+impl Default for VisionSettings {
+    fn default() -> Self {
+        Self {
+            base_size: 1024,
+            image_size: 640,
+            crop_mode: true,
+            min_pixels: None,
+            max_pixels: None,
+        }
+    }
+}
+
+/// Side length in pixels of one SAM patch.
+const VISION_PATCH_SIZE: u32 = 16;
+/// Spatial reduction applied by the SAM neck before the projector.
+const VISION_DOWNSAMPLE_RATIO: u32 = 4;
+
+impl VisionSettings {
+    /// Number of `<image>` placeholder tokens one image occupies in the prompt.
+    ///
+    /// `crop_grid` is the `(width, height)` tile grid picked by dynamic preprocessing; it is only
+    /// consulted in crop mode and a `1x1` grid (or `None`) means the image produced no local
+    /// tiles. Every row of the query grid carries one trailing newline token and the global view
+    /// ends with a view separator.
+    /// This is the single source of the count: the DeepSeek prompt builder lays out exactly this
+    /// many placeholders and the dry-run estimates report it.
+    pub fn vision_tokens(&self, crop_grid: Option<(usize, usize)>) -> usize {
+        // SAM cuts whole patches, then the neck halves the grid twice, rounding up.
+        let queries =
+            |size: u32| (size / VISION_PATCH_SIZE).div_ceil(VISION_DOWNSAMPLE_RATIO) as usize;
+        let global_side = queries(if self.crop_mode {
+            self.base_size
+        } else {
+            self.image_size
+        });
+        let mut tokens = global_side * (global_side + 1) + 1;
+        if self.crop_mode {
+            let (width_crops, height_crops) = crop_grid.unwrap_or((1, 1));
+            if width_crops > 1 || height_crops > 1 {
+                let local_side = queries(self.image_size);
+                tokens += local_side * height_crops * (local_side * width_crops + 1);
+            }
+        }
+        tokens
+    }
+}
+
+/// Resolution presets the DeepSeek-OCR checkpoints were trained with.
+///
+/// The fixed modes encode a single resized view; `Gundam` pairs a 1024px global view with 640px
+/// crop tiles chosen per image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[value(rename_all = "lowercase")]
+pub enum ResolutionMode {
+    /// 512x512, no crops.
+    Tiny,
+    /// 640x640, no crops.
+    Small,
+    /// 1024x1024, no crops.
+    Base,
+    /// 1280x1280, no crops.
+    Large,
+    /// 1024x1024 global view plus dynamic 640x640 crop tiles.
+    Gundam,
+}
+
+impl ResolutionMode {
+    pub const ALL: [Self; 5] = [
+        Self::Tiny,
+        Self::Small,
+        Self::Base,
+        Self::Large,
+        Self::Gundam,
+    ];
+
+    pub fn settings(self) -> VisionSettings {
+        let single = |size| VisionSettings {
+            base_size: size,
+            image_size: size,
+            crop_mode: false,
+            ..VisionSettings::default()
+        };
+        match self {
+            Self::Tiny => single(512),
+            Self::Small => single(640),
+            Self::Base => single(1024),
+            Self::Large => single(1280),
+            Self::Gundam => VisionSettings {
+                base_size: 1024,
+                image_size: 640,
+                crop_mode: true,
+                ..VisionSettings::default()
+            },
+        }
+    }
+
+    /// Map raw settings back onto a preset. Fixed modes only look at `image_size`, since that
+    /// is the only size used without crops.
+    pub fn from_settings(settings: VisionSettings) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| {
+            let preset = mode.settings();
+            if settings.crop_mode {
+                preset.crop_mode
+                    && preset.base_size == settings.base_size
+                    && preset.image_size == settings.image_size
+            } else {
+                !preset.crop_mode && preset.image_size == settings.image_size
+            }
+        })
+    }
+
+    pub fn uses_crops(self) -> bool {
+        self.settings().crop_mode
+    }
+
+    /// Vision tokens this mode produces for one image; see [`VisionSettings::vision_tokens`].
+    pub fn vision_tokens(self, crop_grid: Option<(usize, usize)>) -> usize {
+        self.settings().vision_tokens(crop_grid)
+    }
+}
+
+impl fmt::Display for ResolutionMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tiny => f.write_str("tiny"),
+            Self::Small => f.write_str("small"),
+            Self::Base => f.write_str("base"),
+            Self::Large => f.write_str("large"),
+            Self::Gundam => f.write_str("gundam"),
+        }
+    }
+}
+
+/// A resolution preset the loaded checkpoint was not trained for. Frontends treat it as a bad
+/// request rather than a decode failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedResolution {
+    pub mode: ResolutionMode,
+    /// Global-view sizes the checkpoint supports in crop mode, as `[height, width]`.
+    pub supported: Vec<[u32; 2]>,
+}
+
+impl fmt::Display for UnsupportedResolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = self.mode.settings().base_size;
+        let supported = self
+            .supported
+            .iter()
+            .map(|[height, width]| format!("{height}x{width}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(
+            f,
+            "resolution mode `{}` uses a {side}x{side} global view, but this model only supports \
+             crop resolutions [{supported}]",
+            self.mode
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedResolution {}
+
+/// How crop-mode tiles are scheduled through the vision encoders on CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileEncodingConfig {
+    /// Tiles stacked into one SAM/CLIP forward pass.
+    pub chunk_size: usize,
+    /// Worker threads for tile encoding; `None` shares the global rayon pool.
+    pub max_threads: Option<usize>,
+    /// Upper bound on the estimated activation memory of tiles encoded concurrently, in MiB.
+    /// `0` disables the cap.
+    pub memory_budget_mb: u64,
+}
+
+impl Default for TileEncodingConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: 1,
+            max_threads: None,
+            memory_budget_mb: 2048,
+        }
+    }
 }
 
 /// Decoding parameters that map directly onto generation options.
@@ -29,6 +225,9 @@ pub struct DecodeParameters {
     pub no_repeat_ngram_size: Option<usize>,
     pub seed: Option<u64>,
     pub use_cache: bool,
+    /// Tokens the output is restricted to; only engines whose
+    /// [`OcrEngine::constrained_decoding`] is true accept one.
+    pub constraint: Option<Arc<dyn TokenConstraint>>,
 }
 
 impl DecodeParameters {
@@ -43,6 +242,7 @@ impl DecodeParameters {
             no_repeat_ngram_size: None,
             seed: None,
             use_cache: true,
+            constraint: None,
         }
     }
 }
@@ -96,6 +296,10 @@ pub struct ModelLoadArgs<'a> {
     pub weights_path: Option<&'a std::path::Path>,
     pub device: Device,
     pub dtype: candle_core::DType,
+    /// Explicit quantization settings; `None` falls back to the `DEEPSEEK_OCR_QUANT*` variables.
+    pub quantization: Option<QuantizationConfig>,
+    /// Crop-tile scheduling; backends without tiling ignore it.
+    pub tile_encoding: TileEncodingConfig,
 }
 
 /// Shared interface implemented by all OCR inference backends.
@@ -109,6 +313,10 @@ pub trait OcrEngine: Send {
     fn flash_attention_enabled(&self) -> bool {
         false
     }
+    /// Whether [`DecodeParameters::constraint`] is honoured.
+    fn constrained_decoding(&self) -> bool {
+        false
+    }
 
     fn decode(
         &self,
@@ -0,0 +1,194 @@
+//! Opt-in splitting of two-page spreads and multi-column pages into separate OCR units.
+//!
+//! Given a whole book spread, the model tends to interleave lines from both pages. The pre-pass
+//! here looks for vertical gaps that run the full height of the text — blank gutters or the dark
+//! shadow of a book fold — and cuts the image there. Units are decoded left to right and joined
+//! by [`crate::segments::SegmentedImage::merge`].
+
+use std::fmt;
+
+use clap::ValueEnum;
+use image::{DynamicImage, GrayImage};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    orientation::otsu_threshold,
+    segments::{Region, SegmentedImage},
+};
+
+/// Longest side the gap search works on; larger pages are thumbnailed first.
+const LAYOUT_MAX_SIDE: u32 = 1024;
+/// Rows need at least this fraction of ink to bound the text vertically.
+const CONTENT_INK_FRACTION: f32 = 0.005;
+/// Columns with at most this much ink (relative to the text height) count as blank gap.
+const GAP_INK_FRACTION: f32 = 0.02;
+/// Columns with at least this much ink count as gutter shadow rather than text.
+const SHADOW_INK_FRACTION: f32 = 0.85;
+/// A spread gutter must be centred within this fraction of the width from the middle.
+const SPREAD_CENTRE_BAND: f32 = 0.15;
+/// Units narrower than this fraction of the image width are never cut off.
+const MIN_UNIT_FRACTION: f32 = 0.15;
+
+/// Which layout split runs before OCR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutMode {
+    /// Decode the image as a whole.
+    #[default]
+    Off,
+    /// Split a two-page spread at a gutter near the middle.
+    Spread,
+    /// Split at every strong column gap, up to `max_columns` units.
+    Columns,
+}
+
+impl fmt::Display for LayoutMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Off => "off",
+            Self::Spread => "spread",
+            Self::Columns => "columns",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Layout split settings. Off by default.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayoutConfig {
+    pub mode: LayoutMode,
+    /// Narrowest gap that separates units, as a fraction of the image width.
+    pub min_gap: f32,
+    /// Upper bound on units produced in `columns` mode.
+    pub max_columns: usize,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            mode: LayoutMode::Off,
+            min_gap: 0.02,
+            max_columns: 4,
+        }
+    }
+}
+
+/// Regions of the units found in `image`, left to right; a single full-image region when no
+/// gap qualifies (or the split is off).
+pub fn plan_layout(image: &DynamicImage, config: &LayoutConfig) -> Vec<Region> {
+    let (width, height) = (image.width(), image.height());
+    let full = Region {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    };
+    if config.mode == LayoutMode::Off || width == 0 || height == 0 {
+        return vec![full];
+    }
+    let thumb = if width.max(height) > LAYOUT_MAX_SIDE {
+        image.thumbnail(LAYOUT_MAX_SIDE, LAYOUT_MAX_SIDE).to_luma8()
+    } else {
+        image.to_luma8()
+    };
+    let Some(gaps) = find_gaps(&thumb, config.min_gap) else {
+        return vec![full];
+    };
+    let scale = width as f32 / thumb.width() as f32;
+    let thumb_width = thumb.width() as f32;
+    let centre = |(start, end): (usize, usize)| (start + end) as f32 / 2.0;
+    let mut cuts: Vec<f32> = match config.mode {
+        LayoutMode::Off => Vec::new(),
+        LayoutMode::Spread => gaps
+            .iter()
+            .copied()
+            .filter(|gap| (centre(*gap) / thumb_width - 0.5).abs() <= SPREAD_CENTRE_BAND)
+            .max_by_key(|(start, end)| end - start)
+            .map(centre)
+            .into_iter()
+            .collect(),
+        LayoutMode::Columns => {
+            let mut widest = gaps.clone();
+            widest.sort_by_key(|(start, end)| std::cmp::Reverse(end - start));
+            widest.truncate(config.max_columns.saturating_sub(1));
+            widest.into_iter().map(centre).collect()
+        }
+    };
+    cuts.sort_by(f32::total_cmp);
+
+    let min_unit = width as f32 * MIN_UNIT_FRACTION;
+    let mut edges = vec![0u32];
+    for cut in cuts {
+        let x = (cut * scale).round() as u32;
+        if (x - edges[edges.len() - 1]) as f32 >= min_unit && (width - x) as f32 >= min_unit {
+            edges.push(x);
+        }
+    }
+    edges.push(width);
+    edges
+        .windows(2)
+        .map(|pair| Region {
+            x: pair[0],
+            y: 0,
+            width: pair[1] - pair[0],
+            height,
+        })
+        .collect()
+}
+
+/// Cut `image` into the units planned by [`plan_layout`].
+pub fn split_layout(image: DynamicImage, config: &LayoutConfig) -> SegmentedImage {
+    let regions = plan_layout(&image, config);
+    SegmentedImage::from_regions(image, regions, false)
+}
+
+/// Column ranges `(start, end)` that run blank (or solid gutter shadow) across the whole text
+/// height, are at least `min_gap` of the width wide and have text on both sides.
+fn find_gaps(gray: &GrayImage, min_gap: f32) -> Option<Vec<(usize, usize)>> {
+    let threshold = otsu_threshold(gray)?;
+    let width = gray.width() as usize;
+    let dark: Vec<bool> = gray.pixels().map(|pixel| pixel.0[0] <= threshold).collect();
+
+    let row_ink = dark
+        .chunks(width)
+        .map(|row| row.iter().filter(|ink| **ink).count());
+    let min_row_ink = (width as f32 * CONTENT_INK_FRACTION).max(1.0) as usize;
+    let text_rows: Vec<usize> = row_ink
+        .enumerate()
+        .filter(|(_, count)| *count >= min_row_ink)
+        .map(|(row, _)| row)
+        .collect();
+    let (top, bottom) = (*text_rows.first()?, *text_rows.last()? + 1);
+    let text_height = (bottom - top) as f32;
+
+    let mut column_ink = vec![0usize; width];
+    for row in dark[top * width..bottom * width].chunks(width) {
+        for (column, ink) in row.iter().enumerate() {
+            column_ink[column] += *ink as usize;
+        }
+    }
+    let is_text = |count: usize| {
+        let fraction = count as f32 / text_height;
+        fraction > GAP_INK_FRACTION && fraction < SHADOW_INK_FRACTION
+    };
+    let first = column_ink.iter().position(|count| is_text(*count))?;
+    let last = column_ink.iter().rposition(|count| is_text(*count))?;
+
+    let min_width = ((width as f32 * min_gap).ceil() as usize).max(1);
+    let mut gaps = Vec::new();
+    let mut start = None;
+    for (column, count) in column_ink.iter().enumerate().take(last + 1).skip(first) {
+        match (start, is_text(*count)) {
+            (None, false) => start = Some(column),
+            (Some(begin), true) => {
+                if column - begin >= min_width {
+                    gaps.push((begin, column));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    Some(gaps)
+}
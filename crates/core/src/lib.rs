@@ -1,18 +1,37 @@
 pub mod benchmark;
 pub mod cache;
 pub mod cancellation;
+pub mod cleanup;
 pub mod conversation;
+pub mod estimate;
+pub mod export;
+pub mod formats;
+pub mod formula;
+pub mod grounding;
 pub mod inference;
+pub mod layout;
+pub mod orientation;
+pub mod pipeline;
+pub mod polish;
+pub mod quantization;
+pub mod reading_order;
+pub mod resample;
 pub mod runtime;
 pub mod sampling;
+pub mod segments;
+pub mod slicing;
 pub mod streaming;
+pub mod tables;
+pub mod task;
 pub mod tensor;
+pub(crate) mod zip;
 
 pub use cancellation::CancellationToken;
 pub use inference::{
-    DecodeOutcome, DecodeParameters, ModelKind, ModelLoadArgs, OcrEngine, VisionSettings,
-    normalize_text, render_prompt,
+    DecodeOutcome, DecodeParameters, ModelKind, ModelLoadArgs, OcrEngine, ResolutionMode,
+    VisionSettings, normalize_text, render_prompt,
 };
+pub use orientation::{OrientationMode, OrientationReport, OrientedImage, Rotation};
 
 // #[cfg(feature = "mkl")]
 // extern crate intel_mkl_src;
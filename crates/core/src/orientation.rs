@@ -0,0 +1,412 @@
+//! Input image orientation: EXIF correction and coarse rotation detection.
+//!
+//! `image::open` and `image::load_from_memory` return pixels as stored, so phone photos taken in
+//! portrait often arrive sideways. Frontends decode through [`open_image`] / [`decode_image`]
+//! instead, which honour the EXIF orientation tag and, in [`OrientationMode::Auto`], additionally
+//! turn pages whose text runs sideways or upside down. The returned [`OrientationReport`] maps
+//! grounding boxes from the model's view back onto the upright image.
+
+use std::{fmt, io::Cursor, path::Path, sync::Arc};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use image::{DynamicImage, GrayImage, ImageDecoder, ImageReader, metadata::Orientation};
+use serde::{Deserialize, Serialize};
+
+use crate::formats::describe_decode_error;
+
+/// Longest side the rotation heuristic works on; larger images are thumbnailed first.
+const DETECTION_MAX_SIDE: u32 = 512;
+/// Column-profile contrast must beat the row profile by this factor to call a page sideways.
+const SIDEWAYS_MARGIN: f32 = 1.3;
+/// Minimum ascender/descender skew (in half-line heights) before a page is flipped.
+const FLIP_MARGIN: f32 = 0.03;
+
+/// How input images are re-oriented before vision preprocessing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[value(rename_all = "lowercase")]
+pub enum OrientationMode {
+    /// Use the pixels exactly as stored.
+    Off,
+    /// Apply the EXIF orientation tag.
+    #[default]
+    Exif,
+    /// Apply the EXIF tag, then detect 90/180/270 degree rotations from the text layout.
+    Auto,
+}
+
+impl fmt::Display for OrientationMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Off => f.write_str("off"),
+            Self::Exif => f.write_str("exif"),
+            Self::Auto => f.write_str("auto"),
+        }
+    }
+}
+
+/// Clockwise quarter turn applied on top of the EXIF correction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    pub fn degrees(self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Cw90 => 90,
+            Self::Cw180 => 180,
+            Self::Cw270 => 270,
+        }
+    }
+
+    pub fn apply(self, image: DynamicImage) -> DynamicImage {
+        match self {
+            Self::None => image,
+            Self::Cw90 => image.rotate90(),
+            Self::Cw180 => image.rotate180(),
+            Self::Cw270 => image.rotate270(),
+        }
+    }
+}
+
+impl Serialize for Rotation {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.degrees())
+    }
+}
+
+/// What was done to one input image before it reached the model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct OrientationReport {
+    /// EXIF orientation tag (1-8) that was applied, if the file carried one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exif_orientation: Option<u8>,
+    /// Clockwise rotation applied after the EXIF correction, in degrees when serialized.
+    pub rotation: Rotation,
+    /// Size of the image as handed to the model.
+    pub width: u32,
+    pub height: u32,
+}
+
+impl OrientationReport {
+    /// Map a point in normalised model-view coordinates (`0.0..=1.0`) back onto the upright,
+    /// EXIF-corrected image — the orientation image viewers display.
+    pub fn to_upright_point(&self, x: f32, y: f32) -> (f32, f32) {
+        match self.rotation {
+            Rotation::None => (x, y),
+            Rotation::Cw90 => (y, 1.0 - x),
+            Rotation::Cw180 => (1.0 - x, 1.0 - y),
+            Rotation::Cw270 => (1.0 - y, x),
+        }
+    }
+
+    /// Map a normalised `[x0, y0, x1, y1]` box back onto the upright image.
+    pub fn to_upright_box(&self, bbox: [f32; 4]) -> [f32; 4] {
+        let (ax, ay) = self.to_upright_point(bbox[0], bbox[1]);
+        let (bx, by) = self.to_upright_point(bbox[2], bbox[3]);
+        [ax.min(bx), ay.min(by), ax.max(bx), ay.max(by)]
+    }
+}
+
+/// A decoded image plus the orientation fixes applied to it.
+#[derive(Debug, Clone)]
+pub struct OrientedImage {
+    pub image: DynamicImage,
+    pub report: OrientationReport,
+    /// The JPEG stream the pixels were decoded from, in stored orientation, so the PDF export can
+    /// embed the scan without re-encoding it.
+    pub source_jpeg: Option<Arc<[u8]>>,
+}
+
+/// Open an image file, honouring `mode`.
+pub fn open_image(path: &Path, mode: OrientationMode) -> Result<OrientedImage> {
+    let reader = ImageReader::open(path)
+        .with_context(|| format!("failed to open image at {}", path.display()))?
+        .with_guessed_format()
+        .with_context(|| format!("failed to read image at {}", path.display()))?;
+    read_image(reader, mode)
+        .with_context(|| format!("failed to decode image at {}", path.display()))
+}
+
+/// Decode an in-memory encoded image, honouring `mode`.
+pub fn decode_image(bytes: &[u8], mode: OrientationMode) -> Result<OrientedImage> {
+    let reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .context("failed to sniff image format")?;
+    let jpeg = reader.format() == Some(image::ImageFormat::Jpeg);
+    let mut oriented = read_image(reader, mode)?;
+    if jpeg {
+        oriented.source_jpeg = Some(Arc::from(bytes));
+    }
+    Ok(oriented)
+}
+
+/// Open an image file as a list of pages: every page of a multi-page TIFF (with the `tiff`
+/// feature) or of a scanned PDF (with the `pdf` feature), otherwise the single image.
+pub fn open_pages(path: &Path, mode: OrientationMode) -> Result<Vec<OrientedImage>> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("failed to open image at {}", path.display()))?;
+    decode_pages(&bytes, mode)
+        .with_context(|| format!("failed to decode image at {}", path.display()))
+}
+
+/// In-memory counterpart of [`open_pages`].
+pub fn decode_pages(bytes: &[u8], mode: OrientationMode) -> Result<Vec<OrientedImage>> {
+    #[cfg(feature = "tiff")]
+    if image::guess_format(bytes).ok() == Some(image::ImageFormat::Tiff) {
+        return Ok(crate::formats::tiff_pages(bytes)?
+            .into_iter()
+            .map(|(image, exif)| {
+                let exif = if mode == OrientationMode::Off {
+                    None
+                } else {
+                    exif
+                };
+                orient_image(image, exif, mode)
+            })
+            .collect());
+    }
+    #[cfg(feature = "pdf")]
+    if bytes.starts_with(b"%PDF-") {
+        // A page's `/Rotate` is honoured like an EXIF tag.
+        return Ok(crate::formats::pdf_pages(bytes)?
+            .into_iter()
+            .map(|page| {
+                let rotate = page.orientation.filter(|_| mode != OrientationMode::Off);
+                OrientedImage {
+                    source_jpeg: page.jpeg,
+                    ..orient_image(page.image, rotate, mode)
+                }
+            })
+            .collect());
+    }
+    decode_image(bytes, mode).map(|image| vec![image])
+}
+
+fn read_image<R: std::io::BufRead + std::io::Seek>(
+    reader: ImageReader<R>,
+    mode: OrientationMode,
+) -> Result<OrientedImage> {
+    let mut decoder = reader.into_decoder().map_err(describe_decode_error)?;
+    let exif = match mode {
+        OrientationMode::Off => None,
+        // Files without (or with unreadable) EXIF data are simply left as stored.
+        _ => decoder.orientation().ok(),
+    };
+    let image = DynamicImage::from_decoder(decoder).map_err(describe_decode_error)?;
+    Ok(orient_image(image, exif, mode))
+}
+
+/// Apply an already-extracted EXIF orientation and, in `Auto` mode, the detected rotation.
+pub fn orient_image(
+    mut image: DynamicImage,
+    exif: Option<Orientation>,
+    mode: OrientationMode,
+) -> OrientedImage {
+    let exif = exif.filter(|orientation| *orientation != Orientation::NoTransforms);
+    if let Some(orientation) = exif {
+        image.apply_orientation(orientation);
+    }
+    let rotation = match mode {
+        OrientationMode::Auto => detect_rotation(&image),
+        _ => Rotation::None,
+    };
+    let image = rotation.apply(image);
+    let report = OrientationReport {
+        exif_orientation: exif.map(Orientation::to_exif),
+        rotation,
+        width: image.width(),
+        height: image.height(),
+    };
+    OrientedImage {
+        image,
+        report,
+        source_jpeg: None,
+    }
+}
+
+/// Guess the clockwise rotation that makes the page's text upright.
+///
+/// Text lines make the ink projection across lines alternate between bands and blank gaps while
+/// the projection along them stays flat, which separates upright from sideways pages. Upright
+/// versus upside down is decided by where ink sits within each line band: Latin text has more
+/// ascenders than descenders, so the ink mass leans towards the top of the band. Scripts without
+/// that asymmetry are never flipped.
+pub fn detect_rotation(image: &DynamicImage) -> Rotation {
+    let gray = image
+        .thumbnail(DETECTION_MAX_SIDE, DETECTION_MAX_SIDE)
+        .to_luma8();
+    let Some(ink) = InkMask::from_gray(&gray) else {
+        return Rotation::None;
+    };
+    let row_contrast = profile_contrast(&ink.row_profile());
+    let column_contrast = profile_contrast(&ink.column_profile());
+    if column_contrast > row_contrast * SIDEWAYS_MARGIN {
+        // Lines run top to bottom; pick whichever quarter turn leaves ascenders on top.
+        if ink.rotated_cw().ascender_skew() >= 0.0 {
+            Rotation::Cw90
+        } else {
+            Rotation::Cw270
+        }
+    } else if ink.ascender_skew() < -FLIP_MARGIN {
+        Rotation::Cw180
+    } else {
+        Rotation::None
+    }
+}
+
+/// Binarised page where `true` marks ink.
+struct InkMask {
+    width: usize,
+    height: usize,
+    data: Vec<bool>,
+}
+
+impl InkMask {
+    /// Threshold with Otsu's method, treating the minority class as ink. Returns `None` for
+    /// blank or near-uniform images.
+    fn from_gray(gray: &GrayImage) -> Option<Self> {
+        let threshold = otsu_threshold(gray)?;
+        let dark = gray
+            .pixels()
+            .filter(|pixel| pixel.0[0] <= threshold)
+            .count();
+        let ink_is_dark = dark * 2 <= gray.len();
+        let data = gray
+            .pixels()
+            .map(|pixel| (pixel.0[0] <= threshold) == ink_is_dark)
+            .collect();
+        Some(Self {
+            width: gray.width() as usize,
+            height: gray.height() as usize,
+            data,
+        })
+    }
+
+    fn row_profile(&self) -> Vec<u32> {
+        self.data
+            .chunks(self.width)
+            .map(|row| row.iter().filter(|ink| **ink).count() as u32)
+            .collect()
+    }
+
+    fn column_profile(&self) -> Vec<u32> {
+        let mut profile = vec![0u32; self.width];
+        for row in self.data.chunks(self.width) {
+            for (column, ink) in row.iter().enumerate() {
+                profile[column] += *ink as u32;
+            }
+        }
+        profile
+    }
+
+    fn rotated_cw(&self) -> Self {
+        let (width, height) = (self.height, self.width);
+        let mut data = vec![false; self.data.len()];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                data[x * width + (self.height - 1 - y)] = self.data[y * self.width + x];
+            }
+        }
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Ink-weighted offset of each text line's centre of mass above its band midpoint, in
+    /// half-band units. Positive for upright Latin text, negative when upside down.
+    fn ascender_skew(&self) -> f32 {
+        let profile = self.row_profile();
+        let (mut skew, mut mass) = (0f64, 0f64);
+        let mut start = None;
+        for (row, count) in profile.iter().chain(std::iter::once(&0)).enumerate() {
+            match (start, *count > 0) {
+                (None, true) => start = Some(row),
+                (Some(begin), false) => {
+                    start = None;
+                    let band = &profile[begin..row];
+                    if band.len() < 3 {
+                        continue;
+                    }
+                    let band_mass: f64 = band.iter().map(|value| *value as f64).sum();
+                    let centroid = band
+                        .iter()
+                        .enumerate()
+                        .map(|(offset, value)| offset as f64 * *value as f64)
+                        .sum::<f64>()
+                        / band_mass;
+                    let half = (band.len() - 1) as f64 / 2.0;
+                    skew += (half - centroid) / half * band_mass;
+                    mass += band_mass;
+                }
+                _ => {}
+            }
+        }
+        if mass == 0.0 {
+            0.0
+        } else {
+            (skew / mass) as f32
+        }
+    }
+}
+
+/// Otsu's threshold: the grey level that best separates dark from light pixels. Values at or
+/// below it form the dark class. Returns `None` when the image has a single grey level.
+pub(crate) fn otsu_threshold(gray: &GrayImage) -> Option<u8> {
+    let mut histogram = [0u64; 256];
+    for pixel in gray.pixels() {
+        histogram[pixel.0[0] as usize] += 1;
+    }
+    let total: u64 = histogram.iter().sum();
+    let weighted_total: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(value, count)| value as f64 * *count as f64)
+        .sum();
+    let (mut background, mut weighted_background) = (0u64, 0f64);
+    let (mut best_threshold, mut best_variance) = (0u8, 0f64);
+    for (value, count) in histogram.iter().enumerate() {
+        background += count;
+        if background == 0 || background == total {
+            continue;
+        }
+        weighted_background += value as f64 * *count as f64;
+        let foreground = total - background;
+        let mean_b = weighted_background / background as f64;
+        let mean_f = (weighted_total - weighted_background) / foreground as f64;
+        let variance = background as f64 * foreground as f64 * (mean_b - mean_f).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best_threshold = value as u8;
+        }
+    }
+    (best_variance > 0.0).then_some(best_threshold)
+}
+
+/// Squared coefficient of variation of a projection profile: high when ink comes in bands
+/// separated by blank gaps, low when it is spread evenly.
+fn profile_contrast(profile: &[u32]) -> f32 {
+    if profile.is_empty() {
+        return 0.0;
+    }
+    let len = profile.len() as f64;
+    let mean = profile.iter().map(|value| *value as f64).sum::<f64>() / len;
+    if mean == 0.0 {
+        return 0.0;
+    }
+    let variance = profile
+        .iter()
+        .map(|value| (*value as f64 - mean).powi(2))
+        .sum::<f64>()
+        / len;
+    (variance / (mean * mean)) as f32
+}
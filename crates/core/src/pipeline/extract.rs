@@ -0,0 +1,986 @@
+//! Key-value extraction from forms such as invoices and ID cards.
+//!
+//! The page is read with its own prompt — grounded markdown on DeepSeek-OCR, plain OCR on
+//! PaddleOCR-VL — and every field of the [`ExtractionSchema`] is found by the label printed
+//! next to it. The value is the rest of the label's line up to the next label, the cell below
+//! the label in a table whose header row holds the labels, or the line below when the label
+//! stands alone. A candidate only counts when it parses as the field's type and matches its
+//! `pattern`, so a label followed by the wrong kind of text is skipped for a later one.
+//!
+//! Engines with constrained decoding are also asked for the fields by name, type, description
+//! and labels, and their answer is held to a JSON object with exactly those fields in schema
+//! order and values of the right type (see [`SchemaConstraint`]). Answered values take
+//! precedence over the label search, which fills in the fields answered with `null`. Source
+//! boxes are interpolated from the grounding boxes: a label match has one, an answered value
+//! only when its text appears on the page as written.
+
+use std::{cmp::Reverse, collections::HashSet, ops::Range, sync::Arc};
+
+use anyhow::{Context, Result, bail, ensure};
+use image::DynamicImage;
+use once_cell::sync::Lazy;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use tokenizers::Tokenizer;
+
+use super::{locate::BoundingBox, span_box, terms_regex};
+use crate::{
+    export::{BlockKind, OcrBlock, OcrPage, word_boxes},
+    inference::{DecodeParameters, ModelKind, OcrEngine, VisionSettings},
+    sampling::TokenConstraint,
+    task::{OcrTask, wrap_instruction},
+};
+
+/// Characters between a label and its value, besides whitespace.
+const SEPARATORS: &[char] = &[':', '#', '.', '=', '|'];
+
+/// Longest text value the schema pass may answer, in characters.
+const MAX_TEXT_CHARS: usize = 256;
+
+/// Most digits of a number the schema pass may answer.
+const MAX_DIGITS: usize = 18;
+
+/// Layout of an answered date; `d` is any digit.
+const DATE_LAYOUT: &[u8] = b"\"dddd-dd-dd\"";
+
+/// Numbers with optional thousands separators and a decimal part.
+static NUMBER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"[-+]?(?:\d{1,3}(?:[,.' \u{a0}]\d{3})+(?:[.,]\d+)?|\d+(?:[.,]\d+)?)")
+        .expect("number pattern is valid")
+});
+
+const MONTHS: &str = "jan|feb|mar|apr|may|jun|jul|aug|sep|oct|nov|dec";
+
+/// Date layouts and the capture groups holding their year, month and day.
+static DATES: Lazy<Vec<(Regex, [usize; 3])>> = Lazy::new(|| {
+    [
+        (
+            r"\b(\d{4})[-/.](\d{1,2})[-/.](\d{1,2})\b".to_owned(),
+            [1, 2, 3],
+        ),
+        (
+            r"\b(\d{1,2})[-/.](\d{1,2})[-/.](\d{4}|\d{2})\b".to_owned(),
+            [3, 2, 1],
+        ),
+        (
+            format!(
+                r"(?i)\b(\d{{1,2}})(?:st|nd|rd|th)?[\s.-]+({MONTHS})[a-z]*\.?[\s.,-]+(\d{{4}})\b"
+            ),
+            [3, 2, 1],
+        ),
+        (
+            format!(r"(?i)\b({MONTHS})[a-z]*\.?\s+(\d{{1,2}})(?:st|nd|rd|th)?,?\s+(\d{{4}})\b"),
+            [3, 1, 2],
+        ),
+    ]
+    .into_iter()
+    .map(|(pattern, groups)| (Regex::new(&pattern).expect("date pattern is valid"), groups))
+    .collect()
+});
+
+/// Yes/no answers and ticked or empty checkboxes.
+static BOOLEAN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:yes|no|true|false)\b|[☑☒✓✔☐]|\[\s*[x✓]?\s*\]")
+        .expect("boolean pattern is valid")
+});
+
+/// The fields to extract.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtractionSchema {
+    pub fields: Vec<FieldSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldSpec {
+    /// Key of the field in the result. With `_` and `-` read as spaces, it is also a label.
+    pub name: String,
+    /// What the field holds; part of the prompt of engines with constrained decoding.
+    #[serde(default)]
+    pub description: String,
+    #[serde(default, rename = "type")]
+    pub kind: FieldKind,
+    /// Labels printed on the form for this field, matched case-insensitively as whole words.
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// Regular expression the value must match. The first capture group, if any, is the
+    /// value; otherwise the whole match.
+    #[serde(default)]
+    pub pattern: Option<String>,
+}
+
+/// How a value is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+    /// The text as read.
+    #[default]
+    Text,
+    /// A number; `1,234.50`, `1.234,50` and `1 234,50` all read as 1234.5.
+    Number,
+    /// A calendar date, returned as `YYYY-MM-DD`. Numeric dates are read day first unless
+    /// only month first is valid.
+    Date,
+    /// Yes/no, true/false or a checkbox.
+    Boolean,
+}
+
+/// A typed value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FieldValue {
+    Boolean(bool),
+    Number(f64),
+    Text(String),
+}
+
+/// One field of the schema and what was found for it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExtractedField {
+    pub name: String,
+    /// `None` when the model answered `null` or nothing valid and no label of the field was
+    /// followed by a valid value.
+    pub value: Option<FieldValue>,
+    /// The text on the page the value was read from; `None` for an answered value that does
+    /// not appear on the page as written.
+    pub text: Option<String>,
+    /// Where `text` is in pixels of the image; `None` without grounding boxes.
+    pub bbox: Option<BoundingBox>,
+}
+
+/// Fields of an image, in schema order.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Extraction {
+    pub width: u32,
+    pub height: u32,
+    pub fields: Vec<ExtractedField>,
+}
+
+struct Field {
+    name: String,
+    description: String,
+    kind: FieldKind,
+    /// Labels given in the schema, for the prompt.
+    labels: Vec<String>,
+    label: Regex,
+    pattern: Option<Regex>,
+}
+
+impl Field {
+    /// The value in `candidate` and its byte range there.
+    fn read(&self, candidate: &str) -> Option<(Range<usize>, FieldValue)> {
+        let (offset, text) = match &self.pattern {
+            Some(pattern) => {
+                let captures = pattern.captures(candidate)?;
+                let found = captures.get(1).or_else(|| captures.get(0))?;
+                (found.start(), found.as_str())
+            }
+            None => (0, candidate),
+        };
+        let (range, value) = match self.kind {
+            FieldKind::Text => {
+                let trimmed = text.trim();
+                let start = text.len() - text.trim_start().len();
+                if trimmed.is_empty() {
+                    return None;
+                }
+                (
+                    start..start + trimmed.len(),
+                    FieldValue::Text(trimmed.to_owned()),
+                )
+            }
+            FieldKind::Number => {
+                let found = NUMBER.find(text)?;
+                (
+                    found.range(),
+                    FieldValue::Number(parse_number(found.as_str())?),
+                )
+            }
+            FieldKind::Date => {
+                let (range, date) = find_date(text)?;
+                (range, FieldValue::Text(date))
+            }
+            FieldKind::Boolean => {
+                let found = BOOLEAN.find(text)?;
+                let answer = found.as_str().to_lowercase();
+                let checked = matches!(answer.as_str(), "yes" | "true" | "☑" | "☒" | "✓" | "✔")
+                    || (answer.starts_with('[')
+                        && !answer.trim_matches(['[', ']', ' ']).is_empty());
+                (found.range(), FieldValue::Boolean(checked))
+            }
+        };
+        Some((offset + range.start..offset + range.end, value))
+    }
+
+    /// The answer of the schema pass for this field, checked like a value read from the page.
+    fn answered(&self, answer: &serde_json::Value) -> Option<FieldValue> {
+        match (self.kind, answer) {
+            (FieldKind::Number, serde_json::Value::Number(number)) => {
+                number.as_f64().map(FieldValue::Number)
+            }
+            (FieldKind::Boolean, serde_json::Value::Bool(checked)) => {
+                Some(FieldValue::Boolean(*checked))
+            }
+            (FieldKind::Text | FieldKind::Date, serde_json::Value::String(text)) => {
+                self.read(text).map(|(_, value)| value)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Compiled [`ExtractionSchema`].
+pub struct Extractor {
+    fields: Vec<Field>,
+}
+
+/// A line of text on the page with its interpolated box.
+struct Line<'a> {
+    block: &'a OcrBlock,
+    /// Index of the line in its block; the table row for tables.
+    row: usize,
+    text: &'a str,
+    bbox: [u32; 4],
+}
+
+impl Extractor {
+    /// Compile `schema`. Fails on an empty schema, a missing or repeated name, a field without
+    /// a label that is not blank, or an invalid pattern.
+    pub fn new(schema: &ExtractionSchema) -> Result<Self> {
+        ensure!(
+            !schema.fields.is_empty(),
+            "the extraction schema has no fields"
+        );
+        let mut names = HashSet::new();
+        let mut fields = Vec::with_capacity(schema.fields.len());
+        for spec in &schema.fields {
+            let name = spec.name.trim();
+            ensure!(!name.is_empty(), "every extraction field needs a name");
+            if !names.insert(name) {
+                bail!("extraction field `{name}` is defined twice");
+            }
+            let mut labels = spec.labels.clone();
+            labels.push(name.replace(['_', '-'], " "));
+            let Some(label) = terms_regex(&labels) else {
+                bail!("extraction field `{name}` has no usable label");
+            };
+            let label = RegexBuilder::new(&label)
+                .case_insensitive(true)
+                .build()
+                .with_context(|| format!("invalid labels for extraction field `{name}`"))?;
+            let pattern = spec
+                .pattern
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .with_context(|| format!("invalid pattern for extraction field `{name}`"))?;
+            fields.push(Field {
+                name: name.to_owned(),
+                description: spec.description.trim().to_owned(),
+                kind: spec.kind,
+                labels: spec.labels.clone(),
+                label,
+                pattern,
+            });
+        }
+        Ok(Self { fields })
+    }
+
+    /// Labels found in `line` as `(field index, byte range)`, left to right. Where labels
+    /// overlap the longest wins, so a `date` field does not claim the label `Due date`.
+    fn labels(&self, line: &str) -> Vec<(usize, Range<usize>)> {
+        let mut found: Vec<(usize, Range<usize>)> = self
+            .fields
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, field)| {
+                field
+                    .label
+                    .find_iter(line)
+                    .map(move |label| (idx, label.range()))
+            })
+            .collect();
+        found.sort_by_key(|(_, range)| (Reverse(range.len()), range.start));
+        let mut kept: Vec<(usize, Range<usize>)> = Vec::new();
+        for (idx, range) in found {
+            if kept
+                .iter()
+                .all(|(_, other)| other.end <= range.start || range.end <= other.start)
+            {
+                kept.push((idx, range));
+            }
+        }
+        kept.sort_by_key(|(_, range)| range.start);
+        kept
+    }
+
+    /// Prompt asking `kind` for the fields of one image as a JSON object.
+    pub fn prompt(&self, kind: ModelKind) -> String {
+        let mut instruction = String::from(
+            "Read the document and answer with a JSON object holding the fields below. \
+             Use null for a field the document does not show.",
+        );
+        for field in &self.fields {
+            let kind = match field.kind {
+                FieldKind::Text => "text",
+                FieldKind::Number => "number",
+                FieldKind::Date => "date as YYYY-MM-DD",
+                FieldKind::Boolean => "true or false",
+            };
+            instruction.push_str(&format!("\n- {} ({kind})", field.name));
+            if !field.description.is_empty() {
+                instruction.push_str(&format!(": {}", field.description));
+            }
+            if !field.labels.is_empty() {
+                let labels: Vec<String> = field
+                    .labels
+                    .iter()
+                    .map(|label| format!("\"{label}\""))
+                    .collect();
+                instruction.push_str(&format!(" Labelled {}.", labels.join(" or ")));
+            }
+        }
+        wrap_instruction(kind, 1, &instruction)
+    }
+
+    /// Constraint holding decoding with `tokenizer` to the answer [`Extractor::prompt`] asks
+    /// for.
+    pub fn constraint(&self, tokenizer: &Tokenizer) -> SchemaConstraint {
+        let mut parts = Vec::with_capacity(self.fields.len() * 2 + 1);
+        for (idx, field) in self.fields.iter().enumerate() {
+            let key = serde_json::Value::from(field.name.as_str());
+            let open = if idx == 0 { "{" } else { ", " };
+            parts.push(Part::Literal(format!("{open}{key}: ")));
+            parts.push(Part::Value(field.kind));
+        }
+        parts.push(Part::Literal("}".to_owned()));
+        let vocab_size = u32::try_from(tokenizer.get_vocab_size(true)).unwrap_or(u32::MAX);
+        let pieces = (0..vocab_size)
+            .map(|id| {
+                let text = tokenizer.decode(&[id], true).ok()?;
+                if text.is_empty() {
+                    None
+                } else if text.contains(char::REPLACEMENT_CHARACTER) {
+                    Some(Piece::Partial)
+                } else {
+                    Some(Piece::Text(text))
+                }
+            })
+            .collect();
+        SchemaConstraint { parts, pieces }
+    }
+}
+
+/// Holds the schema pass to `{"name": value, ...}` with the fields of an [`Extractor`] in
+/// schema order, one space after every `:` and `,`. Values are `null` or, by type, a string
+/// of at most 256 characters, a number without exponent, a `"YYYY-MM-DD"` string, or `true`
+/// and `false`. The end of the sequence is only allowed after the closing brace.
+#[derive(Debug)]
+pub struct SchemaConstraint {
+    parts: Vec<Part>,
+    /// Text of every token; `None` for special tokens.
+    pieces: Vec<Option<Piece>>,
+}
+
+#[derive(Debug)]
+enum Part {
+    Literal(String),
+    Value(FieldKind),
+}
+
+#[derive(Debug)]
+enum Piece {
+    Text(String),
+    /// Bytes of a character split across tokens; only allowed inside strings.
+    Partial,
+}
+
+/// Position in the output of a [`SchemaConstraint`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cursor {
+    part: usize,
+    at: At,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum At {
+    /// Bytes of the literal written so far.
+    Literal(usize),
+    Value(Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Start,
+    /// `len` bytes into `null`, `true` or `false`.
+    Keyword(&'static str, usize),
+    /// In a string of `chars` characters, right after a backslash when `escaped`.
+    Text {
+        chars: usize,
+        escaped: bool,
+    },
+    /// `len` bytes into [`DATE_LAYOUT`].
+    Date(usize),
+    Number {
+        digits: usize,
+        at: NumberAt,
+    },
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NumberAt {
+    Sign,
+    Zero,
+    Integer,
+    Point,
+    Fraction,
+}
+
+impl Value {
+    fn push(self, kind: FieldKind, ch: char) -> Option<Self> {
+        let number = |digits, at| Self::Number { digits, at };
+        Some(match (self, ch) {
+            (Self::Start, 'n') => Self::Keyword("null", 1),
+            (Self::Start, 't') if kind == FieldKind::Boolean => Self::Keyword("true", 1),
+            (Self::Start, 'f') if kind == FieldKind::Boolean => Self::Keyword("false", 1),
+            (Self::Start, '"') if kind == FieldKind::Text => Self::Text {
+                chars: 0,
+                escaped: false,
+            },
+            (Self::Start, '"') if kind == FieldKind::Date => Self::Date(1),
+            (Self::Start, '-') if kind == FieldKind::Number => number(0, NumberAt::Sign),
+            (Self::Start, '0'..='9') if kind == FieldKind::Number => number(
+                1,
+                if ch == '0' {
+                    NumberAt::Zero
+                } else {
+                    NumberAt::Integer
+                },
+            ),
+            (Self::Keyword(word, len), _) if word[len..].starts_with(ch) => {
+                if len + 1 == word.len() {
+                    Self::Done
+                } else {
+                    Self::Keyword(word, len + 1)
+                }
+            }
+            (
+                Self::Text {
+                    chars,
+                    escaped: true,
+                },
+                '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't',
+            ) => Self::Text {
+                chars: chars + 1,
+                escaped: false,
+            },
+            (Self::Text { escaped: false, .. }, '"') => Self::Done,
+            (
+                Self::Text {
+                    chars,
+                    escaped: false,
+                },
+                _,
+            ) if chars < MAX_TEXT_CHARS && !ch.is_control() => Self::Text {
+                chars: chars + usize::from(ch != '\\'),
+                escaped: ch == '\\',
+            },
+            (Self::Date(len), _) => {
+                let expected = DATE_LAYOUT[len];
+                let fits = if expected == b'd' {
+                    ch.is_ascii_digit()
+                } else {
+                    ch == char::from(expected)
+                };
+                if !fits {
+                    return None;
+                }
+                if len + 1 == DATE_LAYOUT.len() {
+                    Self::Done
+                } else {
+                    Self::Date(len + 1)
+                }
+            }
+            (
+                Self::Number {
+                    at: NumberAt::Sign, ..
+                },
+                '0'..='9',
+            ) => number(
+                1,
+                if ch == '0' {
+                    NumberAt::Zero
+                } else {
+                    NumberAt::Integer
+                },
+            ),
+            (
+                Self::Number {
+                    digits,
+                    at: at @ (NumberAt::Integer | NumberAt::Fraction),
+                },
+                '0'..='9',
+            ) if digits < MAX_DIGITS => number(digits + 1, at),
+            (
+                Self::Number {
+                    digits,
+                    at: NumberAt::Point,
+                },
+                '0'..='9',
+            ) => number(digits + 1, NumberAt::Fraction),
+            (
+                Self::Number {
+                    digits,
+                    at: NumberAt::Zero | NumberAt::Integer,
+                },
+                '.',
+            ) => number(digits, NumberAt::Point),
+            _ => return None,
+        })
+    }
+
+    /// Whether the value may end here although it could go on.
+    fn complete(self) -> bool {
+        matches!(
+            self,
+            Self::Number {
+                at: NumberAt::Zero | NumberAt::Integer | NumberAt::Fraction,
+                ..
+            }
+        )
+    }
+}
+
+impl SchemaConstraint {
+    fn enter(&self, part: usize) -> Cursor {
+        let at = match self.parts.get(part) {
+            Some(Part::Value(_)) => At::Value(Value::Start),
+            _ => At::Literal(0),
+        };
+        Cursor { part, at }
+    }
+
+    fn finished(&self, cursor: Cursor) -> bool {
+        cursor.part == self.parts.len()
+    }
+
+    fn piece(&self, id: i64) -> Option<&Piece> {
+        self.pieces.get(usize::try_from(id).ok()?)?.as_ref()
+    }
+
+    fn push(&self, cursor: Cursor, ch: char) -> Option<Cursor> {
+        match (self.parts.get(cursor.part)?, cursor.at) {
+            (Part::Literal(text), At::Literal(len)) => {
+                if !text[len..].starts_with(ch) {
+                    return None;
+                }
+                let len = len + ch.len_utf8();
+                Some(if len == text.len() {
+                    self.enter(cursor.part + 1)
+                } else {
+                    Cursor {
+                        at: At::Literal(len),
+                        ..cursor
+                    }
+                })
+            }
+            (Part::Value(kind), At::Value(value)) => match value.push(*kind, ch) {
+                Some(Value::Done) => Some(self.enter(cursor.part + 1)),
+                Some(value) => Some(Cursor {
+                    at: At::Value(value),
+                    ..cursor
+                }),
+                None if value.complete() => self.push(self.enter(cursor.part + 1), ch),
+                None => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn push_piece(&self, cursor: Cursor, piece: &Piece) -> Option<Cursor> {
+        match piece {
+            Piece::Text(text) => text
+                .chars()
+                .try_fold(cursor, |cursor, ch| self.push(cursor, ch)),
+            Piece::Partial => match cursor.at {
+                At::Value(Value::Text {
+                    chars,
+                    escaped: false,
+                }) if chars < MAX_TEXT_CHARS => Some(Cursor {
+                    at: At::Value(Value::Text {
+                        chars: chars + 1,
+                        escaped: false,
+                    }),
+                    ..cursor
+                }),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl TokenConstraint for SchemaConstraint {
+    fn mask(&self, generated: &[i64], eos: Option<i64>, logits: &mut [f32]) {
+        let cursor = generated.iter().try_fold(self.enter(0), |cursor, &id| {
+            self.push_piece(cursor, self.piece(id)?)
+        });
+        for (id, logit) in logits.iter_mut().enumerate() {
+            let id = id as i64;
+            let allowed = match cursor {
+                Some(cursor) if Some(id) == eos => self.finished(cursor),
+                Some(cursor) => self
+                    .piece(id)
+                    .and_then(|piece| self.push_piece(cursor, piece))
+                    .is_some(),
+                // Output that left the grammar can only end.
+                None => Some(id) == eos,
+            };
+            if !allowed {
+                *logit = f32::NEG_INFINITY;
+            }
+        }
+    }
+}
+
+/// Read `image` with the engine's page prompt and extract the fields of `extractor`. Engines
+/// with a grounding prompt also give each value its box; engines with constrained decoding are
+/// asked for the fields in a second pass.
+pub fn extract(
+    engine: &dyn OcrEngine,
+    tokenizer: &Tokenizer,
+    image: &DynamicImage,
+    extractor: &Extractor,
+    vision: VisionSettings,
+    params: &DecodeParameters,
+) -> Result<Extraction> {
+    let task = if OcrTask::supported(engine.kind()).contains(&OcrTask::Grounding) {
+        OcrTask::Grounding
+    } else {
+        OcrTask::Ocr
+    };
+    let prompt = task.render_prompt(engine.kind(), 1, None)?;
+    let outcome = engine.decode(
+        tokenizer,
+        &prompt,
+        std::slice::from_ref(image),
+        vision,
+        params,
+        None,
+        None,
+    )?;
+    let answer = if engine.constrained_decoding() {
+        let mut params = params.clone();
+        params.constraint = Some(Arc::new(extractor.constraint(tokenizer)));
+        let answer = engine.decode(
+            tokenizer,
+            &extractor.prompt(engine.kind()),
+            std::slice::from_ref(image),
+            vision,
+            &params,
+            None,
+            None,
+        )?;
+        Some(answer.text)
+    } else {
+        None
+    };
+    Ok(read_fields(
+        &outcome.text,
+        answer.as_deref(),
+        image.width(),
+        image.height(),
+        extractor,
+    ))
+}
+
+/// Fields of `extractor` in model output for a `width`×`height` image. Blocks are searched in
+/// reading order and the first label followed by a valid value wins.
+pub fn find_fields(text: &str, width: u32, height: u32, extractor: &Extractor) -> Extraction {
+    read_fields(text, None, width, height, extractor)
+}
+
+/// [`find_fields`], with the values of `answer`, the output of the schema pass, taking
+/// precedence over the label search.
+fn read_fields(
+    text: &str,
+    answer: Option<&str>,
+    width: u32,
+    height: u32,
+    extractor: &Extractor,
+) -> Extraction {
+    let page = OcrPage::from_grounded(text, width, height);
+    let lines: Vec<Line> = page
+        .ordered_blocks()
+        .into_iter()
+        .flat_map(|block| {
+            block
+                .line_boxes()
+                .enumerate()
+                .map(move |(row, (text, bbox))| Line {
+                    block,
+                    row,
+                    text,
+                    bbox,
+                })
+        })
+        .collect();
+    let labels: Vec<_> = lines
+        .iter()
+        .map(|line| extractor.labels(line.text))
+        .collect();
+    // Output cut short by the token limit is no object and answers nothing.
+    let answers: serde_json::Map<String, serde_json::Value> = answer
+        .and_then(|answer| serde_json::from_str(answer.trim()).ok())
+        .unwrap_or_default();
+    let fields = extractor
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| {
+            let found = find_field(field, idx, &lines, &labels);
+            let answered = answers
+                .get(&field.name)
+                .and_then(|answer| field.answered(answer));
+            match (answered, found) {
+                (None, Some(found)) => found,
+                (Some(value), Some(found)) if found.value.as_ref() == Some(&value) => found,
+                (Some(value), _) => {
+                    place_answer(field, &value, &lines).unwrap_or_else(|| ExtractedField {
+                        name: field.name.clone(),
+                        value: Some(value),
+                        text: None,
+                        bbox: None,
+                    })
+                }
+                (None, None) => ExtractedField {
+                    name: field.name.clone(),
+                    value: None,
+                    text: None,
+                    bbox: None,
+                },
+            }
+        })
+        .collect();
+    Extraction {
+        width,
+        height,
+        fields,
+    }
+}
+
+/// An answered text value where it appears on the page as written.
+fn place_answer(field: &Field, value: &FieldValue, lines: &[Line]) -> Option<ExtractedField> {
+    let FieldValue::Text(text) = value else {
+        return None;
+    };
+    lines.iter().find_map(|line| {
+        let start = line.text.find(text.as_str())?;
+        Some(hit(field, line, start..start + text.len(), value.clone()))
+    })
+}
+
+fn find_field(
+    field: &Field,
+    idx: usize,
+    lines: &[Line],
+    labels: &[Vec<(usize, Range<usize>)>],
+) -> Option<ExtractedField> {
+    for (line_idx, line) in lines.iter().enumerate() {
+        let found = &labels[line_idx];
+        for (pos, (_, label)) in found.iter().enumerate().filter(|(_, (i, _))| *i == idx) {
+            // The rest of the line, up to the next label.
+            let end = found
+                .get(pos + 1)
+                .map_or(line.text.len(), |(_, next)| next.start);
+            let start = skip_separators(line.text, label.end, end);
+            if let Some((range, value)) = field.read(&line.text[start..end]) {
+                let range = start + range.start..start + range.end;
+                return Some(hit(field, line, range, value));
+            }
+            if !line.text[start..end].trim().is_empty() {
+                continue;
+            }
+            // A header cell: the value is in the cell below.
+            if let Some(found) = below_in_table(field, line, label.clone(), lines) {
+                return Some(found);
+            }
+            // A label on its own: the value is on the line below, before any label there.
+            let Some(below) = line_below(lines, line_idx) else {
+                continue;
+            };
+            let next = &lines[below];
+            let end = labels[below]
+                .first()
+                .map_or(next.text.len(), |(_, label)| label.start);
+            let start = skip_separators(next.text, 0, end);
+            if let Some((range, value)) = field.read(&next.text[start..end]) {
+                let range = start + range.start..start + range.end;
+                return Some(hit(field, next, range, value));
+            }
+        }
+    }
+    None
+}
+
+/// Index of the line right below `lines[idx]`: the closest one underneath that overlaps it
+/// horizontally, or the next line in reading order without grounding boxes.
+fn line_below(lines: &[Line], idx: usize) -> Option<usize> {
+    let line = &lines[idx];
+    if line.block.label.is_empty() {
+        return (idx + 1 < lines.len()).then_some(idx + 1);
+    }
+    let [x0, _, x1, bottom] = line.bbox;
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(other_idx, other)| {
+            *other_idx != idx
+                && !other.block.label.is_empty()
+                && other.bbox[1] >= bottom
+                && other.bbox[0] < x1
+                && other.bbox[2] > x0
+        })
+        .min_by_key(|(_, other)| (other.bbox[1], other.bbox[0]))
+        .map(|(other_idx, _)| other_idx)
+}
+
+/// The value in the table cell below the cell of `label`, when `line` is a table row.
+fn below_in_table(
+    field: &Field,
+    line: &Line,
+    label: Range<usize>,
+    lines: &[Line],
+) -> Option<ExtractedField> {
+    let BlockKind::Table(table) = &line.block.kind else {
+        return None;
+    };
+    if line.block.lines.len() != table.rows {
+        return None;
+    }
+    let grid = table.grid();
+    let label = &line.text[label];
+    let column = grid[line.row]
+        .iter()
+        .position(|cell| cell.contains(label))?;
+    let cell = grid.get(line.row + 1)?.get(column)?;
+    let (range, value) = field.read(cell)?;
+    let row = lines
+        .iter()
+        .find(|other| std::ptr::eq(other.block, line.block) && other.row == line.row + 1)?;
+    // Place the cell in its row line; cells are joined by spaces there.
+    let offset = row.text.find(cell.as_str()).unwrap_or(0);
+    let range = offset + range.start..offset + range.end;
+    Some(hit(field, row, range, value))
+}
+
+fn hit(field: &Field, line: &Line, range: Range<usize>, value: FieldValue) -> ExtractedField {
+    // Text outside any grounding entry only has a made-up box.
+    let bbox = if line.block.label.is_empty() {
+        None
+    } else {
+        let words = word_boxes(line.text, line.bbox);
+        span_box(line.text, &words, range.clone()).map(BoundingBox::from)
+    };
+    ExtractedField {
+        name: field.name.clone(),
+        value: Some(value),
+        text: line.text.get(range).map(str::to_owned),
+        bbox,
+    }
+}
+
+/// Offset of the first character of `text[from..to]` that is not whitespace or a separator.
+fn skip_separators(text: &str, from: usize, to: usize) -> usize {
+    let rest = &text[from..to];
+    let trimmed =
+        rest.trim_start_matches(|ch: char| ch.is_whitespace() || SEPARATORS.contains(&ch));
+    from + rest.len() - trimmed.len()
+}
+
+/// Parse a number written with any of `,`, `.`, `'` or spaces as thousands separators. The
+/// last of `,` and `.` is the decimal separator when both occur. A lone one is too, unless
+/// exactly three digits follow it and the integer part could be the start of a thousands
+/// group: `0.125` and `1234.567` are decimals, `1.234` is not.
+fn parse_number(text: &str) -> Option<f64> {
+    let text: String = text
+        .chars()
+        .filter(|ch| !matches!(ch, ' ' | '\'' | '\u{a0}'))
+        .collect();
+    let lone = |sep: char| {
+        let (before, after) = text.split_once(sep)?;
+        let digits = before.trim_start_matches(['-', '+']);
+        let grouped = after.len() == 3 && digits != "0" && digits.len() <= 3;
+        (!after.contains(sep) && !grouped).then_some(sep)
+    };
+    let decimal = match (text.rfind('.'), text.rfind(',')) {
+        (Some(dot), Some(comma)) => Some(if dot > comma { '.' } else { ',' }),
+        (Some(_), None) => lone('.'),
+        (None, Some(_)) => lone(','),
+        (None, None) => None,
+    };
+    let normalised: String = text
+        .chars()
+        .filter_map(|ch| match ch {
+            '.' | ',' if Some(ch) == decimal => Some('.'),
+            '.' | ',' => None,
+            _ => Some(ch),
+        })
+        .collect();
+    normalised.parse().ok()
+}
+
+/// The first valid date in `text` as `YYYY-MM-DD`, with its byte range.
+fn find_date(text: &str) -> Option<(Range<usize>, String)> {
+    let mut best: Option<(Range<usize>, String)> = None;
+    for (regex, [year, month, day]) in DATES.iter() {
+        for captures in regex.captures_iter(text) {
+            let whole = captures.get(0)?.range();
+            if best
+                .as_ref()
+                .is_some_and(|(range, _)| range.start <= whole.start)
+            {
+                break;
+            }
+            let group = |idx: usize| captures.get(idx).map_or("", |found| found.as_str());
+            let Some(date) = calendar_date(group(*year), group(*month), group(*day)) else {
+                continue;
+            };
+            best = Some((whole, date));
+            break;
+        }
+    }
+    best
+}
+
+fn calendar_date(year: &str, month: &str, day: &str) -> Option<String> {
+    let year: u32 = match year.parse().ok()? {
+        short @ 0..=99 => 2000 + short,
+        full => full,
+    };
+    let month_number = |month: &str| -> Option<u32> {
+        if let Ok(number) = month.parse() {
+            return Some(number);
+        }
+        let prefix = month.get(..3)?.to_lowercase();
+        MONTHS
+            .split('|')
+            .position(|name| name == prefix)
+            .map(|idx| idx as u32 + 1)
+    };
+    let (mut month, mut day) = (month_number(month)?, day.parse::<u32>().ok()?);
+    // Day-first numeric dates whose month is out of range are month first.
+    if month > 12 && day <= 12 {
+        (month, day) = (day, month);
+    }
+    let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return None,
+    };
+    (1..=days)
+        .contains(&day)
+        .then(|| format!("{year:04}-{month:02}-{day:02}"))
+}
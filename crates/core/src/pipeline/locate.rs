@@ -0,0 +1,86 @@
+//! Text search with DeepSeek-OCR's `Locate <|ref|>…<|/ref|> in the image.` prompt. The model
+//! answers with one `<|det|>` span per match; [`parse_locations`] maps those boxes from
+//! grounding units onto the pixels of the searched image.
+
+use anyhow::Result;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use tokenizers::Tokenizer;
+
+use crate::{
+    grounding::{parse_grounded, to_pixels},
+    inference::{DecodeParameters, OcrEngine, VisionSettings},
+    task::OcrTask,
+};
+
+/// A pixel rectangle, `x1`/`y1` exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+}
+
+impl BoundingBox {
+    pub fn width(&self) -> u32 {
+        self.x1.saturating_sub(self.x0)
+    }
+
+    pub fn height(&self) -> u32 {
+        self.y1.saturating_sub(self.y0)
+    }
+
+    pub fn to_array(self) -> [u32; 4] {
+        [self.x0, self.y0, self.x1, self.y1]
+    }
+}
+
+impl From<[u32; 4]> for BoundingBox {
+    fn from([x0, y0, x1, y1]: [u32; 4]) -> Self {
+        Self { x0, y0, x1, y1 }
+    }
+}
+
+/// Find `query` in `image` and return the box of every match in pixels of `image`, in the
+/// order the model listed them. Only DeepSeek-OCR has a locate prompt; other engines fail.
+pub fn locate(
+    engine: &dyn OcrEngine,
+    tokenizer: &Tokenizer,
+    image: &DynamicImage,
+    query: &str,
+    vision: VisionSettings,
+    params: &DecodeParameters,
+) -> Result<Vec<BoundingBox>> {
+    let prompt = OcrTask::Locate.render_prompt(engine.kind(), 1, Some(query))?;
+    let outcome = engine.decode(
+        tokenizer,
+        &prompt,
+        std::slice::from_ref(image),
+        vision,
+        params,
+        None,
+        None,
+    )?;
+    Ok(parse_locations(
+        &outcome.text,
+        image.width(),
+        image.height(),
+    ))
+}
+
+/// Boxes of a locate answer on a `width`×`height` image. Repeated and empty boxes are
+/// dropped; an answer without any `<|det|>` span means no match.
+pub fn parse_locations(text: &str, width: u32, height: u32) -> Vec<BoundingBox> {
+    let mut boxes: Vec<BoundingBox> = Vec::new();
+    for bbox in parse_grounded(text)
+        .into_iter()
+        .flat_map(|block| block.boxes)
+        .map(|bbox| BoundingBox::from(to_pixels(bbox, width, height)))
+    {
+        if bbox.width() > 0 && bbox.height() > 0 && !boxes.contains(&bbox) {
+            boxes.push(bbox);
+        }
+    }
+    boxes
+}
@@ -0,0 +1,67 @@
+//! Typed tasks on top of an [`OcrEngine`](crate::inference::OcrEngine).
+//!
+//! Each task renders the prompt the model was trained with, runs the decode and parses the
+//! output into plain data, so callers never handle prompt markup or grounding units.
+
+pub mod extract;
+pub mod locate;
+pub mod redact;
+
+use std::ops::Range;
+
+/// Alternation of the non-blank `terms`, longest first so a term is not cut short by its
+/// prefix. Terms are bounded by `\b` on the sides that start or end with a word character.
+pub(crate) fn terms_regex(terms: &[String]) -> Option<String> {
+    let mut terms: Vec<&str> = terms
+        .iter()
+        .map(|term| term.trim())
+        .filter(|term| !term.is_empty())
+        .collect();
+    if terms.is_empty() {
+        return None;
+    }
+    terms.sort_by_key(|term| std::cmp::Reverse(term.chars().count()));
+    terms.dedup();
+    let is_word = |ch: Option<char>| ch.is_some_and(|ch| ch.is_alphanumeric() || ch == '_');
+    let alternatives: Vec<String> = terms
+        .iter()
+        .map(|term| {
+            let start = if is_word(term.chars().next()) {
+                r"\b"
+            } else {
+                ""
+            };
+            let end = if is_word(term.chars().last()) {
+                r"\b"
+            } else {
+                ""
+            };
+            format!("{start}{}{end}", regex::escape(term))
+        })
+        .collect();
+    Some(format!("(?:{})", alternatives.join("|")))
+}
+
+/// Union of the boxes of the `words` of `line` that overlap the byte `range` of `line`.
+/// `words` are slices of `line`, as returned by [`word_boxes`](crate::export::word_boxes).
+pub(crate) fn span_box(
+    line: &str,
+    words: &[(&str, [u32; 4])],
+    range: Range<usize>,
+) -> Option<[u32; 4]> {
+    words
+        .iter()
+        .filter(|(word, _)| {
+            let start = word.as_ptr() as usize - line.as_ptr() as usize;
+            start < range.end && start + word.len() > range.start
+        })
+        .map(|(_, bbox)| *bbox)
+        .reduce(|a, b| {
+            [
+                a[0].min(b[0]),
+                a[1].min(b[1]),
+                a[2].max(b[2]),
+                a[3].max(b[3]),
+            ]
+        })
+}
@@ -0,0 +1,414 @@
+//! PII redaction on top of grounded OCR. The page is read with the grounding prompt, the text
+//! of every block is matched against the configured [`RedactionRules`] with its lines joined,
+//! so a name or number wrapped onto the next line is still found, and each line a match
+//! touches is blacked out in the image. Blacking out only the matched words is available, but
+//! their positions are interpolated from character counts, which proportional fonts do not
+//! follow exactly.
+//!
+//! Redaction fails closed where it can: text the model returned without a box blacks out the
+//! whole band it was given on the page. The [`RedactionReport`] is marked incomplete when that
+//! happened or when decoding stopped at the token limit, since text past it was never read.
+//! It says what was removed and where without repeating the matched text: it keeps the last
+//! four characters of long matches and masks the rest.
+
+use std::ops::Range;
+
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use image::{DynamicImage, Rgb};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use tokenizers::Tokenizer;
+
+use super::{locate::BoundingBox, span_box, terms_regex};
+use crate::{
+    export::{OcrPage, word_boxes},
+    inference::{DecodeParameters, OcrEngine, VisionSettings},
+    task::OcrTask,
+};
+
+/// Runs of digit groups separated by single spaces or dashes. Card numbers are looked for
+/// inside them, since a run may also hold an expiry date or a reference next to the number.
+const DIGIT_RUN: &str = r"\b[0-9]+(?:[ -][0-9]+)*\b";
+
+/// What is redacted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionRules {
+    /// Redact 13 to 19 digit numbers that pass the Luhn check.
+    pub card_numbers: bool,
+    /// Pixels added around every redacted box.
+    pub padding: u32,
+    /// What a match blacks out.
+    pub area: RedactionArea,
+    /// Regular expressions, matched case-sensitively unless they set `(?i)`.
+    pub patterns: Vec<PatternRule>,
+    /// Word lists, matched case-insensitively as whole words.
+    pub dictionaries: Vec<DictionaryRule>,
+}
+
+impl Default for RedactionRules {
+    fn default() -> Self {
+        Self {
+            card_numbers: true,
+            padding: 2,
+            area: RedactionArea::Line,
+            patterns: Vec::new(),
+            dictionaries: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatternRule {
+    /// Name reported in the audit.
+    pub name: String,
+    pub regex: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DictionaryRule {
+    /// Name reported in the audit.
+    pub name: String,
+    pub terms: Vec<String>,
+}
+
+/// How much of the page a match blacks out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionArea {
+    /// Every line the match touches, edge to edge of its block.
+    #[default]
+    Line,
+    /// The interpolated boxes of the words the match touches.
+    Words,
+}
+
+/// Which kind of rule produced a redaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleKind {
+    Pattern,
+    Dictionary,
+    CardNumber,
+}
+
+/// One match of a rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Redaction {
+    /// Name of the rule; `card_number` for card numbers.
+    pub rule: String,
+    pub kind: RuleKind,
+    /// Grounding label of the block the match was found in.
+    pub label: String,
+    /// The matched text with letters and digits replaced by `*`, except the last four of
+    /// matches with at least eight.
+    pub masked: String,
+    /// Whether the block had a grounding box. When it did not, `boxes` is the band of the
+    /// page the text was assumed to fill, which may have missed it.
+    pub placed: bool,
+    /// Blacked-out boxes in pixels of the image, one per line of the match.
+    pub boxes: Vec<BoundingBox>,
+}
+
+/// Audit record of a redacted image.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RedactionReport {
+    pub width: u32,
+    pub height: u32,
+    /// False when a match was not placed or decoding was truncated, so the image may still
+    /// show personal data.
+    pub complete: bool,
+    /// Decoding stopped at the token limit; text past it was not checked.
+    pub truncated: bool,
+    pub redactions: Vec<Redaction>,
+}
+
+impl RedactionReport {
+    /// Matches in text without a grounding box.
+    pub fn unplaced(&self) -> usize {
+        self.redactions
+            .iter()
+            .filter(|redaction| !redaction.placed)
+            .count()
+    }
+
+    fn with_truncation(mut self, truncated: bool) -> Self {
+        self.truncated = truncated;
+        self.complete = !truncated && self.unplaced() == 0;
+        self
+    }
+}
+
+/// The redacted image with its audit record.
+#[derive(Debug, Clone)]
+pub struct Redacted {
+    pub image: DynamicImage,
+    pub report: RedactionReport,
+}
+
+struct Matcher {
+    name: String,
+    kind: RuleKind,
+    regex: Regex,
+}
+
+/// Compiled [`RedactionRules`].
+pub struct Redactor {
+    matchers: Vec<Matcher>,
+    padding: u32,
+    area: RedactionArea,
+}
+
+impl Redactor {
+    /// Compile `rules`. Fails on an invalid pattern or when no rule is enabled.
+    pub fn new(rules: &RedactionRules) -> Result<Self> {
+        let mut matchers = Vec::new();
+        for rule in &rules.patterns {
+            let regex = Regex::new(&rule.regex)
+                .with_context(|| format!("invalid redaction pattern `{}`", rule.name))?;
+            matchers.push(Matcher {
+                name: rule.name.clone(),
+                kind: RuleKind::Pattern,
+                regex,
+            });
+        }
+        for rule in &rules.dictionaries {
+            let Some(regex) = terms_regex(&rule.terms) else {
+                continue;
+            };
+            let regex = RegexBuilder::new(&regex)
+                .case_insensitive(true)
+                .build()
+                .with_context(|| format!("invalid redaction dictionary `{}`", rule.name))?;
+            matchers.push(Matcher {
+                name: rule.name.clone(),
+                kind: RuleKind::Dictionary,
+                regex,
+            });
+        }
+        if rules.card_numbers {
+            matchers.push(Matcher {
+                name: "card_number".to_owned(),
+                kind: RuleKind::CardNumber,
+                regex: Regex::new(DIGIT_RUN).expect("digit run pattern is valid"),
+            });
+        }
+        if matchers.is_empty() {
+            bail!("no redaction rules: add a pattern, a dictionary or enable card numbers");
+        }
+        Ok(Self {
+            matchers,
+            padding: rules.padding,
+            area: rules.area,
+        })
+    }
+
+    /// Byte ranges of `text` to redact, with the rule that matched each.
+    fn matches(&self, text: &str) -> Vec<(&Matcher, Range<usize>)> {
+        let mut found = Vec::new();
+        for matcher in &self.matchers {
+            for hit in matcher.regex.find_iter(text).filter(|hit| !hit.is_empty()) {
+                if matcher.kind == RuleKind::CardNumber {
+                    found.extend(card_numbers(hit.as_str()).into_iter().map(|range| {
+                        (matcher, hit.start() + range.start..hit.start() + range.end)
+                    }));
+                } else {
+                    found.push((matcher, hit.range()));
+                }
+            }
+        }
+        found
+    }
+}
+
+/// Card numbers in a run of digit groups: 13 to 19 digits that start and end on a group
+/// boundary and pass the Luhn check. From each group on, the longest such window is taken,
+/// so `4111 1111 1111 1111 12` yields the 16-digit number.
+fn card_numbers(run: &str) -> Vec<Range<usize>> {
+    let mut groups = Vec::new();
+    let mut start = None;
+    for (idx, ch) in run.char_indices().chain([(run.len(), ' ')]) {
+        if ch.is_ascii_digit() {
+            start.get_or_insert(idx);
+        } else if let Some(first) = start.take() {
+            groups.push(first..idx);
+        }
+    }
+    let mut found = Vec::new();
+    let mut first = 0;
+    while first < groups.len() {
+        let mut digits = 0;
+        let mut longest = None;
+        for last in first..groups.len() {
+            digits += groups[last].len();
+            if digits > 19 {
+                break;
+            }
+            let window = groups[first].start..groups[last].end;
+            if digits >= 13 && luhn_valid(&run[window]) {
+                longest = Some(last);
+            }
+        }
+        match longest {
+            Some(last) => {
+                found.push(groups[first].start..groups[last].end);
+                first = last + 1;
+            }
+            None => first += 1,
+        }
+    }
+    found
+}
+
+/// Whether the digits of `number` pass the Luhn checksum. Spaces and dashes are ignored; any
+/// other character, or fewer than two digits, fails.
+pub fn luhn_valid(number: &str) -> bool {
+    let mut sum = 0;
+    let mut digits = 0;
+    for ch in number.chars().rev().filter(|ch| !matches!(ch, ' ' | '-')) {
+        let Some(digit) = ch.to_digit(10) else {
+            return false;
+        };
+        let digit = if digits % 2 == 1 { digit * 2 } else { digit };
+        sum += if digit > 9 { digit - 9 } else { digit };
+        digits += 1;
+    }
+    digits >= 2 && sum % 10 == 0
+}
+
+/// Read `image` with the grounding prompt, black out every match of `redactor` and report
+/// what was removed. The report is incomplete when the output reached `params.max_new_tokens`.
+/// Only DeepSeek-OCR has a grounding prompt; other engines fail.
+pub fn redact(
+    engine: &dyn OcrEngine,
+    tokenizer: &Tokenizer,
+    image: &DynamicImage,
+    redactor: &Redactor,
+    vision: VisionSettings,
+    params: &DecodeParameters,
+) -> Result<Redacted> {
+    let prompt = OcrTask::Grounding.render_prompt(engine.kind(), 1, None)?;
+    let outcome = engine.decode(
+        tokenizer,
+        &prompt,
+        std::slice::from_ref(image),
+        vision,
+        params,
+        None,
+        None,
+    )?;
+    let truncated = outcome.response_tokens >= params.max_new_tokens;
+    let report = find_redactions(&outcome.text, image.width(), image.height(), redactor)
+        .with_truncation(truncated);
+    Ok(Redacted {
+        image: black_out(image, &report.redactions),
+        report,
+    })
+}
+
+/// Matches of `redactor` in grounded model output for a `width`×`height` image, in the order
+/// the model emitted the blocks. The lines of a block are matched joined by spaces; a match
+/// gets one box per line it touches.
+pub fn find_redactions(
+    text: &str,
+    width: u32,
+    height: u32,
+    redactor: &Redactor,
+) -> RedactionReport {
+    let page = OcrPage::from_grounded(text, width, height);
+    let mut redactions = Vec::new();
+    for block in &page.blocks {
+        // Text outside any grounding entry only has a made-up box: black it out whole.
+        let placed = !block.label.is_empty();
+        let lines: Vec<(&str, [u32; 4])> = block.line_boxes().collect();
+        let mut joined = String::new();
+        let mut starts = Vec::with_capacity(lines.len());
+        for (line, _) in &lines {
+            if !joined.is_empty() {
+                joined.push(' ');
+            }
+            starts.push(joined.len());
+            joined.push_str(line);
+        }
+        for (matcher, range) in redactor.matches(&joined) {
+            let boxes = if placed {
+                lines
+                    .iter()
+                    .zip(&starts)
+                    .filter_map(|(&(line, line_box), &start)| {
+                        let local = range.start.max(start)..range.end.min(start + line.len());
+                        if local.start >= local.end {
+                            return None;
+                        }
+                        let local = local.start - start..local.end - start;
+                        Some(match redactor.area {
+                            RedactionArea::Line => line_box,
+                            RedactionArea::Words => {
+                                span_box(line, &word_boxes(line, line_box), local)
+                                    .unwrap_or(line_box)
+                            }
+                        })
+                    })
+                    .map(|bbox| pad(bbox, redactor.padding, width, height))
+                    .collect()
+            } else {
+                vec![pad(block.bbox, redactor.padding, width, height)]
+            };
+            redactions.push(Redaction {
+                rule: matcher.name.clone(),
+                kind: matcher.kind,
+                label: block.label.clone(),
+                masked: mask(&joined[range]),
+                placed,
+                boxes,
+            });
+        }
+    }
+    RedactionReport {
+        width,
+        height,
+        complete: true,
+        truncated: false,
+        redactions,
+    }
+    .with_truncation(false)
+}
+
+/// Copy of `image` with every box of `redactions` filled black.
+pub fn black_out(image: &DynamicImage, redactions: &[Redaction]) -> DynamicImage {
+    let mut canvas = image.to_rgb8();
+    let (width, height) = canvas.dimensions();
+    for bbox in redactions.iter().flat_map(|redaction| &redaction.boxes) {
+        for y in bbox.y0..bbox.y1.min(height) {
+            for x in bbox.x0..bbox.x1.min(width) {
+                canvas.put_pixel(x, y, Rgb([0, 0, 0]));
+            }
+        }
+    }
+    DynamicImage::ImageRgb8(canvas)
+}
+
+fn pad(bbox: [u32; 4], padding: u32, width: u32, height: u32) -> BoundingBox {
+    BoundingBox {
+        x0: bbox[0].saturating_sub(padding),
+        y0: bbox[1].saturating_sub(padding),
+        x1: bbox[2].saturating_add(padding).min(width),
+        y1: bbox[3].saturating_add(padding).min(height),
+    }
+}
+
+fn mask(text: &str) -> String {
+    let total = text.chars().filter(|ch| ch.is_alphanumeric()).count();
+    let keep = if total >= 8 { 4 } else { 0 };
+    let mut seen = 0;
+    text.chars()
+        .map(|ch| {
+            if !ch.is_alphanumeric() {
+                return ch;
+            }
+            seen += 1;
+            if seen > total - keep { ch } else { '*' }
+        })
+        .collect()
+}
@@ -0,0 +1,269 @@
+//! Line-level markdown rules. Fenced code and display math are never touched.
+
+use std::collections::HashSet;
+
+use crate::tables::extract_tables;
+
+/// Characters that end a paragraph; a paragraph ending in anything else may continue.
+const SENTENCE_END: &[char] = &[
+    '.', '!', '?', ':', ';', '"', '\'', ')', ']', '”', '’', '。', '！', '？', '：', '；', '」',
+    '』',
+];
+
+/// Which lines sit inside fenced code or a multi-line display formula, delimiters included.
+fn protected(lines: &[&str]) -> Vec<bool> {
+    let mut flags = Vec::with_capacity(lines.len());
+    let mut closer: Option<&str> = None;
+    for line in lines {
+        let trimmed = line.trim();
+        match closer {
+            Some(close) => {
+                flags.push(true);
+                if trimmed.starts_with(close) || trimmed.ends_with(close) {
+                    closer = None;
+                }
+            }
+            None => {
+                let fence = ["```", "~~~"]
+                    .into_iter()
+                    .find(|fence| trimmed.starts_with(fence));
+                closer = match fence {
+                    Some(fence) => Some(fence),
+                    None if trimmed.starts_with("$$") && !trimmed[2..].contains("$$") => Some("$$"),
+                    None if trimmed.starts_with("\\[") && !trimmed.ends_with("\\]") => Some("\\]"),
+                    None => None,
+                };
+                let single = trimmed.starts_with("$$") || trimmed.starts_with("\\[");
+                flags.push(closer.is_some() || single);
+            }
+        }
+    }
+    flags
+}
+
+/// Whether `line` ends in a word broken with a hyphen, such as `exam-`.
+fn hyphenated(line: &str) -> bool {
+    let mut chars = line.trim_end().chars().rev();
+    chars.next() == Some('-') && chars.next().is_some_and(char::is_alphabetic)
+}
+
+fn starts_lowercase(line: &str) -> bool {
+    line.trim_start()
+        .chars()
+        .next()
+        .is_some_and(char::is_lowercase)
+}
+
+/// Join `next` onto `line`, dropping the hyphen of a broken word or adding a space.
+fn join_onto(line: &mut String, next: &str) {
+    let trimmed = line.trim_end().len();
+    line.truncate(trimmed);
+    if hyphenated(line) {
+        line.pop();
+    } else {
+        line.push(' ');
+    }
+    line.push_str(next.trim_start());
+}
+
+/// Merge words hyphenated across a line break (`exam-` / `ple`) when the next line continues
+/// in lowercase. The hyphen is dropped only when the joined word appears elsewhere in the
+/// document and neither half does on its own; otherwise the break is read as a compound
+/// (`self-` / `driving`) and the hyphen stays. Headings and table rows are left alone.
+pub(super) fn merge_hyphenation(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let protected = protected(&lines);
+    let vocabulary = vocabulary(&lines, &protected);
+    let known = |word: &str| vocabulary.contains(&word.to_lowercase());
+    let mut out: Vec<String> = Vec::with_capacity(lines.len());
+    let mut joinable = false;
+    for (idx, line) in lines.iter().enumerate() {
+        if joinable
+            && !protected[idx]
+            && starts_lowercase(line)
+            && let Some(last) = out.last_mut()
+            && hyphenated(last)
+        {
+            let head = words(last.trim_end().trim_end_matches('-'))
+                .last()
+                .unwrap_or_default();
+            let tail = words(line).next().unwrap_or_default();
+            if known(&format!("{head}{tail}")) && !known(head) && !known(tail) {
+                join_onto(last, line);
+            } else {
+                let trimmed = last.trim_end().len();
+                last.truncate(trimmed);
+                last.push_str(line.trim_start());
+            }
+            continue;
+        }
+        let trimmed = line.trim_start();
+        joinable = !protected[idx] && !trimmed.starts_with(['#', '|', '<']);
+        out.push((*line).to_owned());
+    }
+    out.join("\n")
+}
+
+fn words(text: &str) -> impl DoubleEndedIterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+}
+
+/// Lower-cased words outside code and display math, leaving out both halves of every word
+/// broken at a line end, which are not words of their own.
+fn vocabulary(lines: &[&str], protected: &[bool]) -> HashSet<String> {
+    let mut vocabulary = HashSet::new();
+    let mut after_break = false;
+    for (line, &skip) in lines.iter().zip(protected) {
+        if skip {
+            after_break = false;
+            continue;
+        }
+        let mut line_words: Vec<&str> = words(line).collect();
+        if after_break && !line_words.is_empty() {
+            line_words.remove(0);
+        }
+        after_break = hyphenated(line);
+        if after_break {
+            line_words.pop();
+        }
+        vocabulary.extend(line_words.into_iter().map(str::to_lowercase));
+    }
+    vocabulary
+}
+
+/// Join paragraphs split by a blank line mid-sentence, as happens where the outputs of two
+/// tiles or slices meet: the first ends without closing punctuation and the second starts in
+/// lowercase. Only plain prose paragraphs are joined, never headings, lists, tables or code.
+pub(super) fn join_paragraphs(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let protected = protected(&lines);
+    let mut out: Vec<String> = Vec::with_capacity(lines.len());
+    // Index in `out` of the first line of the current paragraph.
+    let mut paragraph: Option<usize> = None;
+    let mut blank_run = 0;
+    for (idx, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() && !protected[idx] {
+            blank_run += 1;
+            out.push(String::new());
+            continue;
+        }
+        let continues = blank_run > 0
+            && !protected[idx]
+            && starts_lowercase(line)
+            && paragraph.is_some_and(|start| is_prose(&out[start]))
+            && out
+                .iter()
+                .rev()
+                .find(|line| !line.trim().is_empty())
+                .is_some_and(|last| !last.trim_end().ends_with(SENTENCE_END));
+        if continues {
+            out.truncate(out.len() - blank_run);
+            if let Some(last) = out.last_mut() {
+                join_onto(last, line);
+            }
+        } else {
+            if blank_run > 0 || paragraph.is_none() {
+                paragraph = (!protected[idx]).then_some(out.len());
+            }
+            if protected[idx] {
+                paragraph = None;
+            }
+            out.push((*line).to_owned());
+        }
+        blank_run = 0;
+    }
+    out.join("\n")
+}
+
+/// Whether a paragraph starting with `line` is running text rather than a block construct.
+fn is_prose(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    let list_item = trimmed.split_once(' ').is_some_and(|(marker, _)| {
+        matches!(marker, "-" | "*" | "+")
+            || marker.strip_suffix(['.', ')']).is_some_and(|number| {
+                !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit())
+            })
+    });
+    !(list_item || trimmed.starts_with(['#', '|', '<', '>', '!', '$', '\\']))
+}
+
+/// Rewrite ATX heading levels so the shallowest heading is `#` and no level is skipped on the
+/// way down: `##`, `####`, `###` becomes `#`, `##`, `##`.
+pub(super) fn fix_headings(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let protected = protected(&lines);
+    let level = |line: &str| {
+        let hashes = line.bytes().take_while(|&b| b == b'#').count();
+        let heading = (1..=6).contains(&hashes)
+            && line[hashes..].starts_with([' ', '\t'])
+            && !line[hashes..].trim().is_empty();
+        heading.then_some(hashes)
+    };
+    let headings: Vec<Option<usize>> = lines
+        .iter()
+        .zip(&protected)
+        .map(|(line, &protected)| level(line).filter(|_| !protected))
+        .collect();
+    let Some(top) = headings.iter().flatten().min().copied() else {
+        return text.to_owned();
+    };
+    let mut previous = 0;
+    let mut out = Vec::with_capacity(lines.len());
+    for (line, heading) in lines.iter().zip(headings) {
+        match heading {
+            Some(hashes) => {
+                let fixed = (hashes - top + 1).min(previous + 1);
+                previous = fixed;
+                out.push(format!("{}{}", "#".repeat(fixed), &line[hashes..]));
+            }
+            None => out.push((*line).to_owned()),
+        }
+    }
+    out.join("\n")
+}
+
+/// Replace HTML tables without spanning cells by GitHub pipe tables on lines of their own.
+pub(super) fn markdown_tables(text: &str) -> String {
+    // ASCII lowercasing keeps byte offsets, so positions found here index `text`.
+    let lower = text.to_ascii_lowercase();
+    let mut out = String::with_capacity(text.len());
+    let mut pos = 0;
+    while let Some(start) = lower[pos..].find("<table").map(|offset| pos + offset) {
+        let Some(end) = lower[start..]
+            .find("</table>")
+            .map(|offset| start + offset + "</table>".len())
+        else {
+            break;
+        };
+        let tables = extract_tables(&text[start..end]);
+        let markdown = match tables.as_slice() {
+            [table] => table.to_markdown(),
+            _ => None,
+        };
+        match markdown {
+            Some(markdown) => {
+                out.push_str(text[pos..start].trim_end_matches([' ', '\t']));
+                if !(out.is_empty() || out.ends_with("\n\n")) {
+                    out.push_str(if out.ends_with('\n') { "\n" } else { "\n\n" });
+                }
+                out.push_str(&markdown);
+                let after = text[end..].trim_start_matches([' ', '\t']);
+                if !(after.is_empty() || after.starts_with("\n\n")) {
+                    out.push_str(if after.starts_with('\n') {
+                        "\n"
+                    } else {
+                        "\n\n"
+                    });
+                }
+                pos = text.len() - after.len();
+            }
+            None => {
+                out.push_str(&text[pos..end]);
+                pos = end;
+            }
+        }
+    }
+    out.push_str(&text[pos..]);
+    out
+}
@@ -0,0 +1,91 @@
+//! Optional post-processing of the markdown a model returns.
+//!
+//! [`polish`] runs the enabled rules over the finished text in a fixed order — grounding tags
+//! stripped, HTML tables turned into pipe tables, formulas normalised, hyphenated line breaks
+//! merged, split paragraphs joined, heading levels fixed. Rules only rewrite markup and line
+//! breaks, never the words themselves, and report what they could not fix so callers can
+//! surface it alongside the text.
+
+mod markdown;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    formula::{FormulaIssue, FormulaMode, MathDelimiters, normalize_formulas},
+    grounding::strip_grounding,
+    task::OcrTask,
+};
+
+/// Which polish rules run on model output. Everything is off by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PolishConfig {
+    /// Remove `<|ref|>`/`<|det|>` grounding markup, keeping the text it labels.
+    pub strip_grounding: bool,
+    /// Replace HTML tables without spanning cells by GitHub pipe tables.
+    pub markdown_tables: bool,
+    /// Rewrite formulas with one delimiter style, or as MathML.
+    pub formulas: FormulaMode,
+    /// Delimiters written when `formulas` is `latex`.
+    pub math_delimiters: MathDelimiters,
+    /// Merge words hyphenated across a line break.
+    pub merge_hyphenation: bool,
+    /// Join paragraphs split mid-sentence where tiles or slices meet.
+    pub join_paragraphs: bool,
+    /// Start headings at `#` and close skipped levels.
+    pub fix_headings: bool,
+}
+
+impl PolishConfig {
+    pub fn enabled(&self) -> bool {
+        self.strip_grounding
+            || self.markdown_tables
+            || self.formulas != FormulaMode::Off
+            || self.merge_hyphenation
+            || self.join_paragraphs
+            || self.fix_headings
+    }
+
+    /// The rules to run for output of `task`: grounding markup is kept when the task asked for
+    /// boxes.
+    pub fn for_task(mut self, task: Option<OcrTask>) -> Self {
+        if matches!(task, Some(OcrTask::Grounding | OcrTask::Locate)) {
+            self.strip_grounding = false;
+        }
+        self
+    }
+}
+
+/// Polished text and the problems found on the way.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Polished {
+    pub text: String,
+    /// Formulas whose braces, `\left`/`\right` pairs or environments did not balance.
+    pub formula_issues: Vec<FormulaIssue>,
+}
+
+/// Run the rules `config` enables over `text`.
+pub fn polish(text: &str, config: &PolishConfig) -> Polished {
+    let mut text = text.to_owned();
+    if config.strip_grounding {
+        text = strip_grounding(&text);
+    }
+    if config.markdown_tables {
+        text = markdown::markdown_tables(&text);
+    }
+    let (mut text, formula_issues) =
+        normalize_formulas(&text, config.formulas, config.math_delimiters);
+    if config.merge_hyphenation {
+        text = markdown::merge_hyphenation(&text);
+    }
+    if config.join_paragraphs {
+        text = markdown::join_paragraphs(&text);
+    }
+    if config.fix_headings {
+        text = markdown::fix_headings(&text);
+    }
+    Polished {
+        text,
+        formula_issues,
+    }
+}
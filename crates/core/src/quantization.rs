@@ -0,0 +1,337 @@
+use std::{env, fmt};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Supported runtime quantization algorithms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+pub enum QuantizationKind {
+    #[default]
+    #[serde(rename = "none")]
+    #[value(name = "none")]
+    None,
+    #[serde(rename = "q8_0")]
+    #[value(name = "q8_0")]
+    Q8_0,
+    #[serde(rename = "q4_k")]
+    #[value(name = "q4_k")]
+    Q4K,
+}
+
+impl QuantizationKind {
+    pub fn is_enabled(self) -> bool {
+        !matches!(self, Self::None)
+    }
+}
+
+impl fmt::Display for QuantizationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => f.write_str("none"),
+            Self::Q8_0 => f.write_str("Q8_0"),
+            Self::Q4K => f.write_str("Q4_K"),
+        }
+    }
+}
+
+/// Which modules should participate in quantization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+pub enum QuantizationTargets {
+    #[default]
+    #[serde(rename = "text")]
+    #[value(name = "text")]
+    Text,
+    #[serde(rename = "text+projector")]
+    #[value(name = "text+projector")]
+    TextAndProjector,
+}
+
+impl fmt::Display for QuantizationTargets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text => f.write_str("text"),
+            Self::TextAndProjector => f.write_str("text+projector"),
+        }
+    }
+}
+
+/// Vision encoders whose linears can be quantized independently of the text targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[value(rename_all = "lowercase")]
+pub enum VisionTower {
+    /// DeepSeek-OCR SAM ViT-B blocks.
+    Sam,
+    /// DeepSeek-OCR CLIP-L attention and MLP linears.
+    Clip,
+    /// PaddleOCR-VL SigLIP encoder linears.
+    Siglip,
+}
+
+impl fmt::Display for VisionTower {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sam => f.write_str("sam"),
+            Self::Clip => f.write_str("clip"),
+            Self::Siglip => f.write_str("siglip"),
+        }
+    }
+}
+
+/// Per-tower opt-in for vision quantization. Every tower stays in float by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VisionQuantization {
+    pub sam: bool,
+    pub clip: bool,
+    pub siglip: bool,
+}
+
+impl VisionQuantization {
+    pub fn from_towers(towers: &[VisionTower]) -> Self {
+        let mut selection = Self::default();
+        for tower in towers {
+            selection.set(*tower, true);
+        }
+        selection
+    }
+
+    pub fn enabled(self, tower: VisionTower) -> bool {
+        match tower {
+            VisionTower::Sam => self.sam,
+            VisionTower::Clip => self.clip,
+            VisionTower::Siglip => self.siglip,
+        }
+    }
+
+    pub fn set(&mut self, tower: VisionTower, enabled: bool) {
+        match tower {
+            VisionTower::Sam => self.sam = enabled,
+            VisionTower::Clip => self.clip = enabled,
+            VisionTower::Siglip => self.siglip = enabled,
+        }
+    }
+
+    pub fn any(self) -> bool {
+        self.sam || self.clip || self.siglip
+    }
+}
+
+impl fmt::Display for VisionQuantization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let towers = [VisionTower::Sam, VisionTower::Clip, VisionTower::Siglip]
+            .into_iter()
+            .filter(|tower| self.enabled(*tower))
+            .map(|tower| tower.to_string())
+            .collect::<Vec<_>>();
+        if towers.is_empty() {
+            f.write_str("none")
+        } else {
+            f.write_str(&towers.join(","))
+        }
+    }
+}
+
+/// High-level components that may opt into quantization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinearLayerGroup {
+    Text,
+    Projector,
+    Vision,
+}
+
+/// Module categories used for per-module stats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantModule {
+    TextLinear,
+    Projector,
+    LmHead,
+    Vision(VisionTower),
+}
+
+impl fmt::Display for QuantModule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TextLinear => f.write_str("text-linear"),
+            Self::Projector => f.write_str("projector"),
+            Self::LmHead => f.write_str("lm_head"),
+            Self::Vision(tower) => write!(f, "vision-{tower}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ModuleStats {
+    pub candidates: usize,
+    pub quantized: usize,
+    pub fallback: usize,
+}
+
+/// Per-model quantization counters, broken down by module category.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QuantizationStats {
+    pub candidates: usize,
+    pub quantized: usize,
+    pub fallback: usize,
+    pub text_linear: ModuleStats,
+    pub projector: ModuleStats,
+    pub lm_head: ModuleStats,
+    pub sam: ModuleStats,
+    pub clip: ModuleStats,
+    pub siglip: ModuleStats,
+}
+
+impl QuantizationStats {
+    pub fn record(&mut self, module: QuantModule, outcome: QuantizationOutcome) {
+        let module_stats = match module {
+            QuantModule::TextLinear => &mut self.text_linear,
+            QuantModule::Projector => &mut self.projector,
+            QuantModule::LmHead => &mut self.lm_head,
+            QuantModule::Vision(VisionTower::Sam) => &mut self.sam,
+            QuantModule::Vision(VisionTower::Clip) => &mut self.clip,
+            QuantModule::Vision(VisionTower::Siglip) => &mut self.siglip,
+        };
+        module_stats.candidates += 1;
+        self.candidates += 1;
+        match outcome {
+            QuantizationOutcome::Quantized => {
+                self.quantized += 1;
+                module_stats.quantized += 1;
+            }
+            QuantizationOutcome::Fallback => {
+                self.fallback += 1;
+                module_stats.fallback += 1;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum QuantizationOutcome {
+    Quantized,
+    Fallback,
+}
+
+/// Per-model quantization settings, stored on each `[models.entries.<id>]` record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuantizationConfig {
+    pub kind: QuantizationKind,
+    pub targets: QuantizationTargets,
+    pub keep_full_precision_weights: bool,
+    pub verbose_per_layer: bool,
+    /// Vision towers to quantize in addition to `targets`.
+    pub vision: VisionQuantization,
+}
+
+impl QuantizationConfig {
+    /// Read the legacy `DEEPSEEK_OCR_QUANT*` environment variables.
+    pub fn from_env() -> Self {
+        Self {
+            kind: parse_kind_from_env(),
+            targets: parse_targets_from_env(),
+            keep_full_precision_weights: parse_flag_from_env("DEEPSEEK_OCR_QUANT_KEEP_FLOAT"),
+            verbose_per_layer: parse_flag_from_env("DEEPSEEK_OCR_QUANT_VERBOSE"),
+            vision: parse_vision_from_env(),
+        }
+    }
+
+    /// Use the explicitly configured settings, falling back to the environment when the model
+    /// entry and command line left quantization unset.
+    pub fn resolve(configured: Option<Self>) -> Self {
+        configured.unwrap_or_else(Self::from_env)
+    }
+
+    pub fn enabled_for(&self, group: LinearLayerGroup) -> bool {
+        if !self.kind.is_enabled() {
+            return false;
+        }
+        match group {
+            LinearLayerGroup::Text => true,
+            LinearLayerGroup::Projector => {
+                matches!(self.targets, QuantizationTargets::TextAndProjector)
+            }
+            LinearLayerGroup::Vision => self.vision.any(),
+        }
+    }
+
+    /// Like [`Self::enabled_for`], but vision linears additionally require their own tower to be
+    /// selected.
+    pub fn enabled_for_module(&self, group: LinearLayerGroup, module: QuantModule) -> bool {
+        self.enabled_for(group)
+            && match module {
+                QuantModule::Vision(tower) => self.vision.enabled(tower),
+                _ => true,
+            }
+    }
+}
+
+fn parse_kind_from_env() -> QuantizationKind {
+    match env::var("DEEPSEEK_OCR_QUANT") {
+        Ok(value) => match value.trim().to_ascii_lowercase().as_str() {
+            "" | "none" => QuantizationKind::None,
+            "q8_0" | "q8" | "q8.0" => QuantizationKind::Q8_0,
+            "q4_k" | "q4k" => {
+                warn!("DEEPSEEK_OCR_QUANT=Q4_K not yet implemented, falling back to float weights");
+                QuantizationKind::Q4K
+            }
+            other => {
+                warn!(
+                    "unsupported DEEPSEEK_OCR_QUANT value `{other}`, expected none|Q8_0|Q4_K; disabling quantization"
+                );
+                QuantizationKind::None
+            }
+        },
+        Err(_) => QuantizationKind::None,
+    }
+}
+
+fn parse_targets_from_env() -> QuantizationTargets {
+    match env::var("DEEPSEEK_OCR_QUANT_TARGETS") {
+        Ok(value) => match value.trim().to_ascii_lowercase().as_str() {
+            "" | "text" => QuantizationTargets::Text,
+            "text+projector" | "text,projector" | "projector+text" => {
+                QuantizationTargets::TextAndProjector
+            }
+            other => {
+                warn!("unsupported DEEPSEEK_OCR_QUANT_TARGETS value `{other}`, defaulting to text");
+                QuantizationTargets::Text
+            }
+        },
+        Err(_) => QuantizationTargets::Text,
+    }
+}
+
+fn parse_vision_from_env() -> VisionQuantization {
+    let Ok(value) = env::var("DEEPSEEK_OCR_QUANT_VISION") else {
+        return VisionQuantization::default();
+    };
+    let mut selection = VisionQuantization::default();
+    for token in value.split([',', '+']).map(str::trim) {
+        match token.to_ascii_lowercase().as_str() {
+            "" | "none" => {}
+            "all" => {
+                selection = VisionQuantization {
+                    sam: true,
+                    clip: true,
+                    siglip: true,
+                }
+            }
+            other => match VisionTower::from_str(other, true) {
+                Ok(tower) => selection.set(tower, true),
+                Err(_) => warn!(
+                    "unsupported DEEPSEEK_OCR_QUANT_VISION entry `{other}`, expected sam|clip|siglip"
+                ),
+            },
+        }
+    }
+    selection
+}
+
+fn parse_flag_from_env(name: &str) -> bool {
+    match env::var(name) {
+        Ok(value) => matches!(value.trim(), "" | "1" | "true" | "TRUE"),
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,172 @@
+//! Reading order of grounded blocks.
+//!
+//! The model usually emits blocks in reading order, but on multi-column pages it sometimes
+//! jumps between columns. [`reading_order`] rebuilds the order from the boxes alone: running
+//! headers come first, then the body in recursive XY-cut order, then running footers. The cut
+//! prefers columns — it splits at vertical gaps that no block crosses, and only when there is
+//! none at horizontal gaps, merging neighbouring rows whose column gaps line up — so a
+//! full-width title above two columns is read first and each column is read top to bottom
+//! before the next. Blocks that overlap every way are read top to
+//! bottom, left to right.
+
+use std::fmt;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Bands at the top and bottom of a page, as a fraction of its height, that hold running
+/// headers and footers.
+const MARGIN_BAND: f32 = 0.06;
+/// Boxes overlapping by less than this fraction of the page size still count as separated,
+/// since grounding boxes of neighbouring columns often touch.
+const OVERLAP_TOLERANCE: f32 = 0.01;
+
+/// Which order blocks are exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadingOrder {
+    /// Order rebuilt from the block boxes.
+    #[default]
+    Layout,
+    /// The order the model emitted the blocks in.
+    Native,
+}
+
+impl fmt::Display for ReadingOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Layout => "layout",
+            Self::Native => "native",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Where on the page a block sits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageRegion {
+    /// Running header above the body, such as a journal name.
+    Header,
+    #[default]
+    Body,
+    /// Running footer below the body, such as a page number.
+    Footer,
+}
+
+/// Classify a block with grounding `label` and pixel box `bbox` on a page `height` pixels
+/// tall. Header, footer and page-number labels decide; otherwise a short text block inside the
+/// top or bottom band counts as a header or footer. Titles, figures and tables are always body.
+pub fn page_region(label: &str, bbox: [u32; 4], lines: usize, height: u32) -> PageRegion {
+    match label {
+        "header" | "page_header" => return PageRegion::Header,
+        "footer" | "page_footer" | "page_number" | "number" => return PageRegion::Footer,
+        "title" | "sub_title" | "subtitle" | "image" | "figure" | "table" => {
+            return PageRegion::Body;
+        }
+        _ => {}
+    }
+    if lines == 0 || lines > 2 || height == 0 {
+        return PageRegion::Body;
+    }
+    let band = MARGIN_BAND * height as f32;
+    if bbox[3] as f32 <= band {
+        PageRegion::Header
+    } else if bbox[1] as f32 >= height as f32 - band {
+        PageRegion::Footer
+    } else {
+        PageRegion::Body
+    }
+}
+
+/// Indices of `boxes` in reading order on a `width`×`height` page: blocks of the header region
+/// first, then the body, then the footer, each in XY-cut order. `regions` pairs with `boxes`.
+pub fn reading_order(
+    boxes: &[[u32; 4]],
+    regions: &[PageRegion],
+    width: u32,
+    height: u32,
+) -> Vec<usize> {
+    let tolerance = [
+        (OVERLAP_TOLERANCE * width as f32) as u32,
+        (OVERLAP_TOLERANCE * height as f32) as u32,
+    ];
+    let mut order = Vec::with_capacity(boxes.len());
+    for region in [PageRegion::Header, PageRegion::Body, PageRegion::Footer] {
+        let members: Vec<usize> = (0..boxes.len())
+            .filter(|&idx| regions.get(idx).copied().unwrap_or_default() == region)
+            .collect();
+        xy_cut(boxes, members, tolerance, &mut order);
+    }
+    order
+}
+
+/// Append `members` to `order`, cutting columns before rows.
+fn xy_cut(boxes: &[[u32; 4]], members: Vec<usize>, tolerance: [u32; 2], order: &mut Vec<usize>) {
+    if members.len() <= 1 {
+        order.extend(members);
+        return;
+    }
+    let columns = split(boxes, &members, 0, tolerance[0]);
+    if columns.len() > 1 {
+        for column in columns {
+            xy_cut(boxes, column, tolerance, order);
+        }
+        return;
+    }
+    let rows = split(boxes, &members, 1, tolerance[1]);
+    if rows.len() > 1 {
+        // Rows of side-by-side blocks whose gaps line up belong to the same columns: merge
+        // them so each column is read to its end before the next.
+        let mut bands: Vec<Vec<usize>> = Vec::with_capacity(rows.len());
+        for row in rows {
+            if let Some(band) = bands.last_mut() {
+                let mut merged = band.clone();
+                merged.extend(&row);
+                if split(boxes, &merged, 0, tolerance[0]).len() > 1 {
+                    merged.sort_unstable();
+                    *band = merged;
+                    continue;
+                }
+            }
+            bands.push(row);
+        }
+        for band in bands {
+            xy_cut(boxes, band, tolerance, order);
+        }
+        return;
+    }
+    let mut members = members;
+    members.sort_by_key(|&idx| (boxes[idx][1], boxes[idx][0]));
+    order.extend(members);
+}
+
+/// Split `members` into runs along `axis` separated by gaps no box crosses, in ascending
+/// order. Each box is shrunk by `tolerance` at both ends first, unless that would empty it.
+fn split(boxes: &[[u32; 4]], members: &[usize], axis: usize, tolerance: u32) -> Vec<Vec<usize>> {
+    let span = |idx: usize| {
+        let (start, end) = (boxes[idx][axis], boxes[idx][axis + 2]);
+        if end - start > 2 * tolerance {
+            (start + tolerance, end - tolerance)
+        } else {
+            (start, end)
+        }
+    };
+    let mut sorted = members.to_vec();
+    sorted.sort_by_key(|&idx| span(idx));
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut reach = 0;
+    for idx in sorted {
+        let (start, end) = span(idx);
+        match groups.last_mut() {
+            Some(group) if start < reach => group.push(idx),
+            _ => groups.push(vec![idx]),
+        }
+        reach = reach.max(end);
+    }
+    // Keep the model's order inside each group so ties resolve the way it emitted them.
+    for group in &mut groups {
+        group.sort_unstable();
+    }
+    groups
+}
@@ -0,0 +1,292 @@
+//! Pillow-compatible bicubic resampling for 8-bit RGB images.
+//!
+//! Both vision preprocessors must reproduce `PIL.Image.resize(..., BICUBIC)` bit for bit, since
+//! the reference pipelines feed PIL output to the encoders. This is Pillow's separable fixed-point
+//! convolution: a horizontal pass into an intermediate buffer, then a vertical pass. Rows of each
+//! pass run in parallel on the rayon pool, and coefficient tables are cached per
+//! `(input, output)` length so tiles of the same size share them.
+//!
+//! The vertical pass, which touches every byte of the intermediate buffer once per tap, is
+//! written against `wide` vectors; the horizontal pass gathers interleaved pixels and stays
+//! scalar. Lanes are integers rather than `f32x8`: products reach 2^30, past the 24 bits an `f32`
+//! holds exactly, and Pillow parity needs every rounding to match. `benches/resample.rs` covers
+//! both passes.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use image::RgbImage;
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use wide::{i16x8, i32x4, u8x16};
+
+const PRECISION_BITS: i32 = 32 - 8 - 2;
+const PRECISION_SCALE: f64 = (1 << PRECISION_BITS) as f64;
+const ROUNDING_BIAS: i32 = 1 << (PRECISION_BITS - 1);
+/// Coefficient tables kept before the cache is flushed.
+const MAX_CACHED_TABLES: usize = 64;
+/// Rows handed to one rayon task; small images stay on the calling thread.
+const MIN_ROWS_PER_TASK: usize = 16;
+
+type CoeffCache = HashMap<(usize, usize), Arc<ResampleCoeffs>>;
+
+static COEFF_CACHE: Lazy<Mutex<CoeffCache>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Integer filter weights for every output position along one axis.
+struct ResampleCoeffs {
+    /// First input index and tap count per output position.
+    bounds: Vec<(usize, usize)>,
+    coeffs: Vec<i32>,
+    ksize: usize,
+}
+
+impl ResampleCoeffs {
+    fn taps(&self, index: usize) -> (usize, &[i32]) {
+        let (start, len) = self.bounds[index];
+        let offset = index * self.ksize;
+        (start, &self.coeffs[offset..offset + len])
+    }
+}
+
+/// Resize `source` to `width × height` exactly as Pillow's bicubic filter does.
+pub fn resize_bicubic(source: &RgbImage, width: u32, height: u32) -> RgbImage {
+    let (src_width, src_height) = (source.width() as usize, source.height() as usize);
+    let (dst_width, dst_height) = (width as usize, height as usize);
+    if dst_width == 0 || dst_height == 0 {
+        return RgbImage::new(width, height);
+    }
+
+    // At equal sizes the kernel collapses to the identity, so the pass can be skipped.
+    let horizontal: Cow<[u8]> = if src_width == dst_width {
+        Cow::Borrowed(source.as_raw())
+    } else {
+        let coeffs = coefficients(src_width, dst_width);
+        let mut buffer = vec![0u8; src_height * dst_width * 3];
+        buffer
+            .par_chunks_mut(dst_width * 3)
+            .zip(source.as_raw().par_chunks(src_width * 3))
+            .with_min_len(MIN_ROWS_PER_TASK)
+            .for_each(|(out_row, in_row)| resample_row(in_row, out_row, &coeffs));
+        Cow::Owned(buffer)
+    };
+
+    let output = if src_height == dst_height {
+        horizontal.into_owned()
+    } else {
+        let coeffs = coefficients(src_height, dst_height);
+        let stride = dst_width * 3;
+        let mut buffer = vec![0u8; dst_height * stride];
+        buffer
+            .par_chunks_mut(stride)
+            .enumerate()
+            .with_min_len(MIN_ROWS_PER_TASK)
+            .for_each_init(
+                || Accumulator::new(stride),
+                |acc, (dst_y, out_row)| {
+                    let (start, weights) = coeffs.taps(dst_y);
+                    let rows = &horizontal[start * stride..(start + weights.len()) * stride];
+                    acc.resample(rows, stride, weights, out_row);
+                },
+            );
+        buffer
+    };
+
+    RgbImage::from_raw(width, height, output).expect("invalid resized image dimensions")
+}
+
+/// Horizontal pass over one row of interleaved RGB.
+fn resample_row(in_row: &[u8], out_row: &mut [u8], coeffs: &ResampleCoeffs) {
+    for (dst_x, out) in out_row.chunks_exact_mut(3).enumerate() {
+        let (start, weights) = coeffs.taps(dst_x);
+        let pixels = &in_row[start * 3..(start + weights.len()) * 3];
+        let mut acc = [ROUNDING_BIAS; 3];
+        for (pixel, &weight) in pixels.chunks_exact(3).zip(weights) {
+            acc[0] += pixel[0] as i32 * weight;
+            acc[1] += pixel[1] as i32 * weight;
+            acc[2] += pixel[2] as i32 * weight;
+        }
+        out.copy_from_slice(&acc.map(clip8));
+    }
+}
+
+/// Vertical-pass sums for one output row, four bytes per `i32x4`.
+///
+/// Taps are taken in pairs: the bytes of two input rows are interleaved into `i16` lanes so a
+/// single `dot` (`pmaddwd` on x86) multiplies and adds both. `i16` lanes cannot hold a whole
+/// coefficient, so each is split into its low 15 bits and the rest, summed apart and recombined
+/// with wrapping arithmetic; the total is exact because the true sum fits in `i32`.
+struct Accumulator {
+    low: Vec<i32x4>,
+    high: Vec<i32x4>,
+    /// Stands in for the second row of an odd last tap, with weight zero.
+    blank: Vec<u8>,
+}
+
+impl Accumulator {
+    fn new(stride: usize) -> Self {
+        let lanes = stride.div_ceil(16) * 4;
+        Self {
+            low: vec![i32x4::ZERO; lanes],
+            high: vec![i32x4::ZERO; lanes],
+            blank: vec![0; stride],
+        }
+    }
+
+    fn resample(&mut self, rows: &[u8], stride: usize, weights: &[i32], out_row: &mut [u8]) {
+        self.low.fill(i32x4::ZERO);
+        self.high.fill(i32x4::ZERO);
+        for (index, pair) in weights.chunks(2).enumerate() {
+            let first = &rows[2 * index * stride..][..stride];
+            let second = match pair {
+                [_, _] => &rows[(2 * index + 1) * stride..][..stride],
+                _ => &self.blank[..],
+            };
+            let split = |weight: i32| ((weight & 0x7fff) as i16, (weight >> 15) as i16);
+            let (low0, high0) = split(pair[0]);
+            let (low1, high1) = split(pair.get(1).copied().unwrap_or(0));
+            let low_weights = i16x8::from([low0, low1, low0, low1, low0, low1, low0, low1]);
+            let high_weights =
+                i16x8::from([high0, high1, high0, high1, high0, high1, high0, high1]);
+            let mut add = |column: usize, a: u8x16, b: u8x16| {
+                let (front, back) = (u8x16::unpack_low(a, b), u8x16::unpack_high(a, b));
+                let pairs = [
+                    i16x8::from_u8x16_low(front),
+                    i16x8::from_u8x16_high(front),
+                    i16x8::from_u8x16_low(back),
+                    i16x8::from_u8x16_high(back),
+                ];
+                let low = &mut self.low[column * 4..][..4];
+                let high = &mut self.high[column * 4..][..4];
+                for ((low, high), pair) in low.iter_mut().zip(high).zip(pairs) {
+                    *low += pair.dot(low_weights);
+                    *high += pair.dot(high_weights);
+                }
+            };
+            let (a_chunks, b_chunks) = (first.chunks_exact(16), second.chunks_exact(16));
+            let (a_tail, b_tail) = (a_chunks.remainder(), b_chunks.remainder());
+            let full = stride / 16;
+            for (column, (a, b)) in a_chunks.zip(b_chunks).enumerate() {
+                let load = |bytes: &[u8]| u8x16::from(<[u8; 16]>::try_from(bytes).unwrap());
+                add(column, load(a), load(b));
+            }
+            if !a_tail.is_empty() {
+                add(full, load_tail(a_tail), load_tail(b_tail));
+            }
+        }
+        let bias = i32x4::splat(ROUNDING_BIAS);
+        for ((out, &low), &high) in out_row.chunks_mut(4).zip(&self.low).zip(&self.high) {
+            let high: i32x4 = high << 15;
+            let sum: i32x4 = (low + high + bias) >> PRECISION_BITS;
+            let clipped = sum.max(i32x4::ZERO).min(i32x4::splat(255));
+            for (out, &value) in out.iter_mut().zip(clipped.as_array_ref()) {
+                *out = value as u8;
+            }
+        }
+    }
+}
+
+/// Fewer than sixteen bytes as a vector, zero-filled.
+fn load_tail(bytes: &[u8]) -> u8x16 {
+    let mut lanes = [0u8; 16];
+    lanes[..bytes.len()].copy_from_slice(bytes);
+    u8x16::from(lanes)
+}
+
+fn clip8(value: i32) -> u8 {
+    (value >> PRECISION_BITS).clamp(0, 255) as u8
+}
+
+fn coefficients(input_size: usize, output_size: usize) -> Arc<ResampleCoeffs> {
+    let key = (input_size, output_size);
+    if let Some(coeffs) = COEFF_CACHE
+        .lock()
+        .ok()
+        .and_then(|cache| cache.get(&key).cloned())
+    {
+        return coeffs;
+    }
+    let coeffs = Arc::new(compute_resample_coeffs(input_size, output_size));
+    if let Ok(mut cache) = COEFF_CACHE.lock() {
+        if cache.len() >= MAX_CACHED_TABLES {
+            cache.clear();
+        }
+        cache.insert(key, Arc::clone(&coeffs));
+    }
+    coeffs
+}
+
+fn round_half_towards_zero(value: f64) -> isize {
+    if value >= 0.0 {
+        (value + 0.5).floor() as isize
+    } else {
+        (value + 0.5).ceil() as isize
+    }
+}
+
+fn bicubic_kernel(value: f64) -> f64 {
+    const A: f64 = -0.5;
+    let x = value.abs();
+    if x < 1.0 {
+        ((A + 2.0) * x - (A + 3.0)) * x * x + 1.0
+    } else if x < 2.0 {
+        (((x - 5.0) * x + 8.0) * x - 4.0) * A
+    } else {
+        0.0
+    }
+}
+
+fn compute_resample_coeffs(input_size: usize, output_size: usize) -> ResampleCoeffs {
+    let scale = input_size as f64 / output_size as f64;
+    let filterscale = scale.max(1.0);
+    let support = 2.0 * filterscale;
+    let ksize = support.ceil() as usize * 2 + 1;
+
+    let mut bounds = Vec::with_capacity(output_size);
+    let mut coeffs = vec![0i32; output_size * ksize];
+    let mut weights = vec![0.0f64; ksize];
+
+    for out_index in 0..output_size {
+        let center = (out_index as f64 + 0.5) * scale;
+        let mut xmin = round_half_towards_zero(center - support).max(0);
+        let mut xmax = round_half_towards_zero(center + support).min(input_size as isize);
+        if xmin >= input_size as isize {
+            xmin = input_size.saturating_sub(1) as isize;
+        }
+        if xmax <= xmin {
+            xmax = xmin + 1;
+        }
+        let length = (xmax - xmin) as usize;
+        let ss = 1.0 / filterscale;
+        weights.fill(0.0);
+        let mut sum = 0.0;
+        for (i, weight) in weights.iter_mut().enumerate().take(length) {
+            *weight = bicubic_kernel((xmin as f64 + i as f64 - center + 0.5) * ss);
+            sum += *weight;
+        }
+        if sum != 0.0 {
+            for weight in &mut weights[..length] {
+                *weight /= sum;
+            }
+        }
+        for (dst, &weight) in coeffs[out_index * ksize..(out_index + 1) * ksize]
+            .iter_mut()
+            .zip(&weights)
+        {
+            *dst = if weight < 0.0 {
+                (-0.5 + weight * PRECISION_SCALE) as i32
+            } else {
+                (0.5 + weight * PRECISION_SCALE) as i32
+            };
+        }
+        bounds.push((xmin as usize, length));
+    }
+
+    ResampleCoeffs {
+        bounds,
+        coeffs,
+        ksize,
+    }
+}
@@ -38,6 +38,17 @@ pub fn prepare_device_and_dtype(
     Ok((device, dtype))
 }
 
+/// Short backend name used in log lines.
+pub fn backend_label(device: &Device) -> &'static str {
+    if device.is_cuda() {
+        "CUDA"
+    } else if device.is_metal() {
+        "Metal"
+    } else {
+        "CPU"
+    }
+}
+
 pub fn default_dtype_for_device(device: &Device) -> DType {
     if device.is_metal() || device.is_cuda() {
         DType::F16
@@ -53,3 +64,13 @@ pub fn dtype_from_precision(p: Precision) -> DType {
         Precision::Bf16 => DType::BF16,
     }
 }
+
+/// DType a run on `device` ends up with, as picked by [`prepare_device_and_dtype`] and
+/// [`default_dtype_for_device`], without initialising the device.
+pub fn planned_dtype(device: DeviceKind, precision: Option<Precision>) -> DType {
+    match (precision, device) {
+        (Some(precision), _) => dtype_from_precision(precision),
+        (None, DeviceKind::Cpu) => DType::F32,
+        (None, DeviceKind::Metal | DeviceKind::Cuda) => DType::F16,
+    }
+}
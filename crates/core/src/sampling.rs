@@ -22,6 +22,29 @@ pub trait TokenSelectionParams {
     fn no_repeat_ngram_size(&self) -> Option<usize>;
 }
 
+/// Restricts the tokens decoding may produce, for example to a grammar.
+pub trait TokenConstraint: Send + Sync + std::fmt::Debug {
+    /// Set the logits of the tokens that may not follow `generated` to negative infinity.
+    /// `eos` is the end-of-sequence token; it stays allowed only once the output is complete.
+    fn mask(&self, generated: &[i64], eos: Option<i64>, logits: &mut [f32]);
+}
+
+/// `logits` with the tokens `constraint` forbids after `generated` masked out.
+pub fn constrain_logits(
+    logits: &Tensor,
+    constraint: &dyn TokenConstraint,
+    generated: &[i64],
+    eos: Option<i64>,
+) -> Result<Tensor> {
+    let mut values = logits
+        .to_dtype(DType::F32)?
+        .to_vec1::<f32>()
+        .context("failed to extract logits for constrained decoding")?;
+    constraint.mask(generated, eos, &mut values);
+    let len = values.len();
+    Ok(Tensor::from_vec(values, len, logits.device())?)
+}
+
 /// Create a deterministic RNG when a seed is provided.
 pub fn init_rng(seed: Option<u64>) -> StdRng {
     match seed {
@@ -0,0 +1,269 @@
+//! Running OCR over several regions of one image and stitching the outputs back together.
+//!
+//! Two-page spreads and multi-column pages are split at their gutters (see [`crate::layout`]);
+//! very tall pages are cut into overlapping slices (see [`crate::slicing`]). Each region is
+//! decoded with the same prompt; [`SegmentedImage::merge`] then offsets grounding boxes back into
+//! full-image coordinates and, for overlapping regions, drops the text both neighbours read.
+
+use anyhow::Result;
+use image::DynamicImage;
+use serde::Serialize;
+use tokenizers::Tokenizer;
+
+use crate::{
+    cancellation::CancellationToken,
+    grounding::{map_boxes, strip_boxes},
+    inference::{DecodeOutcome, DecodeParameters, OcrEngine, VisionSettings},
+    layout::{LayoutConfig, plan_layout},
+    slicing::{SliceConfig, plan_slices},
+};
+
+/// Longest run of repeated lines searched for where two overlapping segments meet.
+const MAX_OVERLAP_LINES: usize = 40;
+/// Cut-off lines tolerated at each side of a seam before the repeated run starts.
+const MAX_SEAM_SKIP: usize = 1;
+/// A repeated run must carry at least this much text, so stray separators never match.
+const MIN_SEAM_CHARS: usize = 8;
+
+/// Pixel rectangle of the full image covered by one segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// One OCR unit cut from a larger image.
+#[derive(Debug, Clone)]
+pub struct ImageSegment {
+    pub image: DynamicImage,
+    pub region: Region,
+}
+
+/// An image split into OCR units, in reading order.
+#[derive(Debug, Clone)]
+pub struct SegmentedImage {
+    pub width: u32,
+    pub height: u32,
+    pub segments: Vec<ImageSegment>,
+    /// Neighbouring segments share a strip of pixels whose text must be de-duplicated.
+    pub overlapping: bool,
+}
+
+impl SegmentedImage {
+    /// The whole image as a single segment.
+    pub fn single(image: DynamicImage) -> Self {
+        let (width, height) = (image.width(), image.height());
+        Self {
+            width,
+            height,
+            segments: vec![ImageSegment {
+                image,
+                region: Region {
+                    x: 0,
+                    y: 0,
+                    width,
+                    height,
+                },
+            }],
+            overlapping: false,
+        }
+    }
+
+    /// Crop `regions` out of `image`; a single segment when there is at most one region.
+    pub fn from_regions(image: DynamicImage, regions: Vec<Region>, overlapping: bool) -> Self {
+        if regions.len() <= 1 {
+            return Self::single(image);
+        }
+        let segments = regions
+            .into_iter()
+            .map(|region| ImageSegment {
+                image: image.crop_imm(region.x, region.y, region.width, region.height),
+                region,
+            })
+            .collect();
+        Self {
+            width: image.width(),
+            height: image.height(),
+            segments,
+            overlapping,
+        }
+    }
+
+    pub fn is_split(&self) -> bool {
+        self.segments.len() > 1
+    }
+
+    pub fn regions(&self) -> Vec<Region> {
+        self.segments.iter().map(|segment| segment.region).collect()
+    }
+
+    /// Combine per-segment outputs (one per segment, same order) into text for the full image.
+    pub fn merge(&self, outputs: &[String]) -> String {
+        let remapped: Vec<String> = self
+            .segments
+            .iter()
+            .zip(outputs)
+            .map(|(segment, text)| self.to_full_image(segment.region, text))
+            .collect();
+        if self.overlapping {
+            merge_overlapping(&remapped)
+        } else {
+            remapped
+                .iter()
+                .map(|text| text.trim())
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        }
+    }
+
+    /// The part of [`Self::merge`] over the first `outputs` that later segments cannot change.
+    /// A seam drops at most `MAX_SEAM_SKIP` trailing lines of the text before it, so those are
+    /// held back until the next segment is merged.
+    pub fn settled(&self, outputs: &[String]) -> String {
+        let merged = self.merge(outputs);
+        if !self.overlapping || outputs.len() >= self.segments.len() {
+            return merged;
+        }
+        let lines: Vec<&str> = merged.lines().collect();
+        lines[..lines.len().saturating_sub(MAX_SEAM_SKIP)].join("\n")
+    }
+
+    /// Offset grounding boxes from segment-relative to full-image coordinates.
+    fn to_full_image(&self, region: Region, text: &str) -> String {
+        let (width, height) = (self.width as f32, self.height as f32);
+        map_boxes(text, |[x0, y0, x1, y1]| {
+            let x = |value: f32| (region.x as f32 + value * region.width as f32) / width;
+            let y = |value: f32| (region.y as f32 + value * region.height as f32) / height;
+            [x(x0), y(y0), x(x1), y(y1)]
+        })
+    }
+}
+
+/// Split `image` into OCR units: the layout split wins when it finds a gutter, otherwise tall
+/// images are sliced. Both are opt-in; with neither enabled the image stays whole.
+pub fn segment_image(
+    image: DynamicImage,
+    layout: &LayoutConfig,
+    slicing: &SliceConfig,
+) -> SegmentedImage {
+    let columns = plan_layout(&image, layout);
+    if columns.len() > 1 {
+        return SegmentedImage::from_regions(image, columns, false);
+    }
+    let slices = plan_slices(image.width(), image.height(), slicing);
+    SegmentedImage::from_regions(image, slices, true)
+}
+
+/// Decode `prompt` once per segment and merge the results.
+///
+/// The prompt must reference exactly one image. Token counts are summed over segments and
+/// `generated_tokens` is the concatenation of every segment's tokens, so it no longer decodes to
+/// the merged `text`. After each segment, `progress` receives [`SegmentedImage::settled`] text,
+/// which only grows. Once `cancel` fires, the current segment stops early and the remaining ones
+/// are skipped; the text merged so far is returned.
+#[allow(clippy::too_many_arguments)]
+pub fn decode_segmented(
+    engine: &dyn OcrEngine,
+    tokenizer: &Tokenizer,
+    prompt: &str,
+    segmented: &SegmentedImage,
+    vision: VisionSettings,
+    params: &DecodeParameters,
+    progress: Option<&dyn Fn(&str)>,
+    cancel: Option<&CancellationToken>,
+) -> Result<DecodeOutcome> {
+    let mut outputs = Vec::with_capacity(segmented.segments.len());
+    let mut merged = DecodeOutcome {
+        text: String::new(),
+        prompt_tokens: 0,
+        response_tokens: 0,
+        generated_tokens: Vec::new(),
+    };
+    for segment in &segmented.segments {
+        if cancel.is_some_and(CancellationToken::is_cancelled) {
+            break;
+        }
+        let outcome = engine.decode(
+            tokenizer,
+            prompt,
+            std::slice::from_ref(&segment.image),
+            vision,
+            params,
+            None,
+            cancel,
+        )?;
+        merged.prompt_tokens += outcome.prompt_tokens;
+        merged.response_tokens += outcome.response_tokens;
+        merged.generated_tokens.extend(outcome.generated_tokens);
+        outputs.push(outcome.text);
+        if let Some(progress) = progress {
+            progress(&segmented.settled(&outputs));
+        }
+    }
+    merged.text = segmented.merge(&outputs);
+    Ok(merged)
+}
+
+/// Join consecutive outputs, dropping the lines each pair repeats across their shared strip.
+///
+/// Lines are compared with grounding boxes stripped and whitespace collapsed. Up to
+/// `MAX_SEAM_SKIP` lines cut by a segment edge may sit between the repeated run and the seam;
+/// they are dropped too, since the neighbouring segment reads them whole.
+pub fn merge_overlapping(outputs: &[String]) -> String {
+    let mut merged: Vec<String> = Vec::new();
+    for output in outputs {
+        let lines: Vec<String> = output.trim().lines().map(str::to_owned).collect();
+        if lines.is_empty() {
+            continue;
+        }
+        match find_seam(&merged, &lines) {
+            Some((keep, skip)) => {
+                merged.truncate(keep);
+                merged.extend(lines.into_iter().skip(skip));
+            }
+            None => {
+                if !merged.is_empty() {
+                    merged.push(String::new());
+                }
+                merged.extend(lines);
+            }
+        }
+    }
+    merged.join("\n")
+}
+
+/// How many lines of `previous` to keep and of `next` to skip, for the longest repeated run.
+fn find_seam(previous: &[String], next: &[String]) -> Option<(usize, usize)> {
+    let key = |line: &String| {
+        strip_boxes(line)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let previous_keys: Vec<String> = previous.iter().map(key).collect();
+    let next_keys: Vec<String> = next.iter().map(key).collect();
+    let mut best: Option<(usize, usize, usize)> = None;
+    for skip_previous in 0..=MAX_SEAM_SKIP {
+        for skip_next in 0..=MAX_SEAM_SKIP {
+            let end = previous_keys.len().saturating_sub(skip_previous);
+            let available = end
+                .min(next_keys.len().saturating_sub(skip_next))
+                .min(MAX_OVERLAP_LINES);
+            for run in (1..=available).rev() {
+                let tail = &previous_keys[end - run..end];
+                let head = &next_keys[skip_next..skip_next + run];
+                let chars: usize = tail.iter().map(String::len).sum();
+                if tail == head && chars >= MIN_SEAM_CHARS {
+                    if best.is_none_or(|(best_run, ..)| run > best_run) {
+                        best = Some((run, end, skip_next + run));
+                    }
+                    break;
+                }
+            }
+        }
+    }
+    best.map(|(_, keep, skip)| (keep, skip))
+}
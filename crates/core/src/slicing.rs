@@ -0,0 +1,71 @@
+//! Opt-in slicing of very tall images such as receipts and scrolling screenshots.
+//!
+//! Squeezing a 1080×12000 capture into the crop grid leaves text a few pixels high. Instead the
+//! image is cut into overlapping full-width slices that are OCR'd one by one; the overlap lets
+//! [`crate::segments::SegmentedImage::merge`] drop lines both neighbours read.
+
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::segments::{Region, SegmentedImage};
+
+/// When and how tall images are sliced. Off by default.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SliceConfig {
+    pub enabled: bool,
+    /// Only images at least this many times taller than wide are sliced.
+    pub min_aspect_ratio: f32,
+    /// Height of each slice relative to the image width.
+    pub slice_aspect_ratio: f32,
+    /// Fraction of each slice shared with its neighbour.
+    pub overlap: f32,
+}
+
+impl Default for SliceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_aspect_ratio: 3.0,
+            slice_aspect_ratio: 1.5,
+            overlap: 0.15,
+        }
+    }
+}
+
+/// Vertical extents of the slices for a `width × height` image; a single full-height slice when
+/// the image is not tall enough (or slicing is disabled).
+pub fn plan_slices(width: u32, height: u32, config: &SliceConfig) -> Vec<Region> {
+    let full = Region {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    };
+    if !config.enabled || width == 0 || (height as f32) < width as f32 * config.min_aspect_ratio {
+        return vec![full];
+    }
+    let slice_height = ((width as f32 * config.slice_aspect_ratio).round() as u32).clamp(1, height);
+    let overlap = (slice_height as f32 * config.overlap.clamp(0.0, 0.5)).round() as u32;
+    let stride = (slice_height - overlap).max(1);
+    let count = (height - slice_height).div_ceil(stride) + 1;
+    if count <= 1 {
+        return vec![full];
+    }
+    // Spread the slices evenly so the last one ends exactly at the bottom edge.
+    let span = (height - slice_height) as f64;
+    (0..count)
+        .map(|idx| Region {
+            x: 0,
+            y: (span * idx as f64 / (count - 1) as f64).round() as u32,
+            width,
+            height: slice_height,
+        })
+        .collect()
+}
+
+/// Cut `image` into the slices planned by [`plan_slices`].
+pub fn slice_tall_image(image: DynamicImage, config: &SliceConfig) -> SegmentedImage {
+    let regions = plan_slices(image.width(), image.height(), config);
+    SegmentedImage::from_regions(image, regions, true)
+}
@@ -0,0 +1,192 @@
+//! Tables in model output, normalised into cell grids.
+//!
+//! DeepSeek-OCR writes tables as HTML (`<table>` with `rowspan`/`colspan`) or markdown pipe
+//! tables; PaddleOCR-VL's table task emits OTSL tokens (`<fcel>`, `<ecel>`, `<lcel>`, `<ucel>`,
+//! `<xcel>`, `<nl>`). All three become a [`Table`]: spanning cells are anchored at their
+//! top-left position and the grid is padded to a rectangle. Tables can be written as CSV,
+//! JSON or XLSX with [`TableFormat::render`].
+
+mod parse;
+pub mod xlsx;
+
+use std::{fmt, path::Path};
+
+use anyhow::{Result, bail};
+use serde::Serialize;
+
+use crate::grounding::parse_grounded;
+
+/// A table with every cell placed on a `rows`×`columns` grid.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Table {
+    pub rows: usize,
+    pub columns: usize,
+    /// Cells in row-major order of their top-left position.
+    pub cells: Vec<TableCell>,
+    /// Union of the grounding boxes of the block holding the table, in `0..=999` units.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<[f32; 4]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TableCell {
+    pub row: usize,
+    pub column: usize,
+    pub row_span: usize,
+    pub column_span: usize,
+    /// Whether the cell is a header (`<th>`, `<thead>` or the markdown header row).
+    pub header: bool,
+    pub text: String,
+}
+
+impl Table {
+    /// Cell texts as a `rows`×`columns` grid. A spanning cell's text sits at its top-left
+    /// position; the positions it covers are empty.
+    pub fn grid(&self) -> Vec<Vec<String>> {
+        let mut grid = vec![vec![String::new(); self.columns]; self.rows];
+        for cell in &self.cells {
+            grid[cell.row][cell.column].clone_from(&cell.text);
+        }
+        grid
+    }
+
+    /// RFC 4180 CSV of [`Table::grid`], with CRLF line endings.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        for row in self.grid() {
+            let fields: Vec<_> = row.iter().map(|field| csv_field(field)).collect();
+            out.push_str(&fields.join(","));
+            out.push_str("\r\n");
+        }
+        out
+    }
+
+    /// GitHub-flavoured markdown pipe table with the first row as its header, or `None` when a
+    /// cell spans several rows or columns, which pipe tables cannot express.
+    pub fn to_markdown(&self) -> Option<String> {
+        if self
+            .cells
+            .iter()
+            .any(|cell| cell.row_span > 1 || cell.column_span > 1)
+        {
+            return None;
+        }
+        let grid = self.grid();
+        let mut lines = Vec::with_capacity(grid.len() + 1);
+        for (idx, row) in grid.iter().enumerate() {
+            let fields: Vec<_> = row.iter().map(|field| field.replace('|', "\\|")).collect();
+            lines.push(format!("| {} |", fields.join(" | ")));
+            if idx == 0 {
+                lines.push(format!("|{}", " --- |".repeat(self.columns)));
+            }
+        }
+        Some(lines.join("\n"))
+    }
+
+    /// Build a table from cells whose positions are already resolved. As in HTML, column spans
+    /// widen the grid while row spans are cut off at the last row holding a cell.
+    fn from_cells(mut cells: Vec<TableCell>, bbox: Option<[f32; 4]>) -> Option<Self> {
+        if cells.is_empty() {
+            return None;
+        }
+        cells.sort_by_key(|cell| (cell.row, cell.column));
+        let rows = cells.iter().map(|cell| cell.row + 1).max().unwrap_or(0);
+        let columns = cells
+            .iter()
+            .map(|cell| cell.column + cell.column_span.max(1))
+            .max()
+            .unwrap_or(0);
+        for cell in &mut cells {
+            cell.row_span = cell.row_span.clamp(1, rows - cell.row);
+            cell.column_span = cell.column_span.max(1);
+        }
+        Some(Self {
+            rows,
+            columns,
+            cells,
+            bbox,
+        })
+    }
+}
+
+/// Every table in `text`, in output order. Grounded output keeps the box of the block each
+/// table was found in.
+pub fn extract_tables(text: &str) -> Vec<Table> {
+    parse_grounded(text)
+        .into_iter()
+        .flat_map(|block| {
+            let bbox = block.boxes.iter().copied().reduce(|a, b| {
+                [
+                    a[0].min(b[0]),
+                    a[1].min(b[1]),
+                    a[2].max(b[2]),
+                    a[3].max(b[3]),
+                ]
+            });
+            parse::tables(&block.text)
+                .into_iter()
+                .filter_map(move |cells| Table::from_cells(cells, bbox))
+        })
+        .collect()
+}
+
+/// File format for extracted tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    /// One CSV document per table.
+    Csv,
+    /// A JSON array of tables with their cells and spans.
+    Json,
+    /// One workbook with a worksheet per table; spans become merged cells.
+    Xlsx,
+}
+
+impl TableFormat {
+    /// Format named by the extension of `path`.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("csv") => Ok(Self::Csv),
+            Some("json") => Ok(Self::Json),
+            Some("xlsx") => Ok(Self::Xlsx),
+            _ => bail!(
+                "cannot tell the table format of {}; use a .csv, .json or .xlsx extension",
+                path.display()
+            ),
+        }
+    }
+
+    /// Render `tables` as files: CSV yields one per table, the other formats a single file.
+    pub fn render(self, tables: &[Table]) -> Result<Vec<Vec<u8>>> {
+        Ok(match self {
+            Self::Csv => tables
+                .iter()
+                .map(|table| table.to_csv().into_bytes())
+                .collect(),
+            Self::Json => vec![serde_json::to_vec_pretty(tables)?],
+            Self::Xlsx => vec![xlsx::render(tables)?],
+        })
+    }
+}
+
+impl fmt::Display for TableFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+            Self::Xlsx => "xlsx",
+        };
+        f.write_str(name)
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
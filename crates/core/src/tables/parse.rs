@@ -0,0 +1,373 @@
+//! Table markup parsers. Each returns the cells of one table with spans resolved onto grid
+//! positions; [`tables`] finds every table in a block of output.
+
+use std::collections::HashSet;
+
+use super::TableCell;
+
+/// Cell as written in the markup, before it is placed on the grid.
+struct RawCell {
+    text: String,
+    header: bool,
+    row_span: usize,
+    column_span: usize,
+}
+
+/// Span limits HTML applies to `rowspan` and `colspan`.
+const MAX_ROW_SPAN: usize = 65534;
+const MAX_COLUMN_SPAN: usize = 1000;
+
+const OTSL_CELLS: [&str; 5] = ["<fcel>", "<ecel>", "<ched>", "<rhed>", "<srow>"];
+const OTSL_TOKENS: [&str; 9] = [
+    "<fcel>", "<ecel>", "<ched>", "<rhed>", "<srow>", "<lcel>", "<ucel>", "<xcel>", "<nl>",
+];
+
+/// Cells of every HTML, OTSL and markdown table in `text`, in the order they appear.
+pub(super) fn tables(text: &str) -> Vec<Vec<TableCell>> {
+    let mut found = Vec::new();
+    let mut rest = text;
+    while let Some(start) = find_ignore_case(rest, "<table") {
+        scan_plain(&rest[..start], &mut found);
+        let body = &rest[start..];
+        let end = find_ignore_case(body, "</table>")
+            .map(|end| end + "</table>".len())
+            .unwrap_or(body.len());
+        found.push(place(html_rows(&body[..end])));
+        rest = &body[end..];
+    }
+    scan_plain(rest, &mut found);
+    found.retain(|cells| !cells.is_empty());
+    found
+}
+
+/// OTSL or markdown tables in text that holds no HTML table.
+fn scan_plain(text: &str, found: &mut Vec<Vec<TableCell>>) {
+    if OTSL_CELLS.iter().any(|token| text.contains(token)) {
+        found.push(otsl_cells(text));
+        return;
+    }
+    let lines: Vec<&str> = text.lines().map(str::trim).collect();
+    let mut idx = 0;
+    while idx < lines.len() {
+        let is_row = |line: &str| line.starts_with('|');
+        if !(is_row(lines[idx]) && lines.get(idx + 1).is_some_and(|line| is_separator(line))) {
+            idx += 1;
+            continue;
+        }
+        let end = (idx + 2..lines.len())
+            .find(|&end| !is_row(lines[end]))
+            .unwrap_or(lines.len());
+        let rows = [idx]
+            .into_iter()
+            .chain(idx + 2..end)
+            .map(|row| {
+                split_markdown_row(lines[row])
+                    .into_iter()
+                    .map(|text| RawCell {
+                        text: cell_text(&text),
+                        header: row == idx,
+                        row_span: 1,
+                        column_span: 1,
+                    })
+                    .collect()
+            })
+            .collect();
+        found.push(place(rows));
+        idx = end;
+    }
+}
+
+/// Rows of an HTML table. Cells in `<thead>` or written as `<th>` are headers; other tags
+/// inside cells are dropped, with block-level ones turned into spaces.
+fn html_rows(html: &str) -> Vec<Vec<RawCell>> {
+    let mut rows: Vec<Vec<RawCell>> = Vec::new();
+    let mut cell: Option<RawCell> = None;
+    let mut in_head = false;
+    let mut rest = html;
+    loop {
+        let (text, tag) = match rest.find('<') {
+            Some(start) => {
+                let Some(len) = rest[start..].find('>') else {
+                    break;
+                };
+                let tag = &rest[start + 1..start + len];
+                let text = &rest[..start];
+                rest = &rest[start + len + 1..];
+                (text, Some(tag))
+            }
+            None => (std::mem::take(&mut rest), None),
+        };
+        if let Some(cell) = &mut cell {
+            cell.text.push_str(text);
+        }
+        let Some(tag) = tag else {
+            break;
+        };
+        let tag = tag.to_ascii_lowercase();
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|ch: char| ch.is_whitespace() || ch == '/')
+            .next()
+            .unwrap_or_default()
+            .to_owned();
+        match (name.as_str(), closing) {
+            ("thead", _) => in_head = !closing,
+            ("tr", _) => {
+                finish_cell(&mut cell, &mut rows);
+                if !closing {
+                    rows.push(Vec::new());
+                }
+            }
+            ("td" | "th", false) => {
+                finish_cell(&mut cell, &mut rows);
+                cell = Some(RawCell {
+                    text: String::new(),
+                    header: in_head || name == "th",
+                    row_span: span_attribute(&tag, "rowspan", MAX_ROW_SPAN),
+                    column_span: span_attribute(&tag, "colspan", MAX_COLUMN_SPAN),
+                });
+            }
+            ("td" | "th", true) => finish_cell(&mut cell, &mut rows),
+            ("br" | "p" | "div" | "li", _) => {
+                if let Some(cell) = &mut cell {
+                    cell.text.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+    finish_cell(&mut cell, &mut rows);
+    rows
+}
+
+fn finish_cell(cell: &mut Option<RawCell>, rows: &mut Vec<Vec<RawCell>>) {
+    if let Some(mut cell) = cell.take() {
+        cell.text = cell_text(&cell.text);
+        if rows.is_empty() {
+            rows.push(Vec::new());
+        }
+        if let Some(row) = rows.last_mut() {
+            row.push(cell);
+        }
+    }
+}
+
+/// Value of a numeric span attribute such as `colspan="2"`, capped at `max`; 1 when absent or
+/// invalid.
+fn span_attribute(tag: &str, name: &str, max: usize) -> usize {
+    let Some(start) = tag.find(name) else {
+        return 1;
+    };
+    tag[start + name.len()..]
+        .trim_start()
+        .strip_prefix('=')
+        .map(|value| value.trim_start().trim_start_matches(['"', '\'']))
+        .and_then(|value| {
+            let digits = value
+                .find(|ch: char| !ch.is_ascii_digit())
+                .unwrap_or(value.len());
+            value[..digits].parse().ok()
+        })
+        .filter(|&span| span > 0)
+        .map_or(1, |span: usize| span.min(max))
+}
+
+/// Lay rows out on a grid: each cell takes the first column its row has free, and spanning
+/// cells reserve the positions they cover in later rows.
+fn place(rows: Vec<Vec<RawCell>>) -> Vec<TableCell> {
+    let row_count = rows.len();
+    let mut occupied = HashSet::new();
+    let mut cells = Vec::new();
+    for (row, raw_cells) in rows.into_iter().enumerate() {
+        let mut column = 0;
+        for raw in raw_cells {
+            while occupied.contains(&(row, column)) {
+                column += 1;
+            }
+            let row_span = raw.row_span.min(row_count - row);
+            for covered_row in row..row + row_span {
+                for covered_column in column..column + raw.column_span {
+                    occupied.insert((covered_row, covered_column));
+                }
+            }
+            cells.push(TableCell {
+                row,
+                column,
+                row_span,
+                column_span: raw.column_span,
+                header: raw.header,
+                text: raw.text,
+            });
+            column += raw.column_span;
+        }
+    }
+    cells
+}
+
+/// Cells of an OTSL table. `<fcel>` (and the header/section variants) start a cell with
+/// content, `<ecel>` an empty one; `<lcel>` extends the cell to its left, `<ucel>` the cell
+/// above and `<xcel>` both; `<nl>` ends a row.
+fn otsl_cells(text: &str) -> Vec<TableCell> {
+    let mut grid: Vec<Vec<(&str, String)>> = vec![Vec::new()];
+    let mut rest = text;
+    loop {
+        let next = OTSL_TOKENS
+            .iter()
+            .filter_map(|token| rest.find(token).map(|pos| (pos, *token)))
+            .min_by_key(|(pos, _)| *pos);
+        let text_end = next.map_or(rest.len(), |(pos, _)| pos);
+        // Text belongs to the last token of the current row; text after `<nl>` is dropped.
+        if let Some((_, cell_text)) = grid.last_mut().and_then(|row| row.last_mut()) {
+            cell_text.push_str(&rest[..text_end]);
+        }
+        let Some((pos, token)) = next else {
+            break;
+        };
+        rest = &rest[pos + token.len()..];
+        if token == "<nl>" {
+            grid.push(Vec::new());
+        } else if let Some(row) = grid.last_mut() {
+            row.push((token, String::new()));
+        }
+    }
+
+    let token_at = |row: usize, column: usize| {
+        grid.get(row)
+            .and_then(|tokens| tokens.get(column))
+            .map(|(token, _)| *token)
+    };
+    let mut cells = Vec::new();
+    for (row, tokens) in grid.iter().enumerate() {
+        for (column, (token, text)) in tokens.iter().enumerate() {
+            if !OTSL_CELLS.contains(token) {
+                continue;
+            }
+            let column_span = 1
+                + (column + 1..tokens.len())
+                    .take_while(|&next| tokens[next].0 == "<lcel>")
+                    .count();
+            let row_span = 1
+                + (row + 1..grid.len())
+                    .take_while(|&next| token_at(next, column) == Some("<ucel>"))
+                    .count();
+            cells.push(TableCell {
+                row,
+                column,
+                row_span,
+                column_span,
+                header: matches!(*token, "<ched>" | "<rhed>"),
+                text: cell_text(text),
+            });
+        }
+    }
+    cells
+}
+
+/// Whether `line` is a markdown table delimiter row such as `| --- | :-: |`.
+fn is_separator(line: &str) -> bool {
+    let fields = split_markdown_row(line);
+    !fields.is_empty()
+        && fields.iter().all(|field| {
+            let dashes = field.trim_start_matches(':').trim_end_matches(':');
+            !dashes.is_empty() && dashes.chars().all(|ch| ch == '-')
+        })
+}
+
+/// Fields of a markdown table row; `\|` is a literal pipe.
+fn split_markdown_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(inner) if !inner.ends_with('\\') => inner,
+        _ => line,
+    };
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if chars.peek() == Some(&'|') => {
+                fields.last_mut().unwrap().push('|');
+                chars.next();
+            }
+            '|' => fields.push(String::new()),
+            ch => fields.last_mut().unwrap().push(ch),
+        }
+    }
+    fields
+        .into_iter()
+        .map(|field| field.trim().to_owned())
+        .collect()
+}
+
+/// Cell text without markup: tags dropped (`<br>` becomes a space), entities decoded and
+/// whitespace collapsed.
+fn cell_text(raw: &str) -> String {
+    let mut plain = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('<') {
+        let after = &rest[start + 1..];
+        let is_tag = after.starts_with(|ch: char| ch.is_ascii_alphabetic() || ch == '/');
+        let Some(len) = after.find('>').filter(|_| is_tag) else {
+            plain.push_str(&rest[..=start]);
+            rest = after;
+            continue;
+        };
+        plain.push_str(&rest[..start]);
+        plain.push(' ');
+        rest = &after[len + 1..];
+    }
+    plain.push_str(rest);
+    decode_entities(&plain)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Decode the named entities HTML tables commonly carry and numeric character references.
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let decoded = after.find(';').filter(|&len| len <= 10).and_then(|len| {
+            let entity = &after[..len];
+            let ch = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity.strip_prefix('#').and_then(|code| {
+                    match code.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => code.parse().ok(),
+                    }
+                    .and_then(char::from_u32)
+                }),
+            };
+            ch.map(|ch| (ch, len))
+        });
+        match decoded {
+            Some((ch, len)) => {
+                out.push(ch);
+                rest = &after[len + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
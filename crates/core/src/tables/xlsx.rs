@@ -0,0 +1,185 @@
+//! Minimal Office Open XML workbook: one worksheet per table with inline strings, numbers for
+//! plainly numeric cells, bold headers and merged ranges for spanning cells. The package is
+//! written with [`ZipWriter`].
+
+use std::fmt::Write as _;
+
+use anyhow::Result;
+
+use super::Table;
+use crate::{export::escape_xml, zip::ZipWriter};
+
+const MAIN_NS: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+const REL_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const PACKAGE_REL_NS: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+const XML_DECL: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+
+const STYLES: &str = concat!(
+    "<fonts count=\"2\"><font><sz val=\"11\"/><name val=\"Calibri\"/></font>",
+    "<font><b/><sz val=\"11\"/><name val=\"Calibri\"/></font></fonts>",
+    "<fills count=\"2\"><fill><patternFill patternType=\"none\"/></fill>",
+    "<fill><patternFill patternType=\"gray125\"/></fill></fills>",
+    "<borders count=\"1\"><border><left/><right/><top/><bottom/><diagonal/></border></borders>",
+    "<cellStyleXfs count=\"1\"><xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\"/></cellStyleXfs>",
+    "<cellXfs count=\"2\"><xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\"/>",
+    "<xf numFmtId=\"0\" fontId=\"1\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyFont=\"1\"/></cellXfs>",
+    "<cellStyles count=\"1\"><cellStyle name=\"Normal\" xfId=\"0\" builtinId=\"0\"/></cellStyles>",
+);
+
+/// Write `tables` as an `.xlsx` workbook, with sheets named `Table 1`, `Table 2`, ...; a
+/// workbook without tables holds one empty sheet.
+pub fn render(tables: &[Table]) -> Result<Vec<u8>> {
+    let sheets = tables.len().max(1);
+    let mut zip = ZipWriter::default();
+
+    let mut types = format!(
+        "{XML_DECL}<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+         <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+         <Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+         <Override PartName=\"/xl/workbook.xml\" \
+         ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml\"/>\
+         <Override PartName=\"/xl/styles.xml\" \
+         ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml\"/>"
+    );
+    for sheet in 1..=sheets {
+        let _ = write!(
+            types,
+            "<Override PartName=\"/xl/worksheets/sheet{sheet}.xml\" \
+             ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml\"/>"
+        );
+    }
+    types.push_str("</Types>");
+    zip.add("[Content_Types].xml", types.as_bytes())?;
+
+    zip.add(
+        "_rels/.rels",
+        format!(
+            "{XML_DECL}<Relationships xmlns=\"{PACKAGE_REL_NS}\">\
+             <Relationship Id=\"rId1\" Type=\"{REL_NS}/officeDocument\" Target=\"xl/workbook.xml\"/>\
+             </Relationships>"
+        )
+        .as_bytes(),
+    )?;
+
+    let mut workbook =
+        format!("{XML_DECL}<workbook xmlns=\"{MAIN_NS}\" xmlns:r=\"{REL_NS}\"><sheets>");
+    let mut rels = format!("{XML_DECL}<Relationships xmlns=\"{PACKAGE_REL_NS}\">");
+    for sheet in 1..=sheets {
+        let _ = write!(
+            workbook,
+            "<sheet name=\"Table {sheet}\" sheetId=\"{sheet}\" r:id=\"rId{sheet}\"/>"
+        );
+        let _ = write!(
+            rels,
+            "<Relationship Id=\"rId{sheet}\" Type=\"{REL_NS}/worksheet\" \
+             Target=\"worksheets/sheet{sheet}.xml\"/>"
+        );
+    }
+    workbook.push_str("</sheets></workbook>");
+    let _ = write!(
+        rels,
+        "<Relationship Id=\"rId{}\" Type=\"{REL_NS}/styles\" Target=\"styles.xml\"/>\
+         </Relationships>",
+        sheets + 1
+    );
+    zip.add("xl/workbook.xml", workbook.as_bytes())?;
+    zip.add("xl/_rels/workbook.xml.rels", rels.as_bytes())?;
+    zip.add(
+        "xl/styles.xml",
+        format!("{XML_DECL}<styleSheet xmlns=\"{MAIN_NS}\">{STYLES}</styleSheet>").as_bytes(),
+    )?;
+
+    for sheet in 1..=sheets {
+        let xml = worksheet(tables.get(sheet - 1));
+        zip.add(&format!("xl/worksheets/sheet{sheet}.xml"), xml.as_bytes())?;
+    }
+    Ok(zip.finish())
+}
+
+fn worksheet(table: Option<&Table>) -> String {
+    let mut xml = format!("{XML_DECL}<worksheet xmlns=\"{MAIN_NS}\"><sheetData>");
+    let Some(table) = table else {
+        xml.push_str("</sheetData></worksheet>");
+        return xml;
+    };
+    let mut cells = table.cells.iter().peekable();
+    for row in 0..table.rows {
+        let _ = write!(xml, "<row r=\"{}\">", row + 1);
+        while let Some(cell) = cells.next_if(|cell| cell.row == row) {
+            let reference = cell_ref(cell.row, cell.column);
+            let style = if cell.header { " s=\"1\"" } else { "" };
+            match numeric(&cell.text).filter(|_| !cell.header) {
+                Some(number) => {
+                    let _ = write!(xml, "<c r=\"{reference}\"{style}><v>{number}</v></c>");
+                }
+                None if cell.text.is_empty() => {
+                    let _ = write!(xml, "<c r=\"{reference}\"{style}/>");
+                }
+                None => {
+                    let _ = write!(
+                        xml,
+                        "<c r=\"{reference}\"{style} t=\"inlineStr\"><is><t xml:space=\"preserve\">{}</t></is></c>",
+                        escape_xml(&cell.text)
+                    );
+                }
+            }
+        }
+        xml.push_str("</row>");
+    }
+    xml.push_str("</sheetData>");
+
+    let merges: Vec<_> = table
+        .cells
+        .iter()
+        .filter(|cell| cell.row_span > 1 || cell.column_span > 1)
+        .map(|cell| {
+            format!(
+                "<mergeCell ref=\"{}:{}\"/>",
+                cell_ref(cell.row, cell.column),
+                cell_ref(
+                    cell.row + cell.row_span - 1,
+                    cell.column + cell.column_span - 1
+                )
+            )
+        })
+        .collect();
+    if !merges.is_empty() {
+        let _ = write!(
+            xml,
+            "<mergeCells count=\"{}\">{}</mergeCells>",
+            merges.len(),
+            merges.concat()
+        );
+    }
+    xml.push_str("</worksheet>");
+    xml
+}
+
+/// A1-style reference of a zero-based position.
+fn cell_ref(row: usize, column: usize) -> String {
+    let mut letters = Vec::new();
+    let mut index = column + 1;
+    while index > 0 {
+        let rem = (index - 1) % 26;
+        letters.push(b'A' + rem as u8);
+        index = (index - 1) / 26;
+    }
+    letters.reverse();
+    format!("{}{}", String::from_utf8_lossy(&letters), row + 1)
+}
+
+/// `text` if it is a plain decimal number that survives a round trip through a spreadsheet:
+/// no grouping, exponent or leading zeros such as in `007`.
+fn numeric(text: &str) -> Option<&str> {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let (whole, fraction) = match digits.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (digits, None),
+    };
+    let all_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    let valid = all_digits(whole)
+        && (whole == "0" || !whole.starts_with('0'))
+        && fraction.is_none_or(all_digits)
+        && whole.len() <= 15;
+    valid.then_some(text)
+}
@@ -0,0 +1,132 @@
+//! Task presets that render the prompt each backend was trained with.
+//!
+//! DeepSeek-OCR switches behaviour on short instructions after the image (`Free OCR.`,
+//! `<|grounding|>Convert the document to markdown.`), while PaddleOCR-VL expects one of four
+//! task prefixes inside its `User: ... Assistant: ` chat frame. Picking an [`OcrTask`] instead
+//! of typing the prompt avoids a missing `<image>` slot or `<|grounding|>` tag.
+
+use std::fmt;
+
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::inference::ModelKind;
+
+/// What the model should extract from the image(s).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[value(rename_all = "lowercase")]
+pub enum OcrTask {
+    /// Plain text, no layout.
+    Ocr,
+    /// Markdown with headings, lists, tables and formulas.
+    Markdown,
+    /// Markdown with a grounding box for every block.
+    Grounding,
+    /// Boxes around occurrences of a given text.
+    Locate,
+    /// Table structure.
+    Table,
+    /// Formulas as LaTeX.
+    Formula,
+    /// Chart data.
+    Chart,
+    /// Free-form description of a figure or photo.
+    Figure,
+}
+
+impl OcrTask {
+    pub const ALL: [Self; 8] = [
+        Self::Ocr,
+        Self::Markdown,
+        Self::Grounding,
+        Self::Locate,
+        Self::Table,
+        Self::Formula,
+        Self::Chart,
+        Self::Figure,
+    ];
+
+    /// Tasks `kind` has a prompt for.
+    pub fn supported(kind: ModelKind) -> Vec<Self> {
+        Self::ALL
+            .into_iter()
+            .filter(|task| task.instruction(kind).is_some())
+            .collect()
+    }
+
+    /// Full prompt for `images` image slots, conversation template included. `query` is the
+    /// text to find for [`OcrTask::Locate`] and is ignored by every other task.
+    pub fn render_prompt(
+        self,
+        kind: ModelKind,
+        images: usize,
+        query: Option<&str>,
+    ) -> Result<String> {
+        let Some(instruction) = self.instruction(kind) else {
+            let supported = Self::supported(kind)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            bail!("task `{self}` is not available for {kind:?} (supported: {supported})");
+        };
+        let instruction = if self == Self::Locate {
+            let query = query.map(str::trim).filter(|query| !query.is_empty());
+            let Some(query) = query else {
+                bail!("task `locate` needs the text to find");
+            };
+            instruction.replace("{query}", query)
+        } else {
+            instruction.to_string()
+        };
+        Ok(wrap_instruction(kind, images, &instruction))
+    }
+
+    fn instruction(self, kind: ModelKind) -> Option<&'static str> {
+        match kind {
+            ModelKind::Deepseek => Some(match self {
+                Self::Ocr => "Free OCR.",
+                Self::Markdown | Self::Table | Self::Formula => "Convert the document to markdown.",
+                Self::Grounding => "<|grounding|>Convert the document to markdown.",
+                Self::Locate => "Locate <|ref|>{query}<|/ref|> in the image.",
+                Self::Chart => "Parse the figure.",
+                Self::Figure => "Describe this image in detail.",
+            }),
+            ModelKind::PaddleOcrVl => match self {
+                Self::Ocr => Some("OCR:"),
+                Self::Table => Some("Table Recognition:"),
+                Self::Formula => Some("Formula Recognition:"),
+                Self::Chart => Some("Chart Recognition:"),
+                Self::Markdown | Self::Grounding | Self::Locate | Self::Figure => None,
+            },
+        }
+    }
+}
+
+/// Full prompt for `images` image slots followed by `instruction`, conversation template
+/// included.
+pub fn wrap_instruction(kind: ModelKind, images: usize, instruction: &str) -> String {
+    let slots = "<image>".repeat(images);
+    match kind {
+        ModelKind::Deepseek => format!("{slots}\n{instruction}"),
+        ModelKind::PaddleOcrVl => format!("User: {slots}{instruction}\nAssistant: "),
+    }
+}
+
+impl fmt::Display for OcrTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Ocr => "ocr",
+            Self::Markdown => "markdown",
+            Self::Grounding => "grounding",
+            Self::Locate => "locate",
+            Self::Table => "table",
+            Self::Formula => "formula",
+            Self::Chart => "chart",
+            Self::Figure => "figure",
+        };
+        f.write_str(name)
+    }
+}
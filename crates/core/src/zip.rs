@@ -0,0 +1,87 @@
+//! Zip archives for the Office Open XML packages written by the exporters: Flate-compressed
+//! entries with a fixed timestamp, so the same input gives the same bytes.
+
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use flate2::{Compression, Crc, write::DeflateEncoder};
+
+/// Zip archive writer for small in-memory packages.
+#[derive(Default)]
+pub(crate) struct ZipWriter {
+    buf: Vec<u8>,
+    central: Vec<u8>,
+    entries: u16,
+}
+
+/// Version needed to extract: 2.0, for Deflate.
+const ZIP_VERSION: u16 = 20;
+const ZIP_DEFLATE: u16 = 8;
+/// 1980-01-01 00:00 in MS-DOS date format, the earliest zip timestamp.
+const ZIP_DATE: u16 = (1 << 5) | 1;
+
+impl ZipWriter {
+    pub(crate) fn add(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let mut crc = Crc::new();
+        crc.update(data);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(data)
+            .context("failed to compress package part")?;
+        let compressed = encoder
+            .finish()
+            .context("failed to compress package part")?;
+        let sizes = [
+            crc.sum(),
+            u32::try_from(compressed.len()).context("package part too large")?,
+            u32::try_from(data.len()).context("package part too large")?,
+        ];
+        let offset = u32::try_from(self.buf.len()).context("package too large")?;
+        let name_len = u16::try_from(name.len()).context("package part name too long")?;
+
+        let local = &mut self.buf;
+        local.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        for field in [ZIP_VERSION, 0, ZIP_DEFLATE, 0, ZIP_DATE] {
+            local.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in sizes {
+            local.extend_from_slice(&field.to_le_bytes());
+        }
+        local.extend_from_slice(&name_len.to_le_bytes());
+        local.extend_from_slice(&0u16.to_le_bytes());
+        local.extend_from_slice(name.as_bytes());
+        local.extend_from_slice(&compressed);
+
+        let central = &mut self.central;
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        for field in [ZIP_VERSION, ZIP_VERSION, 0, ZIP_DEFLATE, 0, ZIP_DATE] {
+            central.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in sizes {
+            central.extend_from_slice(&field.to_le_bytes());
+        }
+        // Name length, then no extra field, comment, disk number or internal attributes.
+        for field in [name_len, 0, 0, 0, 0] {
+            central.extend_from_slice(&field.to_le_bytes());
+        }
+        central.extend_from_slice(&0u32.to_le_bytes());
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+        self.entries += 1;
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        let offset = self.buf.len() as u32;
+        let size = self.central.len() as u32;
+        self.buf.append(&mut self.central);
+        self.buf.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        for field in [0, 0, self.entries, self.entries] {
+            self.buf.extend_from_slice(&field.to_le_bytes());
+        }
+        self.buf.extend_from_slice(&size.to_le_bytes());
+        self.buf.extend_from_slice(&offset.to_le_bytes());
+        self.buf.extend_from_slice(&0u16.to_le_bytes());
+        self.buf
+    }
+}
@@ -0,0 +1,138 @@
+use deepseek_ocr_core::cleanup::{
+    CleanupConfig, CleanupReport, CropRect, clean_document, estimate_skew,
+};
+use image::{DynamicImage, GrayImage, Luma};
+
+/// A white page of evenly spaced text-like bars tilted `skew` degrees clockwise, optionally
+/// framed by a dark scanner border.
+fn skewed_page(skew: f32, border: u32) -> DynamicImage {
+    let (width, height) = (600u32, 800u32);
+    let (sin, cos) = skew.to_radians().sin_cos();
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    GrayImage::from_fn(width, height, |x, y| {
+        if x < border || y < border || x >= width - border || y >= height - border {
+            return Luma([10]);
+        }
+        // Undo the skew to find where this pixel sits on the straight page.
+        let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+        let (ux, uy) = (cx + dx * cos + dy * sin, cy - dx * sin + dy * cos);
+        let in_text = (150.0..450.0).contains(&ux) && (200.0..600.0).contains(&uy);
+        let on_line = (uy as u32 % 20) < 8;
+        let in_word = (ux as u32 % 37) < 30;
+        if in_text && on_line && in_word {
+            Luma([30])
+        } else {
+            Luma([235])
+        }
+    })
+    .into()
+}
+
+#[test]
+fn estimates_clockwise_skew() {
+    for skew in [-4.0f32, 0.0, 2.5] {
+        let estimate = estimate_skew(&skewed_page(skew, 0), 10.0);
+        assert!(
+            (estimate - skew).abs() <= 0.3,
+            "expected {skew}, estimated {estimate}"
+        );
+    }
+}
+
+#[test]
+fn disabled_cleanup_leaves_the_page_alone() {
+    let page = skewed_page(3.0, 20);
+    let cleaned = clean_document(page.clone(), &CleanupConfig::default());
+    assert_eq!(cleaned.image, page);
+    assert_eq!(cleaned.report.skew_degrees, 0.0);
+    assert_eq!(cleaned.report.crop, None);
+}
+
+#[test]
+fn deskew_and_crop_trim_borders_and_record_geometry() {
+    let page = skewed_page(3.0, 20);
+    let config = CleanupConfig {
+        deskew: true,
+        crop_margins: true,
+        binarize: true,
+        ..CleanupConfig::default()
+    };
+    let cleaned = clean_document(page, &config);
+    let report = cleaned.report;
+    assert!((report.skew_degrees - 3.0).abs() <= 0.3, "{report:?}");
+    let border = report.border.expect("scanner border should be removed");
+    assert_eq!(
+        (border.x, border.y, border.width, border.height),
+        (20, 20, 560, 760)
+    );
+    let crop = report.crop.expect("margins should be cropped");
+    // The text block spans 300x400 px on the straight page; padding keeps a little extra.
+    assert!(crop.width < 400 && crop.height < 500, "{crop:?}");
+    assert_eq!(
+        (cleaned.image.width(), cleaned.image.height()),
+        (crop.width, crop.height)
+    );
+    assert_eq!((report.width, report.height), (crop.width, crop.height));
+
+    // The cleaned page's centre is the straight text block's centre, i.e. the page centre.
+    let (x, y) = report.to_source_point(0.5, 0.5);
+    assert!(
+        (x - 0.5).abs() < 0.03 && (y - 0.5).abs() < 0.03,
+        "({x}, {y})"
+    );
+}
+
+#[test]
+fn boxes_map_back_through_crop_and_skew() {
+    let cropped = CleanupReport {
+        border: None,
+        skew_degrees: 0.0,
+        crop: Some(CropRect {
+            x: 100,
+            y: 50,
+            width: 200,
+            height: 100,
+        }),
+        source_width: 400,
+        source_height: 200,
+        width: 200,
+        height: 100,
+    };
+    assert_eq!(
+        cropped.to_source_box([0.0, 0.0, 1.0, 1.0]),
+        [0.25, 0.25, 0.75, 0.75]
+    );
+
+    let quarter_turn = CleanupReport {
+        border: None,
+        skew_degrees: 90.0,
+        crop: None,
+        source_width: 100,
+        source_height: 100,
+        width: 100,
+        height: 100,
+    };
+    let (x, y) = quarter_turn.to_source_point(1.0, 0.5);
+    assert!(
+        (x - 0.5).abs() < 1e-5 && (y - 1.0).abs() < 1e-5,
+        "({x}, {y})"
+    );
+
+    let bordered = CleanupReport {
+        border: Some(CropRect {
+            x: 10,
+            y: 20,
+            width: 80,
+            height: 160,
+        }),
+        crop: None,
+        source_width: 100,
+        source_height: 200,
+        ..quarter_turn
+    };
+    let (x, y) = bordered.to_source_point(0.5, 0.5);
+    assert!(
+        (x - 0.5).abs() < 1e-5 && (y - 0.5).abs() < 1e-5,
+        "({x}, {y})"
+    );
+}
@@ -0,0 +1,74 @@
+use std::sync::Mutex;
+
+use anyhow::Result;
+use candle_core::{DType, Device};
+use deepseek_ocr_core::{
+    CancellationToken, DecodeOutcome, DecodeParameters, ModelKind, OcrEngine, VisionSettings,
+};
+use image::DynamicImage;
+use tokenizers::Tokenizer;
+
+/// Engine that answers prompts with its replies in turn, repeating the last one, and remembers
+/// the prompts it saw.
+pub struct ScriptedEngine {
+    kind: ModelKind,
+    device: Device,
+    replies: Vec<&'static str>,
+    pub prompts: Mutex<Vec<String>>,
+    /// Reported as the number of generated tokens.
+    pub response_tokens: usize,
+    /// Reported as [`OcrEngine::constrained_decoding`].
+    pub constrained: bool,
+}
+
+impl ScriptedEngine {
+    pub fn with_replies(kind: ModelKind, replies: &[&'static str]) -> Self {
+        Self {
+            kind,
+            device: Device::Cpu,
+            replies: replies.to_vec(),
+            prompts: Mutex::new(Vec::new()),
+            response_tokens: 0,
+            constrained: false,
+        }
+    }
+}
+
+impl OcrEngine for ScriptedEngine {
+    fn kind(&self) -> ModelKind {
+        self.kind
+    }
+
+    fn device(&self) -> &Device {
+        &self.device
+    }
+
+    fn dtype(&self) -> DType {
+        DType::F32
+    }
+
+    fn constrained_decoding(&self) -> bool {
+        self.constrained
+    }
+
+    fn decode(
+        &self,
+        _tokenizer: &Tokenizer,
+        prompt: &str,
+        _images: &[DynamicImage],
+        _vision: VisionSettings,
+        _params: &DecodeParameters,
+        _stream: Option<&dyn Fn(usize, &[i64])>,
+        _cancel: Option<&CancellationToken>,
+    ) -> Result<DecodeOutcome> {
+        let mut prompts = self.prompts.lock().unwrap();
+        let reply = self.replies[prompts.len().min(self.replies.len() - 1)];
+        prompts.push(prompt.to_owned());
+        Ok(DecodeOutcome {
+            text: reply.to_owned(),
+            prompt_tokens: 0,
+            response_tokens: self.response_tokens,
+            generated_tokens: Vec::new(),
+        })
+    }
+}
@@ -0,0 +1,310 @@
+use std::{collections::HashMap, io::Read};
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use deepseek_ocr_core::export::{BlockKind, OcrPage, OutputFormat};
+use flate2::{Crc, read::DeflateDecoder};
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+use roxmltree::{Document, Node};
+
+const LAYOUT: &str = include_str!("fixtures/layout_page.txt");
+const WORD_NS: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+const REL_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+#[cfg(feature = "mathml")]
+const MATH_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/math";
+
+/// A 999×999 page, so grounding units are pixels, with the figure region painted red.
+fn page_image() -> DynamicImage {
+    let image = RgbImage::from_fn(999, 999, |x, y| {
+        if (100..500).contains(&x) && (500..800).contains(&y) {
+            Rgb([255, 0, 0])
+        } else {
+            Rgb([255, 255, 255])
+        }
+    });
+    DynamicImage::ImageRgb8(image)
+}
+
+fn layout_page() -> OcrPage {
+    OcrPage::from_grounded(LAYOUT, 999, 999)
+}
+
+/// The PNG is the 400×300 figure region and nothing else.
+fn assert_figure_crop(png: &[u8]) {
+    let crop = image::load_from_memory(png).expect("PNG crop");
+    assert_eq!(crop.dimensions(), (400, 300));
+    assert!(crop.to_rgb8().pixels().all(|pixel| pixel.0 == [255, 0, 0]));
+}
+
+/// Grid span, vertical merge and text of a table cell.
+type Cell<'a> = (Option<&'a str>, Option<&'a str>, String);
+
+/// A `w:` attribute of `node`.
+fn word<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attribute((WORD_NS, name))
+}
+
+/// Entries of a zip archive, with CRCs checked.
+fn unzip(data: &[u8]) -> HashMap<String, Vec<u8>> {
+    let u16_at = |pos: usize| u16::from_le_bytes([data[pos], data[pos + 1]]) as usize;
+    let u32_at = |pos: usize| u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+    let end = data.len() - 22;
+    assert_eq!(u32_at(end), 0x0605_4b50, "end of central directory");
+    let (count, mut pos) = (u16_at(end + 10), u32_at(end + 16));
+    let mut entries = HashMap::new();
+    for _ in 0..count {
+        assert_eq!(u32_at(pos), 0x0201_4b50, "central directory header");
+        let (crc, compressed) = (u32_at(pos + 16) as u32, u32_at(pos + 20));
+        let name_len = u16_at(pos + 28);
+        let name = String::from_utf8(data[pos + 46..pos + 46 + name_len].to_vec()).unwrap();
+        let local = u32_at(pos + 42);
+        let start = local + 30 + u16_at(local + 26) + u16_at(local + 28);
+        let mut bytes = Vec::new();
+        DeflateDecoder::new(&data[start..start + compressed])
+            .read_to_end(&mut bytes)
+            .unwrap();
+        let mut check = Crc::new();
+        check.update(&bytes);
+        assert_eq!(check.sum(), crc, "crc of {name}");
+        entries.insert(name, bytes);
+        pos += 46 + name_len + u16_at(pos + 30) + u16_at(pos + 32);
+    }
+    entries
+}
+
+#[test]
+fn blocks_are_classified_and_rendered_as_markdown() {
+    let page = layout_page();
+    let kinds: Vec<String> = page
+        .blocks
+        .iter()
+        .map(|block| match &block.kind {
+            BlockKind::Table(table) => format!("Table({}x{})", table.rows, table.columns),
+            kind => format!("{kind:?}"),
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            "Heading(1)",
+            "Heading(2)",
+            "List { ordered: false }",
+            "Table(3x3)",
+            "Formula(\"m = \\\\frac{a}{b}\")",
+            "Figure",
+            "Caption",
+            "Paragraph",
+        ]
+    );
+
+    let plain = OcrPage::from_grounded(
+        "# Notes\n\nFirst line\nsecond line\n\n1. one\n2. two",
+        600,
+        600,
+    );
+    let boxes: Vec<_> = plain.blocks.iter().map(|block| block.bbox).collect();
+    assert_eq!(
+        boxes,
+        [[0, 0, 600, 120], [0, 120, 600, 360], [0, 360, 600, 600]]
+    );
+    assert_eq!(plain.blocks[2].kind, BlockKind::List { ordered: true });
+
+    let markdown =
+        String::from_utf8(OutputFormat::Markdown.render(&[page, plain], &[]).unwrap()).unwrap();
+    assert_eq!(
+        markdown,
+        "# Annual Summary\n\n## Highlights\n\n- Revenue up 12%\n- Costs flat\n\n\
+         <table><tr><th rowspan=\"2\">Region</th><th colspan=\"2\">Revenue</th></tr>\
+         <tr><th>2023</th><th>2024</th></tr><tr><td>EMEA</td><td>1.2</td><td>1.5</td></tr></table>\n\n\
+         $$\nm = \\frac{a}{b}\n$$\n\n*Figure 1: Revenue by region.*\n\n\
+         Margins improved & held at 35%.\n\n# Notes\n\nFirst line\nsecond line\n\n1. one\n2. two"
+    );
+}
+
+#[test]
+fn markdown_captions_escape_emphasis_markers() {
+    let page = OcrPage::from_grounded(
+        "<|ref|>image_caption<|/ref|><|det|>[[100, 810, 500, 840]]<|/det|>\n\
+         Figure 2: *p*_value of x_1 \\ y",
+        999,
+        999,
+    );
+    let markdown = String::from_utf8(OutputFormat::Markdown.render(&[page], &[]).unwrap()).unwrap();
+    assert_eq!(markdown, "*Figure 2: \\*p\\*\\_value of x\\_1 \\\\ y*");
+}
+
+#[test]
+fn html_document_stands_alone() {
+    let html = String::from_utf8(
+        OutputFormat::Html
+            .render(&[layout_page()], &[page_image()])
+            .unwrap(),
+    )
+    .unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    for expected in [
+        "<title>Annual Summary</title>",
+        "<h1>Annual Summary</h1>",
+        "<h2>Highlights</h2>",
+        "<ul><li>Revenue up 12%</li><li>Costs flat</li></ul>",
+        "<th rowspan=\"2\">Region</th><th colspan=\"2\">Revenue</th>",
+        #[cfg(feature = "mathml")]
+        "<mfrac><mi>a</mi><mi>b</mi></mfrac>",
+        #[cfg(not(feature = "mathml"))]
+        "<div class=\"formula\">\\[m = \\frac{a}{b}\\]</div>",
+        "<figcaption>Figure 1: Revenue by region.</figcaption>",
+        "<p>Margins improved &amp; held at 35%.</p>",
+    ] {
+        assert!(html.contains(expected), "missing {expected:?}");
+    }
+    // The caption moved into the figure rather than following it.
+    assert!(!html.contains("class=\"caption\""));
+
+    let encoded = html
+        .split_once("data:image/png;base64,")
+        .and_then(|(_, rest)| rest.split_once('"'))
+        .map(|(encoded, _)| encoded)
+        .expect("embedded figure");
+    assert_figure_crop(&STANDARD.decode(encoded).unwrap());
+}
+
+#[test]
+fn docx_package_holds_styled_blocks_and_figures() {
+    assert!(OutputFormat::Docx.is_binary() && OutputFormat::Docx.embeds_images());
+    let pages = [
+        layout_page(),
+        OcrPage::from_grounded("1. one\n2. two", 999, 999),
+    ];
+    let docx = OutputFormat::Docx
+        .render(&pages, &[page_image(), page_image()])
+        .unwrap();
+    let parts = unzip(&docx);
+    let xml = |name: &str| String::from_utf8(parts[name].clone()).unwrap();
+
+    let types = xml("[Content_Types].xml");
+    for part in [
+        "/word/document.xml",
+        "/word/styles.xml",
+        "/word/numbering.xml",
+    ] {
+        assert!(types.contains(part), "{part} has no content type");
+    }
+    for name in [
+        "_rels/.rels",
+        "word/_rels/document.xml.rels",
+        "word/styles.xml",
+        "word/numbering.xml",
+    ] {
+        Document::parse(&xml(name)).unwrap_or_else(|err| panic!("{name}: {err}"));
+    }
+
+    let document = xml("word/document.xml");
+    let doc = Document::parse(&document).unwrap();
+    let styles: Vec<&str> = doc
+        .descendants()
+        .filter(|node| node.has_tag_name((WORD_NS, "pStyle")))
+        .filter_map(|node| word(node, "val"))
+        .collect();
+    assert_eq!(
+        styles,
+        [
+            "Heading1",
+            "Heading2",
+            "ListParagraph",
+            "ListParagraph",
+            "Formula",
+            "Caption",
+            "Normal",
+            "ListParagraph",
+            "ListParagraph",
+        ]
+    );
+    #[cfg(feature = "mathml")]
+    {
+        let fraction = doc
+            .descendants()
+            .find(|node| node.has_tag_name((MATH_NS, "f")))
+            .expect("formula as an Office Math fraction");
+        let parts: Vec<String> = fraction
+            .children()
+            .map(|part| {
+                part.descendants()
+                    .filter(|node| node.is_text())
+                    .filter_map(|node| node.text())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(parts, ["a", "b"]);
+    }
+    #[cfg(not(feature = "mathml"))]
+    assert!(document.contains(">m = \\frac{a}{b}</w:t>"));
+    let lists: Vec<&str> = doc
+        .descendants()
+        .filter(|node| node.has_tag_name((WORD_NS, "numId")))
+        .filter_map(|node| word(node, "val"))
+        .collect();
+    assert_eq!(lists, ["1", "1", "2", "2"]);
+    let numbering = xml("word/numbering.xml");
+    assert!(numbering.contains("<w:num w:numId=\"2\"><w:abstractNumId w:val=\"1\"/>"));
+
+    let rows: Vec<Vec<Cell>> = doc
+        .descendants()
+        .filter(|node| node.has_tag_name((WORD_NS, "tr")))
+        .map(|row| {
+            row.children()
+                .filter(|cell| cell.has_tag_name((WORD_NS, "tc")))
+                .map(|cell| {
+                    let prop = |name: &str| {
+                        cell.descendants()
+                            .find(|node| node.has_tag_name((WORD_NS, name)))
+                    };
+                    (
+                        prop("gridSpan").and_then(|node| word(node, "val")),
+                        prop("vMerge").map(|node| word(node, "val").unwrap_or("continue")),
+                        cell.descendants()
+                            .filter(|node| node.is_text())
+                            .filter_map(|node| node.text())
+                            .collect(),
+                    )
+                })
+                .collect()
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            vec![
+                (None, Some("restart"), "Region".to_owned()),
+                (Some("2"), None, "Revenue".to_owned()),
+            ],
+            vec![
+                (None, Some("continue"), String::new()),
+                (None, None, "2023".to_owned()),
+                (None, None, "2024".to_owned()),
+            ],
+            vec![
+                (None, None, "EMEA".to_owned()),
+                (None, None, "1.2".to_owned()),
+                (None, None, "1.5".to_owned()),
+            ],
+        ]
+    );
+    let breaks = doc
+        .descendants()
+        .filter(|node| node.has_tag_name((WORD_NS, "br")))
+        .count();
+    assert_eq!(breaks, 1);
+
+    let blip = doc
+        .descendants()
+        .find(|node| node.tag_name().name() == "blip")
+        .and_then(|node| node.attribute((REL_NS, "embed")))
+        .expect("inline figure");
+    let rels = xml("word/_rels/document.xml.rels");
+    let rels = Document::parse(&rels).unwrap();
+    let target = rels
+        .descendants()
+        .find(|node| node.attribute("Id") == Some(blip))
+        .and_then(|node| node.attribute("Target"))
+        .unwrap();
+    assert_figure_crop(&parts[&format!("word/{target}")]);
+}
@@ -0,0 +1,73 @@
+use candle_core::DType;
+use deepseek_ocr_core::{
+    ModelKind,
+    estimate::{DryRunReport, ImageEstimate, ModelFootprint, encoder_parameters},
+};
+
+fn footprint() -> ModelFootprint {
+    ModelFootprint {
+        total_parameters: 1_000_000,
+        active_parameters: 500_000,
+        layers: 2,
+        hidden_size: 64,
+        intermediate_size: 256,
+        vocab_size: 1000,
+        attention_heads: 4,
+        head_dim: 16,
+        kv_width: 128,
+    }
+}
+
+#[test]
+fn reports_split_prompt_tokens_and_size_memory_by_dtype() {
+    let images = vec![
+        ImageEstimate {
+            width: 1200,
+            height: 1600,
+            tile_grid: Some((2, 3)),
+            resized: (1024, 1024),
+            vision_tokens: 273,
+        },
+        ImageEstimate {
+            width: 800,
+            height: 600,
+            tile_grid: Some((3, 2)),
+            resized: (1024, 1024),
+            vision_tokens: 273,
+        },
+    ];
+    let report = DryRunReport::new(
+        ModelKind::Deepseek,
+        DType::BF16,
+        images,
+        600,
+        400,
+        &footprint(),
+    );
+    assert_eq!(report.vision_tokens, 546);
+    assert_eq!(report.text_tokens, 54);
+    assert_eq!(report.dtype, "bf16");
+
+    let memory = report.memory;
+    assert_eq!(memory.weights_bytes, 2_000_000);
+    // Two layers caching 128 elements for each of the 1000 prompt and generated positions.
+    assert_eq!(memory.kv_cache_bytes, 2 * 128 * 1000 * 2);
+    assert_eq!(
+        memory.peak_bytes,
+        memory.weights_bytes + memory.kv_cache_bytes + memory.activation_bytes
+    );
+    let f32_memory = footprint().memory(600, 400, DType::F32);
+    assert_eq!(f32_memory.peak_bytes, 2 * memory.peak_bytes);
+
+    let expected_flops = 2.0 * 600.0 * 500_000.0 + 4.0 * (2 * 4 * 16) as f64 * 600.0 * 600.0;
+    assert!((report.prefill_gflops - expected_flops / 1e9).abs() < 1e-9);
+}
+
+#[test]
+fn encoder_parameters_match_vit_sizes() {
+    // ViT-B/16 and ViT-L/14 encoder stacks are ~85M and ~303M parameters.
+    let base = encoder_parameters(12, 768, 3072);
+    let large = encoder_parameters(24, 1024, 4096);
+    assert!((84_000_000..86_000_000).contains(&base), "{base}");
+    assert!((300_000_000..305_000_000).contains(&large), "{large}");
+}
@@ -0,0 +1,309 @@
+use std::collections::HashSet;
+
+use deepseek_ocr_core::export::{OcrPage, OutputFormat, alto::ALTO_NAMESPACE, page_prompt};
+use roxmltree::{Document, Node, ParsingOptions};
+
+const GROUNDED: &str = include_str!("fixtures/grounded_page.txt");
+const XHTML_NAMESPACE: &str = "http://www.w3.org/1999/xhtml";
+
+fn fixture_page() -> OcrPage {
+    OcrPage::from_grounded(GROUNDED, 1000, 1400).with_image_name("report.png")
+}
+
+fn render_text(format: OutputFormat, pages: &[OcrPage]) -> String {
+    String::from_utf8(format.render(pages, &[]).expect("render")).expect("UTF-8 output")
+}
+
+fn attr_u32(node: Node, name: &str) -> u32 {
+    node.attribute(name)
+        .unwrap_or_else(|| panic!("<{}> lacks {name}", node.tag_name().name()))
+        .parse()
+        .unwrap_or_else(|err| panic!("<{}> {name}: {err}", node.tag_name().name()))
+}
+
+fn contains(outer: [u32; 4], inner: [u32; 4]) -> bool {
+    outer[0] <= inner[0] && outer[1] <= inner[1] && inner[2] <= outer[2] && inner[3] <= outer[3]
+}
+
+fn hocr_bbox(node: Node) -> Option<[u32; 4]> {
+    let title = node.attribute("title")?;
+    let bbox = title
+        .split(';')
+        .map(str::trim)
+        .find_map(|property| property.strip_prefix("bbox "))?;
+    let values: Vec<u32> = bbox
+        .split_whitespace()
+        .map(|value| value.parse().unwrap())
+        .collect();
+    let bbox: [u32; 4] = values.try_into().ok()?;
+    assert!(
+        bbox[0] <= bbox[2] && bbox[1] <= bbox[3],
+        "inverted bbox {bbox:?}"
+    );
+    Some(bbox)
+}
+
+fn alto_box(node: Node) -> [u32; 4] {
+    let (x, y) = (attr_u32(node, "HPOS"), attr_u32(node, "VPOS"));
+    [
+        x,
+        y,
+        x + attr_u32(node, "WIDTH"),
+        y + attr_u32(node, "HEIGHT"),
+    ]
+}
+
+fn alto_children<'a, 'i>(node: Node<'a, 'i>) -> Vec<Node<'a, 'i>> {
+    node.children()
+        .filter(Node::is_element)
+        .inspect(|child| assert_eq!(child.tag_name().namespace(), Some(ALTO_NAMESPACE)))
+        .collect()
+}
+
+fn names(nodes: &[Node]) -> Vec<String> {
+    nodes
+        .iter()
+        .map(|node| node.tag_name().name().to_owned())
+        .collect()
+}
+
+#[test]
+fn grounded_output_becomes_pixel_blocks_in_emission_order() {
+    let page = fixture_page();
+    let labels: Vec<&str> = page
+        .blocks
+        .iter()
+        .map(|block| block.label.as_str())
+        .collect();
+    assert_eq!(labels, ["title", "text", "image", "table", "text"]);
+    // 0..999 grounding units scaled to the 1000x1400 page.
+    assert_eq!(page.blocks[0].bbox, [120, 56, 881, 126]);
+    assert_eq!(page.blocks[0].lines, ["Quarterly Report & Outlook"]);
+    assert!(page.blocks[2].is_illustration());
+    assert_eq!(page.blocks[3].lines, ["Region Q1 Q2", "EMEA 4.1 4.6"]);
+    assert_eq!(
+        page.blocks[1].lines[0],
+        "Revenue grew 12% year over year, driven by services ."
+    );
+
+    let plain = OcrPage::from_grounded("Line one\nLine two", 640, 480);
+    assert_eq!(plain.blocks.len(), 1);
+    assert_eq!(plain.blocks[0].bbox, [0, 0, 640, 480]);
+    assert_eq!(
+        render_text(OutputFormat::Text, &[plain]),
+        "Line one\nLine two"
+    );
+
+    assert_eq!(
+        page_prompt("<image><image><image>\nFree OCR.").unwrap(),
+        "<image>\nFree OCR."
+    );
+    assert_eq!(page_prompt("Free OCR.").unwrap(), "Free OCR.");
+    assert!(page_prompt("<image> first, <image> second").is_err());
+}
+
+#[test]
+fn hocr_fixture_is_well_formed_and_nested() {
+    let mut page = fixture_page();
+    page.blocks[1].confidence = Some(0.874);
+    let hocr = render_text(OutputFormat::Hocr, &[page]);
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(&hocr, options).expect("hOCR must be well-formed XML");
+    let root = doc.root_element();
+    assert_eq!(root.tag_name().namespace(), Some(XHTML_NAMESPACE));
+    assert_eq!(root.tag_name().name(), "html");
+
+    let meta = |name: &str| {
+        doc.descendants()
+            .find(|node| node.has_tag_name("meta") && node.attribute("name") == Some(name))
+            .and_then(|node| node.attribute("content"))
+            .unwrap_or_else(|| panic!("missing {name} meta"))
+    };
+    assert!(!meta("ocr-system").is_empty());
+    let capabilities: HashSet<&str> = meta("ocr-capabilities").split_whitespace().collect();
+
+    let mut ids = HashSet::new();
+    let mut pages = 0;
+    for node in doc.descendants().filter(Node::is_element) {
+        let Some(class) = node.attribute("class") else {
+            continue;
+        };
+        assert!(capabilities.contains(class), "{class} not declared");
+        assert!(ids.insert(node.attribute("id").expect("hOCR elements carry ids")));
+        let bbox = hocr_bbox(node).unwrap_or_else(|| panic!("{class} without bbox"));
+        if class == "ocr_page" {
+            pages += 1;
+            assert_eq!(bbox, [0, 0, 1000, 1400]);
+            assert!(
+                node.attribute("title")
+                    .unwrap()
+                    .contains("image \"report.png\"")
+            );
+            continue;
+        }
+        let parent = node
+            .ancestors()
+            .skip(1)
+            .find_map(hocr_bbox)
+            .expect("nested in a page");
+        assert!(
+            contains(parent, bbox),
+            "{class} {bbox:?} outside {parent:?}"
+        );
+        if class == "ocrx_word" {
+            let wconf = node.attribute("title").unwrap().contains("x_wconf 87");
+            let in_scored_block = node
+                .ancestors()
+                .any(|a| a.attribute("id") == Some("block_1_2"));
+            assert_eq!(wconf, in_scored_block);
+        }
+    }
+    assert_eq!(pages, 1);
+
+    let lines: Vec<String> = doc
+        .descendants()
+        .filter(|node| node.attribute("class") == Some("ocr_line"))
+        .map(|line| {
+            line.children()
+                .filter(|word| word.attribute("class") == Some("ocrx_word"))
+                .map(|word| word.text().unwrap_or_default())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+    assert_eq!(lines.first().unwrap(), "Quarterly Report & Outlook");
+    assert_eq!(
+        lines.last().unwrap(),
+        "Net income (a < b) rose to \"record\" levels."
+    );
+    assert_eq!(
+        doc.descendants()
+            .filter(|node| node.attribute("class") == Some("ocr_photo"))
+            .count(),
+        1
+    );
+}
+
+/// Checks the element order, required attributes and ID references of ALTO v4 by hand. This
+/// is deliberately not validation against `alto-4-2.xsd`: no XSD validator is available as a
+/// Rust crate without linking libxml2, and the schema would have to be vendored since tests do
+/// not fetch it. Anything the schema constrains beyond what is asserted here, such as attribute
+/// value types, goes unchecked.
+#[test]
+fn alto_fixture_follows_the_v4_schema_structure() {
+    let mut page = fixture_page();
+    page.blocks[4].confidence = Some(0.5);
+    let alto = render_text(OutputFormat::Alto, &[page.clone()]);
+    let doc = Document::parse(&alto).expect("ALTO must be well-formed XML");
+    let root = doc.root_element();
+    assert_eq!(root.tag_name().name(), "alto");
+    assert!(
+        root.attribute((
+            "http://www.w3.org/2001/XMLSchema-instance",
+            "schemaLocation"
+        ))
+        .unwrap()
+        .starts_with(ALTO_NAMESPACE)
+    );
+
+    // xsd:sequence of the root and of Description.
+    let sections = alto_children(root);
+    assert_eq!(names(&sections), ["Description", "ReadingOrder", "Layout"]);
+    let description = alto_children(sections[0]);
+    assert_eq!(
+        names(&description),
+        ["MeasurementUnit", "sourceImageInformation", "OCRProcessing"]
+    );
+    assert_eq!(description[0].text(), Some("pixel"));
+    assert!(description[2].attribute("ID").is_some());
+
+    let mut ids = HashSet::new();
+    for node in doc.descendants().filter(Node::is_element) {
+        if let Some(id) = node.attribute("ID") {
+            assert!(ids.insert(id), "duplicate ID {id}");
+        }
+    }
+
+    let pages = alto_children(sections[2]);
+    assert_eq!(names(&pages), ["Page"]);
+    let page_node = pages[0];
+    assert_eq!(attr_u32(page_node, "PHYSICAL_IMG_NR"), 1);
+    assert!(page_node.attribute("ID").is_some());
+    let print_space = alto_children(page_node);
+    assert_eq!(names(&print_space), ["PrintSpace"]);
+    let page_box = alto_box(print_space[0]);
+    assert_eq!(page_box, [0, 0, 1000, 1400]);
+
+    let blocks = alto_children(print_space[0]);
+    assert_eq!(
+        names(&blocks),
+        [
+            "TextBlock",
+            "TextBlock",
+            "Illustration",
+            "TextBlock",
+            "TextBlock"
+        ]
+    );
+    for block in &blocks {
+        assert!(block.attribute("ID").is_some());
+        let block_box = alto_box(*block);
+        assert!(contains(page_box, block_box));
+        for line in alto_children(*block) {
+            assert_eq!(line.tag_name().name(), "TextLine");
+            let line_box = alto_box(line);
+            assert!(contains(block_box, line_box));
+            let parts = alto_children(line);
+            // (String, SP?)+ : words alternate with single spaces.
+            for (idx, part) in parts.iter().enumerate() {
+                let expected = if idx % 2 == 0 { "String" } else { "SP" };
+                assert_eq!(part.tag_name().name(), expected);
+            }
+            assert_eq!(parts.last().unwrap().tag_name().name(), "String");
+            for word in parts
+                .iter()
+                .filter(|part| part.has_tag_name((ALTO_NAMESPACE, "String")))
+            {
+                let content = word.attribute("CONTENT").unwrap();
+                assert!(!content.is_empty() && !content.contains(char::is_whitespace));
+                assert!(contains(line_box, alto_box(*word)));
+                if let Some(wc) = word.attribute("WC") {
+                    let wc: f32 = wc.parse().unwrap();
+                    assert!((0.0..=1.0).contains(&wc));
+                }
+            }
+        }
+    }
+
+    // Reading order references every block once, in layout order.
+    let order = alto_children(sections[1]);
+    assert_eq!(names(&order), ["OrderedGroup"]);
+    let refs: Vec<&str> = alto_children(order[0])
+        .iter()
+        .map(|element| {
+            assert_eq!(element.tag_name().name(), "ElementRef");
+            assert!(element.attribute("ID").is_some());
+            element.attribute("REF").unwrap()
+        })
+        .collect();
+    let block_ids: Vec<&str> = blocks
+        .iter()
+        .map(|block| block.attribute("ID").unwrap())
+        .collect();
+    assert_eq!(refs, block_ids);
+
+    let words: Vec<&str> = blocks[4]
+        .descendants()
+        .filter_map(|node| node.attribute("CONTENT"))
+        .collect();
+    assert_eq!(words.join(" "), page.blocks[4].lines.join(" "));
+    assert!(
+        blocks[4]
+            .descendants()
+            .filter(|node| node.has_tag_name((ALTO_NAMESPACE, "String")))
+            .all(|word| word.attribute("WC") == Some("0.50"))
+    );
+}
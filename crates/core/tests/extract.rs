@@ -0,0 +1,342 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::ScriptedEngine;
+use deepseek_ocr_core::{
+    DecodeParameters, ModelKind, VisionSettings,
+    pipeline::{
+        extract::{
+            ExtractionSchema, Extractor, FieldKind, FieldSpec, FieldValue, extract, find_fields,
+        },
+        locate::BoundingBox,
+    },
+    sampling::TokenConstraint,
+};
+use image::{DynamicImage, RgbImage};
+use tokenizers::{Tokenizer, models::wordlevel::WordLevel};
+
+const INVOICE: &str = include_str!("fixtures/invoice_page.txt");
+
+fn field(name: &str, kind: FieldKind, labels: &[&str], pattern: Option<&str>) -> FieldSpec {
+    FieldSpec {
+        name: name.to_owned(),
+        description: String::new(),
+        kind,
+        labels: labels.iter().map(|label| label.to_string()).collect(),
+        pattern: pattern.map(str::to_owned),
+    }
+}
+
+fn invoice_schema() -> ExtractionSchema {
+    ExtractionSchema {
+        fields: vec![
+            field(
+                "invoice_number",
+                FieldKind::Text,
+                &["Invoice No"],
+                Some(r"INV-[\d-]+"),
+            ),
+            field("date", FieldKind::Date, &[], None),
+            field("due_date", FieldKind::Date, &[], None),
+            field("customer", FieldKind::Text, &[], None),
+            field("account", FieldKind::Text, &[], None),
+            field("total", FieldKind::Number, &["Total due"], None),
+            field("paid", FieldKind::Boolean, &[], None),
+            field("po_number", FieldKind::Text, &["PO"], None),
+        ],
+    }
+}
+
+#[test]
+fn invoice_fields_are_typed_and_placed() {
+    let extractor = Extractor::new(&invoice_schema()).unwrap();
+    let extraction = find_fields(INVOICE, 1000, 1000, &extractor);
+    let values: Vec<(&str, Option<FieldValue>, Option<&str>)> = extraction
+        .fields
+        .iter()
+        .map(|field| {
+            (
+                field.name.as_str(),
+                field.value.clone(),
+                field.text.as_deref(),
+            )
+        })
+        .collect();
+    let text = |value: &str| Some(FieldValue::Text(value.to_owned()));
+    assert_eq!(
+        values,
+        [
+            (
+                "invoice_number",
+                text("INV-2024-0042"),
+                Some("INV-2024-0042")
+            ),
+            // Month first, since 15 is no month.
+            ("date", text("2024-03-15"), Some("03/15/2024")),
+            // `Due date` belongs to `due_date`, not to `date`.
+            ("due_date", text("2024-04-14"), Some("14th April 2024")),
+            // Header cells: the values are in the row below.
+            ("customer", text("Jane Doe"), Some("Jane Doe")),
+            ("account", text("ACC-991"), Some("ACC-991")),
+            // A label on its own line: the value is on the next one.
+            ("total", Some(FieldValue::Number(1234.5)), Some("1.234,50")),
+            ("paid", Some(FieldValue::Boolean(false)), Some("☐")),
+            ("po_number", None, None),
+        ]
+    );
+    let bbox = |name: &str| {
+        extraction
+            .fields
+            .iter()
+            .find(|field| field.name == name)
+            .and_then(|field| field.bbox)
+    };
+    assert_eq!(
+        bbox("customer"),
+        Some(BoundingBox::from([50, 250, 500, 300]))
+    );
+    assert_eq!(bbox("total"), Some(BoundingBox::from([671, 851, 951, 891])));
+    assert_eq!(bbox("po_number"), None);
+
+    let json = serde_json::to_value(&extraction.fields[5]).unwrap();
+    assert_eq!(json["value"], 1234.5);
+}
+
+#[test]
+fn values_must_parse_as_their_type() {
+    let schema = ExtractionSchema {
+        fields: vec![
+            field("amount", FieldKind::Number, &[], None),
+            field("born", FieldKind::Date, &["Date of birth"], None),
+            field("member", FieldKind::Boolean, &[], None),
+        ],
+    };
+    let extractor = Extractor::new(&schema).unwrap();
+    // Without grounding: values are read, but have no box.
+    let text = "Amount: n/a\nAmount: 1,250\nDate of birth: 31/02/1990\n\
+                Date of birth 7 Sept. 1989\nMember [x]";
+    let extraction = find_fields(text, 100, 100, &extractor);
+    let values: Vec<Option<FieldValue>> = extraction
+        .fields
+        .iter()
+        .map(|field| field.value.clone())
+        .collect();
+    assert_eq!(
+        values,
+        [
+            Some(FieldValue::Number(1250.0)),
+            Some(FieldValue::Text("1989-09-07".into())),
+            Some(FieldValue::Boolean(true)),
+        ]
+    );
+    assert!(extraction.fields.iter().all(|field| field.bbox.is_none()));
+}
+
+#[test]
+fn schemas_are_checked_and_read_from_json() {
+    let schema: ExtractionSchema = serde_json::from_str(
+        r#"{"fields": [
+            {"name": "total", "type": "number", "description": "Amount due"},
+            {"name": "iban", "pattern": "[A-Z]{2}\\d{2}[A-Z0-9 ]+"}
+        ]}"#,
+    )
+    .unwrap();
+    assert_eq!(schema.fields[0].kind, FieldKind::Number);
+    assert_eq!(schema.fields[1].kind, FieldKind::Text);
+    assert!(Extractor::new(&schema).is_ok());
+
+    let mut repeated = schema.clone();
+    repeated.fields[1].name = " total".into();
+    let err = Extractor::new(&repeated).err().unwrap();
+    assert!(err.to_string().contains("defined twice"), "{err}");
+
+    let mut invalid = schema;
+    invalid.fields[1].pattern = Some("[A-Z".into());
+    let err = Extractor::new(&invalid).err().unwrap();
+    assert!(err.to_string().contains("`iban`"), "{err}");
+
+    assert!(Extractor::new(&ExtractionSchema::default()).is_err());
+}
+
+#[test]
+fn lone_separators_are_decimal_after_zero_or_long_integers() {
+    let schema = ExtractionSchema {
+        fields: vec![
+            field("rate", FieldKind::Number, &[], None),
+            field("weight", FieldKind::Number, &[], None),
+            field("count", FieldKind::Number, &[], None),
+            field("price", FieldKind::Number, &[], None),
+            field("area", FieldKind::Number, &[], None),
+        ],
+    };
+    let extractor = Extractor::new(&schema).unwrap();
+    let text = "Rate: 0.125\nWeight: 1234.567\nCount: 1.234\nPrice: 12.345,6\nArea: -0,750";
+    let values: Vec<Option<FieldValue>> = find_fields(text, 100, 100, &extractor)
+        .fields
+        .into_iter()
+        .map(|field| field.value)
+        .collect();
+    assert_eq!(
+        values,
+        [0.125, 1234.567, 1234.0, 12345.6, -0.75].map(|number| Some(FieldValue::Number(number)))
+    );
+}
+
+#[test]
+fn fields_without_a_usable_label_are_rejected() {
+    for name in ["_", "-", "__"] {
+        let schema = ExtractionSchema {
+            fields: vec![field(name, FieldKind::Text, &[], None)],
+        };
+        let err = Extractor::new(&schema).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!("extraction field `{name}` has no usable label")
+        );
+    }
+    let labelled = ExtractionSchema {
+        fields: vec![field("_", FieldKind::Text, &["Reference"], None)],
+    };
+    assert!(Extractor::new(&labelled).is_ok());
+}
+
+#[test]
+fn answers_take_precedence_over_labels() {
+    let mut schema = invoice_schema();
+    schema.fields.push(FieldSpec {
+        description: "Kind of document".into(),
+        ..field("document", FieldKind::Text, &[], None)
+    });
+    let extractor = Extractor::new(&schema).unwrap();
+    let mut engine = ScriptedEngine::with_replies(
+        ModelKind::Deepseek,
+        &[
+            INVOICE,
+            r#"{"invoice_number": "INV-2024-0042", "date": null, "due_date": "2024-04-15", "customer": "Jane Doe", "account": null, "total": 1234.5, "paid": true, "po_number": null, "document": "INVOICE"}"#,
+        ],
+    );
+    engine.constrained = true;
+    let image = DynamicImage::ImageRgb8(RgbImage::new(1000, 1000));
+    let extraction = extract(
+        &engine,
+        &Tokenizer::new(WordLevel::default()),
+        &image,
+        &extractor,
+        VisionSettings::default(),
+        &DecodeParameters::with_sampling_defaults(256),
+    )
+    .unwrap();
+    let found: Vec<(&str, Option<FieldValue>, Option<&str>, bool)> = extraction
+        .fields
+        .iter()
+        .map(|field| {
+            (
+                field.name.as_str(),
+                field.value.clone(),
+                field.text.as_deref(),
+                field.bbox.is_some(),
+            )
+        })
+        .collect();
+    let text = |value: &str| Some(FieldValue::Text(value.to_owned()));
+    assert_eq!(
+        found,
+        [
+            // Agreeing with the label search, which placed it.
+            (
+                "invoice_number",
+                text("INV-2024-0042"),
+                Some("INV-2024-0042"),
+                true
+            ),
+            // Answered `null`: the label search fills it in.
+            ("date", text("2024-03-15"), Some("03/15/2024"), true),
+            // The answer wins, but is not on the page as written.
+            ("due_date", text("2024-04-15"), None, false),
+            ("customer", text("Jane Doe"), Some("Jane Doe"), true),
+            ("account", text("ACC-991"), Some("ACC-991"), true),
+            (
+                "total",
+                Some(FieldValue::Number(1234.5)),
+                Some("1.234,50"),
+                true
+            ),
+            ("paid", Some(FieldValue::Boolean(true)), None, false),
+            ("po_number", None, None, false),
+            // No label on the page, but the answer is.
+            ("document", text("INVOICE"), Some("INVOICE"), true),
+        ]
+    );
+
+    let prompts = engine.prompts.lock().unwrap();
+    assert_eq!(prompts.len(), 2);
+    assert!(
+        prompts[1].starts_with("<image>\nRead the document"),
+        "{}",
+        prompts[1]
+    );
+    assert!(
+        prompts[1].contains("\n- invoice_number (text) Labelled \"Invoice No\"."),
+        "{}",
+        prompts[1]
+    );
+    assert!(prompts[1].contains("\n- due_date (date as YYYY-MM-DD)\n"));
+    assert!(prompts[1].ends_with("\n- document (text): Kind of document"));
+}
+
+#[test]
+fn schema_constraint_allows_only_typed_json() {
+    let schema = ExtractionSchema {
+        fields: vec![
+            field("total", FieldKind::Number, &[], None),
+            field("paid", FieldKind::Boolean, &[], None),
+            field("name", FieldKind::Text, &[], None),
+        ],
+    };
+    let pieces = [
+        r#"{"total": "#,
+        "12",
+        ".5",
+        r#", "paid": "#,
+        "true",
+        "null",
+        r#", "name": "#,
+        "\"Jane",
+        " Doe\"",
+        "}",
+        "abc",
+        "\"",
+        "</s>",
+    ];
+    let vocab: HashMap<String, u32> = pieces
+        .iter()
+        .enumerate()
+        .map(|(id, piece)| (piece.to_string(), id as u32))
+        .collect();
+    let tokenizer = Tokenizer::new(
+        WordLevel::builder()
+            .vocab(vocab.into_iter().collect())
+            .unk_token("</s>".into())
+            .build()
+            .unwrap(),
+    );
+    let constraint = Extractor::new(&schema).unwrap().constraint(&tokenizer);
+    let allowed = |generated: &[i64]| -> Vec<usize> {
+        let mut logits = vec![0.0; pieces.len()];
+        constraint.mask(generated, Some(12), &mut logits);
+        (0..logits.len()).filter(|&id| logits[id] == 0.0).collect()
+    };
+    assert_eq!(allowed(&[]), [0]);
+    assert_eq!(allowed(&[0]), [1, 5]);
+    // A number may go on or end.
+    assert_eq!(allowed(&[0, 1]), [1, 2, 3]);
+    assert_eq!(allowed(&[0, 1, 2, 3]), [4, 5]);
+    assert_eq!(allowed(&[0, 1, 2, 3, 4, 6]), [5, 7, 11]);
+    // Any text continues a string, but it must close before the next literal.
+    assert_eq!(allowed(&[0, 1, 2, 3, 4, 6, 7]), [1, 2, 4, 5, 8, 9, 10, 11]);
+    assert_eq!(allowed(&[0, 1, 2, 3, 4, 6, 7, 8]), [9]);
+    assert_eq!(allowed(&[0, 1, 2, 3, 4, 6, 7, 8, 9]), [12]);
+    // Off the grammar, decoding can only stop.
+    assert_eq!(allowed(&[10]), [12]);
+}
@@ -0,0 +1,106 @@
+use deepseek_ocr_core::{formats::COMPILED_FORMATS, orientation::OrientationMode};
+
+#[cfg(not(feature = "bmp"))]
+#[test]
+fn missing_format_errors_list_compiled_formats() {
+    use deepseek_ocr_core::orientation::decode_image;
+
+    let mut bmp = b"BM".to_vec();
+    bmp.resize(64, 0);
+    let err = decode_image(&bmp, OrientationMode::Exif).unwrap_err();
+    let message = format!("{err:#}");
+    assert!(
+        message.contains(&COMPILED_FORMATS.join(", ")),
+        "unexpected error: {message}"
+    );
+}
+
+#[cfg(feature = "tiff")]
+#[test]
+fn multi_page_tiffs_expand_into_pages() {
+    use std::io::Cursor;
+
+    use deepseek_ocr_core::orientation::decode_pages;
+    use tiff::encoder::{TiffEncoder, colortype};
+
+    assert!(COMPILED_FORMATS.contains(&"tiff"));
+    let mut bytes = Cursor::new(Vec::new());
+    {
+        let mut encoder = TiffEncoder::new(&mut bytes).unwrap();
+        encoder
+            .write_image::<colortype::Gray8>(40, 30, &[200u8; 40 * 30])
+            .unwrap();
+        encoder
+            .write_image::<colortype::RGB8>(20, 50, &[10u8; 20 * 50 * 3])
+            .unwrap();
+    }
+    let pages = decode_pages(bytes.get_ref(), OrientationMode::Exif).unwrap();
+    let sizes: Vec<_> = pages
+        .iter()
+        .map(|page| (page.image.width(), page.image.height()))
+        .collect();
+    assert_eq!(sizes, [(40, 30), (20, 50)]);
+    assert_eq!(pages[1].image.to_rgb8().get_pixel(0, 0).0, [10, 10, 10]);
+}
+
+/// A single-strip little-endian TIFF holding `data`, with `tags` as `(tag, value)` pairs on top of
+/// the size and strip layout.
+#[cfg(feature = "tiff")]
+fn bilevel_tiff(width: u32, height: u32, tags: &[(u16, u32)], data: &[u8]) -> Vec<u8> {
+    let mut entries = vec![
+        (256, 4, width),
+        (257, 4, height),
+        (258, 3, 1),
+        (278, 4, height),
+        (279, 4, data.len() as u32),
+    ];
+    entries.extend(tags.iter().map(|&(tag, value)| (tag, 3, value)));
+    let data_offset = 8 + 2 + (entries.len() as u32 + 1) * 12 + 4;
+    entries.push((273, 4, data_offset));
+    entries.sort_by_key(|&(tag, ..)| tag);
+
+    let mut bytes = b"II*\0".to_vec();
+    bytes.extend(8u32.to_le_bytes());
+    bytes.extend((entries.len() as u16).to_le_bytes());
+    for (tag, kind, value) in entries {
+        bytes.extend(tag.to_le_bytes());
+        bytes.extend((kind as u16).to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(value.to_le_bytes());
+    }
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend(data);
+    bytes
+}
+
+#[cfg(feature = "tiff")]
+#[test]
+fn truncated_bilevel_tiffs_are_rejected() {
+    use deepseek_ocr_core::orientation::decode_pages;
+
+    // Uncompressed BlackIsZero, with two of the eight bytes a 16x4 page needs.
+    let tiff = bilevel_tiff(16, 4, &[(259, 1), (262, 1)], &[0xFF, 0x00]);
+    assert!(decode_pages(&tiff, OrientationMode::Exif).is_err());
+}
+
+#[cfg(feature = "tiff")]
+#[test]
+fn group3_fax_tiffs_decode() {
+    use deepseek_ocr_core::orientation::decode_pages;
+
+    // Two one-dimensional T.4 rows of eight white then eight black pixels, each after an EOL,
+    // followed by the return-to-control marker.
+    let fax = [
+        0x00, 0x19, 0x8A, 0x00, 0x33, 0x14, 0x00, 0x40, 0x04, 0x00, 0x40, 0x04, 0x00, 0x40, 0x04,
+    ];
+    // Compression 3 (Group 3), WhiteIsZero.
+    let tiff = bilevel_tiff(16, 2, &[(259, 3), (262, 0)], &fax);
+    let pages = decode_pages(&tiff, OrientationMode::Exif).unwrap();
+    assert_eq!(pages.len(), 1);
+    let gray = pages[0].image.to_luma8();
+    assert_eq!(gray.dimensions(), (16, 2));
+    for y in 0..2 {
+        assert_eq!(gray.get_pixel(7, y).0, [255]);
+        assert_eq!(gray.get_pixel(8, y).0, [0]);
+    }
+}
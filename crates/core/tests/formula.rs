@@ -0,0 +1,197 @@
+#[cfg(feature = "mathml")]
+use deepseek_ocr_core::formula::{mathml::to_mathml, omml::to_omml};
+use deepseek_ocr_core::{
+    formula::{FormulaMode, MathDelimiters, check_balance, find_formulas, normalize_formulas},
+    polish::{PolishConfig, polish},
+};
+#[cfg(feature = "mathml")]
+use roxmltree::Document;
+
+#[test]
+fn delimiters_are_normalised_outside_code_and_prices() {
+    let text = "Energy \\(E = mc^2\\) and $a+b$ cost $5 and $10.\n\
+        \\[\\int_0^1 x\\,dx\\]\n\
+        `$not$ math` then\n```\n$$x$$\n```\n\
+        \\begin{equation}\ny = \\frac{1}{2}\n\\end{equation}";
+    let spans = find_formulas(text);
+    let latex: Vec<_> = spans.iter().map(|span| span.latex.as_str()).collect();
+    assert_eq!(
+        latex,
+        ["E = mc^2", "a+b", "\\int_0^1 x\\,dx", "y = \\frac{1}{2}"]
+    );
+    assert_eq!(
+        spans.iter().map(|span| span.display).collect::<Vec<_>>(),
+        [false, false, true, true]
+    );
+
+    let (dollar, issues) = normalize_formulas(text, FormulaMode::Latex, MathDelimiters::Dollar);
+    assert!(issues.is_empty());
+    assert_eq!(
+        dollar,
+        "Energy $E = mc^2$ and $a+b$ cost $5 and $10.\n\
+         $$\n\\int_0^1 x\\,dx\n$$\n\
+         `$not$ math` then\n```\n$$x$$\n```\n\
+         $$\ny = \\frac{1}{2}\n$$"
+    );
+
+    let (bracket, _) = normalize_formulas(
+        "Inline $x^2$ then $$y$$ done",
+        FormulaMode::Latex,
+        MathDelimiters::Bracket,
+    );
+    assert_eq!(bracket, "Inline \\(x^2\\) then\n\\[\ny\n\\]\ndone");
+
+    let config = PolishConfig::default();
+    assert!(!config.enabled());
+    assert_eq!(polish(text, &config).text, text);
+}
+
+#[test]
+fn unbalanced_formulas_are_reported_and_truncation_repaired() {
+    assert!(
+        check_balance("\\left( \\frac{a}{b} \\right)")
+            .problems
+            .is_empty()
+    );
+
+    let truncated = check_balance("\\begin{pmatrix} \\left[ \\frac{1}{2");
+    assert_eq!(
+        truncated.problems,
+        [
+            "unclosed {",
+            "\\left without a matching \\right",
+            "unclosed \\begin{pmatrix}"
+        ]
+    );
+    assert_eq!(
+        truncated.closers.as_deref(),
+        Some("}\\right.\\end{pmatrix}")
+    );
+
+    let broken = check_balance("a} + \\right)");
+    assert_eq!(broken.problems.len(), 2);
+    assert_eq!(broken.closers, None);
+
+    let text = "Result: $$\\sqrt{x + \\frac{1}{2";
+    let polished = polish(
+        text,
+        &PolishConfig {
+            formulas: FormulaMode::Latex,
+            ..PolishConfig::default()
+        },
+    );
+    assert_eq!(polished.text, "Result:\n$$\n\\sqrt{x + \\frac{1}{2}}\n$$");
+    let messages: Vec<_> = polished
+        .formula_issues
+        .iter()
+        .map(|issue| (issue.formula, issue.message.as_str(), issue.repaired))
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            (0, "missing closing delimiter", true),
+            (0, "unclosed {", true),
+            (0, "unclosed {", true),
+        ]
+    );
+}
+
+#[cfg(feature = "mathml")]
+#[test]
+fn mathml_output_is_well_formed_presentation_markup() {
+    let math = to_mathml(
+        "\\sum_{i=1}^{n} x_i^2 = \\frac{\\alpha}{\\sqrt[3]{\\mathbb{R}}} + \\left| \\hat{v} \\right| \\text{ if } a<b",
+        true,
+    );
+    let doc = Document::parse(&math).unwrap();
+    let root = doc.root_element();
+    assert_eq!(root.tag_name().name(), "math");
+    assert_eq!(
+        root.tag_name().namespace(),
+        Some("http://www.w3.org/1998/Math/MathML")
+    );
+    assert_eq!(root.attribute("display"), Some("block"));
+
+    let tags: Vec<_> = root
+        .descendants()
+        .filter(|node| node.is_element())
+        .map(|node| node.tag_name().name())
+        .collect();
+    for tag in ["munderover", "msubsup", "mfrac", "mroot", "mover", "mtext"] {
+        assert!(tags.contains(&tag), "missing <{tag}> in {math}");
+    }
+    let text: String = root.descendants().filter_map(|node| node.text()).collect();
+    for expected in ["∑", "α", "ℝ", "<", " if "] {
+        assert!(text.contains(expected), "missing {expected:?} in {text:?}");
+    }
+
+    let matrix = to_mathml(
+        "\\begin{pmatrix} a & b \\\\ c & d \\end{pmatrix} \\unknown",
+        false,
+    );
+    let doc = Document::parse(&matrix).unwrap();
+    let cells = doc
+        .descendants()
+        .filter(|node| node.has_tag_name("mtd"))
+        .count();
+    let rows = doc
+        .descendants()
+        .filter(|node| node.has_tag_name("mtr"))
+        .count();
+    assert_eq!((rows, cells), (2, 4));
+    assert!(doc.descendants().any(|node| node.has_tag_name("merror")));
+
+    let (text, _) = normalize_formulas(
+        "Area $\\pi r^2$.",
+        FormulaMode::Mathml,
+        MathDelimiters::Dollar,
+    );
+    assert!(text.starts_with("Area <math xmlns="));
+    assert!(text.ends_with(
+        "display=\"inline\"><mrow><mi>π</mi><msup><mi>r</mi><mn>2</mn></msup></mrow></math>."
+    ));
+}
+
+#[cfg(feature = "mathml")]
+#[test]
+fn omml_maps_mathml_onto_word_equations() {
+    const MATH_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/math";
+    let omml = to_omml(
+        "\\sum_{i=1}^{n} \\sqrt{x_i^2} + \\hat{v} \\left( \\begin{matrix} a & b \\\\ c \\end{matrix} \\right)",
+        true,
+    );
+    let wrapped = format!("<doc xmlns:m=\"{MATH_NS}\">{omml}</doc>");
+    let doc = Document::parse(&wrapped).unwrap();
+    let math = |name: &str| {
+        doc.descendants()
+            .filter(|node| node.has_tag_name((MATH_NS, name)))
+            .collect::<Vec<_>>()
+    };
+    let attribute = |node: roxmltree::Node, child: &str| {
+        node.descendants()
+            .find(|node| node.has_tag_name((MATH_NS, child)))
+            .and_then(|node| node.attribute((MATH_NS, "val")))
+            .map(str::to_owned)
+    };
+
+    assert_eq!(math("oMathPara").len(), 1);
+    let nary = math("nary");
+    assert_eq!(nary.len(), 1);
+    assert_eq!(attribute(nary[0], "chr").as_deref(), Some("∑"));
+    assert_eq!(attribute(nary[0], "limLoc").as_deref(), Some("undOvr"));
+    assert_eq!(math("rad").len(), 1);
+    assert_eq!(math("sSubSup").len(), 1);
+    let accent = math("acc");
+    assert_eq!(accent.len(), 1);
+    assert_eq!(attribute(accent[0], "chr").as_deref(), Some("^"));
+    let fence = math("d");
+    assert_eq!(fence.len(), 1);
+    assert_eq!(attribute(fence[0], "begChr").as_deref(), Some("("));
+    assert_eq!(attribute(fence[0], "endChr").as_deref(), Some(")"));
+    // The short second row is padded to the two columns of the first.
+    let rows: Vec<usize> = math("mr")
+        .into_iter()
+        .map(|row| row.children().filter(|cell| cell.is_element()).count())
+        .collect();
+    assert_eq!(rows, [2, 2]);
+}
@@ -0,0 +1,91 @@
+use deepseek_ocr_core::{
+    layout::{LayoutConfig, LayoutMode, plan_layout, split_layout},
+    segments::Region,
+};
+use image::{DynamicImage, GrayImage, Luma};
+
+/// A white page with one block of jittered "text lines" per `(left, right)` column span.
+fn synthetic_page(width: u32, columns: &[(u32, u32)]) -> DynamicImage {
+    let height = 400u32;
+    let mut page = GrayImage::from_pixel(width, height, Luma([255]));
+    let mut seed = 0x1234_5678u32;
+    let mut next = |modulo: u32| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        (seed >> 16) % modulo
+    };
+    for &(left, right) in columns {
+        for line in 0..16 {
+            let top = 20 + line * 22;
+            let mut x = left + next(4);
+            while x + 6 < right {
+                let glyph = 3 + next(4);
+                for dx in 0..glyph {
+                    for y in top..top + 10 {
+                        page.put_pixel(x + dx, y, Luma([25]));
+                    }
+                }
+                x += glyph + 2 + next(3);
+            }
+        }
+    }
+    DynamicImage::ImageLuma8(page)
+}
+
+fn config(mode: LayoutMode) -> LayoutConfig {
+    LayoutConfig {
+        mode,
+        ..LayoutConfig::default()
+    }
+}
+
+#[test]
+fn spreads_split_at_the_gutter() {
+    let spread = synthetic_page(840, &[(30, 400), (440, 810)]);
+    let regions = plan_layout(&spread, &config(LayoutMode::Spread));
+    assert_eq!(regions.len(), 2, "{regions:?}");
+    // The cut lands inside the gutter and the units cover the page edge to edge.
+    assert!((400..=440).contains(&regions[1].x), "{regions:?}");
+    assert_eq!(regions[0].x, 0);
+    assert_eq!(regions[0].width + regions[1].width, 840);
+
+    assert_eq!(plan_layout(&spread, &config(LayoutMode::Off)).len(), 1);
+    let single = synthetic_page(840, &[(30, 810)]);
+    assert_eq!(plan_layout(&single, &config(LayoutMode::Spread)).len(), 1);
+}
+
+#[test]
+fn column_mode_finds_every_gap_up_to_the_limit() {
+    let page = synthetic_page(900, &[(20, 280), (320, 580), (620, 880)]);
+    let regions = plan_layout(&page, &config(LayoutMode::Columns));
+    assert_eq!(regions.len(), 3, "{regions:?}");
+    assert!(regions.windows(2).all(|pair| pair[0].x < pair[1].x));
+
+    // Only the gap nearest the middle counts as a spread gutter.
+    assert_eq!(plan_layout(&page, &config(LayoutMode::Spread)).len(), 1);
+
+    let capped = LayoutConfig {
+        max_columns: 2,
+        ..config(LayoutMode::Columns)
+    };
+    assert_eq!(plan_layout(&page, &capped).len(), 2);
+}
+
+#[test]
+fn split_outputs_join_in_reading_order_with_boxes_in_page_coordinates() {
+    let spread = split_layout(
+        synthetic_page(840, &[(30, 400), (440, 810)]),
+        &config(LayoutMode::Spread),
+    );
+    assert!(spread.is_split());
+    let Region { x, width, .. } = spread.segments[1].region;
+    let merged = spread.merge(&[
+        "Left page".to_string(),
+        "<|ref|>Right<|/ref|><|det|>[[0, 0, 999, 999]]<|/det|>".to_string(),
+    ]);
+    let x0 = (x as f32 / 840.0 * 999.0).round() as u32;
+    let x1 = ((x + width) as f32 / 840.0 * 999.0).round() as u32;
+    assert_eq!(
+        merged,
+        format!("Left page\n\n<|ref|>Right<|/ref|><|det|>[[{x0}, 0, {x1}, 999]]<|/det|>")
+    );
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::ScriptedEngine;
+use deepseek_ocr_core::{
+    DecodeParameters, ModelKind, VisionSettings,
+    pipeline::locate::{BoundingBox, locate, parse_locations},
+};
+use image::{DynamicImage, RgbImage};
+use tokenizers::{Tokenizer, models::wordlevel::WordLevel};
+
+#[test]
+fn locate_prompts_for_the_query_and_maps_boxes_to_pixels() {
+    let engine = ScriptedEngine::with_replies(
+        ModelKind::Deepseek,
+        &["<|ref|>Total<|/ref|><|det|>[[100, 200, 300, 250], [999, 0, 500, 999]]<|/det|>"],
+    );
+    let tokenizer = Tokenizer::new(WordLevel::default());
+    let image = DynamicImage::ImageRgb8(RgbImage::new(1998, 999));
+    let boxes = locate(
+        &engine,
+        &tokenizer,
+        &image,
+        " Total ",
+        VisionSettings::default(),
+        &DecodeParameters::with_sampling_defaults(64),
+    )
+    .unwrap();
+    assert_eq!(
+        engine.prompts.lock().unwrap().as_slice(),
+        ["<image>\nLocate <|ref|>Total<|/ref|> in the image."]
+    );
+    assert_eq!(
+        boxes,
+        [
+            BoundingBox::from([200, 200, 600, 250]),
+            BoundingBox::from([1000, 0, 1998, 999]),
+        ]
+    );
+    assert_eq!((boxes[0].width(), boxes[0].height()), (400, 50));
+
+    let paddle = ScriptedEngine::with_replies(ModelKind::PaddleOcrVl, &[""]);
+    let err = locate(
+        &paddle,
+        &tokenizer,
+        &image,
+        "Total",
+        VisionSettings::default(),
+        &DecodeParameters::with_sampling_defaults(64),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("not available"), "{err}");
+    assert!(paddle.prompts.lock().unwrap().is_empty());
+}
+
+#[test]
+fn locations_skip_repeats_and_empty_boxes() {
+    let text = "<|ref|>date<|/ref|><|det|>[[10, 10, 20, 20]]<|/det|>\n\
+                <|ref|>date<|/ref|><|det|>[[10, 10, 20, 20], [30, 30, 30, 40]]<|/det|>\n\
+                <|ref|>date<|/ref|><|det|>[[40, 50, 60, 70]]<|/det|>";
+    let boxes: Vec<[u32; 4]> = parse_locations(text, 999, 999)
+        .into_iter()
+        .map(BoundingBox::to_array)
+        .collect();
+    assert_eq!(boxes, [[10, 10, 20, 20], [40, 50, 60, 70]]);
+    assert!(parse_locations("No match in the image.", 999, 999).is_empty());
+}
@@ -0,0 +1,103 @@
+use deepseek_ocr_core::orientation::{
+    OrientationMode, OrientationReport, Rotation, detect_rotation, orient_image,
+};
+use image::{DynamicImage, GrayImage, Luma, metadata::Orientation};
+
+/// A white page with dark "text lines": a dense x-height body, frequent ascenders above it and
+/// sparse descenders below, with glyph positions jittered per line like real prose.
+fn synthetic_page() -> DynamicImage {
+    let (width, height) = (480u32, 360u32);
+    let mut page = GrayImage::from_pixel(width, height, Luma([255]));
+    let mut seed = 0x2545_f491u32;
+    let mut next = |modulo: u32| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        (seed >> 16) % modulo
+    };
+    for line in 0..14 {
+        let top = 20 + line * 24;
+        let mut x = 16 + next(6);
+        while x + 8 < width - 16 {
+            let glyph = 3 + next(4);
+            let ascender = next(3) == 0;
+            let descender = next(8) == 0;
+            for dx in 0..glyph {
+                for y in top + 4..top + 12 {
+                    page.put_pixel(x + dx, y, Luma([20]));
+                }
+                if ascender && dx < 2 {
+                    for y in top..top + 4 {
+                        page.put_pixel(x + dx, y, Luma([20]));
+                    }
+                }
+                if descender && dx < 2 {
+                    for y in top + 12..top + 16 {
+                        page.put_pixel(x + dx, y, Luma([20]));
+                    }
+                }
+            }
+            x += glyph + 2 + next(3) + if next(5) == 0 { 6 } else { 0 };
+        }
+    }
+    DynamicImage::ImageLuma8(page)
+}
+
+#[test]
+fn detects_quarter_and_half_turns() {
+    let page = synthetic_page();
+    assert_eq!(detect_rotation(&page), Rotation::None);
+    // A page turned 90 degrees clockwise needs the opposite quarter turn to be upright again.
+    assert_eq!(detect_rotation(&page.rotate90()), Rotation::Cw270);
+    assert_eq!(detect_rotation(&page.rotate270()), Rotation::Cw90);
+    assert_eq!(detect_rotation(&page.rotate180()), Rotation::Cw180);
+}
+
+#[test]
+fn exif_orientation_is_applied_before_detection() {
+    let page = synthetic_page();
+    // EXIF 6 means the stored pixels must be turned 90 degrees clockwise for display.
+    let stored = page.rotate270();
+    let oriented = orient_image(
+        stored.clone(),
+        Some(Orientation::Rotate90),
+        OrientationMode::Auto,
+    );
+    assert_eq!(oriented.report.exif_orientation, Some(6));
+    assert_eq!(oriented.report.rotation, Rotation::None);
+    assert_eq!(
+        (oriented.report.width, oriented.report.height),
+        (page.width(), page.height())
+    );
+
+    let untouched = orient_image(stored, None, OrientationMode::Exif);
+    assert_eq!(untouched.report.exif_orientation, None);
+    assert_eq!(untouched.report.rotation, Rotation::None);
+}
+
+#[test]
+fn boxes_map_back_onto_the_upright_image() {
+    let report = |rotation| OrientationReport {
+        exif_orientation: None,
+        rotation,
+        width: 100,
+        height: 100,
+    };
+    // Top-left corner region of the upright page.
+    let upright = [0.0, 0.0, 0.2, 0.1];
+    for rotation in [
+        Rotation::None,
+        Rotation::Cw90,
+        Rotation::Cw180,
+        Rotation::Cw270,
+    ] {
+        let seen = match rotation {
+            Rotation::None => upright,
+            Rotation::Cw90 => [0.9, 0.0, 1.0, 0.2],
+            Rotation::Cw180 => [0.8, 0.9, 1.0, 1.0],
+            Rotation::Cw270 => [0.0, 0.8, 0.1, 1.0],
+        };
+        let mapped = report(rotation).to_upright_box(seen);
+        for (got, want) in mapped.iter().zip(upright) {
+            assert!((got - want).abs() < 1e-6, "{rotation:?}: {mapped:?}");
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::io::{Cursor, Read};
+
+use deepseek_ocr_core::{
+    cleanup::{CleanupReport, CropRect},
+    export::{
+        OcrPage, OutputFormat,
+        pdf::{self, PDF_DPI, PageScan},
+    },
+    orientation::{OrientationReport, Rotation},
+};
+use flate2::read::ZlibDecoder;
+use image::{DynamicImage, GrayImage, ImageFormat, Luma, RgbImage};
+
+const GROUNDED: &str = include_str!("fixtures/grounded_page.txt");
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Body of object `id`, located through the cross-reference table.
+fn object(pdf: &[u8], id: usize) -> &[u8] {
+    let text = String::from_utf8_lossy(pdf);
+    let xref = text[text.rfind("startxref\n").unwrap() + 10..]
+        .lines()
+        .next()
+        .unwrap()
+        .parse::<usize>()
+        .unwrap();
+    let entry = &pdf[xref + "xref\n0 ".len()..];
+    let header = find(entry, b"\n").unwrap() + 1;
+    let row = &entry[header + 20 * id..header + 20 * id + 10];
+    let offset: usize = std::str::from_utf8(row).unwrap().parse().unwrap();
+    let body = &pdf[offset..];
+    assert!(
+        body.starts_with(format!("{id} 0 obj\n").as_bytes()),
+        "xref entry {id} does not point at its object"
+    );
+    &body[..find(body, b"endobj").unwrap()]
+}
+
+fn inflate_stream(object: &[u8]) -> Vec<u8> {
+    let start = find(object, b"stream\n").unwrap() + 7;
+    let end = find(object, b"\nendstream").unwrap();
+    let mut out = Vec::new();
+    ZlibDecoder::new(&object[start..end])
+        .read_to_end(&mut out)
+        .expect("stream must be valid zlib data");
+    out
+}
+
+#[test]
+fn pdf_pages_keep_image_resolution_under_an_invisible_text_layer() {
+    let first = OcrPage::from_grounded(GROUNDED, 1000, 1400);
+    let second = OcrPage::from_grounded("Überschrift 中文", 600, 300);
+    let images = [
+        DynamicImage::ImageRgb8(RgbImage::new(1000, 1400)),
+        DynamicImage::ImageLuma8(GrayImage::new(600, 300)),
+    ];
+    let pdf = OutputFormat::Pdf
+        .render(&[first, second.clone()], &images)
+        .expect("render pdf");
+
+    assert!(pdf.starts_with(b"%PDF-1.4\n"));
+    assert!(pdf.ends_with(b"%%EOF\n"));
+    let pages = String::from_utf8_lossy(object(&pdf, 2)).into_owned();
+    assert!(pages.contains("/Count 2"), "{pages}");
+
+    let scale = 72.0 / PDF_DPI;
+    for (idx, (width, height, color)) in [(1000, 1400, "DeviceRGB"), (600, 300, "DeviceGray")]
+        .into_iter()
+        .enumerate()
+    {
+        let page_id = 10 + 3 * idx;
+        let page = String::from_utf8_lossy(object(&pdf, page_id)).into_owned();
+        let media_box = format!(
+            "/MediaBox [0 0 {:.2} {:.2}]",
+            width as f32 * scale,
+            height as f32 * scale
+        );
+        assert!(page.contains(&media_box), "{page}");
+
+        let image = object(&pdf, page_id + 2);
+        let dict = String::from_utf8_lossy(&image[..find(image, b"stream\n").unwrap()]);
+        assert!(dict.contains(&format!("/Width {width} /Height {height}")));
+        assert!(dict.contains(&format!("/ColorSpace /{color}")));
+        let channels = if color == "DeviceRGB" { 3 } else { 1 };
+        assert_eq!(inflate_stream(image).len(), width * height * channels);
+    }
+
+    let content = String::from_utf8(inflate_stream(object(&pdf, 14))).unwrap();
+    assert!(content.contains("/Im0 Do"));
+    assert!(content.contains("3 Tr"), "text must be invisible");
+    let codes: String = second.blocks[0].lines[0]
+        .chars()
+        .map(|ch| format!("{:04X}", u32::from(ch)))
+        .collect();
+    assert!(content.contains(&format!("<{codes}> Tj")), "{content}");
+
+    assert!(
+        OutputFormat::Pdf
+            .render(&[OcrPage::from_grounded("text", 10, 10)], &[])
+            .is_err()
+    );
+}
+
+fn stream_bytes(object: &[u8]) -> &[u8] {
+    let start = find(object, b"stream\n").unwrap() + 7;
+    &object[start..find(object, b"\nendstream").unwrap()]
+}
+
+fn jpeg(width: u32, height: u32) -> Vec<u8> {
+    let gray = GrayImage::from_fn(width, height, |x, y| Luma([(x * 6 + y) as u8]));
+    let mut bytes = Cursor::new(Vec::new());
+    DynamicImage::ImageLuma8(gray)
+        .write_to(&mut bytes, ImageFormat::Jpeg)
+        .unwrap();
+    bytes.into_inner()
+}
+
+#[test]
+fn jpeg_scans_are_embedded_unchanged_and_turned_by_the_image_matrix() {
+    let bytes = jpeg(40, 20);
+    let stored = image::load_from_memory(&bytes).unwrap();
+    let upright = stored.rotate90();
+    let scan = PageScan {
+        image: upright.clone(),
+        source_jpeg: Some(bytes.clone().into()),
+        orientation: Some(OrientationReport {
+            exif_orientation: None,
+            rotation: Rotation::Cw90,
+            width: upright.width(),
+            height: upright.height(),
+        }),
+        cleanup: None,
+    };
+    let page = OcrPage::from_grounded("text", 20, 40);
+    let pdf = pdf::render(&[page], &[scan]).unwrap();
+
+    let image = object(&pdf, 12);
+    let dict = String::from_utf8_lossy(&image[..find(image, b"stream\n").unwrap()]);
+    assert!(dict.contains("/Filter /DCTDecode"), "{dict}");
+    assert!(
+        dict.contains("/Width 40 /Height 20 /ColorSpace /DeviceGray"),
+        "{dict}"
+    );
+    assert_eq!(stream_bytes(image), bytes.as_slice());
+
+    // The page is the upright 20x40 image; its top-left corner shows the stored bottom-left.
+    let page = String::from_utf8_lossy(object(&pdf, 10)).into_owned();
+    assert!(page.contains("/MediaBox [0 0 4.80 9.60]"), "{page}");
+    let content = String::from_utf8(inflate_stream(object(&pdf, 11))).unwrap();
+    assert!(
+        content.contains("0.00 -9.60 4.80 0.00 0.00 9.60 cm"),
+        "{content}"
+    );
+}
+
+#[test]
+fn text_boxes_are_mapped_from_the_cleaned_image_onto_the_scan() {
+    // Cleanup cropped the right half of a 200x100 scan; the OCR page is that 100x100 crop.
+    let cleanup = CleanupReport {
+        border: None,
+        skew_degrees: 0.0,
+        crop: Some(CropRect {
+            x: 100,
+            y: 0,
+            width: 100,
+            height: 100,
+        }),
+        source_width: 200,
+        source_height: 100,
+        width: 100,
+        height: 100,
+    };
+    let scan = PageScan {
+        cleanup: Some(cleanup),
+        ..PageScan::new(DynamicImage::ImageLuma8(GrayImage::new(200, 100)))
+    };
+    let page = OcrPage::from_grounded(
+        "<|ref|>text<|/ref|><|det|>[[0, 0, 999, 999]]<|/det|>\nright",
+        100,
+        100,
+    );
+    let pdf = pdf::render(&[page], &[scan]).unwrap();
+
+    let content = String::from_utf8(inflate_stream(object(&pdf, 11))).unwrap();
+    assert!(
+        content.contains("48.00 0.00 0.00 24.00 0.00 0.00 cm"),
+        "{content}"
+    );
+    assert!(
+        content.contains("1 0 0 1 24.00 "),
+        "the line must start mid-page: {content}"
+    );
+}
+
+#[cfg(feature = "pdf")]
+#[test]
+fn rendered_pdfs_read_back_as_scans() {
+    use deepseek_ocr_core::orientation::{OrientationMode, decode_pages};
+
+    let gray = GrayImage::from_fn(30, 20, |x, y| Luma([(x * 8 + y) as u8]));
+    let bytes = jpeg(24, 16);
+    let jpeg_scan = PageScan {
+        source_jpeg: Some(bytes.clone().into()),
+        ..PageScan::new(image::load_from_memory(&bytes).unwrap())
+    };
+    let pages = [
+        OcrPage::from_grounded("first", 30, 20),
+        OcrPage::from_grounded("second", 24, 16),
+    ];
+    let pdf = pdf::render(
+        &pages,
+        &[
+            PageScan::new(DynamicImage::ImageLuma8(gray.clone())),
+            jpeg_scan,
+        ],
+    )
+    .unwrap();
+
+    let scans = decode_pages(&pdf, OrientationMode::Exif).unwrap();
+    assert_eq!(scans.len(), 2);
+    assert_eq!(scans[0].image.to_luma8(), gray);
+    assert!(scans[0].source_jpeg.is_none());
+    assert_eq!((scans[1].image.width(), scans[1].image.height()), (24, 16));
+    assert_eq!(scans[1].source_jpeg.as_deref(), Some(bytes.as_slice()));
+}
@@ -0,0 +1,83 @@
+use deepseek_ocr_core::{
+    formula::FormulaMode,
+    polish::{PolishConfig, polish},
+    task::OcrTask,
+};
+
+/// Polish `input` with `config` and compare against the expected fixture, ignoring the final
+/// newline of the files.
+fn check(input: &str, expected: &str, config: PolishConfig) {
+    assert!(config.enabled());
+    let polished = polish(input.trim_end(), &config);
+    assert_eq!(polished.text, expected.trim_end());
+    // Rules are idempotent.
+    assert_eq!(polish(&polished.text, &config).text, polished.text);
+}
+
+#[test]
+fn each_rule_matches_its_fixture() {
+    check(
+        include_str!("fixtures/polish/grounding.md"),
+        include_str!("fixtures/polish/grounding.expected.md"),
+        PolishConfig {
+            strip_grounding: true,
+            ..PolishConfig::default()
+        },
+    );
+    check(
+        include_str!("fixtures/polish/hyphenation.md"),
+        include_str!("fixtures/polish/hyphenation.expected.md"),
+        PolishConfig {
+            merge_hyphenation: true,
+            ..PolishConfig::default()
+        },
+    );
+    check(
+        include_str!("fixtures/polish/paragraphs.md"),
+        include_str!("fixtures/polish/paragraphs.expected.md"),
+        PolishConfig {
+            join_paragraphs: true,
+            ..PolishConfig::default()
+        },
+    );
+    check(
+        include_str!("fixtures/polish/headings.md"),
+        include_str!("fixtures/polish/headings.expected.md"),
+        PolishConfig {
+            fix_headings: true,
+            ..PolishConfig::default()
+        },
+    );
+    check(
+        include_str!("fixtures/polish/tables.md"),
+        include_str!("fixtures/polish/tables.expected.md"),
+        PolishConfig {
+            markdown_tables: true,
+            ..PolishConfig::default()
+        },
+    );
+}
+
+#[test]
+fn rules_combine_and_grounding_is_kept_when_requested() {
+    let grounded = include_str!("fixtures/grounded_page.txt");
+    let config = PolishConfig {
+        strip_grounding: true,
+        markdown_tables: true,
+        formulas: FormulaMode::Latex,
+        merge_hyphenation: true,
+        join_paragraphs: true,
+        fix_headings: true,
+        ..PolishConfig::default()
+    };
+    let polished = polish(grounded, &config).text;
+    assert!(!polished.contains("<|ref|>") && !polished.contains("<|det|>"));
+    assert!(polished.starts_with("# Quarterly Report & Outlook\n\nRevenue grew 12%"));
+    assert!(polished.contains("| Region | Q1 | Q2 |\n| --- | --- | --- |\n| EMEA | 4.1 | 4.6 |"));
+
+    let grounding = config.for_task(Some(OcrTask::Grounding));
+    assert!(!grounding.strip_grounding);
+    assert!(polish(grounded, &grounding).text.contains("<|det|>"));
+    assert!(config.for_task(Some(OcrTask::Markdown)).strip_grounding);
+    assert!(!PolishConfig::default().enabled());
+}
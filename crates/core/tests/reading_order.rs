@@ -0,0 +1,136 @@
+use deepseek_ocr_core::{
+    export::{OcrPage, OutputFormat},
+    reading_order::{PageRegion, ReadingOrder, page_region, reading_order},
+};
+use roxmltree::Document;
+
+const TWO_COLUMNS: &str = include_str!("fixtures/two_column_page.txt");
+
+fn render(format: OutputFormat, page: OcrPage) -> String {
+    String::from_utf8(format.render(&[page], &[]).unwrap()).unwrap()
+}
+
+#[test]
+fn columns_are_read_in_turn_between_header_and_footer() {
+    let page = OcrPage::from_grounded(TWO_COLUMNS, 999, 999);
+    let regions: Vec<PageRegion> = page.blocks.iter().map(|block| block.region).collect();
+    assert_eq!(regions[3], PageRegion::Footer);
+    assert_eq!(regions[5], PageRegion::Header);
+    assert_eq!(
+        regions
+            .iter()
+            .filter(|&&region| region == PageRegion::Body)
+            .count(),
+        6
+    );
+    let orders: Vec<usize> = page.blocks.iter().map(|block| block.order).collect();
+    assert_eq!(orders, [1, 4, 2, 7, 3, 0, 5, 6]);
+
+    assert_eq!(
+        render(OutputFormat::Markdown, page.clone()),
+        "Journal of Examples, Vol. 3\n\n# Column Layout\n\nLeft one.\n\nLeft two.\n\n\
+         Right one.\n\nRight two.\n\nClosing paragraph.\n\n12"
+    );
+    let native = page.clone().with_reading_order(ReadingOrder::Native);
+    assert_eq!(
+        render(OutputFormat::Markdown, native),
+        "# Column Layout\n\nRight one.\n\nLeft one.\n\n12\n\nLeft two.\n\n\
+         Journal of Examples, Vol. 3\n\nRight two.\n\nClosing paragraph."
+    );
+
+    let alto = render(OutputFormat::Alto, page.clone());
+    let doc = Document::parse(&alto).unwrap();
+    let layout_page = doc
+        .descendants()
+        .find(|node| node.has_tag_name("Page"))
+        .unwrap();
+    let children: Vec<&str> = layout_page
+        .children()
+        .filter(|node| node.is_element())
+        .map(|node| node.tag_name().name())
+        .collect();
+    assert_eq!(children, ["TopMargin", "BottomMargin", "PrintSpace"]);
+    let print_space = layout_page.last_element_child().unwrap();
+    assert_eq!(print_space.attribute("VPOS"), Some("40"));
+    assert_eq!(print_space.attribute("HEIGHT"), Some("920"));
+    let refs: Vec<&str> = doc
+        .descendants()
+        .filter(|node| node.has_tag_name("ElementRef"))
+        .filter_map(|node| node.attribute("REF"))
+        .collect();
+    let blocks: Vec<&str> = doc
+        .descendants()
+        .filter(|node| node.has_tag_name("TextBlock"))
+        .filter_map(|node| node.attribute("ID"))
+        .collect();
+    // Block IDs follow the reading order even though the footer precedes the body in the file.
+    assert_eq!(
+        refs,
+        (1..=8).map(|idx| format!("P1_B{idx}")).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        blocks,
+        [
+            "P1_B1", "P1_B8", "P1_B2", "P1_B3", "P1_B4", "P1_B5", "P1_B6", "P1_B7"
+        ]
+    );
+
+    let hocr = render(OutputFormat::Hocr, page);
+    assert!(hocr.contains("ocr_carea ocr_par ocr_line ocrx_word ocr_photo ocr_header ocr_footer"));
+    assert!(hocr.contains("<div class=\"ocr_header\" id=\"block_1_1\""));
+    assert!(hocr.contains("<div class=\"ocr_footer\" id=\"block_1_8\""));
+}
+
+#[test]
+fn xy_cut_handles_spanning_blocks_and_overlaps() {
+    // A full-width figure between two column sections: each section is read column by
+    // column, and the figure in between.
+    let boxes = [
+        [0, 0, 45, 30],
+        [55, 0, 100, 30],
+        [0, 40, 100, 60],
+        [55, 70, 100, 100],
+        [0, 70, 45, 100],
+    ];
+    let regions = [PageRegion::Body; 5];
+    assert_eq!(reading_order(&boxes, &regions, 100, 100), [0, 1, 2, 4, 3]);
+
+    // Overlapping boxes cannot be cut and fall back to top-to-bottom, left-to-right.
+    let tangled = [[10, 50, 60, 90], [40, 10, 90, 60], [0, 20, 50, 70]];
+    assert_eq!(
+        reading_order(&tangled, &[PageRegion::Body; 3], 100, 100),
+        [1, 2, 0]
+    );
+
+    // Titles near the top stay in the body; labelled page numbers are footers anywhere.
+    assert_eq!(
+        page_region("title", [0, 0, 100, 5], 1, 100),
+        PageRegion::Body
+    );
+    assert_eq!(
+        page_region("page_number", [0, 40, 10, 45], 1, 100),
+        PageRegion::Footer
+    );
+    assert_eq!(
+        page_region("text", [0, 40, 100, 60], 1, 100),
+        PageRegion::Body
+    );
+}
+
+#[test]
+fn ungrounded_text_follows_the_block_emitted_before_it() {
+    let text = "Intro line.\n\n\
+        <|ref|>text<|/ref|><|det|>[[550, 100, 950, 300]]<|/det|>\nRight.\n\
+        <|ref|>text<|/ref|>\nNo box here.\n\
+        <|ref|>text<|/ref|><|det|>[[50, 100, 450, 300]]<|/det|>\nLeft.";
+    let page = OcrPage::from_grounded(text, 999, 999);
+    let grounded: Vec<bool> = page.blocks.iter().map(|block| block.grounded).collect();
+    assert_eq!(grounded, [false, true, false, true]);
+    // The made-up full-width boxes would otherwise block the column cut.
+    let orders: Vec<usize> = page.blocks.iter().map(|block| block.order).collect();
+    assert_eq!(orders, [0, 2, 3, 1]);
+    assert_eq!(
+        render(OutputFormat::Text, page),
+        "Intro line.\n\nLeft.\n\nRight.\n\nNo box here."
+    );
+}
@@ -0,0 +1,212 @@
+mod common;
+
+use common::ScriptedEngine;
+use deepseek_ocr_core::{
+    DecodeParameters, ModelKind, VisionSettings,
+    pipeline::{
+        locate::BoundingBox,
+        redact::{
+            DictionaryRule, PatternRule, RedactionArea, RedactionRules, Redactor, RuleKind,
+            black_out, find_redactions, luhn_valid, redact,
+        },
+    },
+};
+use image::{DynamicImage, Rgb, RgbImage};
+use tokenizers::{Tokenizer, models::wordlevel::WordLevel};
+
+const PAGE: &str = "Call JANE DOE later\n\n\
+                    <|ref|>text<|/ref|><|det|>[[0, 0, 999, 99]]<|/det|>\n\
+                    Card 4111 1111 1111 1111 ok\n\n\
+                    <|ref|>text<|/ref|><|det|>[[0, 100, 999, 299]]<|/det|>\n\
+                    Mail j.doe@example.org\nSigned by Jane Doe\n\n\
+                    <|ref|>text<|/ref|><|det|>[[0, 300, 999, 399]]<|/det|>\n\
+                    Ref 4111 1111 1111 1112";
+
+fn rules() -> RedactionRules {
+    RedactionRules {
+        patterns: vec![PatternRule {
+            name: "email".into(),
+            regex: r"[\w.+-]+@[\w-]+\.[\w.]+".into(),
+        }],
+        dictionaries: vec![DictionaryRule {
+            name: "names".into(),
+            terms: vec!["Jane".into(), "jane doe".into(), " ".into()],
+        }],
+        padding: 0,
+        area: RedactionArea::Words,
+        ..RedactionRules::default()
+    }
+}
+
+#[test]
+fn luhn_checks_digits_only() {
+    assert!(luhn_valid("4111 1111 1111 1111"));
+    assert!(luhn_valid("5500-0000-0000-0004"));
+    assert!(!luhn_valid("4111 1111 1111 1112"));
+    assert!(!luhn_valid("4111a1111"));
+    assert!(!luhn_valid("0"));
+}
+
+#[test]
+fn matches_cover_the_words_they_touch() {
+    let report = find_redactions(PAGE, 270, 1000, &Redactor::new(&rules()).unwrap());
+    let found: Vec<(&str, RuleKind, &str, Vec<[u32; 4]>)> = report
+        .redactions
+        .iter()
+        .map(|redaction| {
+            (
+                redaction.rule.as_str(),
+                redaction.kind,
+                redaction.masked.as_str(),
+                redaction.boxes.iter().map(|bbox| bbox.to_array()).collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            // Outside any grounding entry: the whole band assumed for the text is blacked out.
+            (
+                "names",
+                RuleKind::Dictionary,
+                "**** ***",
+                vec![[0, 0, 270, 1000]]
+            ),
+            // "Card 4111 1111 1111 1111 ok" is 27 characters over 270 pixels.
+            (
+                "card_number",
+                RuleKind::CardNumber,
+                "**** **** **** 1111",
+                vec![[50, 0, 240, 99]]
+            ),
+            (
+                "email",
+                RuleKind::Pattern,
+                "*.***@******e.org",
+                vec![[61, 100, 270, 199]]
+            ),
+            // The longer term wins over its prefix.
+            (
+                "names",
+                RuleKind::Dictionary,
+                "**** ***",
+                vec![[150, 199, 270, 299]]
+            ),
+        ]
+    );
+    assert_eq!(report.unplaced(), 1);
+    assert!(!report.complete);
+}
+
+#[test]
+fn lines_are_blacked_out_by_default_and_wrapped_matches_found() {
+    let rules = RedactionRules {
+        dictionaries: vec![DictionaryRule {
+            name: "names".into(),
+            terms: vec!["Jane Doe".into()],
+        }],
+        padding: 0,
+        ..RedactionRules::default()
+    };
+    let page = "<|ref|>text<|/ref|><|det|>[[0, 0, 999, 999]]<|/det|>\n\
+                Signed by Jane\nDoe on 4111 1111\n1111 1111 today";
+    let report = find_redactions(page, 100, 300, &Redactor::new(&rules).unwrap());
+    let boxes: Vec<Vec<[u32; 4]>> = report
+        .redactions
+        .iter()
+        .map(|redaction| redaction.boxes.iter().map(|bbox| bbox.to_array()).collect())
+        .collect();
+    assert_eq!(
+        boxes,
+        [
+            vec![[0, 0, 100, 100], [0, 100, 100, 200]],
+            vec![[0, 100, 100, 200], [0, 200, 100, 300]],
+        ]
+    );
+    assert!(report.complete);
+}
+
+#[test]
+fn truncated_output_is_incomplete() {
+    let mut engine = ScriptedEngine::with_replies(
+        ModelKind::Deepseek,
+        &["<|ref|>text<|/ref|><|det|>[[0, 0, 999, 99]]<|/det|>\nCard 4111 1111 1111 1111"],
+    );
+    engine.response_tokens = 64;
+    let image = DynamicImage::ImageRgb8(RgbImage::new(100, 100));
+    let redacted = redact(
+        &engine,
+        &Tokenizer::new(WordLevel::default()),
+        &image,
+        &Redactor::new(&RedactionRules::default()).unwrap(),
+        VisionSettings::default(),
+        &DecodeParameters::with_sampling_defaults(64),
+    )
+    .unwrap();
+    assert!(redacted.report.truncated);
+    assert!(!redacted.report.complete);
+    assert_eq!(redacted.report.unplaced(), 0);
+    assert_eq!(redacted.image.to_rgb8().get_pixel(50, 5), &Rgb([0, 0, 0]));
+}
+
+#[test]
+fn card_numbers_are_found_next_to_other_digits() {
+    let rules = RedactionRules {
+        padding: 0,
+        ..RedactionRules::default()
+    };
+    let redactor = Redactor::new(&rules).unwrap();
+    let page = "<|ref|>text<|/ref|><|det|>[[0, 0, 999, 999]]<|/det|>\n\
+                Card 4111 1111 1111 1111 12/25\n\
+                4111111111111111 5\n\
+                Ref 5500-0000-0000-0004-77 and 1234 5678 9012 3456";
+    let report = find_redactions(page, 100, 100, &redactor);
+    let masked: Vec<&str> = report
+        .redactions
+        .iter()
+        .map(|redaction| redaction.masked.as_str())
+        .collect();
+    assert_eq!(
+        masked,
+        [
+            "**** **** **** 1111",
+            "************1111",
+            "****-****-****-0004"
+        ]
+    );
+}
+
+#[test]
+fn black_out_fills_padded_boxes() {
+    let mut rules = rules();
+    rules.padding = 5;
+    let report = find_redactions(PAGE, 270, 1000, &Redactor::new(&rules).unwrap());
+    assert_eq!(
+        report.redactions[1].boxes,
+        [BoundingBox::from([45, 0, 245, 104])]
+    );
+    let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(270, 1000, Rgb([255, 255, 255])));
+    let redacted = black_out(&image, &report.redactions[1..2]).to_rgb8();
+    assert_eq!(redacted.get_pixel(45, 0), &Rgb([0, 0, 0]));
+    assert_eq!(redacted.get_pixel(244, 103), &Rgb([0, 0, 0]));
+    assert_eq!(redacted.get_pixel(245, 50), &Rgb([255, 255, 255]));
+    assert_eq!(redacted.get_pixel(10, 50), &Rgb([255, 255, 255]));
+}
+
+#[test]
+fn invalid_or_empty_rules_are_rejected() {
+    let mut rules = rules();
+    rules.patterns[0].regex = "[unclosed".into();
+    let err = Redactor::new(&rules).err().unwrap();
+    assert!(err.to_string().contains("`email`"), "{err}");
+
+    let none = RedactionRules {
+        card_numbers: false,
+        dictionaries: vec![DictionaryRule {
+            name: "blank".into(),
+            terms: vec![String::new()],
+        }],
+        ..RedactionRules::default()
+    };
+    assert!(Redactor::new(&none).is_err());
+}
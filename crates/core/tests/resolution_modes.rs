@@ -0,0 +1,56 @@
+use deepseek_ocr_core::inference::{ResolutionMode, VisionSettings};
+
+#[test]
+fn fixed_modes_report_single_view_token_counts() {
+    let counts: Vec<usize> = [
+        ResolutionMode::Tiny,
+        ResolutionMode::Small,
+        ResolutionMode::Base,
+        ResolutionMode::Large,
+    ]
+    .into_iter()
+    .map(|mode| mode.vision_tokens(None))
+    .collect();
+    assert_eq!(counts, vec![73, 111, 273, 421]);
+}
+
+#[test]
+fn gundam_adds_local_tiles_per_crop_grid() {
+    let gundam = ResolutionMode::Gundam;
+    assert_eq!(gundam.vision_tokens(None), 273);
+    assert_eq!(gundam.vision_tokens(Some((1, 1))), 273);
+    // Two columns, one row: 10 rows of 2 * 10 queries plus a newline each.
+    assert_eq!(gundam.vision_tokens(Some((2, 1))), 273 + 10 * 21);
+    assert_eq!(gundam.vision_tokens(Some((3, 3))), 273 + 30 * 31);
+}
+
+#[test]
+fn settings_round_trip_through_presets() {
+    for mode in ResolutionMode::ALL {
+        assert_eq!(ResolutionMode::from_settings(mode.settings()), Some(mode));
+    }
+    let untrained = VisionSettings {
+        base_size: 1280,
+        image_size: 640,
+        crop_mode: true,
+        ..VisionSettings::default()
+    };
+    assert_eq!(ResolutionMode::from_settings(untrained), None);
+}
+
+#[test]
+fn custom_sizes_count_whole_patches_before_downsampling() {
+    // 1030px holds 64 whole 16px patches (16 queries a side), not 1030 / 64 rounded up.
+    let custom = VisionSettings {
+        image_size: 1030,
+        crop_mode: false,
+        ..VisionSettings::default()
+    };
+    assert_eq!(custom.vision_tokens(None), 16 * 17 + 1);
+    // 1000px holds 62 patches, which downsample to 16 queries after rounding up.
+    let custom = VisionSettings {
+        image_size: 1000,
+        ..custom
+    };
+    assert_eq!(custom.vision_tokens(None), 16 * 17 + 1);
+}
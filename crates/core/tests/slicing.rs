@@ -0,0 +1,118 @@
+use deepseek_ocr_core::{
+    grounding::map_boxes,
+    segments::{Region, merge_overlapping},
+    slicing::{SliceConfig, plan_slices, slice_tall_image},
+};
+use image::{DynamicImage, RgbImage};
+
+fn enabled() -> SliceConfig {
+    SliceConfig {
+        enabled: true,
+        ..SliceConfig::default()
+    }
+}
+
+#[test]
+fn only_tall_images_are_sliced() {
+    let config = enabled();
+    assert_eq!(plan_slices(1080, 1920, &config).len(), 1);
+    assert_eq!(plan_slices(1080, 12000, &SliceConfig::default()).len(), 1);
+
+    let slices = plan_slices(1080, 12000, &config);
+    assert!(slices.len() > 1);
+    assert_eq!(slices[0].y, 0);
+    let last = slices.last().unwrap();
+    assert_eq!(last.y + last.height, 12000);
+    for pair in slices.windows(2) {
+        let shared = pair[0].y + pair[0].height - pair[1].y;
+        // Neighbours overlap by at least the configured fraction of a slice.
+        assert!(shared as f32 >= pair[0].height as f32 * config.overlap - 1.0);
+        assert_eq!(pair[0].width, 1080);
+    }
+}
+
+#[test]
+fn overlapping_outputs_drop_repeated_lines() {
+    let first = "Coffee 3.50\nBagel 2.75\nJuice 4.00\nMuff".to_string();
+    let second = "el 2.75\nJuice   4.00\nTea 2.00\nTotal 12.25".to_string();
+    assert_eq!(
+        merge_overlapping(&[first, second]),
+        "Coffee 3.50\nBagel 2.75\nJuice 4.00\nTea 2.00\nTotal 12.25"
+    );
+
+    let unrelated = merge_overlapping(&["alpha beta".to_string(), "gamma delta".to_string()]);
+    assert_eq!(unrelated, "alpha beta\n\ngamma delta");
+}
+
+#[test]
+fn grounding_boxes_move_into_full_image_coordinates() {
+    let image = DynamicImage::ImageRgb8(RgbImage::new(100, 400));
+    let segmented = slice_tall_image(
+        image,
+        &SliceConfig {
+            slice_aspect_ratio: 2.0,
+            overlap: 0.0,
+            ..enabled()
+        },
+    );
+    assert_eq!(
+        segmented.regions(),
+        vec![
+            Region {
+                x: 0,
+                y: 0,
+                width: 100,
+                height: 200
+            },
+            Region {
+                x: 0,
+                y: 200,
+                width: 100,
+                height: 200
+            },
+        ]
+    );
+    let outputs = [
+        "<|ref|>Total<|/ref|><|det|>[[0, 0, 999, 999]]<|/det|>".to_string(),
+        "<|ref|>Tip<|/ref|><|det|>[[0, 0, 999, 999]]<|/det|>".to_string(),
+    ];
+    assert_eq!(
+        segmented.merge(&outputs),
+        "<|ref|>Total<|/ref|><|det|>[[0, 0, 999, 500]]<|/det|>\n\n\
+         <|ref|>Tip<|/ref|><|det|>[[0, 500, 999, 999]]<|/det|>"
+    );
+
+    let untouched = map_boxes("<|det|>not boxes<|/det|>", |bbox| bbox);
+    assert_eq!(untouched, "<|det|>not boxes<|/det|>");
+}
+
+#[test]
+fn settled_text_only_grows_while_segments_finish() {
+    let image = DynamicImage::ImageRgb8(RgbImage::new(100, 600));
+    let segmented = slice_tall_image(
+        image,
+        &SliceConfig {
+            slice_aspect_ratio: 2.0,
+            overlap: 0.0,
+            ..enabled()
+        },
+    );
+    assert_eq!(segmented.segments.len(), 3);
+    let outputs = [
+        "Coffee 3.50\nBagel 2.75\nJuice 4.00\nMuff".to_string(),
+        "el 2.75\nJuice   4.00\nTea 2.00\nTotal 12.25".to_string(),
+        "Total 12.25\nThank you".to_string(),
+    ];
+    let merged = segmented.merge(&outputs);
+    let mut previous = String::new();
+    for done in 1..=outputs.len() {
+        let settled = segmented.settled(&outputs[..done]);
+        assert!(
+            settled.starts_with(&previous),
+            "{settled:?} drops {previous:?}"
+        );
+        assert!(merged.starts_with(&settled), "{merged:?} drops {settled:?}");
+        previous = settled;
+    }
+    assert_eq!(previous, merged);
+}
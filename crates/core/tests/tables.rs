@@ -0,0 +1,160 @@
+use std::{collections::HashMap, io::Read};
+
+use deepseek_ocr_core::tables::{TableFormat, extract_tables};
+use flate2::{Crc, read::DeflateDecoder};
+use roxmltree::Document;
+
+/// Entries of a zip archive, with CRCs checked.
+fn unzip(data: &[u8]) -> HashMap<String, String> {
+    let u16_at = |pos: usize| u16::from_le_bytes([data[pos], data[pos + 1]]) as usize;
+    let u32_at = |pos: usize| u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+    let end = data.len() - 22;
+    assert_eq!(u32_at(end), 0x0605_4b50, "end of central directory");
+    let (count, mut pos) = (u16_at(end + 10), u32_at(end + 16));
+    let mut entries = HashMap::new();
+    for _ in 0..count {
+        assert_eq!(u32_at(pos), 0x0201_4b50, "central directory header");
+        let (crc, compressed) = (u32_at(pos + 16) as u32, u32_at(pos + 20));
+        let name_len = u16_at(pos + 28);
+        let name = String::from_utf8(data[pos + 46..pos + 46 + name_len].to_vec()).unwrap();
+        let local = u32_at(pos + 42);
+        assert_eq!(u32_at(local), 0x0403_4b50, "local header of {name}");
+        let start = local + 30 + u16_at(local + 26) + u16_at(local + 28);
+        let mut text = String::new();
+        DeflateDecoder::new(&data[start..start + compressed])
+            .read_to_string(&mut text)
+            .unwrap();
+        let mut check = Crc::new();
+        check.update(text.as_bytes());
+        assert_eq!(check.sum(), crc, "crc of {name}");
+        entries.insert(name, text);
+        pos += 46 + name_len + u16_at(pos + 30) + u16_at(pos + 32);
+    }
+    entries
+}
+
+#[test]
+fn html_spans_are_resolved_onto_the_grid() {
+    let output = "<|ref|>table<|/ref|><|det|>[[40, 300, 960, 620]]<|/det|>\n\
+        <table><thead><tr><th rowspan=\"2\">Region</th><th colspan=2>Revenue</th></tr>\
+        <tr><th>2023</th><th>2024</th></tr></thead>\
+        <tr><td>North &amp; East</td><td>1,200</td><td>1.5<br>(est.)</td></tr>\
+        <tr><td colspan='3'>Total &#x2248; 2.7</td></tr></table>\n\
+        <|ref|>text<|/ref|><|det|>[[40, 640, 960, 700]]<|/det|>\nNo table here.";
+    let tables = extract_tables(output);
+    assert_eq!(tables.len(), 1);
+    let table = &tables[0];
+    assert_eq!((table.rows, table.columns), (4, 3));
+    assert_eq!(table.bbox, Some([40.0, 300.0, 960.0, 620.0]));
+    assert_eq!(
+        table.grid(),
+        [
+            ["Region", "Revenue", ""],
+            ["", "2023", "2024"],
+            ["North & East", "1,200", "1.5 (est.)"],
+            ["Total ≈ 2.7", "", ""],
+        ]
+    );
+    let region = &table.cells[0];
+    assert_eq!(
+        (region.row_span, region.column_span, region.header),
+        (2, 1, true)
+    );
+    let total = table.cells.last().unwrap();
+    assert_eq!((total.row, total.column_span, total.header), (3, 3, false));
+
+    assert_eq!(
+        table.to_csv(),
+        "Region,Revenue,\r\n,2023,2024\r\nNorth & East,\"1,200\",1.5 (est.)\r\nTotal ≈ 2.7,,\r\n"
+    );
+}
+
+#[test]
+fn otsl_and_markdown_tables_share_the_cell_model() {
+    let otsl = "<fcel>Name<fcel>Q1<lcel><nl><ucel><fcel>Jan<fcel>Feb<nl>\
+        <fcel>Widget<fcel>3<ecel><nl>";
+    let tables = extract_tables(otsl);
+    assert_eq!(tables.len(), 1);
+    assert_eq!(
+        tables[0].grid(),
+        [["Name", "Q1", ""], ["", "Jan", "Feb"], ["Widget", "3", ""]]
+    );
+    let name = &tables[0].cells[0];
+    assert_eq!((name.row_span, name.column_span), (2, 1));
+    assert_eq!(tables[0].cells[1].column_span, 2);
+
+    let markdown = "Intro\n\n| Item | Price |\n|:--|--:|\n| Tea \\| milk | 4 |\n| Cake | |\n\n\
+        | not | a table |\nText after.";
+    let tables = extract_tables(markdown);
+    assert_eq!(tables.len(), 1);
+    assert_eq!(tables[0].bbox, None);
+    assert_eq!(
+        tables[0].grid(),
+        [["Item", "Price"], ["Tea | milk", "4"], ["Cake", ""]]
+    );
+    assert!(tables[0].cells[..2].iter().all(|cell| cell.header));
+    assert!(extract_tables("plain text | with a pipe").is_empty());
+}
+
+#[test]
+fn xlsx_workbook_holds_one_sheet_per_table_with_merges() {
+    let output = "<table><tr><th colspan=\"2\">Totals</th></tr>\
+        <tr><td>007</td><td>-12.50</td></tr></table>\n\n| A | B |\n|---|---|\n| x<y | 2 |";
+    let tables = extract_tables(output);
+    assert_eq!(tables.len(), 2);
+
+    assert_eq!(TableFormat::Csv.render(&tables).unwrap().len(), 2);
+    let json = TableFormat::Json.render(&tables).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&json[0]).unwrap();
+    assert_eq!(value[0]["cells"][0]["column_span"], 2);
+
+    let files = TableFormat::Xlsx.render(&tables).unwrap();
+    let entries = unzip(&files[0]);
+    for part in [
+        "[Content_Types].xml",
+        "_rels/.rels",
+        "xl/workbook.xml",
+        "xl/_rels/workbook.xml.rels",
+        "xl/styles.xml",
+        "xl/worksheets/sheet1.xml",
+        "xl/worksheets/sheet2.xml",
+    ] {
+        let xml = entries
+            .get(part)
+            .unwrap_or_else(|| panic!("missing {part}"));
+        Document::parse(xml).unwrap_or_else(|err| panic!("{part}: {err}"));
+    }
+
+    let sheet = Document::parse(&entries["xl/worksheets/sheet1.xml"]).unwrap();
+    let cells: Vec<_> = sheet
+        .descendants()
+        .filter(|node| node.has_tag_name("c"))
+        .map(|node| {
+            (
+                node.attribute("r").unwrap(),
+                node.attribute("t"),
+                node.descendants()
+                    .find(|child| child.is_text())
+                    .and_then(|child| child.text()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        cells,
+        [
+            ("A1", Some("inlineStr"), Some("Totals")),
+            ("A2", Some("inlineStr"), Some("007")),
+            ("B2", None, Some("-12.50")),
+        ]
+    );
+    let merge = sheet
+        .descendants()
+        .find(|node| node.has_tag_name("mergeCell"))
+        .unwrap();
+    assert_eq!(merge.attribute("ref"), Some("A1:B1"));
+    assert!(entries["xl/worksheets/sheet2.xml"].contains("x&lt;y"));
+
+    let path = std::path::Path::new("tables.XLSX");
+    assert_eq!(TableFormat::from_path(path).unwrap(), TableFormat::Xlsx);
+    assert!(TableFormat::from_path(std::path::Path::new("tables.txt")).is_err());
+}
@@ -0,0 +1,67 @@
+use deepseek_ocr_core::{ModelKind, task::OcrTask};
+
+#[test]
+fn deepseek_prompts_follow_the_reference_instructions() {
+    let render = |task: OcrTask, query| {
+        task.render_prompt(ModelKind::Deepseek, 1, query)
+            .expect("deepseek supports every task")
+    };
+    assert_eq!(render(OcrTask::Ocr, None), "<image>\nFree OCR.");
+    assert_eq!(
+        render(OcrTask::Grounding, None),
+        "<image>\n<|grounding|>Convert the document to markdown."
+    );
+    assert_eq!(
+        render(OcrTask::Locate, Some(" invoice total ")),
+        "<image>\nLocate <|ref|>invoice total<|/ref|> in the image."
+    );
+    // Only the locate task reads the query.
+    assert_eq!(
+        render(OcrTask::Chart, Some("ignored")),
+        "<image>\nParse the figure."
+    );
+    assert_eq!(
+        OcrTask::Ocr
+            .render_prompt(ModelKind::Deepseek, 2, None)
+            .unwrap(),
+        "<image><image>\nFree OCR."
+    );
+}
+
+#[test]
+fn paddle_prompts_use_the_chat_frame_and_reject_unknown_tasks() {
+    let prompt = OcrTask::Table
+        .render_prompt(ModelKind::PaddleOcrVl, 1, None)
+        .unwrap();
+    assert_eq!(prompt, "User: <image>Table Recognition:\nAssistant: ");
+    assert_eq!(
+        OcrTask::supported(ModelKind::PaddleOcrVl),
+        [
+            OcrTask::Ocr,
+            OcrTask::Table,
+            OcrTask::Formula,
+            OcrTask::Chart
+        ]
+    );
+    let err = OcrTask::Grounding
+        .render_prompt(ModelKind::PaddleOcrVl, 1, None)
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("ocr, table, formula, chart"),
+        "{err}"
+    );
+}
+
+#[test]
+fn locate_requires_text_and_tasks_parse_from_lowercase_names() {
+    assert!(
+        OcrTask::Locate
+            .render_prompt(ModelKind::Deepseek, 1, Some("  "))
+            .is_err()
+    );
+    for task in OcrTask::ALL {
+        let json = serde_json::to_string(&task).unwrap();
+        assert_eq!(json, format!("\"{task}\""));
+        assert_eq!(serde_json::from_str::<OcrTask>(&json).unwrap(), task);
+    }
+}
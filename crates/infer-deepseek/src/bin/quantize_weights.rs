@@ -0,0 +1,99 @@
+use std::{env, path::PathBuf, time::Instant};
+
+use anyhow::{Result, bail};
+use deepseek_ocr_infer_deepseek::{
+    gguf::{QuantizeSpec, quantize_safetensors},
+    model::DEFAULT_WEIGHTS_PATH,
+    quantization::{LinearLayerGroup, QuantizationKind},
+};
+
+const USAGE: &str = "usage: quantize_weights [--quant=q8_0|q4_k] [--targets=text[+projector][+vision]] \
+[--output=FILE.gguf] [WEIGHTS.safetensors]";
+
+fn main() -> Result<()> {
+    let mut kind = QuantizationKind::Q8_0;
+    let mut groups = vec![LinearLayerGroup::Text];
+    let mut output: Option<PathBuf> = None;
+    let mut input: Option<PathBuf> = None;
+    for arg in env::args().skip(1) {
+        if let Some(value) = arg.strip_prefix("--quant=") {
+            kind = parse_kind(value)?;
+            continue;
+        }
+        if let Some(value) = arg.strip_prefix("--targets=") {
+            groups = parse_groups(value)?;
+            continue;
+        }
+        if let Some(value) = arg.strip_prefix("--output=") {
+            output = Some(PathBuf::from(value));
+            continue;
+        }
+        if arg == "--help" || arg == "-h" {
+            println!("{USAGE}");
+            return Ok(());
+        }
+        if arg.starts_with("--") {
+            bail!("unknown flag `{arg}`\n{USAGE}");
+        }
+        input = Some(PathBuf::from(arg));
+    }
+
+    let input = input.unwrap_or_else(|| PathBuf::from(DEFAULT_WEIGHTS_PATH));
+    let output = output.unwrap_or_else(|| {
+        let suffix = match kind {
+            QuantizationKind::Q4K => "q4_k",
+            _ => "q8_0",
+        };
+        input.with_extension(format!("{suffix}.gguf"))
+    });
+    let spec = QuantizeSpec { kind, groups };
+
+    println!("input:   {}", input.display());
+    println!("output:  {}", output.display());
+    println!("quant:   {kind}");
+    println!("targets: {:?}", spec.groups);
+    let start = Instant::now();
+    let report = quantize_safetensors(&input, &output, &spec)?;
+    println!(
+        "\nwrote {} tensors in {:.1}s ({} quantized, {} downgraded to Q8_0, {} left in float)",
+        report.tensors,
+        start.elapsed().as_secs_f64(),
+        report.quantized,
+        report.downgraded,
+        report.skipped_unaligned,
+    );
+    for (dtype, count) in &report.per_dtype {
+        println!("  {dtype:<6} {count}");
+    }
+    if let Ok(meta) = std::fs::metadata(&output) {
+        println!(
+            "size: {:.2} GiB",
+            meta.len() as f64 / (1024.0 * 1024.0 * 1024.0)
+        );
+    }
+    Ok(())
+}
+
+fn parse_kind(value: &str) -> Result<QuantizationKind> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "q8_0" | "q8" | "q8.0" => Ok(QuantizationKind::Q8_0),
+        "q4_k" | "q4k" => Ok(QuantizationKind::Q4K),
+        other => bail!("unsupported --quant value `{other}`, expected q8_0|q4_k"),
+    }
+}
+
+fn parse_groups(value: &str) -> Result<Vec<LinearLayerGroup>> {
+    let mut groups = Vec::new();
+    for part in value.split(['+', ',']) {
+        let group = match part.trim().to_ascii_lowercase().as_str() {
+            "text" => LinearLayerGroup::Text,
+            "projector" => LinearLayerGroup::Projector,
+            "vision" => LinearLayerGroup::Vision,
+            other => bail!("unsupported target `{other}`, expected text|projector|vision"),
+        };
+        if !groups.contains(&group) {
+            groups.push(group);
+        }
+    }
+    Ok(groups)
+}
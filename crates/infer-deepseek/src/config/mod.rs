@@ -5,6 +5,7 @@ use std::{
 };
 
 use anyhow::{Context, Result, bail};
+use deepseek_ocr_core::inference::{ResolutionMode, UnsupportedResolution};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -109,6 +110,39 @@ impl DeepseekOcrConfig {
             .and_then(|vision| vision.width.get(name))
             .cloned()
     }
+
+    /// Reject resolution presets this checkpoint was not trained for.
+    ///
+    /// `candidate_resolutions` lists the global-view sizes used by dynamic cropping, so it only
+    /// constrains crop modes; fixed single-view modes are always accepted. Configs without the
+    /// field accept every preset.
+    pub fn validate_resolution_mode(&self, mode: ResolutionMode) -> Result<()> {
+        let settings = mode.settings();
+        if !settings.crop_mode || self.candidate_resolutions.is_empty() {
+            return Ok(());
+        }
+        let side = settings.base_size;
+        if self
+            .candidate_resolutions
+            .iter()
+            .any(|&[height, width]| height == side && width == side)
+        {
+            return Ok(());
+        }
+        Err(UnsupportedResolution {
+            mode,
+            supported: self.candidate_resolutions.clone(),
+        }
+        .into())
+    }
+
+    /// Presets accepted by [`Self::validate_resolution_mode`], in ascending cost.
+    pub fn supported_resolution_modes(&self) -> Vec<ResolutionMode> {
+        ResolutionMode::ALL
+            .into_iter()
+            .filter(|mode| self.validate_resolution_mode(*mode).is_ok())
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,147 @@
+//! Dry-run estimates for DeepSeek-OCR requests; see [`deepseek_ocr_core::estimate`].
+
+use anyhow::Result;
+use deepseek_ocr_core::{
+    estimate::{DryRunReport, EstimateArgs, ImageEstimate, ModelFootprint, encoder_parameters},
+    inference::{ModelKind, ResolutionMode},
+};
+use image::GenericImageView;
+
+use crate::{
+    config::{DeepseekOcrConfig, DeepseekV2Config, load_ocr_config},
+    model::build_prompt_tokens,
+    transformer::weights::should_use_moe,
+    vision::{MAX_CROP_TILES, MIN_CROP_TILES, dynamic_preprocess},
+};
+
+/// Token counts and memory for a request, from dynamic preprocessing and the prompt the model
+/// would build; no weights are read.
+pub fn estimate(args: EstimateArgs<'_>) -> Result<DryRunReport> {
+    let cfg = load_ocr_config(args.config_path)?;
+    if let Some(mode) = ResolutionMode::from_settings(args.vision) {
+        cfg.validate_resolution_mode(mode)?;
+    }
+    let language = cfg.resolved_language_config()?;
+
+    let vision = args.vision;
+    let mut images = Vec::with_capacity(args.images.len());
+    let mut crop_shapes = Vec::with_capacity(args.images.len());
+    for image in args.images {
+        let (width, height) = image.dimensions();
+        // The same preprocessing the model runs, so the grid matches the tiles it encodes.
+        let tile_grid = vision.crop_mode.then(|| {
+            dynamic_preprocess(
+                image,
+                MIN_CROP_TILES,
+                MAX_CROP_TILES,
+                vision.image_size,
+                false,
+            )
+            .ratio
+        });
+        let crop_shape = tile_grid.map(|(w, h)| (w as usize, h as usize));
+        let global = if vision.crop_mode {
+            vision.base_size
+        } else {
+            vision.image_size
+        };
+        images.push(ImageEstimate {
+            width,
+            height,
+            tile_grid,
+            resized: (global, global),
+            vision_tokens: vision.vision_tokens(crop_shape),
+        });
+        crop_shapes.push(crop_shape);
+    }
+    let (tokens, _) = build_prompt_tokens(args.tokenizer, args.prompt, &crop_shapes, vision)?;
+
+    Ok(DryRunReport::new(
+        ModelKind::Deepseek,
+        args.dtype,
+        images,
+        tokens.len(),
+        args.max_new_tokens,
+        &footprint(&cfg, &language),
+    ))
+}
+
+fn footprint(cfg: &DeepseekOcrConfig, language: &DeepseekV2Config) -> ModelFootprint {
+    let hidden = language.hidden_size;
+    let heads = language.num_attention_heads;
+    let head_dim = hidden / heads;
+    let kv_heads = language.num_key_value_heads.unwrap_or(heads);
+    let v_head_dim = language
+        .v_head_dim
+        .filter(|dim| *dim > 0)
+        .unwrap_or(head_dim);
+    let attention = hidden * heads * head_dim
+        + hidden * kv_heads * (head_dim + v_head_dim)
+        + heads * v_head_dim * hidden;
+
+    let dense_mlp = 3 * hidden * language.intermediate_size;
+    let expert_width = language.moe_intermediate_size.unwrap_or(0);
+    let expert = 3 * hidden * expert_width;
+    let routed = language.n_routed_experts.unwrap_or(0);
+    let shared = language.n_shared_experts.unwrap_or(0);
+    let per_token = language.num_experts_per_tok.unwrap_or(0);
+    let (mut total, mut active) = (0usize, 0usize);
+    for layer in 0..language.num_hidden_layers {
+        let (layer_total, layer_active) = if should_use_moe(language, layer) {
+            let gate = hidden * routed;
+            (
+                (routed + shared) * expert + gate,
+                (per_token + shared) * expert + gate,
+            )
+        } else {
+            (dense_mlp, dense_mlp)
+        };
+        total += attention + layer_total;
+        active += attention + layer_active;
+    }
+    let lm_head = language.vocab_size * hidden;
+    let embeddings = if language.tie_word_embeddings {
+        0
+    } else {
+        lm_head
+    };
+    total += lm_head + embeddings;
+    active += lm_head;
+
+    let mlp_ratio = cfg
+        .vision_config
+        .as_ref()
+        .and_then(|vision| vision.mlp_ratio)
+        .unwrap_or(4.0);
+    let towers: u64 = cfg
+        .vision_config
+        .iter()
+        .flat_map(|vision| vision.width.values())
+        .filter_map(|tower| {
+            let width = tower.width?;
+            let intermediate = (width as f32 * mlp_ratio) as usize;
+            Some(encoder_parameters(tower.layers?, width, intermediate))
+        })
+        .sum();
+    let projector = cfg
+        .resolved_projector_config()
+        .map(|projector| {
+            let input = projector.input_dim.unwrap_or(projector.n_embed);
+            ((input + 1) * projector.n_embed) as u64
+        })
+        .unwrap_or(0);
+
+    ModelFootprint {
+        total_parameters: total as u64 + towers + projector,
+        active_parameters: active as u64,
+        layers: language.num_hidden_layers,
+        hidden_size: hidden,
+        intermediate_size: language
+            .intermediate_size
+            .max(expert_width * (per_token + shared)),
+        vocab_size: language.vocab_size,
+        attention_heads: heads,
+        head_dim,
+        kv_width: kv_heads * (head_dim + v_head_dim),
+    }
+}
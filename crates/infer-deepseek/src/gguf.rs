@@ -0,0 +1,409 @@
+//! Pre-quantized weight files.
+//!
+//! `quantize_weights` converts the released safetensors checkpoint into a single GGUF file where
+//! the selected linear layers are stored as ggml blocks (Q8_0/Q4_K) and every other tensor keeps a
+//! float encoding. At load time [`GgufWeights`] serves the float tensors through a regular
+//! [`VarBuilder`] and hands quantized tensors straight to `QMatMul`, so startup never materialises
+//! the full-precision copy of a quantized layer.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result, bail, ensure};
+use candle_core::{
+    DType, Device, Shape, Tensor,
+    quantized::{GgmlDType, QMatMul, QTensor, gguf_file},
+};
+use candle_nn::{Init, VarBuilder, var_builder::SimpleBackend};
+use tracing::{info, trace};
+
+use crate::quantization::{
+    LinearLayerGroup, QuantModule, QuantizationKind, QuantizationOutcome, QuantizationState,
+    backend_label,
+};
+
+/// Value stored under `general.architecture` so foreign GGUF files are rejected early.
+pub const GGUF_ARCHITECTURE: &str = "deepseek-ocr";
+/// Bumped whenever the tensor naming or metadata layout changes.
+pub const GGUF_FORMAT_VERSION: u32 = 1;
+
+const META_ARCHITECTURE: &str = "general.architecture";
+const META_FORMAT_VERSION: &str = "deepseek_ocr.format_version";
+const META_QUANT_KIND: &str = "deepseek_ocr.quantization.kind";
+const META_QUANT_GROUPS: &str = "deepseek_ocr.quantization.groups";
+const META_SOURCE: &str = "deepseek_ocr.quantization.source";
+
+/// Returns `true` when `path` points at a GGUF file (by extension).
+pub fn is_gguf_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gguf"))
+}
+
+/// Map a checkpoint tensor name onto the linear layer group it belongs to.
+///
+/// Only 2-D `*.weight` tensors that feed a matmul are considered; embeddings, norms, convolutions
+/// and the MoE router stay in float.
+pub fn linear_group_for(name: &str, dims: &[usize]) -> Option<LinearLayerGroup> {
+    if dims.len() != 2 || !name.ends_with(".weight") || name.contains("embed") {
+        return None;
+    }
+    if name == "lm_head.weight" {
+        return Some(LinearLayerGroup::Text);
+    }
+    if name.starts_with("model.layers.") {
+        return name
+            .ends_with("_proj.weight")
+            .then_some(LinearLayerGroup::Text);
+    }
+    if name.starts_with("model.projector.") {
+        return Some(LinearLayerGroup::Projector);
+    }
+    if name.starts_with("model.sam_model.") || name.starts_with("model.vision_model.") {
+        return Some(LinearLayerGroup::Vision);
+    }
+    None
+}
+
+/// What `quantize_safetensors` should produce.
+#[derive(Debug, Clone)]
+pub struct QuantizeSpec {
+    pub kind: QuantizationKind,
+    pub groups: Vec<LinearLayerGroup>,
+}
+
+impl QuantizeSpec {
+    fn includes(&self, group: LinearLayerGroup) -> bool {
+        self.groups.contains(&group)
+    }
+
+    fn groups_label(&self) -> String {
+        self.groups
+            .iter()
+            .map(|group| match group {
+                LinearLayerGroup::Text => "text",
+                LinearLayerGroup::Projector => "projector",
+                LinearLayerGroup::Vision => "vision",
+            })
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+}
+
+/// Counters reported by the offline quantizer.
+#[derive(Debug, Clone, Default)]
+pub struct QuantizeReport {
+    pub tensors: usize,
+    pub quantized: usize,
+    pub downgraded: usize,
+    pub skipped_unaligned: usize,
+    pub per_dtype: BTreeMap<String, usize>,
+}
+
+/// Quantize the selected linear layers of a safetensors checkpoint and write a GGUF file.
+///
+/// Layers whose input dimension does not divide the requested block size fall back to Q8_0 (for
+/// Q4_K) and finally to float, mirroring the runtime quantizer.
+pub fn quantize_safetensors(
+    input: &Path,
+    output: &Path,
+    spec: &QuantizeSpec,
+) -> Result<QuantizeReport> {
+    let target = match spec.kind {
+        QuantizationKind::Q8_0 => GgmlDType::Q8_0,
+        QuantizationKind::Q4K => GgmlDType::Q4K,
+        QuantizationKind::None => bail!("quantization kind `none` cannot be exported"),
+    };
+    let safetensors = unsafe { candle_core::safetensors::MmapedSafetensors::new(input) }
+        .with_context(|| format!("failed to mmap weights at {}", input.display()))?;
+    let mut names: Vec<String> = safetensors
+        .tensors()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    names.sort();
+
+    let mut report = QuantizeReport::default();
+    let mut encoded: Vec<(String, QTensor)> = Vec::with_capacity(names.len());
+    for name in names {
+        let tensor = safetensors
+            .load(&name, &Device::Cpu)
+            .with_context(|| format!("failed to read tensor `{name}`"))?;
+        let dims = tensor.dims().to_vec();
+        let group = linear_group_for(&name, &dims).filter(|group| spec.includes(*group));
+        let qtensor = match group {
+            Some(group) => {
+                let in_dim = dims[1];
+                let dtype = if in_dim % target.block_size() == 0 {
+                    Some(target)
+                } else if in_dim % GgmlDType::Q8_0.block_size() == 0 {
+                    report.downgraded += 1;
+                    Some(GgmlDType::Q8_0)
+                } else {
+                    report.skipped_unaligned += 1;
+                    None
+                };
+                match dtype {
+                    Some(dtype) => {
+                        trace!(tensor = %name, ?group, in_dim, to = ?dtype, "quant-export");
+                        report.quantized += 1;
+                        QTensor::quantize(&tensor, dtype)
+                    }
+                    None => QTensor::quantize(&tensor, float_ggml_dtype(tensor.dtype())),
+                }
+            }
+            None => QTensor::quantize(&tensor, float_ggml_dtype(tensor.dtype())),
+        }
+        .with_context(|| format!("failed to encode tensor `{name}`"))?;
+        *report
+            .per_dtype
+            .entry(format!("{:?}", qtensor.dtype()))
+            .or_default() += 1;
+        report.tensors += 1;
+        encoded.push((name, qtensor));
+    }
+
+    let metadata = [
+        (
+            META_ARCHITECTURE,
+            gguf_file::Value::String(GGUF_ARCHITECTURE.to_string()),
+        ),
+        (
+            META_FORMAT_VERSION,
+            gguf_file::Value::U32(GGUF_FORMAT_VERSION),
+        ),
+        (
+            META_QUANT_KIND,
+            gguf_file::Value::String(spec.kind.to_string()),
+        ),
+        (
+            META_QUANT_GROUPS,
+            gguf_file::Value::String(spec.groups_label()),
+        ),
+        (
+            META_SOURCE,
+            gguf_file::Value::String(
+                input
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            ),
+        ),
+    ];
+    let metadata_refs: Vec<(&str, &gguf_file::Value)> =
+        metadata.iter().map(|(key, value)| (*key, value)).collect();
+    let tensor_refs: Vec<(&str, &QTensor)> = encoded
+        .iter()
+        .map(|(name, tensor)| (name.as_str(), tensor))
+        .collect();
+
+    let file =
+        File::create(output).with_context(|| format!("failed to create {}", output.display()))?;
+    let mut writer = BufWriter::new(file);
+    gguf_file::write(&mut writer, &metadata_refs, &tensor_refs)
+        .with_context(|| format!("failed to write GGUF file {}", output.display()))?;
+    Ok(report)
+}
+
+/// BF16 has no GGUF encoding, so it widens to F32 (lossless); F16 is kept as-is.
+fn float_ggml_dtype(dtype: DType) -> GgmlDType {
+    match dtype {
+        DType::F16 => GgmlDType::F16,
+        _ => GgmlDType::F32,
+    }
+}
+
+/// A GGUF file produced by [`quantize_safetensors`], opened for loading.
+pub struct GgufWeights {
+    path: PathBuf,
+    content: gguf_file::Content,
+    reader: Mutex<BufReader<File>>,
+    kind: QuantizationKind,
+    groups: String,
+}
+
+impl GgufWeights {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        let mut reader = BufReader::new(file);
+        let content = gguf_file::Content::read(&mut reader)
+            .with_context(|| format!("failed to parse GGUF header of {}", path.display()))?;
+        let architecture = metadata_string(&content, META_ARCHITECTURE).unwrap_or_default();
+        ensure!(
+            architecture == GGUF_ARCHITECTURE,
+            "{} is not a deepseek-ocr weight file (architecture `{architecture}`)",
+            path.display()
+        );
+        let version = content
+            .metadata
+            .get(META_FORMAT_VERSION)
+            .and_then(|value| value.to_u32().ok())
+            .unwrap_or_default();
+        ensure!(
+            version == GGUF_FORMAT_VERSION,
+            "{} uses format version {version}, expected {GGUF_FORMAT_VERSION}; re-run quantize_weights",
+            path.display()
+        );
+        let kind = match metadata_string(&content, META_QUANT_KIND).as_deref() {
+            Some("Q8_0") => QuantizationKind::Q8_0,
+            Some("Q4_K") => QuantizationKind::Q4K,
+            _ => QuantizationKind::None,
+        };
+        let groups = metadata_string(&content, META_QUANT_GROUPS).unwrap_or_default();
+        info!(
+            path = %path.display(),
+            quant = %kind,
+            groups = %groups,
+            tensors = content.tensor_infos.len(),
+            "opened pre-quantized weights"
+        );
+        Ok(Self {
+            path: path.to_path_buf(),
+            content,
+            reader: Mutex::new(reader),
+            kind,
+            groups,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Quantization recorded in the file metadata.
+    pub fn kind(&self) -> QuantizationKind {
+        self.kind
+    }
+
+    /// Linear layer groups that were quantized, e.g. `text+projector`.
+    pub fn groups(&self) -> &str {
+        &self.groups
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.content.tensor_infos.contains_key(name)
+    }
+
+    /// Whether `name` is stored in a block-quantized (non-float) encoding.
+    pub fn is_quantized(&self, name: &str) -> bool {
+        self.content
+            .tensor_infos
+            .get(name)
+            .is_some_and(|info| !matches!(info.ggml_dtype, GgmlDType::F32 | GgmlDType::F16))
+    }
+
+    /// Read a tensor in its stored encoding.
+    pub fn qtensor(&self, name: &str, device: &Device) -> Result<QTensor> {
+        let mut reader = self.reader.lock().expect("gguf reader mutex poisoned");
+        self.content
+            .tensor(&mut *reader, name, device)
+            .with_context(|| format!("failed to read `{name}` from {}", self.path.display()))
+    }
+
+    /// Build a [`VarBuilder`] that serves every tensor of the file as a float tensor.
+    pub fn var_builder(self: &Arc<Self>, dtype: DType, device: &Device) -> VarBuilder<'static> {
+        VarBuilder::from_backend(
+            Box::new(GgufBackend(Arc::clone(self))),
+            dtype,
+            device.clone(),
+        )
+    }
+}
+
+fn metadata_string(content: &gguf_file::Content, key: &str) -> Option<String> {
+    content
+        .metadata
+        .get(key)
+        .and_then(|value| value.to_string().ok())
+        .cloned()
+}
+
+struct GgufBackend(Arc<GgufWeights>);
+
+impl SimpleBackend for GgufBackend {
+    fn get(
+        &self,
+        s: Shape,
+        name: &str,
+        _: Init,
+        dtype: DType,
+        dev: &Device,
+    ) -> candle_core::Result<Tensor> {
+        if !self.0.contains(name) {
+            return Err(candle_core::Error::CannotFindTensor {
+                path: name.to_string(),
+            }
+            .bt());
+        }
+        let qtensor = self
+            .0
+            .qtensor(name, dev)
+            .map_err(|err| candle_core::Error::Msg(format!("{err:#}")))?;
+        let tensor = qtensor.dequantize(dev)?;
+        if tensor.shape() != &s {
+            Err(candle_core::Error::UnexpectedShape {
+                msg: format!("shape mismatch for {name}"),
+                expected: s,
+                got: tensor.shape().clone(),
+            }
+            .bt())?
+        }
+        tensor.to_dtype(dtype)
+    }
+
+    fn contains_tensor(&self, name: &str) -> bool {
+        self.0.contains(name)
+    }
+}
+
+/// Build a `QMatMul` for `name` when the model's GGUF file stores it block-quantized.
+///
+/// Returns `Ok(None)` for float-encoded tensors (or when no GGUF file is in use) so callers can
+/// fall through to the regular load + runtime quantization path.
+pub(crate) fn load_prequantized_linear(
+    quant: &QuantizationState,
+    name: &str,
+    out_dim: usize,
+    in_dim: usize,
+    device: &Device,
+    module: QuantModule,
+) -> Result<Option<Arc<QMatMul>>> {
+    let Some(gguf) = quant.prequantized().filter(|gguf| gguf.is_quantized(name)) else {
+        return Ok(None);
+    };
+    if device.is_metal() || device.is_cuda() {
+        bail!(
+            "GPU backend: quantized matmuls are disabled on Metal/CUDA.\n\
+             Load the float safetensors checkpoint instead of {} or run on CPU.\n\
+             Context: tensor={name}, backend={}",
+            gguf.path().display(),
+            backend_label(device)
+        );
+    }
+    let qtensor = gguf.qtensor(name, device)?;
+    ensure!(
+        qtensor.shape().dims() == [out_dim, in_dim],
+        "pre-quantized tensor `{name}` has shape {:?}, expected [{out_dim}, {in_dim}]",
+        qtensor.shape().dims()
+    );
+    let dtype = qtensor.dtype();
+    let qmatmul = QMatMul::from_qtensor(qtensor)
+        .with_context(|| format!("failed to build QMatMul for `{name}`"))?;
+    quant.record_attempt(module, QuantizationOutcome::Quantized);
+    trace!(
+        tensor = name,
+        in_dim,
+        out_dim,
+        to = ?dtype,
+        backend = backend_label(device),
+        action = "prequantized",
+        "quant-linear"
+    );
+    Ok(Some(Arc::new(qmatmul)))
+}
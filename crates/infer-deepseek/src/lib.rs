@@ -1,7 +1,10 @@
 pub mod config;
+pub mod estimate;
+pub mod gguf;
 pub mod model;
 pub mod quantization;
 pub mod transformer;
 pub mod vision;
 
+pub use estimate::estimate;
 pub use model::{DeepseekOcrModel, GenerateOptions, OwnedVisionInput, VisionInput, load_model};
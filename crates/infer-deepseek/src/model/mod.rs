@@ -11,31 +11,35 @@ use image::GenericImageView;
 use image::{DynamicImage, Rgb, RgbImage, imageops};
 use rayon::prelude::*;
 use tokenizers::Tokenizer;
-use tracing::trace;
+use tracing::{trace, warn};
 
 use crate::{
     config::{DeepseekOcrConfig, ProjectorConfig, load_ocr_config},
+    gguf::{GgufWeights, is_gguf_path, load_prequantized_linear},
     quantization::{
-        LinearLayerGroup, QuantModule, QuantizationKind, QuantizationOutcome, QuantizationState,
-        backend_label,
+        LinearLayerGroup, QuantModule, QuantizationConfig, QuantizationKind, QuantizationOutcome,
+        QuantizationState, QuantizationStats, backend_label,
     },
     transformer::{
         cache::{DynamicCache, PromptCacheGuard},
         model::{DeepseekLanguageModel, LanguageModelOutput},
+        weights::DeepseekLanguageModelWeights,
     },
     vision::{
-        ClipDebugTrace, ClipVisionModel, SamBackbone, SamDebugTrace, dynamic_preprocess,
-        resample::resize_bicubic,
+        ClipDebugTrace, ClipVisionModel, MAX_CROP_TILES, MIN_CROP_TILES, SamBackbone,
+        SamBackboneParams, SamDebugTrace, dynamic_preprocess, resample::resize_bicubic,
     },
 };
 use deepseek_ocr_core::{
     CancellationToken,
     benchmark::Timer,
     inference::{
-        DecodeOutcome, DecodeParameters, ModelKind, ModelLoadArgs, OcrEngine, VisionSettings,
-        normalize_text,
+        DecodeOutcome, DecodeParameters, ModelKind, ModelLoadArgs, OcrEngine, ResolutionMode,
+        TileEncodingConfig, VisionSettings, normalize_text,
+    },
+    sampling::{
+        TokenConstraint, TokenSelectionParams, constrain_logits, init_rng, select_token_id,
     },
-    sampling::{TokenSelectionParams, init_rng, select_token_id},
 };
 
 pub fn load_model(args: ModelLoadArgs<'_>) -> Result<Box<dyn OcrEngine>> {
@@ -45,10 +49,19 @@ pub fn load_model(args: ModelLoadArgs<'_>) -> Result<Box<dyn OcrEngine>> {
         weights_path,
         device,
         dtype,
+        quantization,
+        tile_encoding,
     } = args;
     match kind {
         ModelKind::Deepseek => {
-            let model = DeepseekOcrModel::load(config_path, weights_path, device, dtype)?;
+            let mut model = DeepseekOcrModel::load_with_quantization(
+                config_path,
+                weights_path,
+                device,
+                dtype,
+                QuantizationConfig::resolve(quantization),
+            )?;
+            model.set_tile_encoding(tile_encoding)?;
             Ok(Box::new(model))
         }
         ModelKind::PaddleOcrVl => Err(anyhow!(
@@ -136,6 +149,7 @@ pub struct GenerateOptions<'a> {
     pub no_repeat_ngram_size: Option<usize>,
     pub do_sample: bool,
     pub seed: Option<u64>,
+    pub constraint: Option<&'a dyn TokenConstraint>,
 }
 
 impl<'a> GenerateOptions<'a> {
@@ -158,6 +172,7 @@ impl<'a> GenerateOptions<'a> {
             no_repeat_ngram_size: None,
             do_sample: false,
             seed: None,
+            constraint: None,
         }
     }
 }
@@ -199,7 +214,7 @@ struct ImageProjector {
 }
 
 impl ImageProjector {
-    fn load(vb: &VarBuilder, cfg: &ProjectorConfig) -> Result<Self> {
+    fn load(vb: &VarBuilder, quant: &QuantizationState, cfg: &ProjectorConfig) -> Result<Self> {
         let input_dim = cfg
             .input_dim
             .with_context(|| "projector input_dim missing from config")?;
@@ -213,10 +228,6 @@ impl ImageProjector {
         let projector_vb = model_vb.pp("projector");
         let layers_vb = projector_vb.pp("layers");
 
-        let weight = layers_vb
-            .get((cfg.n_embed, input_dim), "weight")
-            .with_context(|| "missing projector weight tensor")?
-            .contiguous()?;
         // Optional bias
         let bias = if layers_vb.contains_tensor("bias") {
             Some(
@@ -237,10 +248,43 @@ impl ImageProjector {
             .with_context(|| "missing projector view_seperator tensor")?
             .contiguous()?;
 
+        let prequantized = load_prequantized_linear(
+            quant,
+            &format!("{}.weight", layers_vb.prefix()),
+            cfg.n_embed,
+            input_dim,
+            layers_vb.device(),
+            QuantModule::Projector,
+        )?;
+        if let Some(qmatmul) = prequantized {
+            let weight = if quant.config().keep_full_precision_weights {
+                Some(
+                    layers_vb
+                        .get((cfg.n_embed, input_dim), "weight")?
+                        .contiguous()?,
+                )
+            } else {
+                None
+            };
+            return Ok(Self {
+                input_dim,
+                hidden: cfg.n_embed,
+                weight,
+                qmatmul: Some(qmatmul),
+                bias,
+                image_newline,
+                view_separator,
+            });
+        }
+
+        let weight = layers_vb
+            .get((cfg.n_embed, input_dim), "weight")
+            .with_context(|| "missing projector weight tensor")?
+            .contiguous()?;
+
         // Try runtime quantization if enabled for projector
         use candle_core::quantized::{GgmlDType, QMatMul, QTensor};
         use tracing::trace;
-        let quant = QuantizationState::global();
         let config = quant.config();
         let mut qmatmul: Option<std::sync::Arc<QMatMul>> = None;
         // GPU fast-fail: disallow runtime quantization on Metal/CUDA for projector as well.
@@ -250,7 +294,7 @@ impl ImageProjector {
         {
             anyhow::bail!(
                 "GPU backend: runtime quantization is disabled on Metal/CUDA. Refusing to fallback.\n\
-                 Disable quantization (DEEPSEEK_OCR_QUANT=none) or run on CPU.\n\
+                 Disable quantization (--quant none) or run on CPU.\n\
                  Context: module=projector, in_dim={}, backend={}",
                 input_dim,
                 crate::quantization::backend_label(&weight.device())
@@ -449,6 +493,9 @@ pub struct DeepseekOcrModel {
     device: Device,
     dtype: DType,
     weights_path: PathBuf,
+    quantization: QuantizationState,
+    tile_encoding: TileEncodingConfig,
+    tile_pool: Option<Arc<rayon::ThreadPool>>,
 }
 
 struct VisionModules {
@@ -462,6 +509,8 @@ struct VisionContext<'a> {
     device: &'a Device,
     dtype: DType,
     parallel: bool,
+    tile_encoding: TileEncodingConfig,
+    tile_pool: Option<&'a rayon::ThreadPool>,
 }
 
 impl<'a> VisionContext<'a> {
@@ -473,6 +522,8 @@ impl<'a> VisionContext<'a> {
             device: model.device(),
             dtype: model.dtype(),
             parallel,
+            tile_encoding: model.tile_encoding,
+            tile_pool: model.tile_pool.as_deref(),
         }
     }
 
@@ -722,23 +773,7 @@ impl<'a> VisionContext<'a> {
                 .context("patch tensor must be 4D (batch, channels, height, width)")?;
             if patch_batch > 0 {
                 if self.parallel {
-                    let chunks = patches.chunk(patch_batch, 0)?;
-                    let local_results: Result<Vec<(Tensor, Tensor)>> = chunks
-                        .into_par_iter()
-                        .map(|chunk| self.process_patch_chunk(chunk))
-                        .collect();
-                    let (local_pre_list, local_post_list): (Vec<_>, Vec<_>) = local_results?
-                        .into_iter()
-                        .unzip::<Tensor, Tensor, Vec<_>, Vec<_>>();
-
-                    let local_pre_refs: Vec<_> = local_pre_list.iter().collect();
-                    let local_post_refs: Vec<_> = local_post_list.iter().collect();
-                    let local_pre = Tensor::cat(&local_pre_refs, 0)?
-                        .contiguous()
-                        .context("local pre tokens not contiguous")?;
-                    let local_post = Tensor::cat(&local_post_refs, 0)?
-                        .contiguous()
-                        .context("local post tokens not contiguous")?;
+                    let (local_pre, local_post) = self.encode_tiles_parallel(&patches)?;
                     let local_tokens = self
                         .format_local_tokens(&local_post, crop_shape, newline)
                         .context("format local tokens")?
@@ -762,6 +797,63 @@ impl<'a> VisionContext<'a> {
         Ok((None, None, None))
     }
 
+    /// Encode crop tiles in chunks of `chunk_size`, running as many chunks at once as the
+    /// memory budget allows, on the dedicated tile pool when one is configured.
+    fn encode_tiles_parallel(&self, patches: &Tensor) -> Result<(Tensor, Tensor)> {
+        let (tiles, _c, height, width) = patches.shape().dims4()?;
+        let chunk_size = self.tile_encoding.chunk_size.clamp(1, tiles);
+        let chunks = (0..tiles)
+            .step_by(chunk_size)
+            .map(|start| patches.narrow(0, start, chunk_size.min(tiles - start)))
+            .collect::<candle_core::Result<Vec<_>>>()?;
+        let concurrency = self.tile_concurrency(chunk_size, height, width);
+        trace!(
+            tiles,
+            chunk_size,
+            chunks = chunks.len(),
+            concurrency,
+            "tile-encoding"
+        );
+
+        let mut local_pre_list = Vec::with_capacity(chunks.len());
+        let mut local_post_list = Vec::with_capacity(chunks.len());
+        for wave in chunks.chunks(concurrency) {
+            let encode_wave = || -> Result<Vec<(Tensor, Tensor)>> {
+                wave.par_iter()
+                    .map(|chunk| self.process_patch_chunk(chunk.clone()))
+                    .collect()
+            };
+            let results = match self.tile_pool {
+                Some(pool) => pool.install(encode_wave),
+                None => encode_wave(),
+            }?;
+            for (pre, post) in results {
+                local_pre_list.push(pre);
+                local_post_list.push(post);
+            }
+        }
+
+        let local_pre = Tensor::cat(&local_pre_list, 0)?
+            .contiguous()
+            .context("local pre tokens not contiguous")?;
+        let local_post = Tensor::cat(&local_post_list, 0)?
+            .contiguous()
+            .context("local post tokens not contiguous")?;
+        Ok((local_pre, local_post))
+    }
+
+    /// Number of tile chunks that may be encoded concurrently under the memory budget.
+    fn tile_concurrency(&self, chunk_size: usize, height: usize, width: usize) -> usize {
+        let threads = self
+            .tile_pool
+            .map(rayon::ThreadPool::current_num_threads)
+            .unwrap_or_else(rayon::current_num_threads);
+        let per_chunk =
+            estimate_tile_activation_bytes(&self.vision.sam.params, height, width, self.dtype)
+                * chunk_size as u64;
+        tile_concurrency(threads, self.tile_encoding.memory_budget_mb, per_chunk)
+    }
+
     fn process_patch_chunk(&self, chunk: Tensor) -> Result<(Tensor, Tensor)> {
         let chunk = chunk
             .contiguous()
@@ -833,28 +925,90 @@ impl<'a> VisionContext<'a> {
     }
 }
 
+/// Rough peak activation footprint of one tile in the SAM encoder, which dominates the CLIP pass.
+///
+/// Global-attention blocks materialise `heads × tokens²` f32 scores (plus the softmax copy); every
+/// block also holds a few `tokens × embed × mlp_ratio` buffers.
+/// Chunks encoded at once: as many as fit in `budget_mb` (`0` = no cap) at `per_chunk_bytes`
+/// each, at least one and at most one per thread.
+pub fn tile_concurrency(threads: usize, budget_mb: u64, per_chunk_bytes: u64) -> usize {
+    let threads = threads.max(1);
+    if budget_mb == 0 {
+        return threads;
+    }
+    let affordable = (budget_mb * 1024 * 1024 / per_chunk_bytes.max(1)) as usize;
+    affordable.clamp(1, threads)
+}
+
+fn estimate_tile_activation_bytes(
+    params: &SamBackboneParams,
+    height: usize,
+    width: usize,
+    dtype: DType,
+) -> u64 {
+    let tokens = ((height / params.patch_size) * (width / params.patch_size)) as u64;
+    let scores = 2 * params.num_heads as u64 * tokens * tokens * DType::F32.size_in_bytes() as u64;
+    let mlp_width = (params.embed_dim as f64 * params.mlp_ratio) as u64;
+    let activations = 4 * tokens * mlp_width * dtype.size_in_bytes() as u64;
+    scores + activations
+}
+
 impl DeepseekOcrModel {
     /// Load the OCR model from disk, pulling configuration and language-model weights.
     ///
     /// The vision/projector paths are stubbed for now; they will be filled in once the Candle
-    /// kernels land. `device` controls where tensors are allocated (CPU/GPU).
+    /// kernels land. `device` controls where tensors are allocated (CPU/GPU). Quantization follows
+    /// the `DEEPSEEK_OCR_QUANT*` environment variables; see [`Self::load_with_quantization`].
     pub fn load(
         config_path: Option<&Path>,
         weights_path: Option<&Path>,
         device: Device,
         dtype: DType,
+    ) -> Result<Self> {
+        Self::load_with_quantization(
+            config_path,
+            weights_path,
+            device,
+            dtype,
+            QuantizationConfig::from_env(),
+        )
+    }
+
+    /// Load the OCR model with explicit quantization settings.
+    pub fn load_with_quantization(
+        config_path: Option<&Path>,
+        weights_path: Option<&Path>,
+        device: Device,
+        dtype: DType,
+        quantization: QuantizationConfig,
     ) -> Result<Self> {
         let cfg = Arc::new(load_ocr_config(config_path)?);
         let language_cfg = Arc::new(cfg.resolved_language_config()?);
         let resolved_weights = weights_path
             .map(PathBuf::from)
             .unwrap_or_else(|| PathBuf::from(DEFAULT_WEIGHTS_PATH));
-        let vb = unsafe {
-            VarBuilder::from_mmaped_safetensors(&[resolved_weights.as_path()], dtype, &device)
+        // GGUF files written by `quantize_weights` carry block-quantized linears that are handed
+        // to QMatMul as-is; every other tensor is served through the VarBuilder.
+        let gguf = if is_gguf_path(&resolved_weights) {
+            Some(Arc::new(GgufWeights::open(&resolved_weights)?))
+        } else {
+            None
+        };
+        let mut quant = QuantizationState::new(quantization);
+        if let Some(gguf) = gguf.as_ref() {
+            quant = quant.with_prequantized(Arc::clone(gguf));
         }
-        .with_context(|| format!("failed to mmap weights at {}", resolved_weights.display()))?;
-        let language = DeepseekLanguageModel::load(language_cfg, &vb)
-            .context("failed to load language model")?;
+        let vb = match gguf.as_ref() {
+            Some(gguf) => gguf.var_builder(dtype, &device),
+            None => unsafe {
+                VarBuilder::from_mmaped_safetensors(&[resolved_weights.as_path()], dtype, &device)
+            }
+            .with_context(|| format!("failed to mmap weights at {}", resolved_weights.display()))?,
+        };
+        let language_weights =
+            DeepseekLanguageModelWeights::load_with_quantization(&language_cfg, &vb, &quant)
+                .context("failed to load language model")?;
+        let language = DeepseekLanguageModel::from_weights(language_cfg, language_weights);
         let projector_cfg = Arc::new(
             cfg.resolved_projector_config()
                 .context("projector configuration missing")?,
@@ -865,15 +1019,23 @@ impl DeepseekOcrModel {
             projector_cfg.n_embed,
             language.config().hidden_size
         );
-        let projector = ImageProjector::load(&vb, projector_cfg.as_ref())
+        let projector = ImageProjector::load(&vb, &quant, projector_cfg.as_ref())
             .context("failed to load image projector")?;
-        let sam = SamBackbone::new(cfg.as_ref(), &vb.pp("model").pp("sam_model"))
-            .context("failed to load SAM backbone")?;
-        let clip = ClipVisionModel::load(cfg.as_ref(), &vb.pp("model").pp("vision_model"))
-            .context("failed to load CLIP vision model")?;
+        let sam = SamBackbone::new_with_quantization(
+            cfg.as_ref(),
+            &vb.pp("model").pp("sam_model"),
+            &quant,
+        )
+        .context("failed to load SAM backbone")?;
+        let clip = ClipVisionModel::load_with_quantization(
+            cfg.as_ref(),
+            &vb.pp("model").pp("vision_model"),
+            &quant,
+        )
+        .context("failed to load CLIP vision model")?;
         let vision = VisionModules { sam, clip };
-        // Log quantization summary after all quantizable modules (language + projector) are loaded.
-        QuantizationState::global().log_summary(&device);
+        // Log quantization summary after all quantizable modules (language, projector, vision) are loaded.
+        quant.log_summary(&device);
 
         Ok(Self {
             cfg,
@@ -884,14 +1046,72 @@ impl DeepseekOcrModel {
             device,
             dtype,
             weights_path: resolved_weights,
+            quantization: quant,
+            tile_encoding: TileEncodingConfig::default(),
+            tile_pool: None,
         })
     }
 
+    /// Quantization counters gathered while this model loaded.
+    pub fn quantization_stats(&self) -> QuantizationStats {
+        self.quantization.stats()
+    }
+
+    /// Quantization settings this model was loaded with.
+    pub fn quantization_config(&self) -> QuantizationConfig {
+        self.quantization.config()
+    }
+
+    /// Configure how crop tiles are batched and scheduled during vision encoding.
+    ///
+    /// A `max_threads` limit builds a dedicated rayon pool for tile work; otherwise tiles share
+    /// the global pool.
+    pub fn set_tile_encoding(&mut self, config: TileEncodingConfig) -> Result<()> {
+        ensure!(config.chunk_size > 0, "tile chunk size must be at least 1");
+        // rayon reads zero threads as "one per core", which would silently ignore the limit.
+        ensure!(
+            config.max_threads != Some(0),
+            "tile thread count must be at least 1"
+        );
+        self.tile_pool = match config.max_threads {
+            Some(threads) => Some(Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .thread_name(|idx| format!("deepseek-ocr-tile-{idx}"))
+                    .build()
+                    .context("failed to build tile encoding thread pool")?,
+            )),
+            None => None,
+        };
+        self.tile_encoding = config;
+        Ok(())
+    }
+
+    pub fn tile_encoding(&self) -> TileEncodingConfig {
+        self.tile_encoding
+    }
+
     /// Access the currently loaded configuration.
     pub fn config(&self) -> &DeepseekOcrConfig {
         self.cfg.as_ref()
     }
 
+    /// Reject presets the checkpoint does not support and warn about untrained combinations.
+    pub fn check_vision_settings(&self, vision: VisionSettings) -> Result<()> {
+        match ResolutionMode::from_settings(vision) {
+            Some(mode) => self.cfg.validate_resolution_mode(mode),
+            None => {
+                warn!(
+                    base_size = vision.base_size,
+                    image_size = vision.image_size,
+                    crop_mode = vision.crop_mode,
+                    "vision settings do not match any resolution mode; output quality may suffer"
+                );
+                Ok(())
+            }
+        }
+    }
+
     /// Device backing the allocated tensors.
     pub fn device(&self) -> &Device {
         &self.device
@@ -1381,7 +1601,8 @@ impl DeepseekOcrModel {
             .contiguous()?;
 
         let (patches, crop_shape) = if crop_mode {
-            let preprocess = dynamic_preprocess(image, 2, 9, image_size, false);
+            let preprocess =
+                dynamic_preprocess(image, MIN_CROP_TILES, MAX_CROP_TILES, image_size, false);
             let crop = (preprocess.ratio.0 as usize, preprocess.ratio.1 as usize);
             let tiles = preprocess.tiles;
             if tiles.is_empty() {
@@ -1614,6 +1835,7 @@ impl DeepseekOcrModel {
         let last_logits = logits
             .get(seq_len - 1)
             .context("prefill logits missing final timestep")?;
+        let last_logits = constrained(last_logits, &options, &[])?;
         let mut current = select_token_id(&last_logits, &options, &context_tokens, &mut rng)?;
         if let Some(eos) = options.eos_token_id {
             if current == eos {
@@ -1666,6 +1888,7 @@ impl DeepseekOcrModel {
                 .context("decode logits missing batch dimension")?
                 .get(0)
                 .context("decode logits missing timestep")?;
+            let next_logits = constrained(next_logits, &options, &generated)?;
             current = select_token_id(&next_logits, &options, &context_tokens, &mut rng)?;
             if let Some(eos) = options.eos_token_id {
                 if current == eos {
@@ -1845,6 +2068,7 @@ impl DeepseekOcrModel {
             .context("prefill logits missing batch dimension")?
             .get(tokens.len() - 1)
             .context("prefill logits missing final timestep")?;
+        let logits = constrained(logits, &options, &[])?;
         let mut current = select_token_id(&logits, &options, &tokens, &mut rng)?;
         if let Some(eos) = options.eos_token_id {
             if current == eos {
@@ -1913,6 +2137,7 @@ impl DeepseekOcrModel {
                 .context("decode logits missing batch dimension")?
                 .get(seq_pos)
                 .context("decode logits missing timestep")?;
+            let next_logits = constrained(next_logits, &options, &generated)?;
             current = select_token_id(&next_logits, &options, &tokens, &mut rng)?;
             if let Some(eos) = options.eos_token_id {
                 if current == eos {
@@ -1939,6 +2164,14 @@ impl DeepseekOcrModel {
     }
 }
 
+/// `logits` restricted by the constraint of `options`, if any.
+fn constrained(logits: Tensor, options: &GenerateOptions<'_>, generated: &[i64]) -> Result<Tensor> {
+    match options.constraint {
+        Some(constraint) => constrain_logits(&logits, constraint, generated, options.eos_token_id),
+        None => Ok(logits),
+    }
+}
+
 fn is_cancelled(token: Option<&CancellationToken>) -> bool {
     token.map_or(false, |t| t.is_cancelled())
 }
@@ -2022,6 +2255,10 @@ impl deepseek_ocr_core::inference::OcrEngine for DeepseekOcrModel {
         self.flash_attention_enabled()
     }
 
+    fn constrained_decoding(&self) -> bool {
+        true
+    }
+
     fn decode(
         &self,
         tokenizer: &Tokenizer,
@@ -2032,6 +2269,7 @@ impl deepseek_ocr_core::inference::OcrEngine for DeepseekOcrModel {
         stream: Option<&dyn Fn(usize, &[i64])>,
         cancel: Option<&CancellationToken>,
     ) -> Result<DecodeOutcome> {
+        self.check_vision_settings(vision)?;
         let owned_inputs = prepare_vision_inputs(
             self,
             images,
@@ -2042,16 +2280,10 @@ impl deepseek_ocr_core::inference::OcrEngine for DeepseekOcrModel {
         .with_context(|| "vision input failed")?;
         let embeddings = compute_image_embeddings(self, &owned_inputs)
             .with_context(|| "image embedding failed")?;
-        let (input_ids_vec, mask_vec) = build_prompt_tokens(
-            tokenizer,
-            prompt,
-            &embeddings,
-            &owned_inputs,
-            vision.base_size,
-            vision.image_size,
-            vision.crop_mode,
-        )
-        .with_context(|| "prompt formatting failed")?;
+        let crop_shapes: Vec<_> = owned_inputs.iter().map(|input| input.crop_shape).collect();
+        let (input_ids_vec, mask_vec) = check_embedding_rows(&embeddings, &owned_inputs, vision)
+            .and_then(|()| build_prompt_tokens(tokenizer, prompt, &crop_shapes, vision))
+            .with_context(|| "prompt formatting failed")?;
 
         let input_len = input_ids_vec.len();
         let device = self.device();
@@ -2075,6 +2307,7 @@ impl deepseek_ocr_core::inference::OcrEngine for DeepseekOcrModel {
         options.repetition_penalty = params.repetition_penalty;
         options.no_repeat_ngram_size = params.no_repeat_ngram_size;
         options.seed = params.seed;
+        options.constraint = params.constraint.as_deref();
         options.progress_callback = stream;
         options.cancel = cancel.cloned();
 
@@ -2178,14 +2411,14 @@ fn compute_image_embeddings(
     outputs
 }
 
-fn build_prompt_tokens(
+/// Token ids and image mask of `prompt`: BOS, then each text segment followed by the
+/// placeholders of the image in its `<image>` slot. `crop_shapes` holds the crop grid of every
+/// image, as found by dynamic preprocessing.
+pub(crate) fn build_prompt_tokens(
     tokenizer: &Tokenizer,
     prompt: &str,
-    embeddings: &[Tensor],
-    vision_inputs: &[OwnedVisionInput],
-    base_size: u32,
-    image_size: u32,
-    crop_mode: bool,
+    crop_shapes: &[Option<(usize, usize)>],
+    vision: VisionSettings,
 ) -> Result<(Vec<i64>, Vec<u8>)> {
     let timer = Timer::new("prompt.build_tokens");
     let image_token_id = tokenizer
@@ -2195,16 +2428,10 @@ fn build_prompt_tokens(
 
     let segments: Vec<&str> = prompt.split("<image>").collect();
     anyhow::ensure!(
-        segments.len().saturating_sub(1) == embeddings.len(),
-        "prompt/image embedding mismatch: {} slots vs {} embeddings",
+        segments.len().saturating_sub(1) == crop_shapes.len(),
+        "prompt/image embedding mismatch: {} slots vs {} images",
         segments.len().saturating_sub(1),
-        embeddings.len()
-    );
-    anyhow::ensure!(
-        embeddings.len() == vision_inputs.len(),
-        "vision input count {} does not match embeddings {}",
-        vision_inputs.len(),
-        embeddings.len()
+        crop_shapes.len()
     );
 
     let mut tokens = Vec::new();
@@ -2217,22 +2444,11 @@ fn build_prompt_tokens(
             .encode(*segment, false)
             .map_err(|err| anyhow!("tokenization failed: {err}"))?;
         tokens.extend(encoding.get_ids().iter().map(|&id| id as i64));
-        mask.extend(std::iter::repeat(0u8).take(encoding.len()));
-        if idx < embeddings.len() {
-            let placeholders = build_image_placeholders(
-                image_token_id,
-                &vision_inputs[idx],
-                embeddings[idx]
-                    .shape()
-                    .dims2()
-                    .context("vision embedding must be 2D")?
-                    .0,
-                base_size,
-                image_size,
-                crop_mode,
-            )?;
+        mask.extend(std::iter::repeat_n(0u8, encoding.len()));
+        if let Some(&crop_shape) = crop_shapes.get(idx) {
+            let placeholders = build_image_placeholders(image_token_id, crop_shape, vision);
             tokens.extend(&placeholders);
-            mask.extend(std::iter::repeat(1u8).take(placeholders.len()));
+            mask.extend(std::iter::repeat_n(1u8, placeholders.len()));
         }
     }
 
@@ -2242,65 +2458,44 @@ fn build_prompt_tokens(
         event.add_field("tokens", total_tokens);
         event.add_field("image_tokens", image_tokens);
         event.add_field("segments", segments.len());
-        event.add_field("crop_mode", crop_mode);
+        event.add_field("crop_mode", vision.crop_mode);
     });
 
     Ok((tokens, mask))
 }
 
+/// The `<image>` tokens one image occupies, as many as [`VisionSettings::vision_tokens`] counts.
 fn build_image_placeholders(
     image_token_id: i64,
-    input: &OwnedVisionInput,
-    expected_tokens: usize,
-    base_size: u32,
-    image_size: u32,
-    crop_mode: bool,
-) -> Result<Vec<i64>> {
-    const PATCH_SIZE: u32 = 16;
-    const DOWNSAMPLE_RATIO: u32 = 4;
-
-    let mut placeholders = Vec::new();
-
-    let push_grid = |placeholders: &mut Vec<i64>, rows: usize, cols: usize, add_terminal: bool| {
-        for _ in 0..rows {
-            placeholders.extend(std::iter::repeat(image_token_id).take(cols));
-            placeholders.push(image_token_id);
-        }
-        if add_terminal {
-            placeholders.push(image_token_id);
-        }
-    };
-
-    if crop_mode {
-        let grid = (base_size / PATCH_SIZE) as usize;
-        let num_queries_global = ((grid as f32) / (DOWNSAMPLE_RATIO as f32)).ceil() as usize;
-        push_grid(
-            &mut placeholders,
-            num_queries_global,
-            num_queries_global,
-            true,
-        );
-
-        let (width_crops, height_crops) = input.crop_shape.unwrap_or((1, 1));
-        if width_crops > 1 || height_crops > 1 {
-            let local_grid = (image_size / PATCH_SIZE) as usize;
-            let num_queries_local =
-                ((local_grid as f32) / (DOWNSAMPLE_RATIO as f32)).ceil() as usize;
-            let rows = num_queries_local * height_crops;
-            let cols = num_queries_local * width_crops;
-            push_grid(&mut placeholders, rows, cols, false);
-        }
-    } else {
-        let grid = (image_size / PATCH_SIZE) as usize;
-        let num_queries = ((grid as f32) / (DOWNSAMPLE_RATIO as f32)).ceil() as usize;
-        push_grid(&mut placeholders, num_queries, num_queries, true);
-    }
+    crop_shape: Option<(usize, usize)>,
+    vision: VisionSettings,
+) -> Vec<i64> {
+    vec![image_token_id; vision.vision_tokens(crop_shape)]
+}
 
+/// Check that every image embedding has one row per placeholder.
+fn check_embedding_rows(
+    embeddings: &[Tensor],
+    inputs: &[OwnedVisionInput],
+    vision: VisionSettings,
+) -> Result<()> {
     anyhow::ensure!(
-        placeholders.len() == expected_tokens,
-        "placeholder count {} does not match expected {}",
-        placeholders.len(),
-        expected_tokens
+        embeddings.len() == inputs.len(),
+        "vision input count {} does not match embeddings {}",
+        inputs.len(),
+        embeddings.len()
     );
-    Ok(placeholders)
+    for (embedding, input) in embeddings.iter().zip(inputs) {
+        let rows = embedding
+            .shape()
+            .dims2()
+            .context("vision embedding must be 2D")?
+            .0;
+        let placeholders = vision.vision_tokens(input.crop_shape);
+        anyhow::ensure!(
+            rows == placeholders,
+            "placeholder count {placeholders} does not match expected {rows}"
+        );
+    }
+    Ok(())
 }
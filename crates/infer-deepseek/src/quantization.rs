@@ -1,139 +1,49 @@
-use std::{
-    env, fmt,
-    sync::{
-        Mutex,
-        atomic::{AtomicBool, Ordering},
-    },
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicBool, Ordering},
 };
 
 use candle_core::Device;
-use once_cell::sync::Lazy;
-use tracing::{info, warn};
-
-/// Supported runtime quantization algorithms.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum QuantizationKind {
-    None,
-    Q8_0,
-    Q4K,
-}
-
-impl QuantizationKind {
-    pub fn is_enabled(self) -> bool {
-        !matches!(self, Self::None)
-    }
-}
-
-impl fmt::Display for QuantizationKind {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::None => f.write_str("none"),
-            Self::Q8_0 => f.write_str("Q8_0"),
-            Self::Q4K => f.write_str("Q4_K"),
-        }
-    }
-}
-
-/// High-level components that may opt into quantization.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum LinearLayerGroup {
-    Text,
-    Projector,
-    Vision,
-}
-
-/// Module categories used for per-module stats.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum QuantModule {
-    TextLinear,
-    Projector,
-    LmHead,
-}
-
-impl fmt::Display for QuantModule {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::TextLinear => f.write_str("text-linear"),
-            Self::Projector => f.write_str("projector"),
-            Self::LmHead => f.write_str("lm_head"),
-        }
-    }
-}
-
-/// Which modules should participate in quantization.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum QuantizationTargets {
-    Text,
-    TextAndProjector,
-}
-
-impl fmt::Display for QuantizationTargets {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Text => f.write_str("text"),
-            Self::TextAndProjector => f.write_str("text+projector"),
-        }
-    }
-}
-
-#[derive(Debug, Clone, Copy)]
-pub struct QuantizationConfig {
-    pub kind: QuantizationKind,
-    pub targets: QuantizationTargets,
-    pub keep_full_precision_weights: bool,
-    pub verbose_per_layer: bool,
-}
-
-#[derive(Debug, Clone, Copy, Default)]
-pub struct ModuleStats {
-    pub candidates: usize,
-    pub quantized: usize,
-    pub fallback: usize,
-}
-
-#[derive(Debug, Clone, Copy, Default)]
-pub struct QuantizationStats {
-    pub candidates: usize,
-    pub quantized: usize,
-    pub fallback: usize,
-    pub text_linear: ModuleStats,
-    pub projector: ModuleStats,
-    pub lm_head: ModuleStats,
-}
+pub use deepseek_ocr_core::quantization::{
+    LinearLayerGroup, ModuleStats, QuantModule, QuantizationConfig, QuantizationKind,
+    QuantizationOutcome, QuantizationStats, QuantizationTargets, VisionQuantization, VisionTower,
+};
+pub(crate) use deepseek_ocr_core::runtime::backend_label;
+use tracing::info;
 
-#[derive(Debug, Clone, Copy)]
-pub enum QuantizationOutcome {
-    Quantized,
-    Fallback,
-}
+use crate::gguf::GgufWeights;
 
+/// Quantization settings plus the counters gathered while one model loads.
 pub struct QuantizationState {
     config: QuantizationConfig,
+    prequantized: Option<Arc<GgufWeights>>,
     stats: Mutex<QuantizationStats>,
     summary_logged: AtomicBool,
 }
 
 impl QuantizationState {
-    fn from_env() -> Self {
-        let kind = parse_kind_from_env();
-        let targets = parse_targets_from_env();
-        let keep_full_precision_weights = parse_keep_full_precision_from_env();
-        let verbose_per_layer = parse_verbose_from_env();
+    pub fn new(config: QuantizationConfig) -> Self {
         Self {
-            config: QuantizationConfig {
-                kind,
-                targets,
-                keep_full_precision_weights,
-                verbose_per_layer,
-            },
+            config,
+            prequantized: None,
             stats: Mutex::new(QuantizationStats::default()),
             summary_logged: AtomicBool::new(false),
         }
     }
 
-    pub fn global() -> &'static Self {
-        static INSTANCE: Lazy<QuantizationState> = Lazy::new(QuantizationState::from_env);
-        &INSTANCE
+    /// Settings taken from the `DEEPSEEK_OCR_QUANT*` environment variables.
+    pub fn from_env() -> Self {
+        Self::new(QuantizationConfig::from_env())
+    }
+
+    /// Serve block-quantized linears from a GGUF file instead of quantizing at load time.
+    pub fn with_prequantized(mut self, weights: Arc<GgufWeights>) -> Self {
+        self.prequantized = Some(weights);
+        self
+    }
+
+    pub fn prequantized(&self) -> Option<&GgufWeights> {
+        self.prequantized.as_deref()
     }
 
     pub fn config(&self) -> QuantizationConfig {
@@ -145,49 +55,32 @@ impl QuantizationState {
     }
 
     pub fn enabled_for(&self, group: LinearLayerGroup) -> bool {
-        if !self.config.kind.is_enabled() {
-            return false;
-        }
-        match group {
-            LinearLayerGroup::Text => true,
-            LinearLayerGroup::Projector => {
-                matches!(self.config.targets, QuantizationTargets::TextAndProjector)
-            }
-            LinearLayerGroup::Vision => false,
-        }
+        self.config.enabled_for(group)
+    }
+
+    pub fn enabled_for_module(&self, group: LinearLayerGroup, module: QuantModule) -> bool {
+        self.config.enabled_for_module(group, module)
     }
 
     pub fn record_attempt(&self, module: QuantModule, outcome: QuantizationOutcome) {
-        let mut stats = self
+        self.stats
+            .lock()
+            .expect("quantization stats mutex poisoned")
+            .record(module, outcome);
+    }
+
+    pub fn stats(&self) -> QuantizationStats {
+        *self
             .stats
             .lock()
-            .expect("quantization stats mutex poisoned");
-        stats.candidates += 1;
-        match outcome {
-            QuantizationOutcome::Quantized => stats.quantized += 1,
-            QuantizationOutcome::Fallback => stats.fallback += 1,
-        }
-        let mstats = match module {
-            QuantModule::TextLinear => &mut stats.text_linear,
-            QuantModule::Projector => &mut stats.projector,
-            QuantModule::LmHead => &mut stats.lm_head,
-        };
-        mstats.candidates += 1;
-        match outcome {
-            QuantizationOutcome::Quantized => mstats.quantized += 1,
-            QuantizationOutcome::Fallback => mstats.fallback += 1,
-        }
+            .expect("quantization stats mutex poisoned")
     }
 
     pub fn log_summary(&self, device: &Device) {
         if self.summary_logged.swap(true, Ordering::SeqCst) {
             return;
         }
-        let stats = self
-            .stats
-            .lock()
-            .expect("quantization stats mutex poisoned")
-            .clone();
+        let stats = self.stats();
         info!(
             backend = backend_label(device),
             quant = %self.config.kind,
@@ -206,67 +99,14 @@ impl QuantizationState {
             lm_cand = stats.lm_head.candidates,
             lm_quant = stats.lm_head.quantized,
             lm_fallback = stats.lm_head.fallback,
+            vision = %self.config.vision,
+            sam_cand = stats.sam.candidates,
+            sam_quant = stats.sam.quantized,
+            sam_fallback = stats.sam.fallback,
+            clip_cand = stats.clip.candidates,
+            clip_quant = stats.clip.quantized,
+            clip_fallback = stats.clip.fallback,
             "language runtime quantization summary"
         );
     }
 }
-
-fn parse_kind_from_env() -> QuantizationKind {
-    match env::var("DEEPSEEK_OCR_QUANT") {
-        Ok(value) => match value.trim().to_ascii_lowercase().as_str() {
-            "" | "none" => QuantizationKind::None,
-            "q8_0" | "q8" | "q8.0" => QuantizationKind::Q8_0,
-            "q4_k" | "q4k" => {
-                warn!("DEEPSEEK_OCR_QUANT=Q4_K not yet implemented, falling back to float weights");
-                QuantizationKind::Q4K
-            }
-            other => {
-                warn!(
-                    "unsupported DEEPSEEK_OCR_QUANT value `{other}`, expected none|Q8_0|Q4_K; disabling quantization"
-                );
-                QuantizationKind::None
-            }
-        },
-        Err(_) => QuantizationKind::None,
-    }
-}
-
-fn parse_targets_from_env() -> QuantizationTargets {
-    match env::var("DEEPSEEK_OCR_QUANT_TARGETS") {
-        Ok(value) => match value.trim().to_ascii_lowercase().as_str() {
-            "" | "text" => QuantizationTargets::Text,
-            "text+projector" | "text,projector" | "projector+text" => {
-                QuantizationTargets::TextAndProjector
-            }
-            other => {
-                warn!("unsupported DEEPSEEK_OCR_QUANT_TARGETS value `{other}`, defaulting to text");
-                QuantizationTargets::Text
-            }
-        },
-        Err(_) => QuantizationTargets::Text,
-    }
-}
-
-fn parse_keep_full_precision_from_env() -> bool {
-    match env::var("DEEPSEEK_OCR_QUANT_KEEP_FLOAT") {
-        Ok(value) => matches!(value.trim(), "" | "1" | "true" | "TRUE"),
-        Err(_) => false,
-    }
-}
-
-fn parse_verbose_from_env() -> bool {
-    match env::var("DEEPSEEK_OCR_QUANT_VERBOSE") {
-        Ok(value) => matches!(value.trim(), "" | "1" | "true" | "TRUE"),
-        Err(_) => false,
-    }
-}
-
-pub(crate) fn backend_label(device: &Device) -> &'static str {
-    if device.is_cuda() {
-        "CUDA"
-    } else if device.is_metal() {
-        "Metal"
-    } else {
-        "CPU"
-    }
-}
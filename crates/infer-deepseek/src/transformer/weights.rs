@@ -5,6 +5,7 @@ use std::{
 
 use crate::{
     config::DeepseekV2Config,
+    gguf::load_prequantized_linear,
     quantization::{
         LinearLayerGroup, QuantModule, QuantizationKind, QuantizationOutcome, QuantizationState,
         backend_label,
@@ -41,17 +42,15 @@ impl fmt::Debug for LinearWeights {
 }
 
 impl LinearWeights {
-    fn load(
+    pub(crate) fn load(
         vb: &VarBuilder,
+        quant: &QuantizationState,
         out_dim: usize,
         in_dim: usize,
         bias: bool,
         group: LinearLayerGroup,
+        module: QuantModule,
     ) -> Result<Self> {
-        let weight = vb
-            .get((out_dim, in_dim), "weight")
-            .with_context(|| format!("missing linear weight `{}`", qualified_name(vb, "weight")))?;
-        let weight = weight.contiguous()?;
         let bias =
             if bias && vb.contains_tensor("bias") {
                 Some(vb.get(out_dim, "bias").with_context(|| {
@@ -60,16 +59,40 @@ impl LinearWeights {
             } else {
                 None
             };
+        let keep_fp = quant.config().keep_full_precision_weights;
+        if let Some(qmatmul) = load_prequantized_linear(
+            quant,
+            &qualified_name(vb, "weight"),
+            out_dim,
+            in_dim,
+            vb.device(),
+            module,
+        )? {
+            let weight = if keep_fp {
+                Some(vb.get((out_dim, in_dim), "weight")?.contiguous()?)
+            } else {
+                None
+            };
+            return Ok(Self {
+                weight,
+                bias,
+                qmatmul: Some(qmatmul),
+                out_dim,
+                in_dim,
+            });
+        }
+        let weight = vb
+            .get((out_dim, in_dim), "weight")
+            .with_context(|| format!("missing linear weight `{}`", qualified_name(vb, "weight")))?;
+        let weight = weight.contiguous()?;
         let qmatmul = maybe_quantize_linear(
+            quant,
             &weight,
             in_dim,
             group,
             &qualified_name(vb, "weight"),
-            QuantModule::TextLinear,
+            module,
         )?;
-        let keep_fp = QuantizationState::global()
-            .config()
-            .keep_full_precision_weights;
         let weight = if qmatmul.is_some() && !keep_fp {
             None
         } else {
@@ -108,7 +131,7 @@ pub struct AttentionWeights {
 }
 
 impl AttentionWeights {
-    fn load(cfg: &DeepseekV2Config, vb: &VarBuilder) -> Result<Self> {
+    fn load(cfg: &DeepseekV2Config, vb: &VarBuilder, quant: &QuantizationState) -> Result<Self> {
         let hidden_size = cfg.hidden_size;
         let num_heads = cfg.num_attention_heads;
         ensure!(
@@ -123,31 +146,39 @@ impl AttentionWeights {
 
         let q_proj = LinearWeights::load(
             &attn_vb.pp("q_proj"),
+            quant,
             num_heads * head_dim,
             hidden_size,
             true,
             LinearLayerGroup::Text,
+            QuantModule::TextLinear,
         )?;
         let k_proj = LinearWeights::load(
             &attn_vb.pp("k_proj"),
+            quant,
             num_kv_heads * kv_head_dim,
             hidden_size,
             true,
             LinearLayerGroup::Text,
+            QuantModule::TextLinear,
         )?;
         let v_proj = LinearWeights::load(
             &attn_vb.pp("v_proj"),
+            quant,
             num_kv_heads * v_head_dim,
             hidden_size,
             true,
             LinearLayerGroup::Text,
+            QuantModule::TextLinear,
         )?;
         let o_proj = LinearWeights::load(
             &attn_vb.pp("o_proj"),
+            quant,
             hidden_size,
             num_heads * v_head_dim,
             true,
             LinearLayerGroup::Text,
+            QuantModule::TextLinear,
         )?;
         Ok(Self {
             q_proj,
@@ -166,27 +197,38 @@ pub struct DenseMlpWeights {
 }
 
 impl DenseMlpWeights {
-    fn load(vb: &VarBuilder, hidden_size: usize, intermediate_size: usize) -> Result<Self> {
+    fn load(
+        vb: &VarBuilder,
+        quant: &QuantizationState,
+        hidden_size: usize,
+        intermediate_size: usize,
+    ) -> Result<Self> {
         let gate_proj = LinearWeights::load(
             &vb.pp("gate_proj"),
+            quant,
             intermediate_size,
             hidden_size,
             true,
             LinearLayerGroup::Text,
+            QuantModule::TextLinear,
         )?;
         let up_proj = LinearWeights::load(
             &vb.pp("up_proj"),
+            quant,
             intermediate_size,
             hidden_size,
             true,
             LinearLayerGroup::Text,
+            QuantModule::TextLinear,
         )?;
         let down_proj = LinearWeights::load(
             &vb.pp("down_proj"),
+            quant,
             hidden_size,
             intermediate_size,
             true,
             LinearLayerGroup::Text,
+            QuantModule::TextLinear,
         )?;
         Ok(Self {
             gate_proj,
@@ -205,7 +247,12 @@ pub struct MoeWeights {
 }
 
 impl MoeWeights {
-    fn load(cfg: &DeepseekV2Config, layer_idx: usize, vb: &VarBuilder) -> Result<Self> {
+    fn load(
+        cfg: &DeepseekV2Config,
+        layer_idx: usize,
+        vb: &VarBuilder,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let hidden_size = cfg.hidden_size;
         let moe_intermediate_size = cfg
             .moe_intermediate_size
@@ -234,10 +281,11 @@ impl MoeWeights {
         let mut experts = Vec::with_capacity(num_routed);
         for expert_idx in 0..num_routed {
             let expert_vb = vb.pp(format!("experts.{expert_idx}"));
-            let expert = DenseMlpWeights::load(&expert_vb, hidden_size, moe_intermediate_size)
-                .with_context(|| {
-                    format!("failed to load MoE expert {expert_idx} (layer {layer_idx})")
-                })?;
+            let expert =
+                DenseMlpWeights::load(&expert_vb, quant, hidden_size, moe_intermediate_size)
+                    .with_context(|| {
+                        format!("failed to load MoE expert {expert_idx} (layer {layer_idx})")
+                    })?;
             experts.push(expert);
         }
 
@@ -245,9 +293,9 @@ impl MoeWeights {
             let vb = vb.pp("shared_experts");
             let intermediate = moe_intermediate_size * count;
             Some(
-                DenseMlpWeights::load(&vb, hidden_size, intermediate).with_context(|| {
-                    format!("failed to load shared_experts for layer {layer_idx}")
-                })?,
+                DenseMlpWeights::load(&vb, quant, hidden_size, intermediate).with_context(
+                    || format!("failed to load shared_experts for layer {layer_idx}"),
+                )?,
             )
         } else {
             None
@@ -269,13 +317,18 @@ pub enum MlpWeights {
 }
 
 impl MlpWeights {
-    fn load(cfg: &DeepseekV2Config, layer_idx: usize, vb: &VarBuilder) -> Result<Self> {
+    fn load(
+        cfg: &DeepseekV2Config,
+        layer_idx: usize,
+        vb: &VarBuilder,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let hidden_size = cfg.hidden_size;
         let intermediate_size = cfg.intermediate_size;
         if should_use_moe(cfg, layer_idx) {
-            MoeWeights::load(cfg, layer_idx, vb).map(MlpWeights::Moe)
+            MoeWeights::load(cfg, layer_idx, vb, quant).map(MlpWeights::Moe)
         } else {
-            DenseMlpWeights::load(vb, hidden_size, intermediate_size).map(MlpWeights::Dense)
+            DenseMlpWeights::load(vb, quant, hidden_size, intermediate_size).map(MlpWeights::Dense)
         }
     }
 }
@@ -289,9 +342,14 @@ pub struct TransformerBlockWeights {
 }
 
 impl TransformerBlockWeights {
-    pub fn load(cfg: &DeepseekV2Config, layer_idx: usize, vb: &VarBuilder) -> Result<Self> {
-        let attention = AttentionWeights::load(cfg, vb)?;
-        let mlp = MlpWeights::load(cfg, layer_idx, &vb.pp("mlp"))?;
+    pub fn load(
+        cfg: &DeepseekV2Config,
+        layer_idx: usize,
+        vb: &VarBuilder,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
+        let attention = AttentionWeights::load(cfg, vb, quant)?;
+        let mlp = MlpWeights::load(cfg, layer_idx, &vb.pp("mlp"), quant)?;
         let input_layernorm = RmsNormWeights::load(&vb.pp("input_layernorm"), cfg.hidden_size)?;
         let post_attention_layernorm =
             RmsNormWeights::load(&vb.pp("post_attention_layernorm"), cfg.hidden_size)?;
@@ -310,11 +368,15 @@ pub struct TransformerWeights {
 }
 
 impl TransformerWeights {
-    pub fn load(cfg: &DeepseekV2Config, vb: &VarBuilder) -> Result<Self> {
+    pub fn load(
+        cfg: &DeepseekV2Config,
+        vb: &VarBuilder,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let mut layers = Vec::with_capacity(cfg.num_hidden_layers);
         for layer_idx in 0..cfg.num_hidden_layers {
             let layer_vb = vb.pp(format!("layers.{layer_idx}"));
-            let layer = TransformerBlockWeights::load(cfg, layer_idx, &layer_vb)
+            let layer = TransformerBlockWeights::load(cfg, layer_idx, &layer_vb, quant)
                 .with_context(|| format!("failed to load transformer layer `{layer_idx}`"))?;
             layers.push(layer);
         }
@@ -335,6 +397,15 @@ pub struct DeepseekLanguageModelWeights {
 
 impl DeepseekLanguageModelWeights {
     pub fn load(cfg: &DeepseekV2Config, vb: &VarBuilder) -> Result<Self> {
+        Self::load_with_quantization(cfg, vb, &QuantizationState::from_env())
+    }
+
+    /// Like [`Self::load`], but quantizes (or reads pre-quantized linears) according to `quant`.
+    pub fn load_with_quantization(
+        cfg: &DeepseekV2Config,
+        vb: &VarBuilder,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let model_vb = vb.pp("model");
         let token_embedding = model_vb
             .pp("embed_tokens")
@@ -346,7 +417,7 @@ impl DeepseekLanguageModelWeights {
                 )
             })?;
         let token_embedding = token_embedding.contiguous()?;
-        let transformer = TransformerWeights::load(cfg, &model_vb)?;
+        let transformer = TransformerWeights::load(cfg, &model_vb, quant)?;
         let final_layernorm = RmsNormWeights::load(&model_vb.pp("norm"), cfg.hidden_size)
             .with_context(|| {
                 format!(
@@ -354,17 +425,6 @@ impl DeepseekLanguageModelWeights {
                     qualified_name(&model_vb.pp("norm"), "weight")
                 )
             })?;
-        let lm_head_vb = vb.pp("lm_head");
-        let lm_head = lm_head_vb
-            .get((cfg.vocab_size, cfg.hidden_size), "weight")
-            .with_context(|| {
-                format!(
-                    "missing lm_head weight `{}`",
-                    qualified_name(&lm_head_vb, "weight")
-                )
-            })?
-            .contiguous()?;
-
         if cfg.tie_word_embeddings {
             ensure!(
                 token_embedding.shape().dims() == [cfg.vocab_size, cfg.hidden_size],
@@ -374,21 +434,46 @@ impl DeepseekLanguageModelWeights {
             );
         }
 
-        // Try runtime quantization for lm_head under the Text target.
-        let lm_q = maybe_quantize_linear(
-            &lm_head,
+        let lm_head_vb = vb.pp("lm_head");
+        let lm_head_name = qualified_name(&lm_head_vb, "weight");
+        let load_lm_head = || -> Result<Tensor> {
+            Ok(lm_head_vb
+                .get((cfg.vocab_size, cfg.hidden_size), "weight")
+                .with_context(|| format!("missing lm_head weight `{lm_head_name}`"))?
+                .contiguous()?)
+        };
+        let keep_fp = quant.config().keep_full_precision_weights;
+        let prequantized = load_prequantized_linear(
+            quant,
+            &lm_head_name,
+            cfg.vocab_size,
             cfg.hidden_size,
-            LinearLayerGroup::Text,
-            &qualified_name(&lm_head_vb, "weight"),
+            vb.device(),
             QuantModule::LmHead,
         )?;
-        let keep_fp = QuantizationState::global()
-            .config()
-            .keep_full_precision_weights;
-        let lm_head_weight = if lm_q.is_some() && !keep_fp {
-            None
-        } else {
-            Some(lm_head)
+        let (lm_head_weight, lm_q) = match prequantized {
+            Some(qmatmul) => {
+                let weight = if keep_fp { Some(load_lm_head()?) } else { None };
+                (weight, Some(qmatmul))
+            }
+            None => {
+                let lm_head = load_lm_head()?;
+                // Try runtime quantization for lm_head under the Text target.
+                let lm_q = maybe_quantize_linear(
+                    quant,
+                    &lm_head,
+                    cfg.hidden_size,
+                    LinearLayerGroup::Text,
+                    &lm_head_name,
+                    QuantModule::LmHead,
+                )?;
+                let weight = if lm_q.is_some() && !keep_fp {
+                    None
+                } else {
+                    Some(lm_head)
+                };
+                (weight, lm_q)
+            }
         };
 
         Ok(Self {
@@ -403,7 +488,7 @@ impl DeepseekLanguageModelWeights {
     }
 }
 
-fn should_use_moe(cfg: &DeepseekV2Config, layer_idx: usize) -> bool {
+pub(crate) fn should_use_moe(cfg: &DeepseekV2Config, layer_idx: usize) -> bool {
     let num_routed = cfg.n_routed_experts.unwrap_or(0);
     if num_routed == 0 {
         return false;
@@ -436,29 +521,29 @@ fn qualified_name(vb: &VarBuilder, tensor: &str) -> String {
 const Q8_BLOCK_SIZE: usize = 32;
 
 fn maybe_quantize_linear(
+    quant: &QuantizationState,
     weight: &Tensor,
     in_dim: usize,
     group: LinearLayerGroup,
     tensor_name: &str,
     module: QuantModule,
 ) -> Result<Option<Arc<QMatMul>>> {
-    let quant = QuantizationState::global();
     let config = quant.config();
     // GPU fast-fail: if quant is requested for this group on Metal/CUDA, error out (awaiting upstream kernel fixes).
     if (weight.device().is_metal() || weight.device().is_cuda())
         && config.kind.is_enabled()
-        && quant.enabled_for(group)
+        && quant.enabled_for_module(group, module)
     {
         anyhow::bail!(
             "GPU backend: runtime quantization is disabled on Metal/CUDA. Refusing to fallback.\n\
-             Disable quantization (DEEPSEEK_OCR_QUANT=none) or run on CPU.\n\
+             Disable quantization (--quant none) or run on CPU.\n\
              Context: tensor={}, group={:?}, backend={}",
             tensor_name,
             group,
             crate::quantization::backend_label(&weight.device())
         );
     }
-    if !quant.enabled_for(group) {
+    if !quant.enabled_for_module(group, module) {
         trace!(
             tensor = tensor_name,
             ?group,
@@ -6,6 +6,7 @@ use candle_nn::{
 };
 
 use crate::config::{DeepseekOcrConfig, VisionBackboneConfig};
+use crate::quantization::{LinearLayerGroup, QuantModule, QuantizationState, VisionTower};
 use crate::transformer::weights::LinearWeights;
 
 /// Hyper-parameters describing the CLIP-L vision transformer used by DeepSeek-OCR.
@@ -71,9 +72,18 @@ pub struct ClipDebugTrace {
 
 impl ClipVisionModel {
     pub fn load(cfg: &DeepseekOcrConfig, vb: &VarBuilder) -> Result<Self> {
+        Self::load_with_quantization(cfg, vb, &QuantizationState::from_env())
+    }
+
+    /// Load the model, quantizing attention/MLP linears when the `clip` tower is selected.
+    pub fn load_with_quantization(
+        cfg: &DeepseekOcrConfig,
+        vb: &VarBuilder,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let params = ClipVisionParams::from_config(cfg)?;
         let embeddings = ClipEmbeddings::load(&params, vb.pp("embeddings"))?;
-        let transformer = ClipTransformer::load(&params, &vb.pp("transformer"))?;
+        let transformer = ClipTransformer::load(&params, &vb.pp("transformer"), quant)?;
         let pre_layernorm = layer_norm(
             params.hidden_size,
             params.layernorm_epsilon,
@@ -224,11 +234,15 @@ struct ClipTransformer {
 }
 
 impl ClipTransformer {
-    fn load(params: &ClipVisionParams, vb: &VarBuilder) -> Result<Self> {
+    fn load(params: &ClipVisionParams, vb: &VarBuilder, quant: &QuantizationState) -> Result<Self> {
         let mut layers = Vec::with_capacity(params.num_layers);
         let layers_vb = vb.pp("layers");
         for idx in 0..params.num_layers {
-            layers.push(ClipBlock::load(params, &layers_vb.pp(&idx.to_string()))?);
+            layers.push(ClipBlock::load(
+                params,
+                &layers_vb.pp(&idx.to_string()),
+                quant,
+            )?);
         }
         Ok(Self { layers })
     }
@@ -261,19 +275,19 @@ struct ClipBlock {
 }
 
 impl ClipBlock {
-    fn load(params: &ClipVisionParams, vb: &VarBuilder) -> Result<Self> {
+    fn load(params: &ClipVisionParams, vb: &VarBuilder, quant: &QuantizationState) -> Result<Self> {
         let ln1 = layer_norm(
             params.hidden_size,
             params.layernorm_epsilon,
             vb.pp("layer_norm1"),
         )?;
-        let attn = ClipAttention::load(params, &vb.pp("self_attn"))?;
+        let attn = ClipAttention::load(params, &vb.pp("self_attn"), quant)?;
         let ln2 = layer_norm(
             params.hidden_size,
             params.layernorm_epsilon,
             vb.pp("layer_norm2"),
         )?;
-        let mlp = ClipMlp::load(params, &vb.pp("mlp"))?;
+        let mlp = ClipMlp::load(params, &vb.pp("mlp"), quant)?;
         Ok(Self {
             ln1,
             attn,
@@ -300,7 +314,7 @@ struct ClipAttention {
 }
 
 impl ClipAttention {
-    fn load(params: &ClipVisionParams, vb: &VarBuilder) -> Result<Self> {
+    fn load(params: &ClipVisionParams, vb: &VarBuilder, quant: &QuantizationState) -> Result<Self> {
         let num_heads = params.num_heads;
         let head_dim = params.hidden_size / num_heads;
         ensure!(
@@ -311,15 +325,15 @@ impl ClipAttention {
         );
         let qkv_proj = load_linear(
             &vb.pp("qkv_proj"),
+            quant,
             params.hidden_size * 3,
             params.hidden_size,
-            true,
         )?;
         let out_proj = load_linear(
             &vb.pp("out_proj"),
+            quant,
             params.hidden_size,
             params.hidden_size,
-            true,
         )?;
         Ok(Self {
             num_heads,
@@ -370,18 +384,18 @@ struct ClipMlp {
 }
 
 impl ClipMlp {
-    fn load(params: &ClipVisionParams, vb: &VarBuilder) -> Result<Self> {
+    fn load(params: &ClipVisionParams, vb: &VarBuilder, quant: &QuantizationState) -> Result<Self> {
         let fc1 = load_linear(
             &vb.pp("fc1"),
+            quant,
             params.ffn_hidden_size,
             params.hidden_size,
-            true,
         )?;
         let fc2 = load_linear(
             &vb.pp("fc2"),
+            quant,
             params.hidden_size,
             params.ffn_hidden_size,
-            true,
         )?;
         Ok(Self { fc1, fc2 })
     }
@@ -411,11 +425,18 @@ fn apply_linear(input: &Tensor, weights: &LinearWeights) -> Result<Tensor> {
     );
     let leading = dims[..dims.len() - 1].iter().product::<usize>();
     let input2d = input.reshape((leading, in_dim))?;
-    let weight = weights
-        .weight
-        .as_ref()
-        .context("vision linear weights require float tensors")?;
-    let mut proj = input2d.matmul(&weight.transpose(0, 1)?)?;
+    let mut proj = if let Some(qmatmul) = &weights.qmatmul {
+        // Quantized matmuls run on f32 activations.
+        qmatmul
+            .forward(&input2d.to_dtype(DType::F32)?.contiguous()?)?
+            .to_dtype(input2d.dtype())?
+    } else {
+        let weight = weights
+            .weight
+            .as_ref()
+            .context("vision linear has neither float nor quantized weights")?;
+        input2d.matmul(&weight.transpose(0, 1)?)?
+    };
     if let Some(bias) = &weights.bias {
         proj = proj.broadcast_add(&bias.reshape((1, out_dim))?)?;
     }
@@ -437,30 +458,19 @@ fn scaled_dot_product_attention(q: &Tensor, k: &Tensor, v: &Tensor, scale: f32)
 
 fn load_linear(
     vb: &VarBuilder,
+    quant: &QuantizationState,
     out_dim: usize,
     in_dim: usize,
-    bias: bool,
 ) -> Result<LinearWeights> {
-    let weight = vb
-        .get((out_dim, in_dim), "weight")
-        .with_context(|| "missing linear weight")?
-        .contiguous()?;
-    let bias = if bias && vb.contains_tensor("bias") {
-        Some(
-            vb.get(out_dim, "bias")
-                .with_context(|| "missing linear bias")?
-                .contiguous()?,
-        )
-    } else {
-        None
-    };
-    Ok(LinearWeights {
-        weight: Some(weight),
-        bias,
-        qmatmul: None,
+    LinearWeights::load(
+        vb,
+        quant,
         out_dim,
         in_dim,
-    })
+        true,
+        LinearLayerGroup::Vision,
+        QuantModule::Vision(VisionTower::Clip),
+    )
 }
 
 fn adapt_position_embedding(table: &Tensor, target_tokens: usize) -> Result<Tensor> {
@@ -1,8 +1,12 @@
 pub mod clip;
 pub mod preprocess;
-pub mod resample;
 pub mod sam;
 
+pub use deepseek_ocr_core::resample;
+
 pub use clip::{ClipDebugTrace, ClipVisionModel, ClipVisionParams};
-pub use preprocess::{DynamicPreprocessResult, dynamic_preprocess};
+pub use preprocess::{
+    DynamicPreprocessResult, MAX_CROP_TILES, MIN_CROP_TILES, dynamic_preprocess, select_crop_grid,
+    vision_token_count,
+};
 pub use sam::{SamBackbone, SamBackboneParams, SamDebugTrace};
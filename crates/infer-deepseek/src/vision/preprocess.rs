@@ -1,9 +1,15 @@
 use std::collections::BTreeSet;
 
+use deepseek_ocr_core::inference::VisionSettings;
 use image::{DynamicImage, GenericImageView, RgbImage};
 
 use super::resample::resize_bicubic;
 
+/// Fewest crop tiles dynamic preprocessing may produce in crop mode.
+pub const MIN_CROP_TILES: u32 = 2;
+/// Most crop tiles dynamic preprocessing may produce in crop mode.
+pub const MAX_CROP_TILES: u32 = 9;
+
 #[derive(Debug, Clone)]
 pub struct DynamicPreprocessResult {
     pub tiles: Vec<DynamicImage>,
@@ -18,6 +24,44 @@ pub fn dynamic_preprocess(
     use_thumbnail: bool,
 ) -> DynamicPreprocessResult {
     let (orig_width, orig_height) = image.dimensions();
+    let target_aspect_ratio =
+        select_crop_grid(orig_width, orig_height, min_num, max_num, image_size);
+
+    let target_width = image_size * target_aspect_ratio.0;
+    let target_height = image_size * target_aspect_ratio.1;
+    let base_rgb: RgbImage = image.to_rgb8();
+    let resized_rgb = resize_bicubic(&base_rgb, target_width, target_height);
+    let resized = DynamicImage::ImageRgb8(resized_rgb);
+
+    let mut tiles = Vec::new();
+    let tiles_w = target_width / image_size;
+    let tiles_h = target_height / image_size;
+    for i in 0..tiles_w * tiles_h {
+        let x = (i % tiles_w) * image_size;
+        let y = (i / tiles_w) * image_size;
+        let tile = resized.crop_imm(x, y, image_size, image_size);
+        tiles.push(tile);
+    }
+
+    if use_thumbnail && tiles.len() > 1 {
+        let thumb_rgb = resize_bicubic(&base_rgb, image_size, image_size);
+        tiles.push(DynamicImage::ImageRgb8(thumb_rgb));
+    }
+
+    DynamicPreprocessResult {
+        tiles,
+        ratio: target_aspect_ratio,
+    }
+}
+
+/// Pick the `(width, height)` tile grid whose aspect ratio best matches the source image.
+pub fn select_crop_grid(
+    orig_width: u32,
+    orig_height: u32,
+    min_num: u32,
+    max_num: u32,
+    image_size: u32,
+) -> (u32, u32) {
     let aspect_ratio = orig_width as f64 / orig_height as f64;
 
     let mut target_ratios: BTreeSet<(u32, u32)> = BTreeSet::new();
@@ -48,29 +92,20 @@ pub fn dynamic_preprocess(
         }
     }
 
-    let target_width = image_size * target_aspect_ratio.0;
-    let target_height = image_size * target_aspect_ratio.1;
-    let base_rgb: RgbImage = image.to_rgb8();
-    let resized_rgb = resize_bicubic(&base_rgb, target_width, target_height);
-    let resized = DynamicImage::ImageRgb8(resized_rgb);
-
-    let mut tiles = Vec::new();
-    let tiles_w = target_width / image_size;
-    let tiles_h = target_height / image_size;
-    for i in 0..tiles_w * tiles_h {
-        let x = (i % tiles_w) * image_size;
-        let y = (i / tiles_w) * image_size;
-        let tile = resized.crop_imm(x, y, image_size, image_size);
-        tiles.push(tile);
-    }
-
-    if use_thumbnail && tiles.len() > 1 {
-        let thumb_rgb = resize_bicubic(&base_rgb, image_size, image_size);
-        tiles.push(DynamicImage::ImageRgb8(thumb_rgb));
-    }
+    target_aspect_ratio
+}
 
-    DynamicPreprocessResult {
-        tiles,
-        ratio: target_aspect_ratio,
-    }
+/// Number of `<image>` tokens the model will emit for an image of the given dimensions.
+pub fn vision_token_count(width: u32, height: u32, vision: VisionSettings) -> usize {
+    let grid = vision.crop_mode.then(|| {
+        let (w, h) = select_crop_grid(
+            width,
+            height,
+            MIN_CROP_TILES,
+            MAX_CROP_TILES,
+            vision.image_size,
+        );
+        (w as usize, h as usize)
+    });
+    vision.vision_tokens(grid)
 }
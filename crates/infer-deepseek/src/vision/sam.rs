@@ -1,4 +1,6 @@
 use crate::config::{DeepseekOcrConfig, VisionBackboneConfig, VisionConfig};
+use crate::quantization::{LinearLayerGroup, QuantModule, QuantizationState, VisionTower};
+use crate::transformer::weights::LinearWeights;
 use anyhow::{Context, Result, anyhow, bail, ensure};
 use candle_core::{DType, Device, Module, Tensor, shape::D};
 use candle_nn::{
@@ -108,6 +110,15 @@ pub struct SamDebugTrace {
 
 impl SamBackbone {
     pub fn new(cfg: &DeepseekOcrConfig, vb: &VarBuilder) -> Result<Self> {
+        Self::new_with_quantization(cfg, vb, &QuantizationState::from_env())
+    }
+
+    /// Load the backbone, quantizing the ViT block linears when the `sam` tower is selected.
+    pub fn new_with_quantization(
+        cfg: &DeepseekOcrConfig,
+        vb: &VarBuilder,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let params = SamBackboneParams::from_config(cfg)?;
         let patch_embed =
             PatchEmbed::new(3, params.embed_dim, params.patch_size, vb.pp("patch_embed"))
@@ -130,7 +141,7 @@ impl SamBackbone {
         let mut blocks = Vec::with_capacity(params.depth);
         for idx in 0..params.depth {
             blocks.push(
-                SamBlock::load(idx, &params, &blocks_vb.pp(idx.to_string()))
+                SamBlock::load(idx, &params, &blocks_vb.pp(idx.to_string()), quant)
                     .context(format!("failed to load SAM block {idx}"))?,
             );
         }
@@ -550,43 +561,30 @@ pub fn window_partition_shape(h: usize, w: usize, window: usize) -> WindowPartit
     }
 }
 
-#[derive(Clone)]
-struct LinearLayer {
-    weight: Tensor,
-    bias: Option<Tensor>,
-}
-
-impl LinearLayer {
-    fn load(vb: &VarBuilder, out_dim: usize, in_dim: usize, bias: bool) -> Result<Self> {
-        let weight = vb
-            .get((out_dim, in_dim), "weight")
-            .with_context(|| "missing linear weight `weight`")?;
-        let weight = weight
-            .contiguous()
-            .context("linear weight must be contiguous")?;
-        let bias = if bias && vb.contains_tensor("bias") {
-            Some(
-                vb.get(out_dim, "bias")
-                    .with_context(|| "missing linear bias `bias`")?,
-            )
-        } else {
-            None
-        };
-        Ok(Self { weight, bias })
-    }
+fn load_linear(
+    vb: &VarBuilder,
+    quant: &QuantizationState,
+    out_dim: usize,
+    in_dim: usize,
+) -> Result<LinearWeights> {
+    LinearWeights::load(
+        vb,
+        quant,
+        out_dim,
+        in_dim,
+        true,
+        LinearLayerGroup::Vision,
+        QuantModule::Vision(VisionTower::Sam),
+    )
 }
 
-fn linear_forward(layer: &LinearLayer, input: &Tensor) -> Result<Tensor> {
+fn linear_forward(layer: &LinearWeights, input: &Tensor) -> Result<Tensor> {
     let dims = input.shape().dims();
     anyhow::ensure!(dims.len() >= 2, "linear expects rank >= 2");
     let last_dim = *dims
         .last()
         .expect("linear expects rank >= 2 so last dim exists");
-    let (out_dim, in_dim) = layer
-        .weight
-        .shape()
-        .dims2()
-        .context("linear weight must be 2D")?;
+    let (out_dim, in_dim) = (layer.out_dim, layer.in_dim);
     anyhow::ensure!(
         in_dim == last_dim,
         "linear weight expects input dim {}, got {}",
@@ -595,12 +593,21 @@ fn linear_forward(layer: &LinearLayer, input: &Tensor) -> Result<Tensor> {
     );
     let leading = dims[..dims.len() - 1].iter().product::<usize>();
     let reshaped = input.reshape((leading, in_dim))?;
-    let weight_t = if layer.weight.dtype() == reshaped.dtype() {
-        layer.weight.transpose(0, 1)?
+    let mut output = if let Some(qmatmul) = &layer.qmatmul {
+        // Quantized matmuls run on f32 activations.
+        qmatmul.forward(&reshaped.to_dtype(DType::F32)?.contiguous()?)?
     } else {
-        layer.weight.to_dtype(reshaped.dtype())?.transpose(0, 1)?
+        let weight = layer
+            .weight
+            .as_ref()
+            .context("linear layer has neither float nor quantized weights")?;
+        let weight_t = if weight.dtype() == reshaped.dtype() {
+            weight.transpose(0, 1)?
+        } else {
+            weight.to_dtype(reshaped.dtype())?.transpose(0, 1)?
+        };
+        reshaped.matmul(&weight_t)?
     };
-    let mut output = reshaped.matmul(&weight_t)?;
     if let Some(bias) = &layer.bias {
         let bias = if bias.dtype() == output.dtype() {
             bias.reshape((1, out_dim))?
@@ -632,16 +639,21 @@ struct SamBlock {
 }
 
 impl SamBlock {
-    fn load(index: usize, params: &SamBackboneParams, vb: &VarBuilder) -> Result<Self> {
+    fn load(
+        index: usize,
+        params: &SamBackboneParams,
+        vb: &VarBuilder,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let window_size = if params.global_attn_indexes.contains(&index) {
             0
         } else {
             params.window_size
         };
         let norm1 = layer_norm(params.embed_dim, params.norm_eps, vb.pp("norm1"))?;
-        let attn = SamAttention::load(params, window_size, &vb.pp("attn"))?;
+        let attn = SamAttention::load(params, window_size, &vb.pp("attn"), quant)?;
         let norm2 = layer_norm(params.embed_dim, params.norm_eps, vb.pp("norm2"))?;
-        let mlp = SamMlp::load(params, &vb.pp("mlp"))?;
+        let mlp = SamMlp::load(params, &vb.pp("mlp"), quant)?;
         Ok(Self {
             norm1,
             attn,
@@ -674,19 +686,24 @@ impl SamBlock {
 struct SamAttention {
     num_heads: usize,
     head_dim: usize,
-    qkv: LinearLayer,
-    proj: LinearLayer,
+    qkv: LinearWeights,
+    proj: LinearWeights,
     use_rel_pos: bool,
     rel_pos_h: Option<Tensor>,
     rel_pos_w: Option<Tensor>,
 }
 
 impl SamAttention {
-    fn load(params: &SamBackboneParams, window_size: usize, vb: &VarBuilder) -> Result<Self> {
+    fn load(
+        params: &SamBackboneParams,
+        window_size: usize,
+        vb: &VarBuilder,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let qkv_vb = vb.pp("qkv");
         let proj_vb = vb.pp("proj");
-        let qkv = LinearLayer::load(&qkv_vb, params.embed_dim * 3, params.embed_dim, true)?;
-        let proj = LinearLayer::load(&proj_vb, params.embed_dim, params.embed_dim, true)?;
+        let qkv = load_linear(&qkv_vb, quant, params.embed_dim * 3, params.embed_dim)?;
+        let proj = load_linear(&proj_vb, quant, params.embed_dim, params.embed_dim)?;
         let input_tokens = if window_size > 0 {
             window_size
         } else {
@@ -812,12 +829,16 @@ impl SamAttention {
 }
 
 struct SamMlp {
-    fc1: LinearLayer,
-    fc2: LinearLayer,
+    fc1: LinearWeights,
+    fc2: LinearWeights,
 }
 
 impl SamMlp {
-    fn load(params: &SamBackboneParams, vb: &VarBuilder) -> Result<Self> {
+    fn load(
+        params: &SamBackboneParams,
+        vb: &VarBuilder,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let hidden = (params.embed_dim as f64 * params.mlp_ratio) as usize;
         let fc1_vb = if vb.contains_tensor("fc1.weight") {
             vb.pp("fc1")
@@ -833,8 +854,8 @@ impl SamMlp {
         } else {
             bail!("sam mlp missing fc2/lin2 weights")
         };
-        let fc1 = LinearLayer::load(&fc1_vb, hidden, params.embed_dim, true)?;
-        let fc2 = LinearLayer::load(&fc2_vb, params.embed_dim, hidden, true)?;
+        let fc1 = load_linear(&fc1_vb, quant, hidden, params.embed_dim)?;
+        let fc2 = load_linear(&fc2_vb, quant, params.embed_dim, hidden)?;
         Ok(Self { fc1, fc2 })
     }
 
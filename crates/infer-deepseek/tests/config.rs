@@ -2,7 +2,11 @@ mod common;
 
 use anyhow::{Context, Result};
 use common::test_utils::workspace_path;
-use deepseek_ocr_infer_deepseek::config::{DeepseekOcrConfig, load_ocr_config};
+use deepseek_ocr_core::inference::ResolutionMode;
+use deepseek_ocr_infer_deepseek::{
+    config::{DeepseekOcrConfig, load_ocr_config},
+    vision::vision_token_count,
+};
 
 fn load_test_config() -> Result<DeepseekOcrConfig> {
     let path = workspace_path("DeepSeek-OCR/config.json");
@@ -56,3 +60,31 @@ fn vision_backbone_lookup() -> Result<()> {
     assert_eq!(sam.heads, Some(12));
     Ok(())
 }
+
+#[test]
+fn crop_modes_require_candidate_resolution() -> Result<()> {
+    let mut config = load_test_config()?;
+    config.candidate_resolutions = vec![[1024, 1024]];
+    assert_eq!(config.supported_resolution_modes(), ResolutionMode::ALL);
+
+    config.candidate_resolutions = vec![[1280, 1280]];
+    assert!(
+        config
+            .validate_resolution_mode(ResolutionMode::Gundam)
+            .is_err()
+    );
+    assert!(
+        config
+            .validate_resolution_mode(ResolutionMode::Large)
+            .is_ok()
+    );
+    Ok(())
+}
+
+#[test]
+fn token_count_follows_crop_grid() {
+    let gundam = ResolutionMode::Gundam.settings();
+    assert_eq!(vision_token_count(1280, 640, gundam), 273 + 10 * 21);
+    let base = ResolutionMode::Base.settings();
+    assert_eq!(vision_token_count(1280, 640, base), 273);
+}
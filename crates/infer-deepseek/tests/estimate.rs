@@ -0,0 +1,70 @@
+mod common;
+
+use anyhow::{Result, anyhow};
+use candle_core::DType;
+use common::test_utils::{shared_ocr_model, workspace_path};
+use deepseek_ocr_core::{estimate::EstimateArgs, inference::ResolutionMode};
+use deepseek_ocr_infer_deepseek::estimate;
+use image::{DynamicImage, Rgb, RgbImage};
+use tokenizers::Tokenizer;
+
+/// The dry run must reserve exactly as many prompt positions per image as the vision towers
+/// produce embedding rows, for single views and for every crop grid shape.
+#[test]
+fn estimates_match_the_embedding_rows_of_the_model() -> Result<()> {
+    let tokenizer_path = workspace_path("DeepSeek-OCR/tokenizer.json");
+    if !tokenizer_path.exists() {
+        eprintln!("skipping estimate parity test: tokenizer missing at {tokenizer_path:?}");
+        return Ok(());
+    }
+    let model = match shared_ocr_model() {
+        Ok(model) => model,
+        Err(err) => {
+            eprintln!("skipping estimate parity test: {err}");
+            return Ok(());
+        }
+    };
+    let model = model.lock().expect("ocr model lock poisoned");
+    let tokenizer = Tokenizer::from_file(&tokenizer_path)
+        .map_err(|err| anyhow!("failed to load tokenizer: {err}"))?;
+    let config_path = workspace_path("DeepSeek-OCR/config.json");
+
+    // Landscape, tall and wide pages pick different crop grids in Gundam mode.
+    let images: Vec<DynamicImage> = [(640, 480), (500, 1800), (2400, 900)]
+        .into_iter()
+        .map(|(width, height)| {
+            DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, Rgb([255, 255, 255])))
+        })
+        .collect();
+    let prompt = format!("{}\nFree OCR.", "<image>".repeat(images.len()));
+    for mode in [ResolutionMode::Small, ResolutionMode::Gundam] {
+        let vision = mode.settings();
+        let report = estimate(EstimateArgs {
+            config_path: Some(&config_path),
+            tokenizer: &tokenizer,
+            prompt: &prompt,
+            images: &images,
+            vision,
+            max_new_tokens: 16,
+            dtype: DType::F32,
+        })?;
+        for (image, estimated) in images.iter().zip(&report.images) {
+            let input = model.prepare_vision_input_from_image(
+                image,
+                vision.base_size,
+                vision.image_size,
+                vision.crop_mode,
+            )?;
+            let embeddings = model.compute_image_embeddings(&[Some(input.as_ref())])?;
+            let (rows, _) = embeddings[0].dims2()?;
+            assert_eq!(
+                estimated.vision_tokens,
+                rows,
+                "{mode} estimate for a {}x{} image",
+                image.width(),
+                image.height()
+            );
+        }
+    }
+    Ok(())
+}
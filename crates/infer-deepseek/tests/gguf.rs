@@ -0,0 +1,102 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use candle_core::{DType, Device, Tensor};
+use deepseek_ocr_infer_deepseek::{
+    gguf::{GgufWeights, QuantizeSpec, linear_group_for, quantize_safetensors},
+    quantization::{LinearLayerGroup, QuantizationKind},
+};
+
+#[test]
+fn linear_groups_follow_checkpoint_names() {
+    assert_eq!(
+        linear_group_for("model.layers.3.self_attn.q_proj.weight", &[1280, 1280]),
+        Some(LinearLayerGroup::Text)
+    );
+    assert_eq!(
+        linear_group_for("lm_head.weight", &[129280, 1280]),
+        Some(LinearLayerGroup::Text)
+    );
+    assert_eq!(
+        linear_group_for("model.projector.layers.weight", &[1280, 2048]),
+        Some(LinearLayerGroup::Projector)
+    );
+    assert_eq!(
+        linear_group_for(
+            "model.vision_model.transformer.layers.0.mlp.fc1.weight",
+            &[4096, 1024]
+        ),
+        Some(LinearLayerGroup::Vision)
+    );
+    assert_eq!(
+        linear_group_for("model.layers.2.mlp.gate.weight", &[64, 1280]),
+        None
+    );
+    assert_eq!(
+        linear_group_for("model.embed_tokens.weight", &[129280, 1280]),
+        None
+    );
+    assert_eq!(
+        linear_group_for("model.layers.0.input_layernorm.weight", &[1280]),
+        None
+    );
+}
+
+#[test]
+fn quantized_round_trip_preserves_float_tensors() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("deepseek-ocr-gguf-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let input = dir.join("tiny.safetensors");
+    let output = dir.join("tiny.q8_0.gguf");
+
+    let device = Device::Cpu;
+    let proj = Tensor::randn(0f32, 1.0, (8, 64), &device)?;
+    let norm = Tensor::randn(0f32, 1.0, 64, &device)?;
+    let unaligned = Tensor::randn(0f32, 1.0, (4, 48), &device)?;
+    let tensors = HashMap::from([
+        (
+            "model.layers.0.self_attn.q_proj.weight".to_string(),
+            proj.clone(),
+        ),
+        (
+            "model.layers.0.input_layernorm.weight".to_string(),
+            norm.clone(),
+        ),
+        (
+            "model.layers.0.mlp.down_proj.weight".to_string(),
+            unaligned.clone(),
+        ),
+    ]);
+    candle_core::safetensors::save(&tensors, &input)?;
+
+    let spec = QuantizeSpec {
+        kind: QuantizationKind::Q8_0,
+        groups: vec![LinearLayerGroup::Text],
+    };
+    let report = quantize_safetensors(&input, &output, &spec)?;
+    assert_eq!(report.tensors, 3);
+    assert_eq!(report.quantized, 1);
+    assert_eq!(report.skipped_unaligned, 1);
+
+    let gguf = Arc::new(GgufWeights::open(&output)?);
+    assert_eq!(gguf.kind(), QuantizationKind::Q8_0);
+    assert_eq!(gguf.groups(), "text");
+    assert!(gguf.is_quantized("model.layers.0.self_attn.q_proj.weight"));
+    assert!(!gguf.is_quantized("model.layers.0.input_layernorm.weight"));
+    assert!(!gguf.is_quantized("model.layers.0.mlp.down_proj.weight"));
+
+    let vb = gguf.var_builder(DType::F32, &device);
+    let restored = vb.get(64, "model.layers.0.input_layernorm.weight")?;
+    let diff = (restored - &norm)?.abs()?.max_all()?.to_scalar::<f32>()?;
+    assert_eq!(diff, 0.0);
+
+    let dequantized = vb.get((8, 64), "model.layers.0.self_attn.q_proj.weight")?;
+    let diff = (dequantized - &proj)?
+        .abs()?
+        .max_all()?
+        .to_scalar::<f32>()?;
+    assert!(diff < 0.05, "Q8_0 round-trip deviation too large: {diff}");
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use anyhow::Result;
+use candle_core::{DType, Tensor};
+use common::test_utils::shared_ocr_model;
+use deepseek_ocr_core::inference::TileEncodingConfig;
+use deepseek_ocr_infer_deepseek::model::{DeepseekOcrModel, VisionInput, tile_concurrency};
+
+const MIB: u64 = 1024 * 1024;
+
+fn with_model_mut<F>(label: &str, f: F) -> Result<()>
+where
+    F: FnOnce(&mut DeepseekOcrModel) -> Result<()>,
+{
+    let model = match shared_ocr_model() {
+        Ok(model) => model,
+        Err(err) => {
+            eprintln!("skipping {label}: {err}");
+            return Ok(());
+        }
+    };
+    let mut guard = model.lock().expect("ocr model lock poisoned");
+    let original = guard.tile_encoding();
+    let result = f(&mut guard);
+    guard.set_tile_encoding(original)?;
+    result
+}
+
+#[test]
+fn concurrency_is_bounded_by_threads() {
+    assert_eq!(tile_concurrency(4, 0, 512 * MIB), 4);
+    assert_eq!(tile_concurrency(4, 1 << 20, MIB), 4);
+    assert_eq!(tile_concurrency(8, 256, 0), 8);
+    assert_eq!(tile_concurrency(0, 0, MIB), 1);
+}
+
+#[test]
+fn concurrency_fits_the_memory_budget() {
+    assert_eq!(tile_concurrency(8, 2048, 512 * MIB), 4);
+    assert_eq!(tile_concurrency(8, 2048, 700 * MIB), 2);
+    assert_eq!(tile_concurrency(8, 2048, 2048 * MIB), 1);
+}
+
+#[test]
+fn oversized_chunks_still_run_one_at_a_time() {
+    assert_eq!(tile_concurrency(8, 256, 4096 * MIB), 1);
+}
+
+#[test]
+fn zero_tile_threads_are_rejected() -> Result<()> {
+    with_model_mut("tile thread validation test", |model| {
+        let config = TileEncodingConfig {
+            max_threads: Some(0),
+            ..TileEncodingConfig::default()
+        };
+        let err = model
+            .set_tile_encoding(config)
+            .expect_err("zero tile threads should be rejected");
+        assert!(err.to_string().contains("at least 1"), "{err}");
+        Ok(())
+    })
+}
+
+#[test]
+fn chunked_tiles_match_a_single_batch() -> Result<()> {
+    with_model_mut("tile chunking test", |model| {
+        let device = model.device().clone();
+        let dtype = model.dtype();
+        let global = Tensor::zeros((1, 3, 1024, 1024), dtype, &device)?;
+        let patches = Tensor::randn(0f32, 1.0, (3, 3, 640, 640), &device)?.to_dtype(dtype)?;
+        let mut embed = |config: TileEncodingConfig| -> Result<Vec<f32>> {
+            model.set_tile_encoding(config)?;
+            let input = VisionInput {
+                global: &global,
+                patches: Some(&patches),
+                crop_shape: Some((3, 1)),
+            };
+            let embeddings = model.compute_image_embeddings(&[Some(input)])?;
+            Ok(embeddings[0]
+                .to_dtype(DType::F32)?
+                .flatten_all()?
+                .to_vec1::<f32>()?)
+        };
+
+        // One chunk holding every tile is the serial path.
+        let serial = embed(TileEncodingConfig {
+            chunk_size: 3,
+            max_threads: Some(1),
+            memory_budget_mb: 0,
+        })?;
+        let parallel = embed(TileEncodingConfig {
+            chunk_size: 1,
+            max_threads: Some(2),
+            memory_budget_mb: 0,
+        })?;
+        assert_eq!(serial.len(), parallel.len());
+        for (index, (a, b)) in serial.iter().zip(&parallel).enumerate() {
+            assert!(
+                (a - b).abs() <= 1e-3 * a.abs().max(1.0),
+                "token value {index} differs: serial {a}, parallel {b}"
+            );
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use common::test_utils::workspace_path;
+use deepseek_ocr_infer_deepseek::{
+    config::load_ocr_config,
+    model::DEFAULT_WEIGHTS_PATH,
+    quantization::{
+        QuantizationConfig, QuantizationKind, QuantizationState, VisionQuantization, VisionTower,
+    },
+    vision::{ClipVisionModel, SamBackbone},
+};
+
+/// Largest deviation relative to the float reference that the Q8_0 towers may introduce.
+const MAX_RELATIVE_DEVIATION: f32 = 0.15;
+
+fn q8_vision_state(towers: &[VisionTower]) -> QuantizationState {
+    QuantizationState::new(QuantizationConfig {
+        kind: QuantizationKind::Q8_0,
+        vision: VisionQuantization::from_towers(towers),
+        ..QuantizationConfig::default()
+    })
+}
+
+/// Returns `(max |quantized - reference|, max |reference|)`.
+fn max_deviation(reference: &Tensor, quantized: &Tensor) -> Result<(f32, f32)> {
+    let diff = (quantized - reference)?
+        .abs()?
+        .max_all()?
+        .to_scalar::<f32>()?;
+    let scale = reference.abs()?.max_all()?.to_scalar::<f32>()?;
+    Ok((diff, scale))
+}
+
+fn report(label: &str, reference: &Tensor, quantized: &Tensor) -> Result<()> {
+    assert_eq!(reference.dims(), quantized.dims(), "{label} shape mismatch");
+    let (diff, scale) = max_deviation(reference, quantized)?;
+    let relative = diff / scale.max(f32::EPSILON);
+    eprintln!("{label}: max |Δ| = {diff:.5} (reference max {scale:.5}, relative {relative:.4})");
+    assert!(
+        relative < MAX_RELATIVE_DEVIATION,
+        "{label} deviates by {relative:.4} relative to float"
+    );
+    Ok(())
+}
+
+#[test]
+fn vision_towers_quantize_only_when_selected() -> Result<()> {
+    let cfg = load_ocr_config(Some(&workspace_path("DeepSeek-OCR/config.json")))?;
+    let vb = VarBuilder::zeros(DType::F32, &Device::Cpu);
+
+    let quant = q8_vision_state(&[VisionTower::Sam]);
+    let sam = SamBackbone::new_with_quantization(&cfg, &vb, &quant)?;
+    let stats = quant.stats();
+    // qkv, proj, fc1 and fc2 in every ViT block.
+    assert_eq!(stats.sam.quantized, sam.params.depth * 4);
+    assert_eq!(stats.sam.fallback, 0);
+
+    let clip = ClipVisionModel::load_with_quantization(&cfg, &vb, &quant)?;
+    let stats = quant.stats();
+    assert_eq!(stats.clip.quantized, 0);
+    assert_eq!(stats.clip.fallback, clip.params().num_layers * 4);
+    Ok(())
+}
+
+/// Reads `names` from a baseline npz archive, accepting keys with or without the `.npy` suffix.
+fn read_baseline_arrays(path: &Path, names: &[&str]) -> Result<Vec<Tensor>> {
+    let arrays = Tensor::read_npz(path)
+        .with_context(|| format!("failed to read baseline arrays {}", path.display()))?;
+    names
+        .iter()
+        .map(|name| {
+            arrays
+                .iter()
+                .find(|(key, _)| key.strip_suffix(".npy").unwrap_or(key) == *name)
+                .map(|(_, tensor)| tensor.to_dtype(DType::F32))
+                .transpose()?
+                .with_context(|| format!("array {name} missing from {}", path.display()))
+        })
+        .collect()
+}
+
+/// Both towers run on the captured global view and are compared against the Python tokens, so
+/// the check covers real page content rather than a synthetic pattern.
+#[test]
+fn quantized_vision_towers_match_baseline() -> Result<()> {
+    let weights = workspace_path(DEFAULT_WEIGHTS_PATH);
+    let image_tensors = workspace_path("baselines/sample/image_tensors.npz");
+    let vision_embeddings = workspace_path("baselines/sample/vision_embeddings.npz");
+    if !weights.exists() || !image_tensors.exists() || !vision_embeddings.exists() {
+        eprintln!(
+            "skipping vision quantization parity: weights or baseline arrays not present under {}",
+            workspace_path("").display()
+        );
+        return Ok(());
+    }
+    let cfg = load_ocr_config(Some(&workspace_path("DeepSeek-OCR/config.json")))?;
+    let device = Device::Cpu;
+    let vb = unsafe {
+        VarBuilder::from_mmaped_safetensors(&[weights.as_path()], DType::F32, &device)
+            .context("failed to mmap DeepSeek-OCR weights")?
+    };
+    let sam_vb = vb.pp("model").pp("sam_model");
+    let clip_vb = vb.pp("model").pp("vision_model");
+
+    let quant = q8_vision_state(&[VisionTower::Sam, VisionTower::Clip]);
+    let sam = SamBackbone::new_with_quantization(&cfg, &sam_vb, &quant)?;
+    let clip = ClipVisionModel::load_with_quantization(&cfg, &clip_vb, &quant)?;
+
+    let [image] = <[Tensor; 1]>::try_from(read_baseline_arrays(
+        &image_tensors,
+        &["global_view_image0"],
+    )?)
+    .expect("one array requested");
+    let image = image.unsqueeze(0)?.to_device(&device)?;
+    let [sam_ref, clip_ref] = <[Tensor; 2]>::try_from(read_baseline_arrays(
+        &vision_embeddings,
+        &["global_sam_tokens_image0", "global_clip_tokens_image0"],
+    )?)
+    .expect("two arrays requested");
+
+    let sam_out = sam.forward(&image)?;
+    let (batch, channels, height, width) = sam_out.dims4()?;
+    let sam_tokens = sam_out
+        .reshape((batch, channels, height * width))?
+        .permute((0, 2, 1))?
+        .reshape((batch * height * width, channels))?;
+    report("sam q8_0 vs baseline", &sam_ref, &sam_tokens)?;
+
+    // Feed CLIP the reference SAM features so its deviation is measured alone.
+    let sam_features = sam_ref
+        .reshape((batch, height * width, channels))?
+        .permute((0, 2, 1))?
+        .reshape((batch, channels, height, width))?
+        .contiguous()?;
+    let clip_out = clip.forward(&image, Some(&sam_features))?;
+    let (_, seq, hidden) = clip_out.dims3()?;
+    // The baseline drops the class token.
+    let clip_tokens = clip_out.narrow(1, 1, seq - 1)?.reshape((seq - 1, hidden))?;
+    report("clip q8_0 vs baseline", &clip_ref, &clip_tokens)?;
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! Dry-run estimates for PaddleOCR-VL requests; see [`deepseek_ocr_core::estimate`].
+
+use anyhow::Result;
+use deepseek_ocr_core::{
+    estimate::{DryRunReport, EstimateArgs, ImageEstimate, ModelFootprint, encoder_parameters},
+    inference::ModelKind,
+};
+use image::GenericImageView;
+
+use crate::{
+    config::{PaddleOcrVlConfig, load_config},
+    model::{build_prompt_tokens, projector_token_count},
+    vision::{SiglipPreprocessConfig, smart_resize},
+};
+
+/// Token counts and memory for a request, from `smart_resize`, the projector's patch merge and
+/// the prompt tokenization; no weights are read.
+pub fn estimate(args: EstimateArgs<'_>) -> Result<DryRunReport> {
+    let cfg = load_config(args.config_path)?.value;
+    let vision = args.vision;
+    let preprocess = SiglipPreprocessConfig::from_vision_config(&cfg.vision_config)
+        .with_max_image_size(vision.image_size)
+        .with_pixel_bounds(
+            vision.min_pixels.map(|value| value as usize),
+            vision.max_pixels.map(|value| value as usize),
+        );
+    let patch = preprocess.patch_size;
+
+    let mut images = Vec::with_capacity(args.images.len());
+    let mut grids = Vec::with_capacity(args.images.len());
+    for image in args.images {
+        let (width, height) = image.dimensions();
+        let (resized_height, resized_width) = smart_resize(
+            height,
+            width,
+            (patch * preprocess.merge_size) as u32,
+            preprocess.min_pixels as u32,
+            preprocess.max_pixels as u32,
+        )?;
+        let grid = (
+            preprocess.temporal_patch_size.max(1),
+            resized_height as usize / patch,
+            resized_width as usize / patch,
+        );
+        images.push(ImageEstimate {
+            width,
+            height,
+            tile_grid: None,
+            resized: (resized_width, resized_height),
+            vision_tokens: projector_token_count(grid, preprocess.merge_size)?,
+        });
+        grids.push(grid);
+    }
+    let (tokens, _) = build_prompt_tokens(args.tokenizer, args.prompt, &grids, &cfg)?;
+
+    Ok(DryRunReport::new(
+        ModelKind::PaddleOcrVl,
+        args.dtype,
+        images,
+        tokens.len(),
+        args.max_new_tokens,
+        &footprint(&cfg),
+    ))
+}
+
+fn footprint(cfg: &PaddleOcrVlConfig) -> ModelFootprint {
+    let hidden = cfg.hidden_size;
+    let heads = cfg.num_attention_heads;
+    let kv_heads = cfg.resolved_num_key_value_heads();
+    let attention = 2 * hidden * heads * cfg.head_dim + 2 * hidden * kv_heads * cfg.head_dim;
+    let mlp = 3 * hidden * cfg.intermediate_size;
+    let layers = cfg.num_hidden_layers * (attention + mlp);
+    let lm_head = cfg.vocab_size * hidden;
+    let embeddings = if cfg.tie_word_embeddings { 0 } else { lm_head };
+
+    let vision = &cfg.vision_config;
+    let patch_embedding = vision.num_channels * vision.patch_size.pow(2) * vision.hidden_size;
+    let encoder = encoder_parameters(
+        vision.num_hidden_layers,
+        vision.hidden_size,
+        vision.intermediate_size,
+    );
+    let merged = vision.hidden_size * vision.merge_kernel_area();
+    let projector = merged * merged + merged * hidden;
+
+    ModelFootprint {
+        total_parameters: (layers + lm_head + embeddings + patch_embedding + projector) as u64
+            + encoder,
+        active_parameters: (layers + lm_head) as u64,
+        layers: cfg.num_hidden_layers,
+        hidden_size: hidden,
+        intermediate_size: cfg.intermediate_size,
+        vocab_size: cfg.vocab_size,
+        attention_heads: heads,
+        head_dim: cfg.head_dim,
+        kv_width: 2 * kv_heads * cfg.head_dim,
+    }
+}
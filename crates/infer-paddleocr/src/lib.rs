@@ -1,8 +1,11 @@
 pub mod config;
+pub mod estimate;
+pub mod quantization;
 pub mod transformer;
 pub mod vision;
 pub mod weights;
 
 mod model;
 
+pub use estimate::estimate;
 pub use model::{PaddleOcrModel, load_model};
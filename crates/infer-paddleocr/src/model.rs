@@ -12,6 +12,7 @@ use tokenizers::Tokenizer;
 
 use crate::{
     config::{LoadedPaddleConfig, PaddleOcrVlConfig, load_config},
+    quantization::{QuantizationConfig, QuantizationState, QuantizationStats},
     transformer::ErnieDecoder,
     vision::{SiglipPreprocessConfig, SiglipProjector, SiglipVisionModel, preprocess_image},
 };
@@ -37,6 +38,7 @@ pub struct PaddleOcrModel {
     vision: SiglipVisionModel,
     projector: SiglipProjector,
     decoder: ErnieDecoder,
+    quantization: QuantizationState,
 }
 
 #[derive(Debug)]
@@ -81,6 +83,7 @@ impl PaddleOcrModel {
             device,
             dtype,
             weights_path,
+            quantization,
             ..
         } = args;
         let LoadedPaddleConfig { value, path } = load_config(args.config_path)?;
@@ -92,13 +95,20 @@ impl PaddleOcrModel {
             VarBuilder::from_mmaped_safetensors(&[resolved_weights.as_path()], *dtype, device)
         }
         .with_context(|| format!("failed to mmap weights at {}", resolved_weights.display()))?;
-        let vision = SiglipVisionModel::load(&vb, &config.vision_config, *dtype)
+        let quantization = QuantizationState::new(QuantizationConfig::resolve(*quantization));
+        let vision = SiglipVisionModel::load(&vb, &config.vision_config, *dtype, &quantization)
             .context("failed to load SigLIP vision model")?;
-        let projector =
-            SiglipProjector::load(&vb, &config.vision_config, config.hidden_size, *dtype)
-                .context("failed to load projector module")?;
-        let decoder =
-            ErnieDecoder::load(Arc::clone(&config), &vb).context("failed to load Ernie decoder")?;
+        let projector = SiglipProjector::load(
+            &vb,
+            &config.vision_config,
+            config.hidden_size,
+            *dtype,
+            &quantization,
+        )
+        .context("failed to load projector module")?;
+        let decoder = ErnieDecoder::load(Arc::clone(&config), &vb, &quantization)
+            .context("failed to load Ernie decoder")?;
+        quantization.log_summary(device);
         Ok(Self {
             config,
             config_path: path,
@@ -108,6 +118,7 @@ impl PaddleOcrModel {
             vision,
             projector,
             decoder,
+            quantization,
         })
     }
 
@@ -131,6 +142,11 @@ impl PaddleOcrModel {
         &self.decoder
     }
 
+    /// Quantization counters collected while this model loaded.
+    pub fn quantization_stats(&self) -> QuantizationStats {
+        self.quantization.stats()
+    }
+
     #[allow(dead_code)]
     fn encode_image(
         &self,
@@ -138,7 +154,11 @@ impl PaddleOcrModel {
         vision_settings: VisionSettings,
     ) -> Result<ProjectedImage> {
         let prep_cfg = SiglipPreprocessConfig::from_vision_config(&self.config.vision_config)
-            .with_max_image_size(vision_settings.image_size);
+            .with_max_image_size(vision_settings.image_size)
+            .with_pixel_bounds(
+                vision_settings.min_pixels.map(|value| value as usize),
+                vision_settings.max_pixels.map(|value| value as usize),
+            );
         let patches = preprocess_image(image, &self.device, &prep_cfg)
             .context("failed to preprocess image for SigLIP")?;
         let vision_hidden = self
@@ -275,6 +295,10 @@ impl OcrEngine for PaddleOcrModel {
             params.use_cache,
             "PaddleOCR decoder currently requires use_cache=true"
         );
+        ensure!(
+            params.constraint.is_none(),
+            "PaddleOCR-VL does not support constrained decoding"
+        );
         let cancelled = || cancel.map_or(false, |token| token.is_cancelled());
         if cancelled() {
             return Ok(DecodeOutcome {
@@ -1019,6 +1043,8 @@ mod tests {
             weights_path: Some(weights_path.as_path()),
             device: device.clone(),
             dtype: DType::F32,
+            quantization: None,
+            tile_encoding: Default::default(),
         };
         let model = PaddleOcrModel::load(&args)?;
         let prep_cfg = SiglipPreprocessConfig::from_vision_config(&model.config().vision_config);
@@ -0,0 +1,296 @@
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::{Result, bail};
+use candle_core::{
+    Device, Tensor,
+    quantized::{GgmlDType, QMatMul, QTensor},
+};
+pub use deepseek_ocr_core::quantization::{
+    LinearLayerGroup, ModuleStats, QuantModule, QuantizationConfig, QuantizationKind,
+    QuantizationOutcome, QuantizationStats, QuantizationTargets, VisionQuantization, VisionTower,
+};
+use deepseek_ocr_core::runtime::backend_label;
+use tracing::{info, trace};
+
+const Q8_BLOCK_SIZE: usize = 32;
+
+/// Quantization settings for one PaddleOCR-VL model plus the counters gathered while it loads.
+///
+/// The Ernie decoder linears and `lm_head` form the `Text` group; `mlp_AR.linear_{1,2}` form the
+/// `Projector` group. SigLIP encoder linears belong to the `Vision` group and are only quantized
+/// when the `siglip` tower is selected.
+pub struct QuantizationState {
+    config: QuantizationConfig,
+    stats: Mutex<QuantizationStats>,
+    summary_logged: AtomicBool,
+}
+
+impl QuantizationState {
+    pub fn new(config: QuantizationConfig) -> Self {
+        Self {
+            config,
+            stats: Mutex::new(QuantizationStats::default()),
+            summary_logged: AtomicBool::new(false),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self::new(QuantizationConfig::default())
+    }
+
+    pub fn config(&self) -> QuantizationConfig {
+        self.config
+    }
+
+    pub fn enabled_for(&self, group: LinearLayerGroup) -> bool {
+        self.config.enabled_for(group)
+    }
+
+    pub fn enabled_for_module(&self, group: LinearLayerGroup, module: QuantModule) -> bool {
+        self.config.enabled_for_module(group, module)
+    }
+
+    pub fn record_attempt(&self, module: QuantModule, outcome: QuantizationOutcome) {
+        self.stats
+            .lock()
+            .expect("quantization stats mutex poisoned")
+            .record(module, outcome);
+    }
+
+    pub fn stats(&self) -> QuantizationStats {
+        *self
+            .stats
+            .lock()
+            .expect("quantization stats mutex poisoned")
+    }
+
+    pub fn log_summary(&self, device: &Device) {
+        if self.summary_logged.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let stats = self.stats();
+        info!(
+            backend = backend_label(device),
+            quant = %self.config.kind,
+            targets = %self.config.targets,
+            keep_fp = self.config.keep_full_precision_weights,
+            candidates = stats.candidates,
+            quantized = stats.quantized,
+            fallback = stats.fallback,
+            text_cand = stats.text_linear.candidates,
+            text_quant = stats.text_linear.quantized,
+            text_fallback = stats.text_linear.fallback,
+            proj_cand = stats.projector.candidates,
+            proj_quant = stats.projector.quantized,
+            proj_fallback = stats.projector.fallback,
+            lm_cand = stats.lm_head.candidates,
+            lm_quant = stats.lm_head.quantized,
+            lm_fallback = stats.lm_head.fallback,
+            vision = %self.config.vision,
+            siglip_cand = stats.siglip.candidates,
+            siglip_quant = stats.siglip.quantized,
+            siglip_fallback = stats.siglip.fallback,
+            "paddle runtime quantization summary"
+        );
+    }
+
+    /// Quantize a `[out_dim, in_dim]` weight when its group is enabled.
+    ///
+    /// Returns `Ok(None)` when the group is disabled or the layer cannot be quantized (unaligned
+    /// width, unsupported kind); the caller then keeps the float weight.
+    pub(crate) fn quantize_linear(
+        &self,
+        weight: &Tensor,
+        group: LinearLayerGroup,
+        module: QuantModule,
+        tensor_name: &str,
+    ) -> Result<Option<Arc<QMatMul>>> {
+        if !self.enabled_for_module(group, module) {
+            self.record_attempt(module, QuantizationOutcome::Fallback);
+            return Ok(None);
+        }
+        let device = weight.device();
+        if device.is_metal() || device.is_cuda() {
+            bail!(
+                "GPU backend: runtime quantization is disabled on Metal/CUDA.\n\
+                 Disable quantization (--quant none) or run on CPU.\n\
+                 Context: tensor={tensor_name}, group={group:?}, backend={}",
+                backend_label(device)
+            );
+        }
+        let (_, in_dim) = weight.dims2()?;
+        match self.config.kind {
+            QuantizationKind::Q8_0 if in_dim % Q8_BLOCK_SIZE == 0 => {
+                let qmatmul =
+                    QTensor::quantize(weight, GgmlDType::Q8_0).and_then(QMatMul::from_qtensor)?;
+                trace!(
+                    tensor = tensor_name,
+                    ?group,
+                    in_dim,
+                    from = ?weight.dtype(),
+                    to = "Q8_0",
+                    action = "quantized",
+                    "quant-linear"
+                );
+                self.record_attempt(module, QuantizationOutcome::Quantized);
+                Ok(Some(Arc::new(qmatmul)))
+            }
+            kind => {
+                trace!(
+                    tensor = tensor_name,
+                    ?group,
+                    in_dim,
+                    %kind,
+                    action = "fallback",
+                    reason = if kind == QuantizationKind::Q4K {
+                        "q4k_unimplemented"
+                    } else {
+                        "unaligned"
+                    },
+                    "quant-linear"
+                );
+                self.record_attempt(module, QuantizationOutcome::Fallback);
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use candle_core::{DType, Module};
+
+    use super::*;
+
+    fn q8(targets: QuantizationTargets) -> QuantizationState {
+        QuantizationState::new(QuantizationConfig {
+            kind: QuantizationKind::Q8_0,
+            targets,
+            ..QuantizationConfig::default()
+        })
+    }
+
+    fn weight(out_dim: usize, in_dim: usize) -> Result<Tensor> {
+        let weight = Tensor::arange(0f32, (out_dim * in_dim) as f32, &Device::Cpu)?
+            .reshape((out_dim, in_dim))?
+            .sin()?;
+        Ok(weight)
+    }
+
+    fn quantized_tensor(qmatmul: &QMatMul) -> &QTensor {
+        match qmatmul {
+            QMatMul::QTensor(tensor) => tensor,
+            _ => panic!("expected a quantized tensor"),
+        }
+    }
+
+    #[test]
+    fn q8_round_trip_stays_close_to_float() -> Result<()> {
+        let quant = q8(QuantizationTargets::Text);
+        let weight = weight(16, 64)?;
+        let qmatmul = quant
+            .quantize_linear(
+                &weight,
+                LinearLayerGroup::Text,
+                QuantModule::TextLinear,
+                "w",
+            )?
+            .expect("aligned text linear should quantize");
+
+        let restored = quantized_tensor(&qmatmul).dequantize(&Device::Cpu)?;
+        let diff = (restored - &weight)?.abs()?.max_all()?.to_scalar::<f32>()?;
+        assert!(diff < 0.01, "Q8_0 round-trip deviation too large: {diff}");
+
+        let input = Tensor::arange(0f32, 128.0, &Device::Cpu)?
+            .reshape((2, 64))?
+            .cos()?;
+        let reference = input.matmul(&weight.t()?)?;
+        let diff = (qmatmul.forward(&input)? - &reference)?
+            .abs()?
+            .max_all()?
+            .to_scalar::<f32>()?;
+        let scale = reference.abs()?.max_all()?.to_scalar::<f32>()?;
+        assert!(
+            diff < 0.02 * scale,
+            "Q8_0 matmul deviation too large: {diff}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn q8_weights_take_about_a_quarter_of_f32() -> Result<()> {
+        let quant = q8(QuantizationTargets::Text);
+        let weight = weight(32, 256)?;
+        let qmatmul = quant
+            .quantize_linear(&weight, LinearLayerGroup::Text, QuantModule::LmHead, "w")?
+            .expect("aligned lm_head should quantize");
+        let float_bytes = weight.elem_count() * DType::F32.size_in_bytes();
+        // Each block of 32 weights is 32 `i8` values plus an `f16` scale.
+        let blocks = weight.elem_count() / Q8_BLOCK_SIZE;
+        let size = quantized_tensor(&qmatmul).storage_size_in_bytes();
+        assert_eq!(size, blocks * 34);
+        assert!(size * 3 < float_bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn unaligned_and_disabled_linears_fall_back() -> Result<()> {
+        let quant = q8(QuantizationTargets::Text);
+        let unaligned = weight(8, 48)?;
+        let aligned = weight(8, 64)?;
+        assert!(
+            quant
+                .quantize_linear(
+                    &unaligned,
+                    LinearLayerGroup::Text,
+                    QuantModule::TextLinear,
+                    "u"
+                )?
+                .is_none()
+        );
+        assert!(
+            quant
+                .quantize_linear(
+                    &aligned,
+                    LinearLayerGroup::Projector,
+                    QuantModule::Projector,
+                    "p"
+                )?
+                .is_none()
+        );
+        assert!(
+            quant
+                .quantize_linear(
+                    &aligned,
+                    LinearLayerGroup::Text,
+                    QuantModule::TextLinear,
+                    "t"
+                )?
+                .is_some()
+        );
+
+        let stats = quant.stats();
+        assert_eq!(
+            (stats.candidates, stats.quantized, stats.fallback),
+            (3, 1, 2)
+        );
+        assert_eq!(stats.text_linear.quantized, 1);
+        assert_eq!(stats.text_linear.fallback, 1);
+        assert_eq!(stats.projector.fallback, 1);
+
+        let both = q8(QuantizationTargets::TextAndProjector);
+        assert!(
+            both.quantize_linear(
+                &aligned,
+                LinearLayerGroup::Projector,
+                QuantModule::Projector,
+                "p"
+            )?
+            .is_some()
+        );
+        Ok(())
+    }
+}
@@ -5,14 +5,18 @@ use candle_core::{DType, Tensor};
 use candle_nn::ops::rms_norm;
 use deepseek_ocr_core::tensor::gather_token_embeddings;
 
-use crate::config::PaddleOcrVlConfig;
+use crate::{
+    config::PaddleOcrVlConfig,
+    quantization::{QuantModule, QuantizationState},
+};
 
 use super::{
     attention::{AttentionContext, supports_flash_attention},
     block::{build_attention_bias, decoder_layer_forward},
     cache::{DynamicCache, PromptCacheGuard},
+    ops::apply_linear,
     rope::ErnieRotaryEmbedding,
-    weights::ErnieModelWeights,
+    weights::{ErnieModelWeights, LinearWeights},
 };
 
 pub struct DecoderOutput {
@@ -23,27 +27,35 @@ pub struct DecoderOutput {
 pub struct ErnieDecoder {
     cfg: Arc<PaddleOcrVlConfig>,
     weights: ErnieModelWeights,
-    lm_head: Tensor,
+    lm_head: LinearWeights,
     rotary: ErnieRotaryEmbedding,
 }
 
 impl ErnieDecoder {
-    pub fn load(cfg: Arc<PaddleOcrVlConfig>, vb: &candle_nn::VarBuilder) -> Result<Self> {
+    pub fn load(
+        cfg: Arc<PaddleOcrVlConfig>,
+        vb: &candle_nn::VarBuilder,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let model_vb = vb.pp("model");
-        let weights = ErnieModelWeights::load(&model_vb, cfg.as_ref())
+        let weights = ErnieModelWeights::load(&model_vb, cfg.as_ref(), quant)
             .context("failed to load Ernie decoder weights")?;
-        let lm_head = vb
-            .pp("lm_head")
-            .get((cfg.vocab_size, cfg.hidden_size), "weight")
-            .context("missing lm_head.weight")?
-            .contiguous()?;
+        let lm_head = LinearWeights::load(
+            vb.pp("lm_head"),
+            cfg.vocab_size,
+            cfg.hidden_size,
+            false,
+            quant,
+            QuantModule::LmHead,
+        )
+        .context("missing lm_head.weight")?;
         Self::from_parts(cfg, weights, lm_head)
     }
 
     pub(crate) fn from_parts(
         cfg: Arc<PaddleOcrVlConfig>,
         weights: ErnieModelWeights,
-        lm_head: Tensor,
+        lm_head: LinearWeights,
     ) -> Result<Self> {
         let rotary = ErnieRotaryEmbedding::new(Arc::clone(&cfg))?;
         Ok(Self {
@@ -70,7 +82,7 @@ impl ErnieDecoder {
         &self.weights.final_norm
     }
 
-    pub fn lm_head(&self) -> &Tensor {
+    pub fn lm_head(&self) -> &LinearWeights {
         &self.lm_head
     }
 
@@ -183,10 +195,7 @@ impl ErnieDecoder {
             self.cfg.rms_norm_eps as f32,
         )
         .context("final rms norm failed")?;
-        let (batch, seq_len, hidden_size) = normed.shape().dims3()?;
-        let flat = normed.reshape((batch * seq_len, hidden_size))?;
-        let logits = flat.matmul(&self.lm_head.transpose(0, 1)?)?;
-        let logits = logits.reshape((batch, seq_len, self.cfg.vocab_size))?;
+        let logits = apply_linear(&normed, &self.lm_head).context("lm_head projection failed")?;
 
         Ok(DecoderOutput {
             hidden_states: normed,
@@ -272,10 +281,7 @@ mod tests {
     }
 
     fn linear(out_dim: usize, in_dim: usize, device: &Device) -> Result<LinearWeights> {
-        Ok(LinearWeights {
-            weight: Tensor::zeros((out_dim, in_dim), DType::F32, device)?,
-            bias: None,
-        })
+        LinearWeights::from_tensor(Tensor::zeros((out_dim, in_dim), DType::F32, device)?, None)
     }
 
     fn layer_weights(cfg: &PaddleOcrVlConfig, device: &Device) -> Result<ErnieDecoderLayerWeights> {
@@ -329,7 +335,7 @@ mod tests {
             layers,
             final_norm,
         };
-        let lm_head = Tensor::zeros((cfg.vocab_size, cfg.hidden_size), DType::F32, &device)?;
+        let lm_head = linear(cfg.vocab_size, cfg.hidden_size, &device)?;
         let decoder = ErnieDecoder::from_parts(Arc::clone(&cfg), weights, lm_head)?;
 
         let input_ids = Tensor::zeros((2, 4), DType::I64, &device)?;
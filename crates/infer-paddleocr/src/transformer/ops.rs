@@ -1,11 +1,11 @@
-use anyhow::{Result, ensure};
-use candle_core::{Tensor, shape::D};
+use anyhow::{Result, bail, ensure};
+use candle_core::{DType, Module, Tensor, shape::D};
 
 use super::LinearWeights;
 
 pub fn apply_linear(input: &Tensor, weights: &LinearWeights) -> Result<Tensor> {
     let (batch, seq_len, in_dim) = input.shape().dims3()?;
-    let (out_dim, weight_in) = weights.weight.shape().dims2()?;
+    let (out_dim, weight_in) = (weights.out_dim, weights.in_dim);
     ensure!(
         in_dim == weight_in,
         "linear weight expects input dim {} got {}",
@@ -13,7 +13,14 @@ pub fn apply_linear(input: &Tensor, weights: &LinearWeights) -> Result<Tensor> {
         in_dim
     );
     let flat = input.reshape((batch * seq_len, in_dim))?;
-    let mut out = flat.matmul(&weights.weight.transpose(0, 1)?)?;
+    let mut out = match (&weights.qmatmul, &weights.weight) {
+        // Quantized CPU matmuls only accept f32 activations.
+        (Some(qmatmul), _) => qmatmul
+            .forward(&flat.to_dtype(DType::F32)?.contiguous()?)?
+            .to_dtype(flat.dtype())?,
+        (None, Some(weight)) => flat.matmul(&weight.transpose(0, 1)?)?,
+        (None, None) => bail!("linear layer has neither float nor quantized weights"),
+    };
     if let Some(bias) = &weights.bias {
         out = out.broadcast_add(&bias.reshape((1, out_dim))?)?;
     }
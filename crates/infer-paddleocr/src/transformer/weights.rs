@@ -1,17 +1,33 @@
+use std::sync::Arc;
+
 use anyhow::{Context, Result};
-use candle_core::Tensor;
+use candle_core::{Tensor, quantized::QMatMul};
 use candle_nn::VarBuilder;
 
-use crate::config::PaddleOcrVlConfig;
+use crate::{
+    config::PaddleOcrVlConfig,
+    quantization::{LinearLayerGroup, QuantModule, QuantizationState},
+};
 
 #[derive(Debug)]
 pub struct LinearWeights {
-    pub weight: Tensor,
+    /// Float weight; dropped once the layer is quantized unless full precision is kept.
+    pub weight: Option<Tensor>,
+    pub qmatmul: Option<Arc<QMatMul>>,
     pub bias: Option<Tensor>,
+    pub out_dim: usize,
+    pub in_dim: usize,
 }
 
 impl LinearWeights {
-    pub fn load(vb: VarBuilder, out_dim: usize, in_dim: usize, use_bias: bool) -> Result<Self> {
+    pub fn load(
+        vb: VarBuilder,
+        out_dim: usize,
+        in_dim: usize,
+        use_bias: bool,
+        quant: &QuantizationState,
+        module: QuantModule,
+    ) -> Result<Self> {
         let weight = vb
             .get((out_dim, in_dim), "weight")
             .context("missing linear weight")?
@@ -25,7 +41,32 @@ impl LinearWeights {
         } else {
             None
         };
-        Ok(Self { weight, bias })
+        let name = format!("{}.weight", vb.prefix());
+        let qmatmul = quant.quantize_linear(&weight, LinearLayerGroup::Text, module, &name)?;
+        let weight = if qmatmul.is_some() && !quant.config().keep_full_precision_weights {
+            None
+        } else {
+            Some(weight)
+        };
+        Ok(Self {
+            weight,
+            qmatmul,
+            bias,
+            out_dim,
+            in_dim,
+        })
+    }
+
+    /// Wrap an already-loaded float weight (`[out_dim, in_dim]`).
+    pub fn from_tensor(weight: Tensor, bias: Option<Tensor>) -> Result<Self> {
+        let (out_dim, in_dim) = weight.dims2()?;
+        Ok(Self {
+            weight: Some(weight),
+            qmatmul: None,
+            bias,
+            out_dim,
+            in_dim,
+        })
     }
 }
 
@@ -38,7 +79,11 @@ pub struct ErnieAttentionWeights {
 }
 
 impl ErnieAttentionWeights {
-    pub fn load(vb: &VarBuilder, cfg: &PaddleOcrVlConfig) -> Result<Self> {
+    pub fn load(
+        vb: &VarBuilder,
+        cfg: &PaddleOcrVlConfig,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let head_dim = cfg.head_dim;
         let num_heads = cfg.num_attention_heads;
         let num_kv_heads = cfg.resolved_num_key_value_heads();
@@ -49,24 +94,32 @@ impl ErnieAttentionWeights {
             num_heads * head_dim,
             cfg.hidden_size,
             cfg.use_bias,
+            quant,
+            QuantModule::TextLinear,
         )?;
         let k_proj = LinearWeights::load(
             attn_vb.pp("k_proj"),
             num_kv_heads * head_dim,
             cfg.hidden_size,
             cfg.use_bias,
+            quant,
+            QuantModule::TextLinear,
         )?;
         let v_proj = LinearWeights::load(
             attn_vb.pp("v_proj"),
             num_kv_heads * head_dim,
             cfg.hidden_size,
             cfg.use_bias,
+            quant,
+            QuantModule::TextLinear,
         )?;
         let o_proj = LinearWeights::load(
             attn_vb.pp("o_proj"),
             cfg.hidden_size,
             num_heads * head_dim,
             cfg.use_bias,
+            quant,
+            QuantModule::TextLinear,
         )?;
         Ok(Self {
             q_proj,
@@ -85,25 +138,35 @@ pub struct ErnieMlpWeights {
 }
 
 impl ErnieMlpWeights {
-    pub fn load(vb: &VarBuilder, cfg: &PaddleOcrVlConfig) -> Result<Self> {
+    pub fn load(
+        vb: &VarBuilder,
+        cfg: &PaddleOcrVlConfig,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let mlp_vb = vb.pp("mlp");
         let gate_proj = LinearWeights::load(
             mlp_vb.pp("gate_proj"),
             cfg.intermediate_size,
             cfg.hidden_size,
             cfg.use_bias,
+            quant,
+            QuantModule::TextLinear,
         )?;
         let up_proj = LinearWeights::load(
             mlp_vb.pp("up_proj"),
             cfg.intermediate_size,
             cfg.hidden_size,
             cfg.use_bias,
+            quant,
+            QuantModule::TextLinear,
         )?;
         let down_proj = LinearWeights::load(
             mlp_vb.pp("down_proj"),
             cfg.hidden_size,
             cfg.intermediate_size,
             cfg.use_bias,
+            quant,
+            QuantModule::TextLinear,
         )?;
         Ok(Self {
             gate_proj,
@@ -122,9 +185,13 @@ pub struct ErnieDecoderLayerWeights {
 }
 
 impl ErnieDecoderLayerWeights {
-    pub fn load(vb: &VarBuilder, cfg: &PaddleOcrVlConfig) -> Result<Self> {
-        let attention = ErnieAttentionWeights::load(vb, cfg)?;
-        let mlp = ErnieMlpWeights::load(vb, cfg)?;
+    pub fn load(
+        vb: &VarBuilder,
+        cfg: &PaddleOcrVlConfig,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
+        let attention = ErnieAttentionWeights::load(vb, cfg, quant)?;
+        let mlp = ErnieMlpWeights::load(vb, cfg, quant)?;
         let input_layernorm = vb
             .pp("input_layernorm")
             .get(cfg.hidden_size, "weight")
@@ -152,7 +219,11 @@ pub struct ErnieModelWeights {
 }
 
 impl ErnieModelWeights {
-    pub fn load(vb: &VarBuilder, cfg: &PaddleOcrVlConfig) -> Result<Self> {
+    pub fn load(
+        vb: &VarBuilder,
+        cfg: &PaddleOcrVlConfig,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let embed_tokens = vb
             .pp("embed_tokens")
             .get((cfg.vocab_size, cfg.hidden_size), "weight")
@@ -162,7 +233,7 @@ impl ErnieModelWeights {
         let mut layers = Vec::with_capacity(cfg.num_hidden_layers);
         for idx in 0..cfg.num_hidden_layers {
             let layer_vb = vb.pp(format!("layers.{idx}"));
-            layers.push(ErnieDecoderLayerWeights::load(&layer_vb, cfg)?);
+            layers.push(ErnieDecoderLayerWeights::load(&layer_vb, cfg, quant)?);
         }
 
         let final_norm = vb
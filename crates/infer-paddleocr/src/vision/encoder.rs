@@ -1,10 +1,15 @@
+use std::sync::Arc;
+
 use anyhow::{Context, Result, ensure};
-use candle_core::{DType, Device, Module, Tensor, shape::D};
+use candle_core::{DType, Device, Module, Tensor, quantized::QMatMul, shape::D};
 use candle_nn::{Linear, VarBuilder, ops::softmax};
 use deepseek_ocr_core::tensor::gather_token_embeddings;
 
 use super::SiglipImagePatches;
-use crate::config::PaddleOcrVisionConfig;
+use crate::{
+    config::PaddleOcrVisionConfig,
+    quantization::{LinearLayerGroup, QuantModule, QuantizationState, VisionTower},
+};
 
 pub struct SiglipVisionModel {
     embeddings: SiglipEmbeddings,
@@ -14,11 +19,16 @@ pub struct SiglipVisionModel {
 }
 
 impl SiglipVisionModel {
-    pub fn load(vb: &VarBuilder, cfg: &PaddleOcrVisionConfig, model_dtype: DType) -> Result<Self> {
+    pub fn load(
+        vb: &VarBuilder,
+        cfg: &PaddleOcrVisionConfig,
+        model_dtype: DType,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let compute_dtype = resolve_compute_dtype(model_dtype);
         let vision_vb = vb.pp("visual").pp("vision_model");
         let embeddings = SiglipEmbeddings::load(&vision_vb.pp("embeddings"), cfg)?;
-        let encoder = SiglipEncoder::load(&vision_vb.pp("encoder"), cfg, compute_dtype)?;
+        let encoder = SiglipEncoder::load(&vision_vb.pp("encoder"), cfg, compute_dtype, quant)?;
         let post_layernorm = PreciseLayerNorm::load(
             vision_vb.pp("post_layernorm"),
             cfg.hidden_size,
@@ -353,11 +363,21 @@ struct SiglipEncoder {
 }
 
 impl SiglipEncoder {
-    fn load(vb: &VarBuilder, cfg: &PaddleOcrVisionConfig, compute_dtype: DType) -> Result<Self> {
+    fn load(
+        vb: &VarBuilder,
+        cfg: &PaddleOcrVisionConfig,
+        compute_dtype: DType,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let mut layers = Vec::with_capacity(cfg.num_hidden_layers);
         for idx in 0..cfg.num_hidden_layers {
             let layer_vb = vb.pp(format!("layers.{idx}"));
-            layers.push(SiglipEncoderLayer::load(&layer_vb, cfg, compute_dtype)?);
+            layers.push(SiglipEncoderLayer::load(
+                &layer_vb,
+                cfg,
+                compute_dtype,
+                quant,
+            )?);
         }
         let head_dim = cfg.hidden_size / cfg.num_attention_heads;
         let rotary = SiglipRotaryEmbedding::new(head_dim, compute_dtype);
@@ -506,21 +526,26 @@ struct SiglipEncoderLayer {
 }
 
 impl SiglipEncoderLayer {
-    fn load(vb: &VarBuilder, cfg: &PaddleOcrVisionConfig, compute_dtype: DType) -> Result<Self> {
+    fn load(
+        vb: &VarBuilder,
+        cfg: &PaddleOcrVisionConfig,
+        compute_dtype: DType,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let layer_norm1 = PreciseLayerNorm::load(
             vb.pp("layer_norm1"),
             cfg.hidden_size,
             cfg.layer_norm_eps as f64,
             compute_dtype,
         )?;
-        let attention = SiglipAttention::load(&vb.pp("self_attn"), cfg, compute_dtype)?;
+        let attention = SiglipAttention::load(&vb.pp("self_attn"), cfg, compute_dtype, quant)?;
         let layer_norm2 = PreciseLayerNorm::load(
             vb.pp("layer_norm2"),
             cfg.hidden_size,
             cfg.layer_norm_eps as f64,
             compute_dtype,
         )?;
-        let mlp = SiglipMlp::load(&vb.pp("mlp"), cfg, compute_dtype)?;
+        let mlp = SiglipMlp::load(&vb.pp("mlp"), cfg, compute_dtype, quant)?;
         Ok(Self {
             layer_norm1,
             layer_norm2,
@@ -550,30 +575,39 @@ struct SiglipAttention {
 }
 
 impl SiglipAttention {
-    fn load(vb: &VarBuilder, cfg: &PaddleOcrVisionConfig, compute_dtype: DType) -> Result<Self> {
+    fn load(
+        vb: &VarBuilder,
+        cfg: &PaddleOcrVisionConfig,
+        compute_dtype: DType,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let q_proj = DTypedLinear::load(
             vb.pp("q_proj"),
             cfg.hidden_size,
             cfg.hidden_size,
             compute_dtype,
+            quant,
         )?;
         let k_proj = DTypedLinear::load(
             vb.pp("k_proj"),
             cfg.hidden_size,
             cfg.hidden_size,
             compute_dtype,
+            quant,
         )?;
         let v_proj = DTypedLinear::load(
             vb.pp("v_proj"),
             cfg.hidden_size,
             cfg.hidden_size,
             compute_dtype,
+            quant,
         )?;
         let out_proj = DTypedLinear::load(
             vb.pp("out_proj"),
             cfg.hidden_size,
             cfg.hidden_size,
             compute_dtype,
+            quant,
         )?;
         let head_dim = cfg.hidden_size / cfg.num_attention_heads;
         Ok(Self {
@@ -669,18 +703,25 @@ struct SiglipMlp {
 }
 
 impl SiglipMlp {
-    fn load(vb: &VarBuilder, cfg: &PaddleOcrVisionConfig, compute_dtype: DType) -> Result<Self> {
+    fn load(
+        vb: &VarBuilder,
+        cfg: &PaddleOcrVisionConfig,
+        compute_dtype: DType,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
         let fc1 = DTypedLinear::load(
             vb.pp("fc1"),
             cfg.intermediate_size,
             cfg.hidden_size,
             compute_dtype,
+            quant,
         )?;
         let fc2 = DTypedLinear::load(
             vb.pp("fc2"),
             cfg.hidden_size,
             cfg.intermediate_size,
             compute_dtype,
+            quant,
         )?;
         Ok(Self { fc1, fc2 })
     }
@@ -789,12 +830,23 @@ pub(crate) struct LayerDebug {
 }
 
 struct DTypedLinear {
-    linear: Linear,
+    /// Float weight; dropped once quantized unless `keep_full_precision_weights` is set.
+    weight: Option<Tensor>,
+    /// Q8_0 replacement for `weight` when the `siglip` tower is quantized.
+    qmatmul: Option<Arc<QMatMul>>,
+    bias: Tensor,
     compute_dtype: DType,
 }
 
 impl DTypedLinear {
-    fn load(vb: VarBuilder, out_dim: usize, in_dim: usize, compute_dtype: DType) -> Result<Self> {
+    fn load(
+        vb: VarBuilder,
+        out_dim: usize,
+        in_dim: usize,
+        compute_dtype: DType,
+        quant: &QuantizationState,
+    ) -> Result<Self> {
+        let name = format!("{}.weight", vb.prefix());
         let weight = vb
             .get((out_dim, in_dim), "weight")
             .context("missing linear weight")?;
@@ -809,19 +861,43 @@ impl DTypedLinear {
         } else {
             bias
         };
+        let qmatmul = quant.quantize_linear(
+            &weight,
+            LinearLayerGroup::Vision,
+            QuantModule::Vision(VisionTower::Siglip),
+            &name,
+        )?;
+        let weight = if qmatmul.is_some() && !quant.config().keep_full_precision_weights {
+            None
+        } else {
+            Some(weight)
+        };
         Ok(Self {
-            linear: Linear::new(weight, Some(bias)),
+            weight,
+            qmatmul,
+            bias,
             compute_dtype,
         })
     }
 
     fn forward(&self, input: &Tensor) -> Result<Tensor> {
+        if let Some(qmatmul) = &self.qmatmul {
+            let out = qmatmul
+                .forward(&input.to_dtype(DType::F32)?.contiguous()?)?
+                .broadcast_add(&self.bias.to_dtype(DType::F32)?)?;
+            return Ok(out.to_dtype(input.dtype())?);
+        }
+        let weight = self
+            .weight
+            .clone()
+            .context("siglip linear has neither a float nor a quantized weight")?;
+        let linear = Linear::new(weight, Some(self.bias.clone()));
         if input.dtype() == self.compute_dtype {
-            let out = self.linear.forward(input)?;
+            let out = linear.forward(input)?;
             Ok(out)
         } else {
             let cast = input.to_dtype(self.compute_dtype)?;
-            let projected = self.linear.forward(&cast)?;
+            let projected = linear.forward(&cast)?;
             let restored = projected.to_dtype(input.dtype())?;
             Ok(restored)
         }
@@ -883,3 +959,61 @@ fn rotate_half_local(tensor: &Tensor) -> Result<Tensor> {
     let neg_second = second.neg()?;
     Ok(Tensor::cat(&[&neg_second, &first], D::Minus1)?)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::quantization::{QuantizationConfig, QuantizationKind, VisionQuantization};
+
+    const WIDTH: usize = 64;
+
+    fn load_linear(quant: &QuantizationState) -> Result<DTypedLinear> {
+        let device = Device::Cpu;
+        let weight = (Tensor::arange(0f32, (WIDTH * WIDTH) as f32, &device)?
+            .reshape((WIDTH, WIDTH))?
+            .sin()?
+            * 0.05)?;
+        let bias = Tensor::full(0.5f32, WIDTH, &device)?;
+        let tensors = HashMap::from([
+            ("proj.weight".to_string(), weight),
+            ("proj.bias".to_string(), bias),
+        ]);
+        let vb = VarBuilder::from_tensors(tensors, DType::F32, &device);
+        DTypedLinear::load(vb.pp("proj"), WIDTH, WIDTH, DType::F32, quant)
+    }
+
+    fn siglip_q8(keep_full_precision_weights: bool) -> QuantizationState {
+        QuantizationState::new(QuantizationConfig {
+            kind: QuantizationKind::Q8_0,
+            vision: VisionQuantization::from_towers(&[VisionTower::Siglip]),
+            keep_full_precision_weights,
+            ..QuantizationConfig::default()
+        })
+    }
+
+    #[test]
+    fn quantized_siglip_linears_drop_the_float_weight() -> Result<()> {
+        let float = load_linear(&QuantizationState::disabled())?;
+        assert!(float.weight.is_some() && float.qmatmul.is_none());
+        let quantized = load_linear(&siglip_q8(false))?;
+        assert!(quantized.weight.is_none() && quantized.qmatmul.is_some());
+        assert!(load_linear(&siglip_q8(true))?.weight.is_some());
+
+        let input = Tensor::arange(0f32, (2 * WIDTH) as f32, &Device::Cpu)?
+            .reshape((2, WIDTH))?
+            .cos()?;
+        let reference = float.forward(&input)?;
+        let deviation = (quantized.forward(&input)? - &reference)?
+            .abs()?
+            .max_all()?
+            .to_scalar::<f32>()?;
+        let scale = reference.abs()?.max_all()?.to_scalar::<f32>()?;
+        assert!(
+            deviation < 0.02 * scale,
+            "Q8_0 output deviates by {deviation} (scale {scale})"
+        );
+        Ok(())
+    }
+}
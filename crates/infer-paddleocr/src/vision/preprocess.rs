@@ -1,6 +1,6 @@
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Result, anyhow};
 use candle_core::{Device, Tensor};
-use fast_image_resize as fir;
+use deepseek_ocr_core::resample::resize_bicubic;
 use image::{DynamicImage, ImageBuffer, Rgb, RgbImage};
 
 use crate::config::PaddleOcrVisionConfig;
@@ -54,6 +54,13 @@ impl SiglipPreprocessConfig {
         self
     }
 
+    /// Override either area bound, keeping the current value for the one left unset.
+    pub fn with_pixel_bounds(self, min_pixels: Option<usize>, max_pixels: Option<usize>) -> Self {
+        let min_pixels = min_pixels.unwrap_or(self.min_pixels);
+        let max_pixels = max_pixels.unwrap_or(self.max_pixels).max(min_pixels);
+        self.with_min_max(min_pixels, max_pixels)
+    }
+
     pub fn with_normalization(mut self, mean: [f32; 3], std: [f32; 3]) -> Self {
         self.image_mean = mean;
         self.image_std = std;
@@ -238,24 +245,7 @@ fn resize_rgb_image(image: &RgbImage, width: u32, height: u32) -> Result<RgbImag
     if width == 0 || height == 0 {
         return Err(anyhow!("target dimensions must be positive"));
     }
-    let mut owned = image.clone().into_raw();
-    let src = fir::images::Image::from_slice_u8(
-        image.width(),
-        image.height(),
-        owned.as_mut_slice(),
-        fir::PixelType::U8x3,
-    )
-    .context("failed to build fast_image_resize source image")?;
-    let mut dst = fir::images::Image::new(width, height, fir::PixelType::U8x3);
-    let options = fir::ResizeOptions::new()
-        .resize_alg(fir::ResizeAlg::Convolution(fir::FilterType::CatmullRom));
-    let mut resizer = fir::Resizer::new();
-    resizer
-        .resize(&src, &mut dst, &options)
-        .map_err(|err| anyhow!("fast_image_resize failed: {err}"))?;
-    let buffer = dst.into_vec();
-    ImageBuffer::from_raw(width, height, buffer)
-        .ok_or_else(|| anyhow!("failed to convert resized buffer into image"))
+    Ok(resize_bicubic(image, width, height))
 }
 
 pub fn smart_resize(
@@ -1,13 +1,18 @@
+use std::sync::Arc;
+
 use anyhow::{Context, Result, ensure};
-use candle_core::{DType, Module, Tensor, shape::D};
+use candle_core::{DType, Module, Tensor, quantized::QMatMul, shape::D};
 use candle_nn::{Linear, VarBuilder};
 
-use crate::config::PaddleOcrVisionConfig;
+use crate::{
+    config::PaddleOcrVisionConfig,
+    quantization::{LinearLayerGroup, QuantModule, QuantizationState},
+};
 
 pub struct SiglipProjector {
     pre_norm: ProjectorLayerNorm,
-    linear1: Linear,
-    linear2: Linear,
+    linear1: ProjectorLinear,
+    linear2: ProjectorLinear,
     merge_size: usize,
     vision_hidden: usize,
 }
@@ -24,6 +29,7 @@ impl SiglipProjector {
         vision_cfg: &PaddleOcrVisionConfig,
         output_hidden: usize,
         model_dtype: DType,
+        quant: &QuantizationState,
     ) -> Result<Self> {
         let merge_size = vision_cfg.spatial_merge_size;
         let vision_hidden = vision_cfg.hidden_size;
@@ -41,12 +47,14 @@ impl SiglipProjector {
             merged_hidden,
             merged_hidden,
             compute_dtype,
+            quant,
         )?;
         let linear2 = load_linear(
             projector_vb.pp("linear_2"),
             output_hidden,
             merged_hidden,
             compute_dtype,
+            quant,
         )?;
         Ok(Self {
             pre_norm,
@@ -147,12 +155,34 @@ fn resolve_projector_compute_dtype(dtype: DType) -> DType {
     }
 }
 
+/// Projector linear that runs either a float `Linear` or a Q8_0 matmul plus float bias.
+enum ProjectorLinear {
+    Float(Linear),
+    Quantized { qmatmul: Arc<QMatMul>, bias: Tensor },
+}
+
+impl ProjectorLinear {
+    fn forward(&self, input: &Tensor) -> Result<Tensor> {
+        match self {
+            Self::Float(linear) => Ok(linear.forward(input)?),
+            Self::Quantized { qmatmul, bias } => {
+                let out = qmatmul
+                    .forward(&input.to_dtype(DType::F32)?.contiguous()?)?
+                    .to_dtype(input.dtype())?;
+                Ok(out.broadcast_add(bias)?)
+            }
+        }
+    }
+}
+
 fn load_linear(
     vb: VarBuilder,
     out_dim: usize,
     in_dim: usize,
     compute_dtype: DType,
-) -> Result<Linear> {
+    quant: &QuantizationState,
+) -> Result<ProjectorLinear> {
+    let name = format!("{}.weight", vb.prefix());
     let weight = vb
         .get((out_dim, in_dim), "weight")
         .context("missing projector linear weight")?
@@ -161,7 +191,15 @@ fn load_linear(
         .get(out_dim, "bias")
         .context("missing projector linear bias")?
         .to_dtype(compute_dtype)?;
-    Ok(Linear::new(weight, Some(bias)))
+    match quant.quantize_linear(
+        &weight,
+        LinearLayerGroup::Projector,
+        QuantModule::Projector,
+        &name,
+    )? {
+        Some(qmatmul) => Ok(ProjectorLinear::Quantized { qmatmul, bias }),
+        None => Ok(ProjectorLinear::Float(Linear::new(weight, Some(bias)))),
+    }
 }
 
 struct ProjectorLayerNorm {
@@ -3,7 +3,7 @@ use std::sync::Arc;
 use anyhow::Result;
 use deepseek_ocr_config::{AppConfig, LocalFileSystem};
 use deepseek_ocr_core::{
-    DecodeParameters, VisionSettings,
+    DecodeParameters,
     runtime::{default_dtype_for_device, prepare_device_and_dtype},
 };
 use rocket::{Config, data::ToByteUnit};
@@ -26,11 +26,7 @@ pub async fn run(args: Args) -> Result<()> {
         prepare_device_and_dtype(app_config.inference.device, app_config.inference.precision)?;
     let dtype = maybe_dtype.unwrap_or_else(|| default_dtype_for_device(&device));
 
-    let vision_settings = VisionSettings {
-        base_size: app_config.inference.base_size,
-        image_size: app_config.inference.image_size,
-        crop_mode: app_config.inference.crop_mode,
-    };
+    let vision_settings = app_config.inference.vision_settings();
     let decode_defaults = DecodeParameters {
         max_new_tokens: app_config.inference.max_new_tokens,
         do_sample: app_config.inference.do_sample,
@@ -45,6 +41,7 @@ pub async fn run(args: Args) -> Result<()> {
         no_repeat_ngram_size: app_config.inference.no_repeat_ngram_size,
         seed: app_config.inference.seed,
         use_cache: app_config.inference.use_cache,
+        constraint: None,
     };
 
     let state = AppState::bootstrap(
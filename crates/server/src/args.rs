@@ -2,7 +2,15 @@ use std::path::PathBuf;
 
 use clap::Parser;
 use deepseek_ocr_config::{AppConfig, ConfigOverride, ConfigOverrides};
-use deepseek_ocr_core::runtime::{DeviceKind, Precision};
+use deepseek_ocr_core::{
+    formula::{FormulaMode, MathDelimiters},
+    inference::ResolutionMode,
+    layout::LayoutMode,
+    orientation::OrientationMode,
+    quantization::{QuantizationKind, QuantizationTargets, VisionQuantization, VisionTower},
+    reading_order::ReadingOrder,
+    runtime::{DeviceKind, Precision},
+};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "DeepSeek-OCR API Server", long_about = None)]
@@ -35,6 +43,32 @@ pub struct Args {
     #[arg(long, help_heading = "Inference")]
     pub dtype: Option<Precision>,
 
+    /// Weight quantization for the selected model (none/q8_0/q4_k). CPU only.
+    #[arg(long, help_heading = "Inference")]
+    pub quant: Option<QuantizationKind>,
+
+    /// Which linear layers to quantize (text/text+projector).
+    #[arg(long, value_name = "TARGETS", help_heading = "Inference")]
+    pub quant_targets: Option<QuantizationTargets>,
+
+    /// Vision towers to quantize as well (comma separated: sam,clip,siglip).
+    #[arg(
+        long,
+        value_name = "TOWERS",
+        value_delimiter = ',',
+        help_heading = "Inference"
+    )]
+    pub quant_vision: Option<Vec<VisionTower>>,
+
+    /// Named resolution preset (tiny/small/base/large/gundam); replaces the raw size flags.
+    #[arg(
+        long,
+        value_name = "MODE",
+        conflicts_with_all = ["base_size", "image_size", "crop_mode"],
+        help_heading = "Inference"
+    )]
+    pub resolution: Option<ResolutionMode>,
+
     /// Global view resolution.
     #[arg(long, help_heading = "Inference")]
     pub base_size: Option<u32>,
@@ -47,6 +81,80 @@ pub struct Args {
     #[arg(long, help_heading = "Inference")]
     pub crop_mode: Option<bool>,
 
+    /// Input image orientation handling: off, exif (default) or auto (EXIF plus rotation detection).
+    #[arg(long, value_name = "MODE", help_heading = "Inference")]
+    pub orientation: Option<OrientationMode>,
+
+    /// Crop tiles encoded per SAM/CLIP forward pass (defaults to 1).
+    #[arg(long, value_name = "TILES", help_heading = "Inference")]
+    pub tile_chunk_size: Option<usize>,
+
+    /// Worker threads for crop-tile encoding (defaults to the global rayon pool).
+    #[arg(long, value_name = "THREADS", help_heading = "Inference")]
+    pub tile_threads: Option<usize>,
+
+    /// Memory budget for concurrently encoded tiles in MiB (defaults to 2048, 0 = unlimited).
+    #[arg(long, value_name = "MIB", help_heading = "Inference")]
+    pub tile_memory_mb: Option<u64>,
+
+    /// Straighten skewed scans before encoding (true/false).
+    #[arg(long, value_name = "BOOL", help_heading = "Document cleanup")]
+    pub deskew: Option<bool>,
+
+    /// Trim scanner borders and blank margins around the content (true/false).
+    #[arg(long, value_name = "BOOL", help_heading = "Document cleanup")]
+    pub crop_margins: Option<bool>,
+
+    /// Stretch contrast so faint scans reach full black and white (true/false).
+    #[arg(long, value_name = "BOOL", help_heading = "Document cleanup")]
+    pub normalize_contrast: Option<bool>,
+
+    /// Reduce pages to pure black and white (true/false).
+    #[arg(long, value_name = "BOOL", help_heading = "Document cleanup")]
+    pub binarize: Option<bool>,
+
+    /// OCR very tall images (receipts, long screenshots) as overlapping slices (true/false).
+    #[arg(long, value_name = "BOOL", help_heading = "Document cleanup")]
+    pub slice_tall: Option<bool>,
+
+    /// Fraction of each slice shared with the next one (defaults to 0.15).
+    #[arg(long, value_name = "FRACTION", help_heading = "Document cleanup")]
+    pub slice_overlap: Option<f32>,
+
+    /// Split two-page spreads or multi-column pages before OCR: off (default), spread or columns.
+    #[arg(long, value_name = "MODE", help_heading = "Document cleanup")]
+    pub split_layout: Option<LayoutMode>,
+
+    /// Remove `<|ref|>`/`<|det|>` grounding markup, keeping the text it labels. Kept for
+    /// requests with the `grounding` or `locate` task.
+    #[arg(long, value_name = "BOOL", help_heading = "Markdown polish")]
+    pub strip_grounding: Option<bool>,
+
+    /// Replace HTML tables without spanning cells by GitHub pipe tables.
+    #[arg(long, value_name = "BOOL", help_heading = "Markdown polish")]
+    pub markdown_tables: Option<bool>,
+
+    /// Merge words hyphenated across a line break.
+    #[arg(long, value_name = "BOOL", help_heading = "Markdown polish")]
+    pub merge_hyphenation: Option<bool>,
+
+    /// Join paragraphs split mid-sentence where tiles or slices meet.
+    #[arg(long, value_name = "BOOL", help_heading = "Markdown polish")]
+    pub join_paragraphs: Option<bool>,
+
+    /// Start headings at `#` and close skipped heading levels.
+    #[arg(long, value_name = "BOOL", help_heading = "Markdown polish")]
+    pub fix_headings: Option<bool>,
+
+    /// Default formula rewrite for text responses: off (default), latex or mathml (`mathml`
+    /// feature). Streamed responses are never polished.
+    #[arg(long, value_name = "MODE", help_heading = "Markdown polish")]
+    pub polish_formulas: Option<FormulaMode>,
+
+    /// Default delimiters for normalised LaTeX: dollar (default) or bracket.
+    #[arg(long, value_name = "STYLE", help_heading = "Markdown polish")]
+    pub math_delimiters: Option<MathDelimiters>,
+
     /// Default max tokens budget per request.
     #[arg(long, help_heading = "Inference")]
     pub max_new_tokens: Option<usize>,
@@ -79,6 +187,12 @@ pub struct Args {
     #[arg(long, help_heading = "Inference")]
     pub seed: Option<u64>,
 
+    /// Default block order of page documents: layout (default) rebuilds it from the boxes,
+    /// reading columns in turn and keeping running headers and footers apart; native keeps the
+    /// order the model emitted.
+    #[arg(long, value_name = "ORDER", help_heading = "Application")]
+    pub reading_order: Option<ReadingOrder>,
+
     /// Host/IP for Rocket to bind to.
     #[arg(long, help_heading = "Application")]
     pub host: Option<String>,
@@ -96,11 +210,19 @@ impl From<&Args> for ConfigOverrides {
         overrides.model_config = args.model_config.clone();
         overrides.tokenizer = args.tokenizer.clone();
         overrides.weights = args.weights.clone();
+        overrides.quantization.kind = args.quant;
+        overrides.quantization.targets = args.quant_targets;
+        overrides.quantization.vision = args
+            .quant_vision
+            .as_deref()
+            .map(VisionQuantization::from_towers);
         overrides.inference.device = args.device;
         overrides.inference.precision = args.dtype;
+        overrides.inference.resolution = args.resolution;
         overrides.inference.base_size = args.base_size;
         overrides.inference.image_size = args.image_size;
         overrides.inference.crop_mode = args.crop_mode;
+        overrides.inference.orientation = args.orientation;
         overrides.inference.max_new_tokens = args.max_new_tokens;
         overrides.inference.do_sample = args.do_sample;
         overrides.inference.temperature = args.temperature;
@@ -109,6 +231,24 @@ impl From<&Args> for ConfigOverrides {
         overrides.inference.repetition_penalty = args.repetition_penalty;
         overrides.inference.no_repeat_ngram_size = args.no_repeat_ngram_size;
         overrides.inference.seed = args.seed;
+        overrides.inference.tile_chunk_size = args.tile_chunk_size;
+        overrides.inference.tile_threads = args.tile_threads;
+        overrides.inference.tile_memory_mb = args.tile_memory_mb;
+        overrides.inference.deskew = args.deskew;
+        overrides.inference.crop_margins = args.crop_margins;
+        overrides.inference.normalize_contrast = args.normalize_contrast;
+        overrides.inference.binarize = args.binarize;
+        overrides.inference.slice_tall = args.slice_tall;
+        overrides.inference.slice_overlap = args.slice_overlap;
+        overrides.inference.split_layout = args.split_layout;
+        overrides.inference.strip_grounding = args.strip_grounding;
+        overrides.inference.markdown_tables = args.markdown_tables;
+        overrides.inference.merge_hyphenation = args.merge_hyphenation;
+        overrides.inference.join_paragraphs = args.join_paragraphs;
+        overrides.inference.fix_headings = args.fix_headings;
+        overrides.inference.polish_formulas = args.polish_formulas;
+        overrides.inference.math_delimiters = args.math_delimiters;
+        overrides.inference.reading_order = args.reading_order;
         overrides.server.host = args.host.clone();
         overrides.server.port = args.port;
         overrides
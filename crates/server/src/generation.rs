@@ -1,7 +1,16 @@
 use std::{convert::TryFrom, sync::Arc};
 
 use base64::Engine;
-use deepseek_ocr_core::{DecodeOutcome, DecodeParameters, ModelKind, VisionSettings};
+use deepseek_ocr_core::{
+    CancellationToken, DecodeOutcome, DecodeParameters, ModelKind, OcrEngine, VisionSettings,
+    cleanup::{CleanupConfig, clean_document},
+    inference::UnsupportedResolution,
+    layout::{LayoutConfig, LayoutMode},
+    orientation::{OrientationMode, OrientedImage, decode_pages},
+    segments::{decode_segmented, segment_image},
+    slicing::SliceConfig,
+    task::OcrTask,
+};
 use image::DynamicImage;
 use reqwest::blocking::Client;
 use rocket::tokio;
@@ -10,7 +19,7 @@ use tracing::info;
 
 use crate::{
     error::ApiError,
-    models::{ApiMessage, ImagePayload, MessageContent, MessagePart},
+    models::{ApiMessage, ImageMetadata, ImagePayload, MessageContent, MessagePart},
     state::{GenerationInputs, SharedModel},
     stream::{StreamContext, StreamController},
 };
@@ -46,6 +55,8 @@ pub async fn generate_async(
             prompt,
             images,
             inputs.vision,
+            &inputs.layout,
+            &inputs.slicing,
             params,
             stream_for_block,
         )
@@ -76,14 +87,71 @@ fn generate_blocking(
     prompt: String,
     images: Vec<DynamicImage>,
     vision: VisionSettings,
+    layout: &LayoutConfig,
+    slicing: &SliceConfig,
     params: DecodeParameters,
     stream: Option<StreamContext>,
 ) -> Result<GenerationResult, ApiError> {
+    // Split the page before taking the model lock, so the crops do not hold up other requests.
+    let segmenting = slicing.enabled || layout.mode != LayoutMode::Off;
+    let segmented = match images.as_slice() {
+        [image] if segmenting => {
+            Some(segment_image(image.clone(), layout, slicing)).filter(|s| s.is_split())
+        }
+        _ => None,
+    };
     let guard = model
         .lock()
         .map_err(|_| ApiError::Internal("model lock poisoned".into()))?;
     let tokenizer_ref = tokenizer.as_ref();
     let stream_controller = stream.map(|ctx| StreamController::new(Arc::clone(&tokenizer), ctx));
+
+    if let Some(segmented) = segmented {
+        info!(
+            "[generate] splitting {}x{} image into {} segments",
+            segmented.width,
+            segmented.height,
+            segmented.segments.len()
+        );
+        if let Some(controller) = stream_controller.as_ref() {
+            controller.send_initial();
+        }
+        // Streams get the merged text as each segment settles; once the client has gone, the
+        // remaining segments are skipped.
+        let cancel = CancellationToken::new();
+        let progress = |settled: &str| {
+            if let Some(controller) = stream_controller.as_ref() {
+                controller.emit_progress(settled);
+                if controller.is_closed() {
+                    cancel.cancel();
+                }
+            }
+        };
+        let outcome = decode_segmented(
+            guard.as_ref(),
+            tokenizer_ref,
+            &prompt,
+            &segmented,
+            vision,
+            &params,
+            Some(&progress),
+            Some(&cancel),
+        )
+        .map_err(decode_error)?;
+        drop(guard);
+        if let Some(controller) = stream_controller.as_ref() {
+            controller.emit_text(
+                &outcome.text,
+                outcome.prompt_tokens,
+                outcome.response_tokens,
+            );
+        }
+        return Ok(GenerationResult {
+            text: outcome.text,
+            prompt_tokens: outcome.prompt_tokens,
+            response_tokens: outcome.response_tokens,
+        });
+    }
     let mut callback_box: Option<Box<dyn Fn(usize, &[i64])>> = None;
     if let Some(controller) = stream_controller.as_ref() {
         controller.send_initial();
@@ -106,13 +174,7 @@ fn generate_blocking(
         Ok(output) => output,
         Err(err) => {
             drop(guard);
-            let message = err.to_string();
-            if message.contains("prompt formatting failed")
-                || message.contains("prompt/image embedding mismatch")
-            {
-                return Err(ApiError::BadRequest(message));
-            }
-            return Err(ApiError::Internal(format!("generation failed: {err:#}")));
+            return Err(decode_error(err));
         }
     };
 
@@ -161,20 +223,117 @@ fn generate_blocking(
     })
 }
 
+/// Run a pipeline task with the request's model, off the async runtime.
+pub async fn run_pipeline<T, F>(inputs: GenerationInputs, task: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn OcrEngine, &Tokenizer, VisionSettings) -> anyhow::Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let guard = inputs
+            .model
+            .lock()
+            .map_err(|_| ApiError::Internal("model lock poisoned".into()))?;
+        task(guard.as_ref(), &inputs.tokenizer, inputs.vision).map_err(decode_error)
+    })
+    .await
+    .map_err(|err| ApiError::Internal(format!("generation task failed: {err}")))?
+}
+
+fn decode_error(err: anyhow::Error) -> ApiError {
+    if let Some(unsupported) = err
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<UnsupportedResolution>())
+    {
+        return ApiError::BadRequest(unsupported.to_string());
+    }
+    let message = err.to_string();
+    if message.contains("prompt formatting failed")
+        || message.contains("prompt/image embedding mismatch")
+    {
+        return ApiError::BadRequest(message);
+    }
+    ApiError::Internal(format!("generation failed: {err:#}"))
+}
+
+/// Prompt text, prepared images and what was done to each image on the way.
+pub type PromptInputs = (String, Vec<DynamicImage>, Vec<ImageMetadata>);
+
+/// Build the prompt and images of a request. With a `task`, the task's prompt replaces the
+/// message text, which is only kept as the text to find for [`OcrTask::Locate`].
 pub fn convert_messages(
     kind: ModelKind,
     messages: &[ApiMessage],
-) -> Result<(String, Vec<DynamicImage>), ApiError> {
-    match kind {
-        ModelKind::Deepseek => convert_deepseek_messages(messages),
-        ModelKind::PaddleOcrVl => convert_paddle_messages(messages),
+    task: Option<OcrTask>,
+    orientation: OrientationMode,
+    cleanup: &CleanupConfig,
+) -> Result<PromptInputs, ApiError> {
+    let (prompt, images) = read_messages(kind, messages, task, orientation)?;
+    let (images, reports) = images
+        .into_iter()
+        .map(|oriented| {
+            let cleaned = clean_document(oriented.image, cleanup);
+            let metadata = ImageMetadata {
+                orientation: oriented.report,
+                cleanup: cleanup.enabled().then_some(cleaned.report),
+            };
+            (cleaned.image, metadata)
+        })
+        .unzip();
+    Ok((prompt, images, reports))
+}
+
+/// Like [`convert_messages`], but returns the images as decoded and turned upright, before
+/// clean-up.
+pub fn read_messages(
+    kind: ModelKind,
+    messages: &[ApiMessage],
+    task: Option<OcrTask>,
+    orientation: OrientationMode,
+) -> Result<(String, Vec<OrientedImage>), ApiError> {
+    let (prompt, images) = match kind {
+        ModelKind::Deepseek => convert_deepseek_messages(messages, orientation)?,
+        ModelKind::PaddleOcrVl => convert_paddle_messages(messages, orientation)?,
+    };
+    let prompt = match task {
+        Some(task) => {
+            let query = (task == OcrTask::Locate).then(|| user_query(messages));
+            task.render_prompt(kind, images.len(), query.as_deref())
+                .map_err(|err| ApiError::BadRequest(format!("{err:#}")))?
+        }
+        None => prompt,
+    };
+    Ok((prompt, images))
+}
+
+/// Text of the latest user message without its images, the text to find for
+/// [`OcrTask::Locate`]; system messages never contribute to it.
+fn user_query(messages: &[ApiMessage]) -> String {
+    let Some(message) = messages
+        .iter()
+        .rfind(|message| message.role.eq_ignore_ascii_case("user"))
+    else {
+        return String::new();
+    };
+    match &message.content {
+        MessageContent::Text(text) => text.trim().to_owned(),
+        MessageContent::Parts(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                MessagePart::Text { text } | MessagePart::InputText { text } => Some(text.trim()),
+                _ => None,
+            })
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
     }
 }
 
 fn convert_deepseek_messages(
     messages: &[ApiMessage],
-) -> Result<(String, Vec<DynamicImage>), ApiError> {
-    let (sections, images) = collect_prompt_sections(messages)?;
+    orientation: OrientationMode,
+) -> Result<(String, Vec<OrientedImage>), ApiError> {
+    let (sections, images) = collect_prompt_sections(messages, orientation)?;
     let mut prompt = String::from("");
     let body = sections.join("\n\n").trim().to_owned();
     prompt.push_str(&body);
@@ -184,15 +343,17 @@ fn convert_deepseek_messages(
 
 fn convert_paddle_messages(
     messages: &[ApiMessage],
-) -> Result<(String, Vec<DynamicImage>), ApiError> {
-    let (sections, images) = collect_prompt_sections(messages)?;
+    orientation: OrientationMode,
+) -> Result<(String, Vec<OrientedImage>), ApiError> {
+    let (sections, images) = collect_prompt_sections(messages, orientation)?;
     let prompt = sections.join("\n\n").trim().to_owned();
     Ok((prompt, images))
 }
 
 fn collect_prompt_sections(
     messages: &[ApiMessage],
-) -> Result<(Vec<String>, Vec<DynamicImage>), ApiError> {
+    orientation: OrientationMode,
+) -> Result<(Vec<String>, Vec<OrientedImage>), ApiError> {
     let latest_user_idx = messages
         .iter()
         .rposition(|message| message.role.eq_ignore_ascii_case("user"))
@@ -208,14 +369,15 @@ fn collect_prompt_sections(
         if !message.role.eq_ignore_ascii_case("system") {
             continue;
         }
-        let (text, mut msg_images) = flatten_content(&message.content)?;
+        let (text, mut msg_images) = flatten_content(&message.content, orientation)?;
         if !text.is_empty() {
             sections.push(text);
         }
         all_images.append(&mut msg_images);
     }
 
-    let (user_text, mut user_images) = flatten_content(&messages[latest_user_idx].content)?;
+    let (user_text, mut user_images) =
+        flatten_content(&messages[latest_user_idx].content, orientation)?;
     if !user_text.is_empty() {
         sections.push(user_text);
     }
@@ -236,7 +398,10 @@ fn collect_prompt_sections(
     Ok((sections, all_images))
 }
 
-fn flatten_content(content: &MessageContent) -> Result<(String, Vec<DynamicImage>), ApiError> {
+fn flatten_content(
+    content: &MessageContent,
+    orientation: OrientationMode,
+) -> Result<(String, Vec<OrientedImage>), ApiError> {
     match content {
         MessageContent::Text(text) => Ok((text.trim().to_owned(), Vec::new())),
         MessageContent::Parts(parts) => {
@@ -245,8 +410,10 @@ fn flatten_content(content: &MessageContent) -> Result<(String, Vec<DynamicImage
             for part in parts.iter().rev() {
                 match part {
                     MessagePart::ImageUrl { image_url } | MessagePart::InputImage { image_url } => {
-                        buffer.push_str("<image>");
-                        images.push(load_image(image_url)?);
+                        // Multi-page TIFFs expand into one image slot per page.
+                        let pages = load_image(image_url, orientation)?;
+                        buffer.push_str(&"<image>".repeat(pages.len()));
+                        images.extend(pages);
                     }
                     MessagePart::Text { text } | MessagePart::InputText { text } => {
                         if !buffer.is_empty() {
@@ -261,20 +428,23 @@ fn flatten_content(content: &MessageContent) -> Result<(String, Vec<DynamicImage
     }
 }
 
-fn load_image(spec: &ImagePayload) -> Result<DynamicImage, ApiError> {
+pub fn load_image(
+    spec: &ImagePayload,
+    orientation: OrientationMode,
+) -> Result<Vec<OrientedImage>, ApiError> {
     let url = spec.url();
     if let Some(rest) = url.strip_prefix("data:") {
-        return load_data_url(rest);
+        return load_data_url(rest, orientation);
     }
     if url.starts_with("http://") || url.starts_with("https://") {
-        return fetch_remote_image(url);
+        return fetch_remote_image(url, orientation);
     }
     Err(ApiError::BadRequest(
         "only data: URIs or http(s) image URLs are supported".into(),
     ))
 }
 
-fn load_data_url(data: &str) -> Result<DynamicImage, ApiError> {
+fn load_data_url(data: &str, orientation: OrientationMode) -> Result<Vec<OrientedImage>, ApiError> {
     let (meta, payload) = data
         .split_once(',')
         .ok_or_else(|| ApiError::BadRequest("invalid data URL".into()))?;
@@ -286,11 +456,14 @@ fn load_data_url(data: &str) -> Result<DynamicImage, ApiError> {
     let decoded = base64::engine::general_purpose::STANDARD
         .decode(payload)
         .map_err(|err| ApiError::BadRequest(format!("invalid base64 image payload: {err}")))?;
-    image::load_from_memory(&decoded)
-        .map_err(|err| ApiError::BadRequest(format!("failed to decode inline image: {err}")))
+    decode_pages(&decoded, orientation)
+        .map_err(|err| ApiError::BadRequest(format!("failed to decode inline image: {err:#}")))
 }
 
-fn fetch_remote_image(url: &str) -> Result<DynamicImage, ApiError> {
+fn fetch_remote_image(
+    url: &str,
+    orientation: OrientationMode,
+) -> Result<Vec<OrientedImage>, ApiError> {
     let client = Client::new();
     let response = client
         .get(url)
@@ -301,6 +474,26 @@ fn fetch_remote_image(url: &str) -> Result<DynamicImage, ApiError> {
     let bytes = response
         .bytes()
         .map_err(|err| ApiError::BadRequest(format!("failed to read image body: {err}")))?;
-    image::load_from_memory(&bytes)
-        .map_err(|err| ApiError::BadRequest(format!("failed to decode remote image: {err}")))
+    decode_pages(&bytes, orientation)
+        .map_err(|err| ApiError::BadRequest(format!("failed to decode remote image: {err:#}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locate_query_comes_from_the_user_text_only() {
+        let messages: Vec<ApiMessage> = serde_json::from_str(
+            r#"[
+                {"role": "system", "content": "You are an OCR engine."},
+                {"role": "user", "content": [
+                    {"type": "image_url", "image_url": "data:image/png;base64,AAAA"},
+                    {"type": "text", "text": " Total due "}
+                ]}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(user_query(&messages), "Total due");
+    }
 }
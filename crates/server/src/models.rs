@@ -1,3 +1,19 @@
+use deepseek_ocr_core::{
+    cleanup::CleanupReport,
+    export::OutputFormat,
+    formula::{FormulaIssue, FormulaMode, MathDelimiters},
+    inference::ResolutionMode,
+    layout::LayoutMode,
+    orientation::{OrientationMode, OrientationReport},
+    pipeline::{
+        extract::{ExtractedField, ExtractionSchema},
+        locate::BoundingBox,
+        redact::{Redaction, RedactionRules},
+    },
+    reading_order::ReadingOrder,
+    tables::Table,
+    task::OcrTask,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize)]
@@ -8,6 +24,47 @@ pub struct ResponsesResponse {
     pub model: String,
     pub output: Vec<ResponseOutput>,
     pub usage: Usage,
+    pub metadata: ResponseMetadata,
+    /// Tables found in the output, as cell grids.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tables: Vec<Table>,
+    /// Formulas the polish rules found unbalanced.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub formula_issues: Vec<FormulaIssue>,
+}
+
+/// Vendor extension echoing the vision settings a request was served with.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResponseMetadata {
+    pub vision: VisionMetadata,
+    /// Orientation and clean-up applied to each input image, in prompt order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageMetadata>,
+}
+
+/// How one input image was prepared. Grounding boxes refer to the final cleaned view; undo
+/// `cleanup` and then `rotation` to place them on the upright image.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageMetadata {
+    #[serde(flatten)]
+    pub orientation: OrientationReport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cleanup: Option<CleanupReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VisionMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<ResolutionMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_size: Option<u32>,
+    pub image_size: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crop_mode: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_pixels: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_pixels: Option<u32>,
 }
 
 #[derive(Debug, Serialize)]
@@ -41,6 +98,13 @@ pub struct ChatCompletionResponse {
     pub model: String,
     pub choices: Vec<ChatChoice>,
     pub usage: Usage,
+    pub metadata: ResponseMetadata,
+    /// Tables found in the output, as cell grids.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tables: Vec<Table>,
+    /// Formulas the polish rules found unbalanced.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub formula_issues: Vec<FormulaIssue>,
 }
 
 #[derive(Debug, Serialize)]
@@ -97,6 +161,21 @@ pub struct ResponsesRequest {
     pub seed: Option<u64>,
     #[serde(default)]
     pub use_cache: Option<bool>,
+    /// Task preset that renders the model's own prompt; see [`OcrTask`].
+    #[serde(default)]
+    pub task: Option<OcrTask>,
+    /// `hocr`, `alto`, `markdown` or `html` return the pages as a document instead of the
+    /// model text.
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    /// Block order of page documents, `layout` or `native`; the server default when unset.
+    #[serde(default)]
+    pub reading_order: Option<ReadingOrder>,
+    /// Markdown polish applied to non-streamed text output.
+    #[serde(default)]
+    pub polish: PolishOverrides,
+    #[serde(flatten)]
+    pub vision: VisionOverrides,
 }
 
 #[derive(Debug, Deserialize)]
@@ -124,6 +203,147 @@ pub struct ChatCompletionRequest {
     pub seed: Option<u64>,
     #[serde(default)]
     pub use_cache: Option<bool>,
+    /// Task preset that renders the model's own prompt; see [`OcrTask`].
+    #[serde(default)]
+    pub task: Option<OcrTask>,
+    /// `hocr`, `alto`, `markdown` or `html` return the pages as a document instead of the
+    /// model text.
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    /// Block order of page documents, `layout` or `native`; the server default when unset.
+    #[serde(default)]
+    pub reading_order: Option<ReadingOrder>,
+    /// Markdown polish applied to non-streamed text output.
+    #[serde(default)]
+    pub polish: PolishOverrides,
+    #[serde(flatten)]
+    pub vision: VisionOverrides,
+}
+
+/// Body of `/v1/locate`: one image and the text to find in it.
+#[derive(Debug, Deserialize)]
+pub struct LocateRequest {
+    pub model: String,
+    /// Image to search, as a `data:` URI or an http(s) URL.
+    pub image: ImagePayload,
+    /// Text to find.
+    pub query: String,
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+    #[serde(flatten)]
+    pub vision: VisionOverrides,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocateResponse {
+    pub model: String,
+    pub query: String,
+    /// Size of the searched image after orientation handling; boxes are in its pixels.
+    pub width: u32,
+    pub height: u32,
+    /// One box per match, in the order the model listed them; empty when nothing matched.
+    pub boxes: Vec<BoundingBox>,
+    pub metadata: ResponseMetadata,
+}
+
+/// Body of `/v1/redact`: one image and, optionally, the rules to apply instead of the
+/// configured ones.
+#[derive(Debug, Deserialize)]
+pub struct RedactRequest {
+    pub model: String,
+    /// Image to redact, as a `data:` URI or an http(s) URL.
+    pub image: ImagePayload,
+    #[serde(default)]
+    pub rules: Option<RedactionRules>,
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+    #[serde(flatten)]
+    pub vision: VisionOverrides,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RedactResponse {
+    pub model: String,
+    /// Size of the image after orientation handling; boxes are in its pixels.
+    pub width: u32,
+    pub height: u32,
+    /// The redacted image as a `data:image/png;base64,` URI.
+    pub image: String,
+    /// False when a match had no grounding box or decoding was truncated: the image may
+    /// still show personal data.
+    pub complete: bool,
+    /// Decoding stopped at `max_tokens`; text past it was not checked.
+    pub truncated: bool,
+    /// Every match, in the order the model emitted the blocks.
+    pub redactions: Vec<Redaction>,
+    pub metadata: ResponseMetadata,
+}
+
+/// Body of `/v1/extract`: one image and the fields to read from it.
+#[derive(Debug, Deserialize)]
+pub struct ExtractRequest {
+    pub model: String,
+    /// Image of the form, as a `data:` URI or an http(s) URL.
+    pub image: ImagePayload,
+    pub schema: ExtractionSchema,
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+    #[serde(flatten)]
+    pub vision: VisionOverrides,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExtractResponse {
+    pub model: String,
+    /// Size of the image after orientation handling; boxes are in its pixels.
+    pub width: u32,
+    pub height: u32,
+    /// One entry per schema field, in schema order; `value` is `null` when it was not found.
+    pub fields: Vec<ExtractedField>,
+    pub metadata: ResponseMetadata,
+}
+
+/// Optional per-request vision settings, merged over the server defaults.
+///
+/// `resolution`, `base_size` and `crop_mode` apply to DeepSeek-OCR; `min_pixels` and
+/// `max_pixels` to PaddleOCR-VL; `image_size`, `orientation` and `split_layout` to both.
+#[derive(Debug, Default, Deserialize)]
+pub struct VisionOverrides {
+    #[serde(default)]
+    pub resolution: Option<ResolutionMode>,
+    #[serde(default)]
+    pub base_size: Option<u32>,
+    #[serde(default)]
+    pub image_size: Option<u32>,
+    #[serde(default)]
+    pub crop_mode: Option<bool>,
+    #[serde(default)]
+    pub min_pixels: Option<u32>,
+    #[serde(default)]
+    pub max_pixels: Option<u32>,
+    #[serde(default)]
+    pub orientation: Option<OrientationMode>,
+    #[serde(default)]
+    pub split_layout: Option<LayoutMode>,
+}
+
+/// Optional per-request markdown polish settings, merged over the server defaults.
+#[derive(Debug, Default, Deserialize)]
+pub struct PolishOverrides {
+    #[serde(default)]
+    pub strip_grounding: Option<bool>,
+    #[serde(default)]
+    pub markdown_tables: Option<bool>,
+    #[serde(default)]
+    pub formulas: Option<FormulaMode>,
+    #[serde(default)]
+    pub math_delimiters: Option<MathDelimiters>,
+    #[serde(default)]
+    pub merge_hyphenation: Option<bool>,
+    #[serde(default)]
+    pub join_paragraphs: Option<bool>,
+    #[serde(default)]
+    pub fix_headings: Option<bool>,
 }
 
 #[derive(Debug, Deserialize)]
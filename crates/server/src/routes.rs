@@ -1,18 +1,44 @@
-use std::{sync::Arc, time::SystemTime};
+use std::{io::Cursor, sync::Arc, time::SystemTime};
 
-use rocket::{Either, Route, State, serde::json::Json, tokio::sync::mpsc};
+use base64::Engine;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use rocket::{Either, Route, State, http::ContentType, serde::json::Json, tokio::sync::mpsc};
 use tracing::debug;
 use uuid::Uuid;
 
-use deepseek_ocr_core::{DecodeParameters, ModelKind};
+use deepseek_ocr_core::{
+    DecodeParameters, ModelKind,
+    cleanup::clean_document,
+    estimate::{DryRunReport, EstimateArgs},
+    export::{
+        OcrPage, OutputFormat, page_prompt,
+        pdf::{self, PageScan},
+    },
+    formula::FormulaIssue,
+    orientation::OrientedImage,
+    pipeline::{
+        extract::{Extractor, extract},
+        locate::locate,
+        redact::{Redactor, redact},
+    },
+    polish::{PolishConfig, Polished, polish},
+    tables::extract_tables,
+    task::OcrTask,
+};
+use deepseek_ocr_infer_deepseek::estimate as estimate_deepseek;
+use deepseek_ocr_infer_paddleocr::estimate as estimate_paddle;
 
 use crate::{
     error::ApiError,
-    generation::{base_decode_parameters, convert_messages, generate_async},
+    generation::{
+        GenerationResult, base_decode_parameters, convert_messages, generate_async, load_image,
+        read_messages, run_pipeline,
+    },
     models::{
-        ChatChoice, ChatCompletionRequest, ChatCompletionResponse, ChatMessageResponse, ModelInfo,
-        ModelsResponse, ResponseContent, ResponseOutput, ResponsesRequest, ResponsesResponse,
-        Usage,
+        ChatChoice, ChatCompletionRequest, ChatCompletionResponse, ChatMessageResponse,
+        ExtractRequest, ExtractResponse, ImageMetadata, ImagePayload, LocateRequest,
+        LocateResponse, ModelInfo, ModelsResponse, RedactRequest, RedactResponse, ResponseContent,
+        ResponseMetadata, ResponseOutput, ResponsesRequest, ResponsesResponse, Usage,
     },
     state::{AppState, GenerationInputs},
     stream::{BoxEventStream, StreamContext, StreamController, StreamKind, into_event_stream},
@@ -50,8 +76,19 @@ pub async fn responses_endpoint(
     state: &State<AppState>,
     req: Json<ResponsesRequest>,
 ) -> Result<Either<Json<ResponsesResponse>, BoxEventStream>, ApiError> {
-    let (gen_inputs, active_model_id) = state.prepare_generation(&req.model)?;
-    let (prompt, images) = convert_messages(gen_inputs.kind, &req.input)?;
+    let (mut gen_inputs, active_model_id) = state.prepare_generation(&req.model)?;
+    gen_inputs.apply_vision_overrides(&req.vision)?;
+    gen_inputs.apply_reading_order_override(req.reading_order);
+    gen_inputs.apply_polish_overrides(&req.polish);
+    let (prompt, images, image_metadata) = convert_messages(
+        gen_inputs.kind,
+        &req.input,
+        req.task,
+        gen_inputs.orientation,
+        &gen_inputs.cleanup,
+    )?;
+    check_output_format(req.output_format, req.stream)?;
+    let metadata = gen_inputs.response_metadata(image_metadata);
     if prompt_missing_image(&prompt) {
         let fallback = missing_image_markdown();
         if req.stream.unwrap_or(false) {
@@ -59,9 +96,10 @@ pub async fn responses_endpoint(
                 fallback,
                 &gen_inputs,
                 active_model_id,
+                metadata,
             )));
         }
-        let response = fallback_response_response(active_model_id, &fallback);
+        let response = fallback_response_response(active_model_id, &fallback, metadata);
         return Ok(Either::Left(Json(response)));
     }
     let max_tokens = req
@@ -96,6 +134,7 @@ pub async fn responses_endpoint(
                 model: active_model_id.clone(),
                 created,
             },
+            metadata,
         };
         let task_context = context.clone();
         rocket::tokio::spawn(async move {
@@ -110,7 +149,17 @@ pub async fn responses_endpoint(
         });
         return Ok(Either::Right(stream));
     }
-    let generation = generate_async(gen_inputs, prompt, images, decode, None).await?;
+    let polish_config = gen_inputs.polish.for_task(req.task);
+    let page_images = if req.output_format.embeds_images() {
+        images.clone()
+    } else {
+        Vec::new()
+    };
+    let (generation, pages) =
+        generate_output(req.output_format, gen_inputs, prompt, images, decode).await?;
+    // Tables come from the raw output, whose grounding boxes polish may strip.
+    let tables = extract_tables(&generation.text);
+    let (text, formula_issues) = polish_output(req.output_format, &polish_config, generation.text);
     let created = current_timestamp();
     let response = ResponsesResponse {
         id: format!("resp-{}", Uuid::new_v4()),
@@ -123,7 +172,7 @@ pub async fn responses_endpoint(
             role: "assistant".into(),
             content: vec![ResponseContent {
                 r#type: "output_text".into(),
-                text: generation.text.clone(),
+                text: render_output(req.output_format, text, &pages, &page_images)?,
             }],
         }],
         usage: Usage {
@@ -131,6 +180,9 @@ pub async fn responses_endpoint(
             completion_tokens: generation.response_tokens,
             total_tokens: generation.prompt_tokens + generation.response_tokens,
         },
+        metadata,
+        tables,
+        formula_issues,
     };
     Ok(Either::Left(Json(response)))
 }
@@ -140,8 +192,19 @@ pub async fn chat_completions_endpoint(
     state: &State<AppState>,
     req: Json<ChatCompletionRequest>,
 ) -> Result<Either<Json<ChatCompletionResponse>, BoxEventStream>, ApiError> {
-    let (gen_inputs, active_model_id) = state.prepare_generation(&req.model)?;
-    let (prompt, images) = convert_messages(gen_inputs.kind, &req.messages)?;
+    let (mut gen_inputs, active_model_id) = state.prepare_generation(&req.model)?;
+    gen_inputs.apply_vision_overrides(&req.vision)?;
+    gen_inputs.apply_reading_order_override(req.reading_order);
+    gen_inputs.apply_polish_overrides(&req.polish);
+    let (prompt, images, image_metadata) = convert_messages(
+        gen_inputs.kind,
+        &req.messages,
+        req.task,
+        gen_inputs.orientation,
+        &gen_inputs.cleanup,
+    )?;
+    check_output_format(req.output_format, req.stream)?;
+    let metadata = gen_inputs.response_metadata(image_metadata);
     if prompt_missing_image(&prompt) {
         let fallback = missing_image_markdown();
         if req.stream.unwrap_or(false) {
@@ -149,9 +212,10 @@ pub async fn chat_completions_endpoint(
                 fallback,
                 &gen_inputs,
                 active_model_id,
+                metadata,
             )));
         }
-        let response = fallback_chat_response(active_model_id, &fallback);
+        let response = fallback_chat_response(active_model_id, &fallback, metadata);
         return Ok(Either::Left(Json(response)));
     }
     debug!(prompt = %prompt, "Prepared chat prompt");
@@ -182,6 +246,7 @@ pub async fn chat_completions_endpoint(
                 model: active_model_id.clone(),
                 created,
             },
+            metadata,
         };
         let task_context = context.clone();
         rocket::tokio::spawn(async move {
@@ -196,7 +261,17 @@ pub async fn chat_completions_endpoint(
         });
         return Ok(Either::Right(stream));
     }
-    let generation = generate_async(gen_inputs, prompt, images, decode, None).await?;
+    let polish_config = gen_inputs.polish.for_task(req.task);
+    let page_images = if req.output_format.embeds_images() {
+        images.clone()
+    } else {
+        Vec::new()
+    };
+    let (generation, pages) =
+        generate_output(req.output_format, gen_inputs, prompt, images, decode).await?;
+    // Tables come from the raw output, whose grounding boxes polish may strip.
+    let tables = extract_tables(&generation.text);
+    let (text, formula_issues) = polish_output(req.output_format, &polish_config, generation.text);
     let created = current_timestamp();
     let response = ChatCompletionResponse {
         id: format!("chatcmpl-{}", Uuid::new_v4()),
@@ -207,7 +282,7 @@ pub async fn chat_completions_endpoint(
             index: 0,
             message: ChatMessageResponse {
                 role: "assistant".into(),
-                content: generation.text.clone(),
+                content: render_output(req.output_format, text, &pages, &page_images)?,
             },
             finish_reason: "stop".into(),
         }],
@@ -216,16 +291,360 @@ pub async fn chat_completions_endpoint(
             completion_tokens: generation.response_tokens,
             total_tokens: generation.prompt_tokens + generation.response_tokens,
         },
+        metadata,
+        tables,
+        formula_issues,
     };
     Ok(Either::Left(Json(response)))
 }
 
+/// Dry run of a chat completion request: per-image vision tokens, prompt size and a memory
+/// estimate, computed without loading the model weights.
+#[post("/estimate", format = "json", data = "<req>")]
+pub async fn estimate_endpoint(
+    state: &State<AppState>,
+    req: Json<ChatCompletionRequest>,
+) -> Result<Json<DryRunReport>, ApiError> {
+    let mut inputs = state.prepare_estimate(&req.model)?;
+    inputs.apply_vision_overrides(&req.vision)?;
+    let (prompt, images, _) = convert_messages(
+        inputs.kind,
+        &req.messages,
+        req.task,
+        inputs.orientation,
+        &inputs.cleanup,
+    )?;
+    let args = EstimateArgs {
+        config_path: Some(&inputs.config_path),
+        tokenizer: &inputs.tokenizer,
+        prompt: &prompt,
+        images: &images,
+        vision: inputs.vision,
+        max_new_tokens: req.max_tokens.unwrap_or(state.default_max_new_tokens()),
+        dtype: inputs.dtype,
+    };
+    let report = match inputs.kind {
+        ModelKind::Deepseek => estimate_deepseek(args),
+        ModelKind::PaddleOcrVl => estimate_paddle(args),
+    }
+    .map_err(|err| ApiError::BadRequest(format!("{err:#}")))?;
+    Ok(Json(report))
+}
+
+/// Searchable PDF of the attached images: each page image under an invisible text layer
+/// placed with the grounding boxes decoded for it.
+#[post("/pdf", format = "json", data = "<req>")]
+pub async fn pdf_endpoint(
+    state: &State<AppState>,
+    req: Json<ChatCompletionRequest>,
+) -> Result<(ContentType, Vec<u8>), ApiError> {
+    let pdf = binary_document(OutputFormat::Pdf, state, req).await?;
+    Ok((ContentType::PDF, pdf))
+}
+
+/// Word document of the attached images, built from the layout blocks decoded for each page
+/// with figures cropped from the images.
+#[post("/docx", format = "json", data = "<req>")]
+pub async fn docx_endpoint(
+    state: &State<AppState>,
+    req: Json<ChatCompletionRequest>,
+) -> Result<(ContentType, Vec<u8>), ApiError> {
+    let docx = binary_document(OutputFormat::Docx, state, req).await?;
+    let content_type = ContentType::new(
+        "application",
+        "vnd.openxmlformats-officedocument.wordprocessingml.document",
+    );
+    Ok((content_type, docx))
+}
+
+/// Boxes of every match of a text in one image, in pixels of the image as turned upright.
+/// Clean-up and layout splitting are skipped so the boxes line up with the submitted image.
+#[post("/locate", format = "json", data = "<req>")]
+pub async fn locate_endpoint(
+    state: &State<AppState>,
+    req: Json<LocateRequest>,
+) -> Result<Json<LocateResponse>, ApiError> {
+    let (mut gen_inputs, active_model_id) = state.prepare_generation(&req.model)?;
+    gen_inputs.apply_vision_overrides(&req.vision)?;
+    OcrTask::Locate
+        .render_prompt(gen_inputs.kind, 1, Some(&req.query))
+        .map_err(|err| ApiError::BadRequest(format!("{err:#}")))?;
+    let (image, image_metadata) = single_page(&req.image, &gen_inputs, "locate")?;
+    let (width, height) = image.dimensions();
+    let metadata = gen_inputs.response_metadata(vec![image_metadata]);
+    let max_tokens = req.max_tokens.unwrap_or(state.default_max_new_tokens());
+    let decode = base_decode_parameters(&gen_inputs, max_tokens);
+    let query = req.query.clone();
+    let boxes = run_pipeline(gen_inputs, move |engine, tokenizer, vision| {
+        locate(engine, tokenizer, &image, &query, vision, &decode)
+    })
+    .await?;
+    Ok(Json(LocateResponse {
+        model: active_model_id,
+        query: req.query.clone(),
+        width,
+        height,
+        boxes,
+        metadata,
+    }))
+}
+
+/// Black out what the redaction rules match in one image, turned upright. The request's
+/// `rules` replace the configured ones; the redacted image comes back as a PNG data URI with
+/// an audit of every match.
+#[post("/redact", format = "json", data = "<req>")]
+pub async fn redact_endpoint(
+    state: &State<AppState>,
+    req: Json<RedactRequest>,
+) -> Result<Json<RedactResponse>, ApiError> {
+    let (mut gen_inputs, active_model_id) = state.prepare_generation(&req.model)?;
+    gen_inputs.apply_vision_overrides(&req.vision)?;
+    OcrTask::Grounding
+        .render_prompt(gen_inputs.kind, 1, None)
+        .map_err(|err| ApiError::BadRequest(format!("{err:#}")))?;
+    let rules = req.rules.as_ref().unwrap_or(state.redaction_rules());
+    let redactor = Redactor::new(rules).map_err(|err| ApiError::BadRequest(format!("{err:#}")))?;
+    let (image, image_metadata) = single_page(&req.image, &gen_inputs, "redact")?;
+    let metadata = gen_inputs.response_metadata(vec![image_metadata]);
+    let max_tokens = req.max_tokens.unwrap_or(state.default_max_new_tokens());
+    let decode = base_decode_parameters(&gen_inputs, max_tokens);
+    let redacted = run_pipeline(gen_inputs, move |engine, tokenizer, vision| {
+        redact(engine, tokenizer, &image, &redactor, vision, &decode)
+    })
+    .await?;
+    let mut png = Vec::new();
+    redacted
+        .image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|err| ApiError::Internal(format!("failed to encode redacted image: {err}")))?;
+    let report = redacted.report;
+    Ok(Json(RedactResponse {
+        model: active_model_id,
+        width: report.width,
+        height: report.height,
+        image: format!(
+            "data:image/png;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(png)
+        ),
+        complete: report.complete,
+        truncated: report.truncated,
+        redactions: report.redactions,
+        metadata,
+    }))
+}
+
+/// Typed values of the schema's fields in one image, turned upright. Values come with their
+/// pixel box when the model grounds its output.
+#[post("/extract", format = "json", data = "<req>")]
+pub async fn extract_endpoint(
+    state: &State<AppState>,
+    req: Json<ExtractRequest>,
+) -> Result<Json<ExtractResponse>, ApiError> {
+    let (mut gen_inputs, active_model_id) = state.prepare_generation(&req.model)?;
+    gen_inputs.apply_vision_overrides(&req.vision)?;
+    let extractor =
+        Extractor::new(&req.schema).map_err(|err| ApiError::BadRequest(format!("{err:#}")))?;
+    let (image, image_metadata) = single_page(&req.image, &gen_inputs, "extract")?;
+    let metadata = gen_inputs.response_metadata(vec![image_metadata]);
+    let max_tokens = req.max_tokens.unwrap_or(state.default_max_new_tokens());
+    let decode = base_decode_parameters(&gen_inputs, max_tokens);
+    let extraction = run_pipeline(gen_inputs, move |engine, tokenizer, vision| {
+        extract(engine, tokenizer, &image, &extractor, vision, &decode)
+    })
+    .await?;
+    Ok(Json(ExtractResponse {
+        model: active_model_id,
+        width: extraction.width,
+        height: extraction.height,
+        fields: extraction.fields,
+        metadata,
+    }))
+}
+
+/// The one page of `payload`, turned upright, for a `task` that works on a single image.
+/// Clean-up is skipped so results line up with the submitted image.
+fn single_page(
+    payload: &ImagePayload,
+    inputs: &GenerationInputs,
+    task: &str,
+) -> Result<(DynamicImage, ImageMetadata), ApiError> {
+    let mut pages = load_image(payload, inputs.orientation)?;
+    if pages.len() != 1 {
+        return Err(ApiError::BadRequest(format!(
+            "{task} works on a single image, got {} pages",
+            pages.len()
+        )));
+    }
+    let OrientedImage { image, report, .. } = pages.remove(0);
+    let metadata = ImageMetadata {
+        orientation: report,
+        cleanup: None,
+    };
+    Ok((image, metadata))
+}
+
+/// Decode every attached image as a page and render them as the binary document `format`.
+async fn binary_document(
+    format: OutputFormat,
+    state: &State<AppState>,
+    req: Json<ChatCompletionRequest>,
+) -> Result<Vec<u8>, ApiError> {
+    if req.stream.unwrap_or(false) {
+        return Err(ApiError::BadRequest(format!(
+            "{format} output cannot be streamed"
+        )));
+    }
+    let (mut gen_inputs, _) = state.prepare_generation(&req.model)?;
+    gen_inputs.apply_vision_overrides(&req.vision)?;
+    gen_inputs.apply_reading_order_override(req.reading_order);
+    let (prompt, inputs) = read_messages(
+        gen_inputs.kind,
+        &req.messages,
+        req.task,
+        gen_inputs.orientation,
+    )?;
+    // PDF pages show the scans as submitted; the other formats crop from the cleaned images.
+    let mut scans = Vec::new();
+    let mut images = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        if format == OutputFormat::Pdf {
+            let cleaned = clean_document(input.image.clone(), &gen_inputs.cleanup);
+            images.push(cleaned.image);
+            scans.push(PageScan::from_input(input, cleaned.report));
+        } else {
+            images.push(clean_document(input.image, &gen_inputs.cleanup).image);
+        }
+    }
+    if images.is_empty() {
+        return Err(ApiError::BadRequest(format!(
+            "{format} output needs at least one image"
+        )));
+    }
+    let max_tokens = req.max_tokens.unwrap_or(state.default_max_new_tokens());
+    let mut decode = base_decode_parameters(&gen_inputs, max_tokens);
+    apply_decode_overrides(
+        &mut decode,
+        req.do_sample,
+        req.temperature,
+        req.top_p,
+        req.top_k,
+        req.repetition_penalty,
+        req.no_repeat_ngram_size,
+        req.seed,
+        req.use_cache,
+    );
+    let (_, pages) = generate_output(format, gen_inputs, prompt, images.clone(), decode).await?;
+    Ok(match format {
+        OutputFormat::Pdf => pdf::render(&pages, &scans)?,
+        _ => format.render(&pages, &images)?,
+    })
+}
+
+/// Page documents are rendered from the finished text, so they cannot be streamed; PDF and
+/// DOCX are binary and only served by `/v1/pdf` and `/v1/docx`.
+fn check_output_format(format: OutputFormat, stream: Option<bool>) -> Result<(), ApiError> {
+    if format.is_binary() {
+        return Err(ApiError::BadRequest(format!(
+            "{format} output is served by /v1/{format}"
+        )));
+    }
+    if format.is_document() && stream.unwrap_or(false) {
+        return Err(ApiError::BadRequest(format!(
+            "{format} output cannot be streamed"
+        )));
+    }
+    Ok(())
+}
+
+/// Run generation for `format`. Page documents decode every image on its own with a
+/// single-slot prompt and return one page per image; texts are joined and usage is summed
+/// over the pages.
+async fn generate_output(
+    format: OutputFormat,
+    inputs: GenerationInputs,
+    prompt: String,
+    images: Vec<DynamicImage>,
+    decode: DecodeParameters,
+) -> Result<(GenerationResult, Vec<OcrPage>), ApiError> {
+    if !format.is_document() {
+        let generation = generate_async(inputs, prompt, images, decode, None).await?;
+        return Ok((generation, Vec::new()));
+    }
+    let prompt = page_prompt(&prompt).map_err(|err| ApiError::BadRequest(err.to_string()))?;
+    let mut total = GenerationResult {
+        text: String::new(),
+        prompt_tokens: 0,
+        response_tokens: 0,
+    };
+    let mut pages = Vec::with_capacity(images.len());
+    for image in images {
+        let (width, height) = image.dimensions();
+        let generation = generate_async(
+            inputs.clone(),
+            prompt.clone(),
+            vec![image],
+            decode.clone(),
+            None,
+        )
+        .await?;
+        if !total.text.is_empty() {
+            total.text.push_str("\n\n");
+        }
+        total.text.push_str(&generation.text);
+        total.prompt_tokens += generation.prompt_tokens;
+        total.response_tokens += generation.response_tokens;
+        pages.push(
+            OcrPage::from_grounded(&generation.text, width, height)
+                .with_reading_order(inputs.reading_order),
+        );
+    }
+    Ok((total, pages))
+}
+
+/// Text output after the enabled polish rules; page documents are rendered from the grounded
+/// pages and left alone.
+fn polish_output(
+    format: OutputFormat,
+    config: &PolishConfig,
+    text: String,
+) -> (String, Vec<FormulaIssue>) {
+    if format.is_document() || !config.enabled() {
+        return (text, Vec::new());
+    }
+    let Polished {
+        text,
+        formula_issues,
+    } = polish(&text, config);
+    (text, formula_issues)
+}
+
+/// The response text: `text` itself, or the page document of `format`, which crops its figures
+/// from `images` when it embeds them.
+fn render_output(
+    format: OutputFormat,
+    text: String,
+    pages: &[OcrPage],
+    images: &[DynamicImage],
+) -> Result<String, ApiError> {
+    if !format.is_document() {
+        return Ok(text);
+    }
+    let bytes = format.render(pages, images)?;
+    String::from_utf8(bytes).map_err(|err| ApiError::Internal(err.to_string()))
+}
+
 pub fn v1_routes() -> Vec<Route> {
     routes![
         health,
         list_models,
         responses_endpoint,
-        chat_completions_endpoint
+        chat_completions_endpoint,
+        estimate_endpoint,
+        pdf_endpoint,
+        docx_endpoint,
+        locate_endpoint,
+        redact_endpoint,
+        extract_endpoint
     ]
 }
 
@@ -280,7 +699,11 @@ fn missing_image_markdown() -> String {
     "⚠️ **Image Required**\n\n- This OCR backend expects at least one `<image>` placeholder or attached image.\n- Please include `input_image` / `image_url`, or add `<image>` inside the prompt.\n\n---\n\n⚠️ **需要图像输入**\n\n- 当前 OCR 模型需要至少一个 `<image>` 占位符或实际图片。\n- 请在请求中附带 `input_image`/`image_url`，或在 prompt 中插入 `<image>`。".into()
 }
 
-fn fallback_response_response(model: String, text: &str) -> ResponsesResponse {
+fn fallback_response_response(
+    model: String,
+    text: &str,
+    metadata: ResponseMetadata,
+) -> ResponsesResponse {
     let created = current_timestamp();
     ResponsesResponse {
         id: format!("resp-{}", Uuid::new_v4()),
@@ -301,10 +724,17 @@ fn fallback_response_response(model: String, text: &str) -> ResponsesResponse {
             completion_tokens: 0,
             total_tokens: 0,
         },
+        metadata,
+        tables: Vec::new(),
+        formula_issues: Vec::new(),
     }
 }
 
-fn fallback_chat_response(model: String, text: &str) -> ChatCompletionResponse {
+fn fallback_chat_response(
+    model: String,
+    text: &str,
+    metadata: ResponseMetadata,
+) -> ChatCompletionResponse {
     let created = current_timestamp();
     ChatCompletionResponse {
         id: format!("chatcmpl-{}", Uuid::new_v4()),
@@ -324,6 +754,9 @@ fn fallback_chat_response(model: String, text: &str) -> ChatCompletionResponse {
             completion_tokens: 0,
             total_tokens: 0,
         },
+        metadata,
+        tables: Vec::new(),
+        formula_issues: Vec::new(),
     }
 }
 
@@ -331,6 +764,7 @@ fn stream_fallback_response(
     text: String,
     inputs: &GenerationInputs,
     model: String,
+    metadata: ResponseMetadata,
 ) -> BoxEventStream {
     let response_id = format!("resp-{}", Uuid::new_v4());
     let output_id = format!("msg-{}", Uuid::new_v4());
@@ -345,6 +779,7 @@ fn stream_fallback_response(
             model,
             created,
         },
+        metadata,
     };
     let controller = StreamController::new(Arc::clone(&inputs.tokenizer), context);
     controller.send_initial();
@@ -352,7 +787,12 @@ fn stream_fallback_response(
     stream
 }
 
-fn stream_fallback_chat(text: String, inputs: &GenerationInputs, model: String) -> BoxEventStream {
+fn stream_fallback_chat(
+    text: String,
+    inputs: &GenerationInputs,
+    model: String,
+    metadata: ResponseMetadata,
+) -> BoxEventStream {
     let completion_id = format!("chatcmpl-{}", Uuid::new_v4());
     let created = current_timestamp();
     let (sender, rx) = mpsc::unbounded_channel();
@@ -364,6 +804,7 @@ fn stream_fallback_chat(text: String, inputs: &GenerationInputs, model: String)
             model,
             created,
         },
+        metadata,
     };
     let controller = StreamController::new(Arc::clone(&inputs.tokenizer), context);
     controller.send_initial();
@@ -1,4 +1,5 @@
 use std::{
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
     time::Instant,
 };
@@ -9,12 +10,24 @@ use tokenizers::Tokenizer;
 use tracing::info;
 
 use deepseek_ocr_config::{AppConfig, LocalFileSystem};
-use deepseek_ocr_core::{DecodeParameters, ModelKind, ModelLoadArgs, OcrEngine, VisionSettings};
+use deepseek_ocr_core::{
+    DecodeParameters, ModelKind, ModelLoadArgs, OcrEngine, ResolutionMode, VisionSettings,
+    cleanup::CleanupConfig, layout::LayoutConfig, orientation::OrientationMode,
+    pipeline::redact::RedactionRules, polish::PolishConfig, reading_order::ReadingOrder,
+    slicing::SliceConfig,
+};
 use deepseek_ocr_infer_deepseek::load_model as load_deepseek_model;
-use deepseek_ocr_infer_paddleocr::load_model as load_paddle_model;
+use deepseek_ocr_infer_paddleocr::{
+    load_model as load_paddle_model,
+    vision::{
+        SiglipPreprocessConfig,
+        preprocess::{DEFAULT_MAX_PIXELS, DEFAULT_MIN_PIXELS},
+    },
+};
 
 use crate::{
     error::ApiError,
+    models::{ImageMetadata, PolishOverrides, ResponseMetadata, VisionMetadata, VisionOverrides},
     resources::{ensure_config_file, ensure_tokenizer_file, prepare_weights_path},
 };
 
@@ -30,6 +43,13 @@ pub struct AppState {
     manager: ModelManager,
     current: Mutex<Option<LoadedModel>>,
     vision: VisionSettings,
+    orientation: OrientationMode,
+    cleanup: CleanupConfig,
+    slicing: SliceConfig,
+    layout: LayoutConfig,
+    polish: PolishConfig,
+    reading_order: ReadingOrder,
+    redaction: RedactionRules,
     decode_defaults: DecodeParameters,
     available_models: Vec<ModelListing>,
 }
@@ -40,9 +60,223 @@ pub struct GenerationInputs {
     pub model: SharedModel,
     pub tokenizer: Arc<Tokenizer>,
     pub vision: VisionSettings,
+    pub orientation: OrientationMode,
+    pub cleanup: CleanupConfig,
+    pub slicing: SliceConfig,
+    pub layout: LayoutConfig,
+    pub polish: PolishConfig,
+    pub reading_order: ReadingOrder,
     pub defaults: DecodeParameters,
 }
 
+/// Request defaults plus the model config and tokenizer: what a dry-run estimate needs, without
+/// any weights.
+pub struct EstimateInputs {
+    pub kind: ModelKind,
+    pub config_path: PathBuf,
+    pub tokenizer: Arc<Tokenizer>,
+    pub dtype: DType,
+    pub vision: VisionSettings,
+    pub orientation: OrientationMode,
+    pub cleanup: CleanupConfig,
+}
+
+/// DeepSeek-OCR view sizes must cover whole SAM queries (16px patches, 4x downsampling).
+const DEEPSEEK_SIZE_STEP: u32 = 64;
+const DEEPSEEK_MAX_SIZE: u32 = 2048;
+/// One merged SigLIP patch (14px patches, 2x2 merge) is the smallest area PaddleOCR-VL accepts.
+const PADDLE_MIN_AREA: u32 = 28 * 28;
+
+impl GenerationInputs {
+    /// Merge per-request vision overrides over the server defaults, rejecting fields the
+    /// selected backend does not understand.
+    pub fn apply_vision_overrides(&mut self, overrides: &VisionOverrides) -> Result<(), ApiError> {
+        self.vision = merge_vision(self.kind, self.vision, overrides)?;
+        if let Some(orientation) = overrides.orientation {
+            self.orientation = orientation;
+        }
+        if let Some(mode) = overrides.split_layout {
+            self.layout.mode = mode;
+        }
+        Ok(())
+    }
+
+    /// Use the request's block order for structured exports when it names one.
+    pub fn apply_reading_order_override(&mut self, order: Option<ReadingOrder>) {
+        if let Some(order) = order {
+            self.reading_order = order;
+        }
+    }
+
+    /// Merge per-request polish settings over the server defaults.
+    pub fn apply_polish_overrides(&mut self, overrides: &PolishOverrides) {
+        let polish = &mut self.polish;
+        let toggles = [
+            (overrides.strip_grounding, &mut polish.strip_grounding),
+            (overrides.markdown_tables, &mut polish.markdown_tables),
+            (overrides.merge_hyphenation, &mut polish.merge_hyphenation),
+            (overrides.join_paragraphs, &mut polish.join_paragraphs),
+            (overrides.fix_headings, &mut polish.fix_headings),
+        ];
+        for (value, toggle) in toggles {
+            if let Some(value) = value {
+                *toggle = value;
+            }
+        }
+        if let Some(formulas) = overrides.formulas {
+            self.polish.formulas = formulas;
+        }
+        if let Some(delimiters) = overrides.math_delimiters {
+            self.polish.math_delimiters = delimiters;
+        }
+    }
+
+    /// Vision settings this request ran with and how its images were prepared, as reported back
+    /// to the client.
+    pub fn response_metadata(&self, images: Vec<ImageMetadata>) -> ResponseMetadata {
+        let vision = self.vision;
+        let vision = match self.kind {
+            ModelKind::Deepseek => VisionMetadata {
+                resolution: ResolutionMode::from_settings(vision),
+                base_size: Some(vision.base_size),
+                image_size: vision.image_size,
+                crop_mode: Some(vision.crop_mode),
+                min_pixels: None,
+                max_pixels: None,
+            },
+            ModelKind::PaddleOcrVl => {
+                let preprocess = SiglipPreprocessConfig::default()
+                    .with_max_image_size(vision.image_size)
+                    .with_pixel_bounds(
+                        vision.min_pixels.map(|value| value as usize),
+                        vision.max_pixels.map(|value| value as usize),
+                    );
+                VisionMetadata {
+                    resolution: None,
+                    base_size: None,
+                    image_size: vision.image_size,
+                    crop_mode: None,
+                    min_pixels: Some(preprocess.min_pixels as u32),
+                    max_pixels: Some(preprocess.max_pixels as u32),
+                }
+            }
+        };
+        ResponseMetadata { vision, images }
+    }
+}
+
+impl EstimateInputs {
+    /// Same merge as [`GenerationInputs::apply_vision_overrides`], minus the layout split: the
+    /// estimate covers one decode of each whole page. A split decodes every region as an image
+    /// of its own, which adds vision tokens per region that the estimate does not count.
+    pub fn apply_vision_overrides(&mut self, overrides: &VisionOverrides) -> Result<(), ApiError> {
+        self.vision = merge_vision(self.kind, self.vision, overrides)?;
+        if let Some(orientation) = overrides.orientation {
+            self.orientation = orientation;
+        }
+        Ok(())
+    }
+}
+
+fn merge_vision(
+    kind: ModelKind,
+    defaults: VisionSettings,
+    overrides: &VisionOverrides,
+) -> Result<VisionSettings, ApiError> {
+    match kind {
+        ModelKind::Deepseek => merge_deepseek_vision(defaults, overrides),
+        ModelKind::PaddleOcrVl => merge_paddle_vision(defaults, overrides),
+    }
+}
+
+fn merge_deepseek_vision(
+    defaults: VisionSettings,
+    overrides: &VisionOverrides,
+) -> Result<VisionSettings, ApiError> {
+    if overrides.min_pixels.is_some() || overrides.max_pixels.is_some() {
+        return Err(ApiError::BadRequest(
+            "`min_pixels`/`max_pixels` only apply to PaddleOCR-VL models".into(),
+        ));
+    }
+    if let Some(mode) = overrides.resolution {
+        if overrides.base_size.is_some()
+            || overrides.image_size.is_some()
+            || overrides.crop_mode.is_some()
+        {
+            return Err(ApiError::BadRequest(
+                "`resolution` cannot be combined with `base_size`, `image_size` or `crop_mode`"
+                    .into(),
+            ));
+        }
+        return Ok(mode.settings());
+    }
+    let mut vision = defaults;
+    if let Some(size) = overrides.base_size {
+        vision.base_size = validate_deepseek_size("base_size", size)?;
+    }
+    if let Some(size) = overrides.image_size {
+        vision.image_size = validate_deepseek_size("image_size", size)?;
+    }
+    if let Some(crop_mode) = overrides.crop_mode {
+        vision.crop_mode = crop_mode;
+    }
+    Ok(vision)
+}
+
+fn validate_deepseek_size(field: &str, size: u32) -> Result<u32, ApiError> {
+    if size == 0 || !size.is_multiple_of(DEEPSEEK_SIZE_STEP) || size > DEEPSEEK_MAX_SIZE {
+        return Err(ApiError::BadRequest(format!(
+            "`{field}` must be a multiple of {DEEPSEEK_SIZE_STEP} between {DEEPSEEK_SIZE_STEP} and {DEEPSEEK_MAX_SIZE} (got {size})"
+        )));
+    }
+    Ok(size)
+}
+
+fn merge_paddle_vision(
+    defaults: VisionSettings,
+    overrides: &VisionOverrides,
+) -> Result<VisionSettings, ApiError> {
+    if overrides.resolution.is_some()
+        || overrides.base_size.is_some()
+        || overrides.crop_mode.is_some()
+    {
+        return Err(ApiError::BadRequest(
+            "`resolution`, `base_size` and `crop_mode` only apply to DeepSeek-OCR models".into(),
+        ));
+    }
+    let mut vision = defaults;
+    if let Some(size) = overrides.image_size {
+        if size == 0 {
+            return Err(ApiError::BadRequest("`image_size` must be positive".into()));
+        }
+        vision.image_size = size;
+    }
+    if overrides.min_pixels.is_some() {
+        vision.min_pixels = overrides.min_pixels;
+    }
+    if overrides.max_pixels.is_some() {
+        vision.max_pixels = overrides.max_pixels;
+    }
+    let min_pixels = vision.min_pixels.unwrap_or(DEFAULT_MIN_PIXELS as u32);
+    let max_pixels = vision.max_pixels.unwrap_or(DEFAULT_MAX_PIXELS as u32);
+    if min_pixels < PADDLE_MIN_AREA {
+        return Err(ApiError::BadRequest(format!(
+            "`min_pixels` must be at least {PADDLE_MIN_AREA} (got {min_pixels})"
+        )));
+    }
+    if max_pixels > DEFAULT_MAX_PIXELS as u32 {
+        return Err(ApiError::BadRequest(format!(
+            "`max_pixels` must not exceed {DEFAULT_MAX_PIXELS} (got {max_pixels})"
+        )));
+    }
+    if min_pixels > max_pixels {
+        return Err(ApiError::BadRequest(format!(
+            "`min_pixels` ({min_pixels}) must not exceed `max_pixels` ({max_pixels})"
+        )));
+    }
+    Ok(vision)
+}
+
 impl AppState {
     pub fn bootstrap(
         fs: LocalFileSystem,
@@ -61,6 +295,13 @@ impl AppState {
                 kind: entry.kind,
             })
             .collect::<Vec<_>>();
+        let orientation = config.inference.orientation;
+        let cleanup = config.inference.cleanup;
+        let slicing = config.inference.slicing;
+        let layout = config.inference.layout;
+        let polish = config.inference.polish;
+        let reading_order = config.inference.reading_order;
+        let redaction = config.redaction.clone();
 
         let manager = ModelManager::new(fs, config, device, dtype);
 
@@ -68,6 +309,13 @@ impl AppState {
             manager,
             current: Mutex::new(None),
             vision,
+            orientation,
+            cleanup,
+            slicing,
+            layout,
+            polish,
+            reading_order,
+            redaction,
             decode_defaults,
             available_models,
         })
@@ -81,6 +329,11 @@ impl AppState {
         self.decode_defaults.max_new_tokens
     }
 
+    /// Rules `/v1/redact` applies when a request brings none.
+    pub fn redaction_rules(&self) -> &RedactionRules {
+        &self.redaction
+    }
+
     pub fn prepare_generation(
         &self,
         requested_model: &str,
@@ -93,11 +346,42 @@ impl AppState {
             model: shared_model,
             tokenizer,
             vision: self.vision,
+            orientation: self.orientation,
+            cleanup: self.cleanup,
+            slicing: self.slicing,
+            layout: self.layout,
+            polish: self.polish,
+            reading_order: self.reading_order,
             defaults: self.decode_defaults.clone(),
         };
         Ok((inputs, model_id))
     }
 
+    /// Resolve the config and tokenizer of `requested_model` for a dry run. The weights are
+    /// neither downloaded nor loaded; a tokenizer already in memory is reused.
+    pub fn prepare_estimate(&self, requested_model: &str) -> Result<EstimateInputs, ApiError> {
+        self.validate_model(requested_model)?;
+        let loaded_tokenizer = self.current.lock().ok().and_then(|guard| {
+            guard
+                .as_ref()
+                .filter(|loaded| loaded.id == requested_model)
+                .map(|loaded| Arc::clone(&loaded.tokenizer))
+        });
+        let (kind, config_path, tokenizer) = self
+            .manager
+            .estimate_resources(requested_model, loaded_tokenizer)
+            .map_err(|err| ApiError::Internal(err.to_string()))?;
+        Ok(EstimateInputs {
+            kind,
+            config_path,
+            tokenizer,
+            dtype: self.manager.dtype,
+            vision: self.vision,
+            orientation: self.orientation,
+            cleanup: self.cleanup,
+        })
+    }
+
     fn validate_model(&self, requested: &str) -> Result<(), ApiError> {
         if self
             .available_models
@@ -188,6 +472,8 @@ impl ModelManager {
             weights_path: Some(&weights_path),
             device: self.device.clone(),
             dtype: self.dtype,
+            quantization: resources.quantization,
+            tile_encoding: self.config.inference.tile_encoding,
         };
         let start = Instant::now();
         let model = match resources.kind {
@@ -202,12 +488,7 @@ impl ModelManager {
             model.flash_attention_enabled(),
             weights_path.display()
         );
-        let tokenizer = Arc::new(Tokenizer::from_file(&tokenizer_path).map_err(|err| {
-            anyhow::anyhow!(
-                "failed to load tokenizer from {}: {err}",
-                tokenizer_path.display()
-            )
-        })?);
+        let tokenizer = load_tokenizer(&tokenizer_path)?;
         Ok(LoadedModel {
             id: model_id.to_string(),
             kind: resources.kind,
@@ -215,4 +496,161 @@ impl ModelManager {
             tokenizer,
         })
     }
+
+    fn estimate_resources(
+        &self,
+        model_id: &str,
+        tokenizer: Option<Arc<Tokenizer>>,
+    ) -> Result<(ModelKind, PathBuf, Arc<Tokenizer>)> {
+        let resources = self
+            .config
+            .model_resources(&self.fs, model_id)
+            .with_context(|| format!("model `{model_id}` not found in configuration"))?;
+        let config_path = ensure_config_file(&self.fs, &resources.config, resources.kind)?;
+        let tokenizer = match tokenizer {
+            Some(tokenizer) => tokenizer,
+            None => load_tokenizer(&ensure_tokenizer_file(
+                &self.fs,
+                &resources.tokenizer,
+                resources.kind,
+            )?)?,
+        };
+        Ok((resources.kind, config_path, tokenizer))
+    }
+}
+
+fn load_tokenizer(path: &Path) -> Result<Arc<Tokenizer>> {
+    let tokenizer = Tokenizer::from_file(path).map_err(|err| {
+        anyhow::anyhow!("failed to load tokenizer from {}: {err}", path.display())
+    })?;
+    Ok(Arc::new(tokenizer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> VisionSettings {
+        VisionSettings {
+            base_size: 1024,
+            image_size: 640,
+            crop_mode: true,
+            min_pixels: None,
+            max_pixels: None,
+        }
+    }
+
+    fn rejected(result: Result<VisionSettings, ApiError>) -> bool {
+        matches!(result, Err(ApiError::BadRequest(_)))
+    }
+
+    #[test]
+    fn deepseek_request_fields_override_defaults() {
+        let overrides = VisionOverrides {
+            image_size: Some(768),
+            crop_mode: Some(false),
+            ..VisionOverrides::default()
+        };
+        let vision = merge_deepseek_vision(defaults(), &overrides).unwrap();
+        assert_eq!(vision.base_size, 1024);
+        assert_eq!(vision.image_size, 768);
+        assert!(!vision.crop_mode);
+
+        let unchanged = merge_deepseek_vision(defaults(), &VisionOverrides::default()).unwrap();
+        assert_eq!(unchanged, defaults());
+    }
+
+    #[test]
+    fn deepseek_resolution_preset_replaces_defaults() {
+        let overrides = VisionOverrides {
+            resolution: Some(ResolutionMode::Tiny),
+            ..VisionOverrides::default()
+        };
+        let vision = merge_deepseek_vision(defaults(), &overrides).unwrap();
+        assert_eq!(vision, ResolutionMode::Tiny.settings());
+    }
+
+    #[test]
+    fn deepseek_rejects_conflicting_or_foreign_fields() {
+        let preset_and_size = VisionOverrides {
+            resolution: Some(ResolutionMode::Small),
+            base_size: Some(1024),
+            ..VisionOverrides::default()
+        };
+        assert!(rejected(merge_deepseek_vision(
+            defaults(),
+            &preset_and_size
+        )));
+
+        let pixels = VisionOverrides {
+            min_pixels: Some(1024),
+            ..VisionOverrides::default()
+        };
+        assert!(rejected(merge_deepseek_vision(defaults(), &pixels)));
+
+        for size in [0, 100, 2048 + 64] {
+            let overrides = VisionOverrides {
+                base_size: Some(size),
+                ..VisionOverrides::default()
+            };
+            assert!(
+                rejected(merge_deepseek_vision(defaults(), &overrides)),
+                "{size}"
+            );
+        }
+    }
+
+    #[test]
+    fn paddle_request_pixels_override_defaults() {
+        let overrides = VisionOverrides {
+            image_size: Some(448),
+            min_pixels: Some(56 * 56),
+            max_pixels: Some(1024 * 1024),
+            ..VisionOverrides::default()
+        };
+        let vision = merge_paddle_vision(defaults(), &overrides).unwrap();
+        assert_eq!(vision.image_size, 448);
+        assert_eq!(vision.min_pixels, Some(56 * 56));
+        assert_eq!(vision.max_pixels, Some(1024 * 1024));
+    }
+
+    #[test]
+    fn paddle_rejects_deepseek_fields_and_bad_pixel_bounds() {
+        let crop = VisionOverrides {
+            crop_mode: Some(true),
+            ..VisionOverrides::default()
+        };
+        assert!(rejected(merge_paddle_vision(defaults(), &crop)));
+
+        let preset = VisionOverrides {
+            resolution: Some(ResolutionMode::Base),
+            ..VisionOverrides::default()
+        };
+        assert!(rejected(merge_paddle_vision(defaults(), &preset)));
+
+        let zero_size = VisionOverrides {
+            image_size: Some(0),
+            ..VisionOverrides::default()
+        };
+        assert!(rejected(merge_paddle_vision(defaults(), &zero_size)));
+
+        let too_small = VisionOverrides {
+            min_pixels: Some(PADDLE_MIN_AREA - 1),
+            ..VisionOverrides::default()
+        };
+        assert!(rejected(merge_paddle_vision(defaults(), &too_small)));
+
+        let too_large = VisionOverrides {
+            max_pixels: Some(DEFAULT_MAX_PIXELS as u32 + 1),
+            ..VisionOverrides::default()
+        };
+        assert!(rejected(merge_paddle_vision(defaults(), &too_large)));
+
+        let inverted = VisionOverrides {
+            min_pixels: Some(512 * 512),
+            max_pixels: Some(256 * 256),
+            ..VisionOverrides::default()
+        };
+        assert!(rejected(merge_paddle_vision(defaults(), &inverted)));
+    }
 }
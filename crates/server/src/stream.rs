@@ -4,7 +4,7 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use deepseek_ocr_core::streaming::DeltaTracker;
+use deepseek_ocr_core::{streaming::DeltaTracker, tables::extract_tables};
 use rocket::{
     response::stream::{Event, EventStream},
     tokio::sync::mpsc,
@@ -13,6 +13,8 @@ use serde_json::json;
 use tokenizers::Tokenizer;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
+use crate::models::ResponseMetadata;
+
 pub type BoxEventStream =
     EventStream<Pin<Box<dyn rocket::futures::stream::Stream<Item = Event> + Send>>>;
 
@@ -27,6 +29,7 @@ pub fn into_event_stream(rx: mpsc::UnboundedReceiver<Event>) -> BoxEventStream {
 pub struct StreamContext {
     pub sender: mpsc::UnboundedSender<Event>,
     pub kind: StreamKind,
+    pub metadata: ResponseMetadata,
 }
 
 impl StreamContext {
@@ -82,6 +85,7 @@ struct StreamControllerInner {
     sender: mpsc::UnboundedSender<Event>,
     tokenizer: Arc<Tokenizer>,
     kind: StreamKind,
+    metadata: ResponseMetadata,
     runtime: Mutex<StreamRuntime>,
 }
 
@@ -104,6 +108,7 @@ impl StreamController {
                 sender: context.sender,
                 tokenizer,
                 kind: context.kind,
+                metadata: context.metadata,
                 runtime: Mutex::new(StreamRuntime::default()),
             }),
         }
@@ -129,6 +134,33 @@ impl StreamController {
         }
     }
 
+    /// Emit whatever `text` adds to the text sent so far, for outputs that grow a piece at a
+    /// time rather than token by token.
+    pub fn emit_progress(&self, text: &str) {
+        let delta = {
+            let mut state = self
+                .inner
+                .runtime
+                .lock()
+                .expect("stream state lock poisoned");
+            state.delta.advance(text, true)
+        };
+        if !delta.is_empty() {
+            self.inner.emit_delta(delta, false);
+        }
+    }
+
+    /// Emit the rest of an output that was not streamed token by token, then finish.
+    pub fn emit_text(&self, text: &str, prompt_tokens: usize, completion_tokens: usize) {
+        self.emit_progress(text);
+        self.inner.finalize(text, prompt_tokens, completion_tokens);
+    }
+
+    /// True once the client has stopped listening.
+    pub fn is_closed(&self) -> bool {
+        self.inner.sender.is_closed()
+    }
+
     pub fn emit_fallback(&self, text: &str) {
         self.inner.emit_delta(text.to_string(), true);
         self.inner.finalize(text, 0, 0);
@@ -302,6 +334,7 @@ impl StreamControllerInner {
             }
             state.finished = true;
         }
+        let tables = extract_tables(normalized);
 
         match &self.kind {
             StreamKind::Responses {
@@ -311,7 +344,7 @@ impl StreamControllerInner {
                 created,
             } => {
                 let total_tokens = prompt_tokens + completion_tokens;
-                let payload = json!({
+                let mut payload = json!({
                     "type": "response.completed",
                     "response": {
                         "id": response_id,
@@ -332,8 +365,12 @@ impl StreamControllerInner {
                             "output_tokens": completion_tokens,
                             "total_tokens": total_tokens,
                         },
+                        "metadata": self.metadata,
                     }
                 });
+                if !tables.is_empty() {
+                    payload["response"]["tables"] = json!(tables);
+                }
                 let _ = self.sender.send(Event::json(&payload));
                 let _ = self.sender.send(Event::data("[DONE]"));
             }
@@ -342,7 +379,7 @@ impl StreamControllerInner {
                 model,
                 created,
             } => {
-                let payload = json!({
+                let mut payload = json!({
                     "id": completion_id,
                     "object": "chat.completion.chunk",
                     "created": created,
@@ -356,8 +393,12 @@ impl StreamControllerInner {
                         "prompt_tokens": prompt_tokens,
                         "completion_tokens": completion_tokens,
                         "total_tokens": prompt_tokens + completion_tokens,
-                    }
+                    },
+                    "metadata": self.metadata,
                 });
+                if !tables.is_empty() {
+                    payload["tables"] = json!(tables);
+                }
                 let _ = self.sender.send(Event::json(&payload));
                 let _ = self.sender.send(Event::data("[DONE]"));
             }